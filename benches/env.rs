@@ -0,0 +1,28 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use dinglebit_config::{Config, Environment};
+use std::collections::HashMap;
+
+/// The cost of reading from the environment compared to a map, with
+/// and without working out the variable name each time.
+fn lookup(c: &mut Criterion) {
+    std::env::set_var("BENCH_HTTP_PORT", "8080");
+    let env = Environment::new("bench");
+    let prepared = env.prepare("http.port");
+    let map = HashMap::from([("http.port", "8080")]);
+
+    let mut group = c.benchmark_group("env get");
+    group.bench_function("environment", |b| {
+        b.iter(|| env.get_ref(black_box("http.port")))
+    });
+    group.bench_function("environment missing", |b| {
+        b.iter(|| env.get_ref(black_box("http.host")))
+    });
+    group.bench_function("prepared", |b| {
+        b.iter(|| env.get_prepared(black_box(&prepared)))
+    });
+    group.bench_function("map", |b| b.iter(|| map.get_ref(black_box("http.port"))));
+    group.finish();
+}
+
+criterion_group!(benches, lookup);
+criterion_main!(benches);
@@ -0,0 +1,48 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use dinglebit_config::{Config, MultiConfig, Simple};
+
+/// A layer of a few hundred keys, the way a service's file layers
+/// look, with one key only this layer has.
+fn layer(n: usize) -> Simple {
+    let mut s = String::new();
+    for i in 0..200 {
+        s.push_str(&format!("service.setting{} = layer {} value {}\n", i, n, i));
+    }
+    s.push_str(&format!("layer{}.only = {}\n", n, n));
+    Simple::from_str(&s).unwrap()
+}
+
+fn stack(depth: usize) -> MultiConfig {
+    MultiConfig::new(
+        (0..depth)
+            .map(|n| Box::new(layer(n)) as Box<dyn Config + Send + Sync>)
+            .collect(),
+    )
+}
+
+/// Lookups that are answered by the top layer, by the bottom layer
+/// (so every layer is probed) and by no layer at all.
+fn depth(c: &mut Criterion) {
+    let mut group = c.benchmark_group("multi get");
+    for depth in [1, 2, 5, 10] {
+        let cfg = stack(depth);
+        let bottom = format!("layer{}.only", depth - 1);
+        group.bench_with_input(BenchmarkId::new("top", depth), &cfg, |b, cfg| {
+            b.iter(|| cfg.get_ref(black_box("service.setting42")))
+        });
+        group.bench_with_input(BenchmarkId::new("bottom", depth), &cfg, |b, cfg| {
+            b.iter(|| cfg.get_ref(black_box(&bottom)))
+        });
+        group.bench_with_input(BenchmarkId::new("missing", depth), &cfg, |b, cfg| {
+            b.iter(|| cfg.get_ref(black_box("service.missing.key")))
+        });
+        let cfg = cfg.indexed();
+        group.bench_with_input(BenchmarkId::new("bottom indexed", depth), &cfg, |b, cfg| {
+            b.iter(|| cfg.get_ref(black_box(&bottom)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, depth);
+criterion_main!(benches);
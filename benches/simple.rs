@@ -0,0 +1,36 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use dinglebit_config::Simple;
+
+/// A config shaped like the ones our batch jobs use: a few thousand
+/// dot-notation keys sharing prefixes, with comments and blank lines.
+fn document(lines: usize) -> String {
+    let mut s = String::new();
+    for i in 0..lines {
+        match i % 10 {
+            0 => s.push_str("# section comment\n"),
+            5 => s.push('\n'),
+            _ => s.push_str(&format!(
+                "jobs.batch{}.step{}.setting = value number {}\n",
+                i / 100,
+                i % 100,
+                i
+            )),
+        }
+    }
+    s
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("simple parse");
+    for lines in [1_000, 10_000] {
+        let doc = document(lines);
+        group.throughput(Throughput::Bytes(doc.len() as u64));
+        group.bench_function(format!("{} lines", lines), |b| {
+            b.iter(|| Simple::from_str(black_box(&doc)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -0,0 +1,460 @@
+//! Configuration from Azure App Configuration and secrets from Azure
+//! Key Vault.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+
+use crate::http::{encode, get_json};
+use crate::{redact, Config, Error};
+
+const APP_CONFIGURATION: &str = "https://azconfig.io";
+const KEY_VAULT: &str = "https://vault.azure.net";
+const KEY_VAULT_REF: &str = "application/vnd.microsoft.appconfig.keyvaultref+json";
+const IMDS: &str = "http://169.254.169.254/metadata/identity/oauth2/token";
+
+/// How to authenticate with Azure.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Credential {
+    /// The managed identity of the host (App Service, Functions or a
+    /// VM), optionally the user-assigned one with the given client id.
+    ManagedIdentity(Option<String>),
+    /// A bearer token that was acquired elsewhere (e.g. with `az
+    /// account get-access-token`). It must be for the right resource.
+    Token(String),
+}
+
+impl Credential {
+    /// A token for the given resource.
+    fn token(&self, resource: &str) -> Result<String, Error> {
+        let client_id = match self {
+            Credential::Token(token) => return Ok(token.clone()),
+            Credential::ManagedIdentity(client_id) => client_id,
+        };
+        let client = match client_id {
+            Some(id) => format!("&client_id={}", encode(id)),
+            None => String::new(),
+        };
+
+        // App Service and Functions have their own endpoint; VMs use
+        // the instance metadata service.
+        match (env::var("IDENTITY_ENDPOINT"), env::var("IDENTITY_HEADER")) {
+            (Ok(endpoint), Ok(header)) => fetch_token(
+                &format!(
+                    "{}?api-version=2019-08-01&resource={}{}",
+                    endpoint,
+                    encode(resource),
+                    client
+                ),
+                &[("X-IDENTITY-HEADER", &header)],
+            ),
+            _ => fetch_token(
+                &format!(
+                    "{}?api-version=2018-02-01&resource={}{}",
+                    IMDS,
+                    encode(resource),
+                    client
+                ),
+                &[("Metadata", "true")],
+            ),
+        }
+    }
+}
+
+fn fetch_token(url: &str, headers: &[(&str, &str)]) -> Result<String, Error> {
+    match get_json(url, headers)?
+        .get("access_token")
+        .and_then(|t| t.as_str())
+    {
+        Some(token) => Ok(token.to_string()),
+        None => Err(Error::Remote(format!("{}: no access_token", url))),
+    }
+}
+
+/// GET the given URL with the token and return the JSON body.
+fn get(url: &str, token: &str) -> Result<serde_json::Value, Error> {
+    get_json(url, &[("Authorization", &format!("Bearer {}", token))])
+}
+
+/// Fetch the secret at the given Key Vault URL
+/// (`https://{vault}/secrets/{name}[/{version}]`).
+fn secret(url: &str, token: &str) -> Result<String, Error> {
+    let v = get(&format!("{}?api-version=7.4", url), token)?;
+    match v.get("value").and_then(|v| v.as_str()) {
+        Some(value) => Ok(value.to_string()),
+        None => Err(Error::Remote(format!("{}: no value", url))),
+    }
+}
+
+/// The key-values of an Azure App Configuration store. Use
+/// `AppConfiguration::builder` to fetch them.
+///
+/// Keys use `:` as a separator by convention, so they are available
+/// with dots (`db:host` is `db.host`). Key Vault references are
+/// resolved with the same credential, so the secrets they point at are
+/// returned in their place. Only references to secrets of a vault
+/// (`https://{vault}.vault.azure.net/secrets/...`) are followed, since
+/// whoever can write to the store decides where the vault token is sent.
+#[derive(PartialEq)]
+pub struct AppConfiguration {
+    values: HashMap<String, String>,
+}
+
+/// Build an `AppConfiguration`.
+pub struct AppConfigurationBuilder {
+    endpoint: String,
+    credential: Credential,
+    labels: Vec<String>,
+    prefix: Option<String>,
+    vaults: Vec<String>,
+}
+
+impl AppConfiguration {
+    /// Start building a request to the store at the given endpoint
+    /// (`https://{store}.azconfig.io`).
+    pub fn builder(endpoint: &str, credential: Credential) -> AppConfigurationBuilder {
+        AppConfigurationBuilder {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            credential,
+            labels: vec![],
+            prefix: None,
+            vaults: vec![],
+        }
+    }
+}
+
+impl AppConfigurationBuilder {
+    /// Only use key-values with the given label. When called more
+    /// than once, later labels override earlier ones for the same key
+    /// (e.g. `label("base").label("prod")`). Without a label, only
+    /// key-values without one are used.
+    pub fn label(mut self, label: &str) -> Self {
+        self.labels.push(label.to_string());
+        self
+    }
+
+    /// Only use keys starting with the given prefix (e.g. `app:`),
+    /// which is removed from them.
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Only resolve Key Vault references to secrets of the vault at the
+    /// given URL (`https://{vault}.vault.azure.net`). Can be called more
+    /// than once. Without it, references to any vault are resolved.
+    pub fn vault(mut self, url: &str) -> Self {
+        self.vaults.push(url.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Fetch the key-values from the store.
+    pub fn build(self) -> Result<AppConfiguration, Error> {
+        let token = self.credential.token(APP_CONFIGURATION)?;
+        let mut vault_token = None;
+        let prefix = self.prefix.as_deref().unwrap_or("");
+        let labels = if self.labels.is_empty() {
+            vec!["\0".to_string()]
+        } else {
+            self.labels.clone()
+        };
+
+        let mut values = HashMap::new();
+        for label in &labels {
+            let mut next = Some(format!(
+                "/kv?key={}&label={}&api-version=1.0",
+                encode(&format!("{}*", prefix)),
+                encode(label)
+            ));
+            while let Some(path) = next.take() {
+                let page = get(&format!("{}{}", self.endpoint, path), &token)?;
+                let items = page.get("items").and_then(|i| i.as_array());
+                for item in items.into_iter().flatten() {
+                    let key = match item.get("key").and_then(|k| k.as_str()) {
+                        Some(key) => key.strip_prefix(prefix).unwrap_or(key),
+                        None => continue,
+                    };
+                    let value = match item.get("value").and_then(|v| v.as_str()) {
+                        Some(value) => value.to_string(),
+                        None => continue,
+                    };
+                    let content_type = item.get("content_type").and_then(|c| c.as_str());
+                    let value = match content_type {
+                        Some(c) if c.starts_with(KEY_VAULT_REF) => {
+                            let uri = reference(&value)?;
+                            check_vault(&uri, &self.vaults)?;
+                            if vault_token.is_none() {
+                                vault_token = Some(self.credential.token(KEY_VAULT)?);
+                            }
+                            secret(&uri, vault_token.as_deref().unwrap_or(""))?
+                        }
+                        _ => value,
+                    };
+                    values.insert(key.replace(':', "."), value);
+                }
+                next = page
+                    .get("@nextLink")
+                    .and_then(|n| n.as_str())
+                    .map(str::to_string);
+            }
+        }
+        Ok(AppConfiguration { values })
+    }
+}
+
+/// The secret URL of a Key Vault reference (`{"uri": "..."}`).
+fn reference(value: &str) -> Result<String, Error> {
+    let v: serde_json::Value =
+        serde_json::from_str(value).map_err(|e| Error::Parse(e.to_string()))?;
+    match v.get("uri").and_then(|u| u.as_str()) {
+        Some(uri) => Ok(uri.to_string()),
+        None => Err(Error::Parse("expected a Key Vault reference".to_string())),
+    }
+}
+
+/// Check that a Key Vault reference is to a secret of one of the given
+/// vaults or, without any, of a vault in the Azure cloud, before the
+/// vault token is sent to it.
+fn check_vault(uri: &str, vaults: &[String]) -> Result<(), Error> {
+    let (scheme, rest) = uri.split_once("://").unwrap_or(("", uri));
+    let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let vault = match vaults.is_empty() {
+        true => {
+            let host = authority.to_ascii_lowercase();
+            scheme.eq_ignore_ascii_case("https")
+                && host.ends_with(".vault.azure.net")
+                && !host.contains(['@', ':'])
+        }
+        false => vaults
+            .iter()
+            .any(|v| v.eq_ignore_ascii_case(&format!("{}://{}", scheme, authority))),
+    };
+    match vault && path.starts_with("/secrets/") && !path.contains(['?', '#']) {
+        true => Ok(()),
+        false => Err(Error::Remote(format!(
+            "{}: not a secret of an allowed Key Vault",
+            uri
+        ))),
+    }
+}
+
+impl Config for AppConfiguration {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values.get(key).cloned()
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.values.get(key).map(|v| Cow::Borrowed(v.as_str()))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+}
+
+impl fmt::Debug for AppConfiguration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redact::debug(self, f)
+    }
+}
+
+/// The secrets of an Azure Key Vault.
+///
+/// Secret names can only contain letters, digits and dashes, so `--`
+/// is used as a separator by convention and is available with dots
+/// (`db--password` is `db.password`). Disabled secrets are skipped.
+#[derive(PartialEq)]
+pub struct KeyVault {
+    values: HashMap<String, String>,
+}
+
+impl KeyVault {
+    /// Fetch the current version of every secret of the vault at the
+    /// given URL (`https://{vault}.vault.azure.net`).
+    pub fn load(url: &str, credential: &Credential) -> Result<Self, Error> {
+        let token = credential.token(KEY_VAULT)?;
+        let mut values = HashMap::new();
+        let mut next = Some(format!(
+            "{}/secrets?api-version=7.4",
+            url.trim_end_matches('/')
+        ));
+        while let Some(url) = next.take() {
+            let page = get(&url, &token)?;
+            let secrets = page.get("value").and_then(|v| v.as_array());
+            for item in secrets.into_iter().flatten() {
+                let enabled = item
+                    .pointer("/attributes/enabled")
+                    .and_then(|e| e.as_bool())
+                    .unwrap_or(true);
+                let id = match item.get("id").and_then(|i| i.as_str()) {
+                    Some(id) if enabled => id,
+                    _ => continue,
+                };
+                let name = id.rsplit('/').next().unwrap_or(id);
+                values.insert(name.replace("--", "."), secret(id, &token)?);
+            }
+            next = page
+                .get("nextLink")
+                .and_then(|n| n.as_str())
+                .map(str::to_string);
+        }
+        Ok(Self { values })
+    }
+}
+
+impl Config for KeyVault {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values.get(key).cloned()
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.values.get(key).map(|v| Cow::Borrowed(v.as_str()))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+}
+
+impl fmt::Debug for KeyVault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redact::debug_secrets(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::azure::{check_vault, fetch_token, AppConfiguration, Credential, KeyVault};
+    use crate::http::serve;
+    use crate::{Config, Error};
+
+    #[test]
+    fn app_configuration() {
+        let (url, server) = serve(4, |request| {
+            let body = if request.contains("/secrets/db-password") {
+                r#"{"value": "hunter2"}"#.to_string()
+            } else if request.contains("after=1") {
+                r#"{"items": [{"key": "app:db:port", "label": "base", "value": "5432"}]}"#
+                    .to_string()
+            } else if request.contains("label=base") {
+                r#"{"items": [
+                    {"key": "app:db:host", "label": "base", "value": "base"},
+                    {"key": "app:db:password", "label": "base", "value": "{\"uri\": \"{url}/secrets/db-password\"}",
+                     "content_type": "application/vnd.microsoft.appconfig.keyvaultref+json;charset=utf-8"}
+                ], "@nextLink": "/kv?after=1"}"#
+                    .to_string()
+            } else {
+                r#"{"items": [{"key": "app:db:host", "label": "prod", "value": "prod"}]}"#
+                    .to_string()
+            };
+            (200, body)
+        });
+        let cfg = AppConfiguration::builder(&url, Credential::Token("t".to_string()))
+            .label("base")
+            .label("prod")
+            .prefix("app:")
+            .vault(&format!("{}/", url))
+            .build();
+        let requests = server.join().unwrap();
+        assert!(requests[0][0].contains("key=app%3A%2A&label=base"));
+        assert!(requests.iter().all(|r| r
+            .iter()
+            .any(|h| h.to_lowercase() == "authorization: bearer t")));
+        let cfg = cfg.unwrap();
+        assert_eq!(cfg.string("db.host"), "prod");
+        assert_eq!(cfg.int("db.port"), 5432);
+        assert_eq!(cfg.string("db.password"), "hunter2");
+    }
+
+    #[test]
+    fn foreign_vault() {
+        let (url, server) = serve(1, |_| {
+            let body = r#"{"items": [
+                {"key": "db:password", "value": "{\"uri\": \"{url}/secrets/db-password\"}",
+                 "content_type": "application/vnd.microsoft.appconfig.keyvaultref+json;charset=utf-8"}
+            ]}"#;
+            (200, body.to_string())
+        });
+        let cfg = AppConfiguration::builder(&url, Credential::Token("t".to_string())).build();
+        assert_eq!(server.join().unwrap().len(), 1);
+        assert_eq!(
+            cfg,
+            Err(Error::Remote(format!(
+                "{}/secrets/db-password: not a secret of an allowed Key Vault",
+                url
+            )))
+        );
+
+        let vaults = ["https://app.vault.azure.net".to_string()];
+        for (uri, vaults, ok) in [
+            ("https://app.vault.azure.net/secrets/a", &[][..], true),
+            ("https://APP.Vault.Azure.Net/secrets/a/1", &[], true),
+            ("http://app.vault.azure.net/secrets/a", &[], false),
+            ("https://evil.example/secrets/a", &[], false),
+            (
+                "https://evil.example/x.vault.azure.net/secrets/a",
+                &[],
+                false,
+            ),
+            (
+                "https://app.vault.azure.net.evil.example/secrets/a",
+                &[],
+                false,
+            ),
+            (
+                "https://app.vault.azure.net@evil.example/secrets/a",
+                &[],
+                false,
+            ),
+            ("https://app.vault.azure.net:8443/secrets/a", &[], false),
+            ("https://app.vault.azure.net/keys/a", &[], false),
+            ("https://app.vault.azure.net/secrets/a?x=1", &[], false),
+            ("https://app.vault.azure.net/secrets/a", &vaults[..], true),
+            (
+                "https://other.vault.azure.net/secrets/a",
+                &vaults[..],
+                false,
+            ),
+        ] {
+            assert_eq!(check_vault(uri, vaults).is_ok(), ok, "{}", uri);
+        }
+    }
+
+    #[test]
+    fn key_vault() {
+        let (url, server) = serve(4, |request| {
+            let body = if request.contains("page=2") {
+                r#"{"value": [{"id": "{url}/secrets/token"}]}"#
+            } else if request.contains("/secrets?") {
+                r#"{"value": [
+                    {"id": "{url}/secrets/db--password"},
+                    {"id": "{url}/secrets/old", "attributes": {"enabled": false}}
+                ], "nextLink": "{url}/secrets?api-version=7.4&page=2"}"#
+            } else if request.contains("db--password") {
+                r#"{"value": "hunter2"}"#
+            } else {
+                r#"{"value": "abc"}"#
+            };
+            (200, body.to_string())
+        });
+        let cfg = KeyVault::load(&url, &Credential::Token("t".to_string()));
+        server.join().unwrap();
+        let cfg = cfg.unwrap();
+        assert_eq!(cfg.string("db.password"), "hunter2");
+        assert_eq!(cfg.string("token"), "abc");
+        assert_eq!(cfg.get("old"), None);
+    }
+
+    #[test]
+    fn managed_identity() {
+        let (url, server) = serve(1, |_| (200, r#"{"access_token": "t"}"#.to_string()));
+        assert_eq!(
+            fetch_token(&url, &[("Metadata", "true")]),
+            Ok("t".to_string())
+        );
+        assert!(server.join().unwrap()[0]
+            .iter()
+            .any(|h| h.to_lowercase() == "metadata: true"));
+    }
+}
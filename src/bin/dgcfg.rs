@@ -0,0 +1,197 @@
+//! Query, validate, compare and convert configs from the command
+//! line using the same parsers and layering applications use.
+
+use std::collections::HashMap;
+use std::process::exit;
+
+use dinglebit_config::redact::redact;
+use dinglebit_config::{
+    convert, diff, load_auto, Config, Environment, Format, MultiConfig, Schema,
+};
+
+const USAGE: &str = "usage: dgcfg <command> [options] [args]
+
+commands:
+  get KEY            print the value of KEY
+  list               print every key and value, with secrets redacted
+  validate SCHEMA    check the layers against a schema file
+  diff OLD NEW       print the differences between two files, with
+                     secrets redacted
+  convert FILE       write FILE in another format (requires -o)
+  init SCHEMA        write a starter config for a schema file
+
+options:
+  -f, --file FILE      add FILE as a layer; earlier files override later ones
+  -e, --env PREFIX     add the environment (with PREFIX) as the top layer
+  -o, --output FORMAT  write list/convert/init output as FORMAT (json, toml, ...)
+      --show-secrets   don't redact secrets in list and diff
+  -h, --help           print this message";
+
+#[derive(Default)]
+struct Args {
+    command: String,
+    files: Vec<String>,
+    env: Option<String>,
+    output: Option<Format>,
+    show_secrets: bool,
+    rest: Vec<String>,
+}
+
+fn parse_args(args: &[String]) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| match iter.next() {
+            Some(v) => Ok(v.to_string()),
+            None => Err(format!("{} requires a value", name)),
+        };
+        match arg.as_str() {
+            "-f" | "--file" => parsed.files.push(value(arg)?),
+            "-e" | "--env" => parsed.env = Some(value(arg)?),
+            "-o" | "--output" => {
+                let name = value(arg)?;
+                match Format::from_name(&name) {
+                    Some(f) => parsed.output = Some(f),
+                    None => return Err(format!("unknown format '{}'", name)),
+                }
+            }
+            "--show-secrets" => parsed.show_secrets = true,
+            "-h" | "--help" => return Err(String::new()),
+            a if a.starts_with('-') && a.len() > 1 => {
+                return Err(format!("unknown option '{}'", a))
+            }
+            a if parsed.command.is_empty() => parsed.command = a.to_string(),
+            a => parsed.rest.push(a.to_string()),
+        }
+    }
+    match parsed.command.is_empty() {
+        true => Err("missing command".to_string()),
+        false => Ok(parsed),
+    }
+}
+
+fn load(path: &str) -> Result<Box<dyn Config + Send + Sync>, String> {
+    load_auto(path).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Layer the environment and files the same way an application using
+/// `MultiConfig` would.
+fn layers(args: &Args) -> Result<MultiConfig, String> {
+    let mut configs: Vec<Box<dyn Config + Send + Sync>> = vec![];
+    if let Some(prefix) = &args.env {
+        configs.push(Box::new(Environment::new(prefix)));
+    }
+    for file in args.files.iter() {
+        configs.push(load(file)?);
+    }
+    Ok(MultiConfig::new(configs))
+}
+
+/// The values of the config with secrets redacted (see
+/// `redact::redact`), unless `--show-secrets` was given.
+fn shown(args: &Args, cfg: &dyn Config) -> HashMap<String, String> {
+    cfg.keys()
+        .into_iter()
+        .filter_map(|k| {
+            let value = cfg.get(&k)?;
+            let value = match args.show_secrets {
+                true => value,
+                false => redact(&k, &value).into_owned(),
+            };
+            Some((k, value))
+        })
+        .collect()
+}
+
+/// The positional argument at `i`, or an error naming it.
+fn arg<'a>(args: &'a Args, i: usize, name: &str) -> Result<&'a str, String> {
+    match args.rest.get(i) {
+        Some(a) => Ok(a),
+        None => Err(format!("{} requires {}", args.command, name)),
+    }
+}
+
+/// Run the command, returning the exit code.
+fn run(args: &Args) -> Result<i32, String> {
+    match args.command.as_str() {
+        "get" => {
+            let key = arg(args, 0, "a key")?;
+            match layers(args)?.get(key) {
+                Some(value) => {
+                    println!("{}", value);
+                    Ok(0)
+                }
+                None => {
+                    eprintln!("{}: not found", key);
+                    Ok(1)
+                }
+            }
+        }
+        "list" => {
+            let cfg = shown(args, &layers(args)?);
+            print!("{}", convert(&cfg, args.output.unwrap_or(Format::Simple)));
+            Ok(0)
+        }
+        "validate" => {
+            let schema = load(arg(args, 0, "a schema")?)?;
+            let schema = Schema::from_config(schema.as_ref()).map_err(|e| e.to_string())?;
+            match schema.validate(&layers(args)?) {
+                Ok(()) => Ok(0),
+                Err(errors) => {
+                    for e in errors.iter() {
+                        eprintln!("{}", e);
+                    }
+                    Ok(1)
+                }
+            }
+        }
+        "diff" => {
+            let old = load(arg(args, 0, "two files")?)?;
+            let new = load(arg(args, 1, "two files")?)?;
+            let changes = diff(old.as_ref(), new.as_ref());
+            for change in changes.iter() {
+                match args.show_secrets {
+                    true => println!("{}", change),
+                    false => println!("{}", change.redacted()),
+                }
+            }
+            Ok(if changes.is_empty() { 0 } else { 1 })
+        }
+        "convert" => {
+            let cfg = load(arg(args, 0, "a file")?)?;
+            match args.output {
+                Some(format) => {
+                    print!("{}", convert(cfg.as_ref(), format));
+                    Ok(0)
+                }
+                None => Err("convert requires --output".to_string()),
+            }
+        }
+        "init" => {
+            let schema = load(arg(args, 0, "a schema")?)?;
+            let schema = Schema::from_config(schema.as_ref()).map_err(|e| e.to_string())?;
+            let starter = schema
+                .scaffold(args.output.unwrap_or(Format::Simple))
+                .map_err(|e| e.to_string())?;
+            print!("{}", starter);
+            Ok(0)
+        }
+        c => Err(format!("unknown command '{}'", c)),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let code = match parse_args(&args).and_then(|args| run(&args)) {
+        Ok(code) => code,
+        Err(e) if e.is_empty() => {
+            println!("{}", USAGE);
+            0
+        }
+        Err(e) => {
+            eprintln!("dgcfg: {}\n\n{}", e, USAGE);
+            2
+        }
+    };
+    exit(code);
+}
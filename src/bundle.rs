@@ -0,0 +1,314 @@
+//! Config packs: a tar or zip archive of config files loaded as one
+//! layered source, so an entire environment's configuration can be
+//! distributed as a single file.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::{Config, Error, Format, MultiConfig, Value, ValueError};
+
+/// The name of the manifest at the root of an archive.
+pub const MANIFEST: &str = "MANIFEST";
+
+/// The config files of an archive, layered so later files override
+/// earlier ones.
+///
+/// If the archive has a `MANIFEST` at its root, it lists the files to
+/// load, one per line, in the order they are applied. Blank lines and
+/// lines starting with `#` are ignored, and files that aren't listed
+/// aren't loaded. Without a manifest, every file is loaded in name
+/// order (e.g. `00-base.toml`, then `10-prod.toml`). The format of each
+/// file comes from its name (see `Format::from_path`), or is sniffed if
+/// the name doesn't say.
+///
+/// The whole archive is read before any of it is used, so a missing or
+/// broken file fails the load instead of leaving part of it applied.
+/// Files that aren't loaded aren't decoded either, so an archive can
+/// carry e.g. images or certificates alongside the config. Errors name
+/// the file of the archive they come from (`pack.tar/prod.toml`).
+pub struct Bundle {
+    config: MultiConfig,
+    files: Vec<String>,
+}
+
+impl Bundle {
+    /// Load the tar archive at the given path. Archives ending in
+    /// `.tar.gz` or `.tgz` are decompressed first.
+    pub fn from_tar<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| Error::file(path, &e))?;
+        let name = path.to_string_lossy();
+        let entries = if name.ends_with(".gz") || name.ends_with(".tgz") {
+            read_tar(flate2::read::GzDecoder::new(file))
+        } else {
+            read_tar(file)
+        };
+        Self::from_entries(path, entries.map_err(|e| Error::file(path, &e))?)
+    }
+
+    /// Load the zip archive at the given path.
+    pub fn from_zip<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| Error::file(path, &e))?;
+        let entries = read_zip(file).map_err(|e| Error::file(path, &e))?;
+        Self::from_entries(path, entries)
+    }
+
+    /// The files that were loaded, in the order they were applied.
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    fn from_entries(path: &Path, mut entries: BTreeMap<String, Vec<u8>>) -> Result<Self, Error> {
+        let files: Vec<String> = match entries.remove(MANIFEST) {
+            Some(manifest) => decode(path, MANIFEST, &manifest)?
+                .lines()
+                .map(|l| l.trim())
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(|l| l.trim_start_matches("./").to_string())
+                .collect(),
+            None => entries.keys().cloned().collect(),
+        };
+
+        let mut configs = Vec::with_capacity(files.len());
+        for name in files.iter().rev() {
+            let s = match entries.get(name) {
+                Some(bytes) => decode(path, name, bytes)?,
+                None => {
+                    return Err(Error::File {
+                        path: path.join(name),
+                        kind: io::ErrorKind::NotFound,
+                    })
+                }
+            };
+            let format = Format::from_path(name).unwrap_or_else(|| Format::sniff(s));
+            configs.push(
+                format
+                    .load_str(s)
+                    .map_err(|e| in_file(&path.join(name), e))?,
+            );
+        }
+
+        Ok(Self {
+            config: MultiConfig::new(configs),
+            files,
+        })
+    }
+}
+
+/// The contents of the file of the archive as text.
+fn decode<'a>(path: &Path, name: &str, bytes: &'a [u8]) -> Result<&'a str, Error> {
+    std::str::from_utf8(bytes).map_err(|_| Error::File {
+        path: path.join(name),
+        kind: io::ErrorKind::InvalidData,
+    })
+}
+
+/// The error with the file of the archive it came from.
+fn in_file(file: &Path, e: Error) -> Error {
+    let at = |m: &str| format!("{}: {}", file.display(), m);
+    match e {
+        Error::InvalidKeyValuePair => Error::Parse(at("invalid key/value pair")),
+        Error::Parse(m) => Error::Parse(at(&m)),
+        Error::Eval(m) => Error::Eval(at(&m)),
+        Error::Unsupported(m) => Error::Unsupported(at(&m)),
+        e => e,
+    }
+}
+
+/// The regular files of a tar archive by their path.
+fn read_tar<R: Read>(reader: R) -> io::Result<BTreeMap<String, Vec<u8>>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = BTreeMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.to_string_lossy().to_string();
+        let mut bytes = vec![];
+        entry.read_to_end(&mut bytes)?;
+        entries.insert(name.trim_start_matches("./").to_string(), bytes);
+    }
+    Ok(entries)
+}
+
+/// The files of a zip archive by their path.
+fn read_zip(file: File) -> io::Result<BTreeMap<String, Vec<u8>>> {
+    let mut archive = zip::ZipArchive::new(file)?;
+    let mut entries = BTreeMap::new();
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        if !entry.is_file() {
+            continue;
+        }
+        let name = entry.name().to_string();
+        let mut bytes = vec![];
+        entry.read_to_end(&mut bytes)?;
+        entries.insert(name, bytes);
+    }
+    Ok(entries)
+}
+
+impl Config for Bundle {
+    fn get(&self, key: &str) -> Option<String> {
+        self.config.get(key)
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.config.get_ref(key)
+    }
+
+    fn get_value(&self, key: &str) -> Option<Value> {
+        self.config.get_value(key)
+    }
+
+    fn try_get_ref(&self, key: &str) -> Result<Cow<'_, str>, ValueError> {
+        self.config.try_get_ref(key)
+    }
+
+    fn get_many(&self, keys: &[&str]) -> Vec<Option<String>> {
+        self.config.get_many(keys)
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.config.keys()
+    }
+}
+
+impl fmt::Debug for Bundle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Bundle")
+            .field("files", &self.files)
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bundle::Bundle;
+    use crate::testing::TempDir;
+    use crate::{Config, Error};
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    fn tar<C: AsRef<[u8]>>(dir: &TempDir, name: &str, files: &[(&str, C)]) -> PathBuf {
+        let path = dir.join(name);
+        let mut builder = tar::Builder::new(std::fs::File::create(&path).unwrap());
+        for (name, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.as_ref().len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, contents.as_ref())
+                .unwrap();
+        }
+        builder.finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn from_tar() {
+        let dir = TempDir::new("bundle-tar");
+        let path = tar(
+            &dir,
+            "sorted.tar",
+            &[
+                ("10-prod.cfg", "host = prod\n"),
+                ("00-base.ini", "host = base\nport = 80\n"),
+            ],
+        );
+        let cfg = Bundle::from_tar(&path).unwrap();
+        assert_eq!(cfg.files(), &["00-base.ini", "10-prod.cfg"]);
+        assert_eq!(cfg.string("host"), "prod");
+        assert_eq!(cfg.int("port"), 80);
+
+        let path = tar(
+            &dir,
+            "manifest.tar",
+            &[
+                ("MANIFEST", "# applied in order\n./prod.cfg\n\nbase.cfg\n"),
+                ("base.cfg", "host = base\nport = 80\n"),
+                ("prod.cfg", "host = prod\n"),
+                ("ignored.cfg", "ignored = true\n"),
+            ],
+        );
+        let cfg = Bundle::from_tar(&path).unwrap();
+        assert_eq!(cfg.files(), &["prod.cfg", "base.cfg"]);
+        assert_eq!(cfg.string("host"), "base");
+        assert_eq!(cfg.get("ignored"), None);
+
+        let path = tar(
+            &dir,
+            "missing.tar",
+            &[
+                ("MANIFEST", "base.cfg\nprod.cfg\n"),
+                ("base.cfg", "a = b\n"),
+            ],
+        );
+        assert!(matches!(
+            Bundle::from_tar(&path),
+            Err(Error::File { path, kind: std::io::ErrorKind::NotFound }) if path.ends_with("prod.cfg")
+        ));
+    }
+
+    #[test]
+    fn errors() {
+        let dir = TempDir::new("bundle-errors");
+        let png: &[u8] = b"\x89PNG\r\n";
+        let path = tar(
+            &dir,
+            "binary.tar",
+            &[
+                ("MANIFEST", &b"base.cfg\n"[..]),
+                ("base.cfg", b"host = base\n"),
+                ("logo.png", png),
+            ],
+        );
+        assert_eq!(Bundle::from_tar(&path).unwrap().string("host"), "base");
+
+        let path = tar(
+            &dir,
+            "invalid.tar",
+            &[("base.cfg", &b"host = base\n"[..]), ("bad.cfg", png)],
+        );
+        assert!(matches!(
+            Bundle::from_tar(&path),
+            Err(Error::File { path, kind: std::io::ErrorKind::InvalidData }) if path.ends_with("bad.cfg")
+        ));
+
+        let path = tar(&dir, "broken.tar", &[("broken.cfg", "host\n")]);
+        match Bundle::from_tar(&path) {
+            Err(Error::Parse(m)) => assert!(
+                m.ends_with("broken.tar/broken.cfg: invalid key/value pair"),
+                "{}",
+                m
+            ),
+            r => panic!("{:?}", r.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn from_zip() {
+        let dir = TempDir::new("bundle-zip");
+        let path = dir.join("bundle.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("MANIFEST", options).unwrap();
+        zip.write_all(b"base.cfg\nprod.env\n").unwrap();
+        zip.start_file("base.cfg", options).unwrap();
+        zip.write_all(b"host = base\n").unwrap();
+        zip.start_file("prod.env", options).unwrap();
+        zip.write_all(b"HOST=prod\n").unwrap();
+        zip.finish().unwrap();
+
+        let cfg = Bundle::from_zip(&path).unwrap();
+        assert_eq!(cfg.string("host"), "prod");
+    }
+}
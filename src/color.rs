@@ -0,0 +1,199 @@
+//! Colors for user preferences in desktop and terminal apps.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+/// What the errors for colors say was expected.
+pub(crate) const EXPECTED: &str = "color like `#ff8800` or `rgb(255, 136, 0)`";
+
+/// The named colors: the basic CSS colors and a few common extras.
+const NAMED: &[(&str, [u8; 3])] = &[
+    ("aqua", [0, 255, 255]),
+    ("black", [0, 0, 0]),
+    ("blue", [0, 0, 255]),
+    ("cyan", [0, 255, 255]),
+    ("fuchsia", [255, 0, 255]),
+    ("gray", [128, 128, 128]),
+    ("green", [0, 128, 0]),
+    ("grey", [128, 128, 128]),
+    ("lime", [0, 255, 0]),
+    ("magenta", [255, 0, 255]),
+    ("maroon", [128, 0, 0]),
+    ("navy", [0, 0, 128]),
+    ("olive", [128, 128, 0]),
+    ("orange", [255, 165, 0]),
+    ("purple", [128, 0, 128]),
+    ("red", [255, 0, 0]),
+    ("silver", [192, 192, 192]),
+    ("teal", [0, 128, 128]),
+    ("white", [255, 255, 255]),
+    ("yellow", [255, 255, 0]),
+];
+
+/// A color with an alpha channel, read with `Config::color`. It can be
+/// written as:
+///
+/// - hex: `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`,
+/// - `rgb(r, g, b)` or `rgba(r, g, b, a)`, where the channels are
+///   0-255 and the alpha is 0.0-1.0 (either may be a percentage),
+/// - a name: the basic CSS colors (`red`, `navy`, `silver`, ...),
+///   `orange`, `cyan`, `magenta`, `grey` and `transparent`.
+///
+/// ```
+/// use dinglebit_config::{Color, Config};
+/// use std::collections::HashMap;
+///
+/// let cfg = HashMap::from([
+///     ("theme.accent", "#ff8800"),
+///     ("theme.shadow", "rgba(0, 0, 0, 0.5)"),
+/// ]);
+/// assert_eq!(cfg.color("theme.accent"), Color::rgb(255, 136, 0));
+/// assert_eq!(cfg.color("theme.shadow").a, 128);
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Color {
+    /// The red channel.
+    pub r: u8,
+
+    /// The green channel.
+    pub g: u8,
+
+    /// The blue channel.
+    pub b: u8,
+
+    /// The opacity, where 0 is transparent and 255 is opaque.
+    pub a: u8,
+}
+
+impl Color {
+    /// An opaque color.
+    pub fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self::rgba(r, g, b, 255)
+    }
+
+    /// A color with the given opacity.
+    pub fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Parse a color, returning `None` if it's malformed or an unknown
+    /// name. Names and hex digits aren't case sensitive.
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        if let Some(hex) = s.strip_prefix('#') {
+            return Self::hex(hex);
+        }
+        let lower = s.to_ascii_lowercase();
+        if let Some(args) = lower
+            .strip_suffix(')')
+            .and_then(|l| l.strip_prefix("rgba(").or_else(|| l.strip_prefix("rgb(")))
+        {
+            let parts: Vec<&str> = args.split(',').map(str::trim).collect();
+            let alpha = match parts.len() {
+                3 => 255,
+                4 => channel(parts[3], 1.0)?,
+                _ => return None,
+            };
+            return Some(Self::rgba(
+                channel(parts[0], 255.0)?,
+                channel(parts[1], 255.0)?,
+                channel(parts[2], 255.0)?,
+                alpha,
+            ));
+        }
+        if lower == "transparent" {
+            return Some(Self::rgba(0, 0, 0, 0));
+        }
+        NAMED
+            .iter()
+            .find(|(name, _)| *name == lower)
+            .map(|(_, [r, g, b])| Self::rgb(*r, *g, *b))
+    }
+
+    fn hex(hex: &str) -> Option<Self> {
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok();
+        let pair = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        match hex.len() {
+            3 | 4 => {
+                let short = |i| digit(i).map(|d| d * 17);
+                let a = match hex.len() {
+                    4 => short(3)?,
+                    _ => 255,
+                };
+                Some(Self::rgba(short(0)?, short(1)?, short(2)?, a))
+            }
+            6 | 8 => {
+                let a = match hex.len() {
+                    8 => pair(6)?,
+                    _ => 255,
+                };
+                Some(Self::rgba(pair(0)?, pair(2)?, pair(4)?, a))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Parse a channel of `rgb(...)` where `max` is full intensity, or a
+/// percentage of it.
+fn channel(s: &str, max: f64) -> Option<u8> {
+    let fraction = match s.strip_suffix('%') {
+        Some(p) => p.trim().parse::<f64>().ok()? / 100.0,
+        None => s.parse::<f64>().ok()? / max,
+    };
+    match (0.0..=1.0).contains(&fraction) {
+        true => Some((fraction * 255.0 + 0.5) as u8),
+        false => None,
+    }
+}
+
+/// Renders the color as `#rrggbb`, or `#rrggbbaa` if it isn't opaque.
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)?;
+        if self.a != 255 {
+            write!(f, "{:02x}", self.a)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::Color;
+
+    #[test]
+    fn parse() {
+        let orange = Some(Color::rgb(255, 136, 0));
+        assert_eq!(Color::parse("#ff8800"), orange);
+        assert_eq!(Color::parse("#FF8800"), orange);
+        assert_eq!(Color::parse("#f80"), orange);
+        assert_eq!(Color::parse("rgb(255, 136, 0)"), orange);
+        assert_eq!(Color::parse("RGB(100%, 53.3%, 0%)"), orange);
+        assert_eq!(Color::parse("#f808"), Some(Color::rgba(255, 136, 0, 136)));
+        assert_eq!(
+            Color::parse("#ff880080"),
+            Some(Color::rgba(255, 136, 0, 128))
+        );
+        assert_eq!(
+            Color::parse("rgba(255, 136, 0, 0.5)"),
+            Some(Color::rgba(255, 136, 0, 128))
+        );
+        assert_eq!(Color::parse(" Navy "), Some(Color::rgb(0, 0, 128)));
+        assert_eq!(Color::parse("transparent"), Some(Color::rgba(0, 0, 0, 0)));
+
+        assert_eq!(Color::parse("#ff88"), Some(Color::rgba(255, 255, 136, 136)));
+        assert_eq!(Color::parse("#ff88000"), None);
+        assert_eq!(Color::parse("#gg8800"), None);
+        assert_eq!(Color::parse("rgb(256, 0, 0)"), None);
+        assert_eq!(Color::parse("rgb(0, 0)"), None);
+        assert_eq!(Color::parse("rgba(0, 0, 0, 2)"), None);
+        assert_eq!(Color::parse("chartreuse"), None);
+
+        assert_eq!(Color::rgb(255, 136, 0).to_string(), "#ff8800");
+        assert_eq!(Color::rgba(255, 136, 0, 128).to_string(), "#ff880080");
+    }
+}
@@ -0,0 +1,340 @@
+//! Wrappers for composing configs without a `MultiConfig`, created
+//! with the combinator methods of `Config` (e.g. `Config::or`).
+
+use std::borrow::Cow;
+
+use crate::{suggest, Config, Defaults, Error, SourceHealth};
+
+/// A config that falls back to another for keys it doesn't have,
+/// created with `Config::or`.
+pub struct Or<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: Config, B: Config> Or<A, B> {
+    pub(crate) fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<A: Config, B: Config> Config for Or<A, B> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.first.get(key).or_else(|| self.second.get(key))
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.first.get_ref(key).or_else(|| self.second.get_ref(key))
+    }
+
+    /// If the first config fails, the value of the second is used, or
+    /// else the error of the first.
+    fn lookup(&self, key: &str) -> Result<Option<String>, Error> {
+        match self.first.lookup(key) {
+            Ok(Some(value)) => Ok(Some(value)),
+            Ok(None) => self.second.lookup(key),
+            Err(e) => match self.second.lookup(key) {
+                Ok(Some(value)) => Ok(Some(value)),
+                _ => Err(e),
+            },
+        }
+    }
+
+    /// The keys of both configs, without duplicates.
+    fn keys(&self) -> Vec<String> {
+        let mut keys = self.first.keys();
+        for key in self.second.keys() {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        keys
+    }
+
+    fn similar_keys(&self, key: &str) -> Vec<String> {
+        let mut keys = self.first.similar_keys(key);
+        keys.extend(self.second.similar_keys(key));
+        suggest::similar(key, &keys)
+    }
+
+    /// Ok when both configs are, down when both are, and degraded
+    /// otherwise, with the reasons of those that aren't ok.
+    fn source_health(&self) -> SourceHealth {
+        let both = [
+            (self.first.source_name(), self.first.source_health()),
+            (self.second.source_name(), self.second.source_health()),
+        ];
+        let reasons: Vec<String> = both
+            .iter()
+            .filter(|(_, health)| !health.is_ok())
+            .map(|(name, health)| format!("{}: {}", name, health))
+            .collect();
+        if reasons.is_empty() {
+            return SourceHealth::Ok;
+        }
+        match both.iter().all(|(_, health)| !health.is_ready()) {
+            true => SourceHealth::Down(reasons.join("; ")),
+            false => SourceHealth::Degraded(reasons.join("; ")),
+        }
+    }
+
+    fn source_of(&self, key: &str) -> Option<String> {
+        self.first
+            .source_of(key)
+            .or_else(|| self.second.source_of(key))
+    }
+}
+
+/// A config whose keys are read under a prefix, created with
+/// `Config::prefixed`.
+pub struct Prefixed<C> {
+    config: C,
+    prefix: String,
+}
+
+impl<C: Config> Prefixed<C> {
+    pub(crate) fn new(config: C, prefix: &str) -> Self {
+        Self {
+            config,
+            prefix: format!("{}.", prefix.trim_end_matches('.')),
+        }
+    }
+}
+
+impl<C: Config> Config for Prefixed<C> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.config.get(key.strip_prefix(&self.prefix)?)
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.config.get_ref(key.strip_prefix(&self.prefix)?)
+    }
+
+    fn lookup(&self, key: &str) -> Result<Option<String>, Error> {
+        match key.strip_prefix(&self.prefix) {
+            Some(key) => self.config.lookup(key),
+            None => Ok(None),
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.config
+            .keys()
+            .into_iter()
+            .map(|k| format!("{}{}", self.prefix, k))
+            .collect()
+    }
+
+    fn source_health(&self) -> SourceHealth {
+        self.config.source_health()
+    }
+
+    fn source_name(&self) -> String {
+        self.config.source_name()
+    }
+
+    fn source_of(&self, key: &str) -> Option<String> {
+        self.config.source_of(key.strip_prefix(&self.prefix)?)
+    }
+}
+
+/// A config that only has the keys a predicate accepts, created with
+/// `Config::filtered`.
+pub struct Filtered<C, F> {
+    config: C,
+    accept: F,
+}
+
+impl<C: Config, F: Fn(&str) -> bool> Filtered<C, F> {
+    pub(crate) fn new(config: C, accept: F) -> Self {
+        Self { config, accept }
+    }
+}
+
+impl<C: Config, F: Fn(&str) -> bool> Config for Filtered<C, F> {
+    fn get(&self, key: &str) -> Option<String> {
+        match (self.accept)(key) {
+            true => self.config.get(key),
+            false => None,
+        }
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        match (self.accept)(key) {
+            true => self.config.get_ref(key),
+            false => None,
+        }
+    }
+
+    fn lookup(&self, key: &str) -> Result<Option<String>, Error> {
+        match (self.accept)(key) {
+            true => self.config.lookup(key),
+            false => Ok(None),
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        let mut keys = self.config.keys();
+        keys.retain(|k| (self.accept)(k));
+        keys
+    }
+
+    fn source_health(&self) -> SourceHealth {
+        self.config.source_health()
+    }
+
+    fn source_name(&self) -> String {
+        self.config.source_name()
+    }
+
+    fn source_of(&self, key: &str) -> Option<String> {
+        match (self.accept)(key) {
+            true => self.config.source_of(key),
+            false => None,
+        }
+    }
+}
+
+/// A config whose keys are translated by a function before they're
+/// read, created with `Config::mapped_keys`.
+pub struct MappedKeys<C, F> {
+    config: C,
+    map: F,
+}
+
+impl<C: Config, F: Fn(&str) -> String> MappedKeys<C, F> {
+    pub(crate) fn new(config: C, map: F) -> Self {
+        Self { config, map }
+    }
+}
+
+impl<C: Config, F: Fn(&str) -> String> Config for MappedKeys<C, F> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.config.get(&(self.map)(key))
+    }
+
+    fn lookup(&self, key: &str) -> Result<Option<String>, Error> {
+        self.config.lookup(&(self.map)(key))
+    }
+
+    // The keys can't be listed, as the function can't be reversed.
+
+    fn source_health(&self) -> SourceHealth {
+        self.config.source_health()
+    }
+
+    fn source_name(&self) -> String {
+        self.config.source_name()
+    }
+
+    fn source_of(&self, key: &str) -> Option<String> {
+        self.config.source_of(&(self.map)(key))
+    }
+}
+
+/// A config with default values for some keys, created with
+/// `Config::with_default`. More defaults can be added with
+/// `with_default` on the wrapper, which doesn't wrap it again.
+pub struct WithDefault<C> {
+    config: C,
+    defaults: Defaults,
+}
+
+impl<C: Config> WithDefault<C> {
+    pub(crate) fn new(config: C, key: &str, value: String) -> Self {
+        Self {
+            config,
+            defaults: Defaults::new().set(key, value),
+        }
+    }
+
+    /// Add the default for another key.
+    pub fn with_default(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.defaults = self.defaults.set(key, value);
+        self
+    }
+}
+
+impl<C: Config> Config for WithDefault<C> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.config.get(key).or_else(|| self.defaults.get(key))
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.config
+            .get_ref(key)
+            .or_else(|| self.defaults.get_ref(key))
+    }
+
+    fn lookup(&self, key: &str) -> Result<Option<String>, Error> {
+        match self.config.lookup(key)? {
+            Some(value) => Ok(Some(value)),
+            None => Ok(self.defaults.get(key)),
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        let mut keys = self.config.keys();
+        for key in self.defaults.keys() {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        keys
+    }
+
+    fn source_health(&self) -> SourceHealth {
+        self.config.source_health()
+    }
+
+    fn source_name(&self) -> String {
+        self.config.source_name()
+    }
+
+    /// `Defaults` for the values that come from the defaults.
+    fn source_of(&self, key: &str) -> Option<String> {
+        self.config
+            .source_of(key)
+            .or_else(|| self.defaults.source_of(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Config, Simple};
+    use std::collections::HashMap;
+
+    #[test]
+    fn combinators() {
+        let env = HashMap::from([("db.host", "prod-db"), ("log.level", "warn")]);
+        let file = Simple::from_str("db.host = localhost\ndb.pool = 5\ntoken = x").unwrap();
+        let cfg = env.or(file);
+        assert_eq!(cfg.string("db.host"), "prod-db");
+        assert_eq!(cfg.int("db.pool"), 5);
+        assert_eq!(cfg.source_of("db.pool"), Some("Simple".to_string()));
+        assert_eq!(cfg.keys().len(), 4);
+
+        let db = HashMap::from([("host", "localhost"), ("port", "5432")]).prefixed("db");
+        assert_eq!(db.get("db.host"), Some("localhost".to_string()));
+        assert_eq!(db.get("host"), None);
+        let mut keys = db.keys();
+        keys.sort();
+        assert_eq!(keys, vec!["db.host", "db.port"]);
+
+        let public = cfg.filtered(|k| k != "token");
+        assert_eq!(public.get("token"), None);
+        assert_eq!(public.get("log.level"), Some("warn".to_string()));
+        assert!(!public.keys().contains(&"token".to_string()));
+
+        let upper = HashMap::from([("DB_HOST", "env-db")])
+            .mapped_keys(|k| k.replace('.', "_").to_uppercase());
+        assert_eq!(upper.get("db.host"), Some("env-db".to_string()));
+
+        let defaulted = HashMap::from([("a", "1")])
+            .with_default("a", "0")
+            .with_default("b", "2");
+        assert_eq!(defaulted.int("a"), 1);
+        assert_eq!(defaulted.int("b"), 2);
+        assert_eq!(defaulted.source_of("b"), Some("Defaults".to_string()));
+    }
+}
@@ -0,0 +1,130 @@
+//! Keys with values that only apply to some hosts or platforms.
+
+use std::borrow::Cow;
+use std::env::consts;
+
+use crate::Config;
+
+/// A view of a config where keys can have conditional variants,
+/// written as the key with an `@condition` suffix:
+///
+/// ```ini
+/// cache.dir = /var/cache/app
+/// cache.dir@windows = C:\cache\app
+/// cache.dir@host:build-03 = /scratch/cache
+/// ```
+///
+/// A get for `cache.dir` returns the variant for the first condition
+/// that applies and falls back to the plain key. The conditions are,
+/// in order: `host:<hostname>`, the operating system (`linux`,
+/// `macos`, `windows`, ...), the OS family (`unix` or `windows`) and
+/// the CPU architecture (`x86_64`, `aarch64`, ...).
+pub struct Conditional<'a> {
+    config: &'a dyn Config,
+    conditions: Vec<String>,
+}
+
+impl<'a> Conditional<'a> {
+    /// Create a view using the conditions of the current host.
+    pub fn new(config: &'a dyn Config) -> Self {
+        let mut conditions = vec![];
+        if let Some(host) = crate::sys::hostname() {
+            conditions.push(format!("host:{}", host));
+        }
+        conditions.push(consts::OS.to_string());
+        conditions.push(consts::FAMILY.to_string());
+        conditions.push(consts::ARCH.to_string());
+        Self::with_conditions(config, &conditions)
+    }
+
+    /// Create a view using the given conditions, tried in order.
+    pub fn with_conditions(config: &'a dyn Config, conditions: &[String]) -> Self {
+        Self {
+            config,
+            conditions: conditions.to_vec(),
+        }
+    }
+
+    /// The conditions that are tried, in order.
+    pub fn conditions(&self) -> &[String] {
+        &self.conditions
+    }
+}
+
+/// Split a key into its base and condition. An `@` at the start of a
+/// segment isn't a condition (e.g. the XML attribute `server.@port`).
+fn split(key: &str) -> Option<(&str, &str)> {
+    let (at, _) = key
+        .match_indices('@')
+        .find(|(i, _)| *i > 0 && !key[..*i].ends_with('.'))?;
+    Some((&key[..at], &key[at + 1..]))
+}
+
+impl Config for Conditional<'_> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.get_ref(key).map(|v| v.into_owned())
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.conditions
+            .iter()
+            .find_map(|c| self.config.get_ref(&format!("{}@{}", key, c)))
+            .or_else(|| self.config.get_ref(key))
+    }
+
+    /// The plain keys, including those that only have variants for
+    /// conditions that apply. Variants themselves are left out.
+    fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = vec![];
+        for key in self.config.keys() {
+            let key = match split(&key) {
+                Some((base, c)) if self.conditions.iter().any(|x| x == c) => base.to_string(),
+                Some(_) => continue,
+                None => key,
+            };
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        keys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::conditional::{split, Conditional};
+    use crate::Config;
+    use std::collections::HashMap;
+
+    #[test]
+    fn conditions() {
+        let cfg = HashMap::from([
+            ("cache.dir", "/var/cache"),
+            ("cache.dir@windows", "C:\\cache"),
+            ("cache.dir@host:build-03", "/scratch"),
+            ("cache.size@linux", "10"),
+            ("server.@port", "80"),
+        ]);
+        let conditions = vec!["host:build-03".to_string(), "linux".to_string()];
+        let c = Conditional::with_conditions(&cfg, &conditions);
+        assert_eq!(c.string("cache.dir"), "/scratch");
+        assert_eq!(c.int("cache.size"), 10);
+        assert_eq!(c.int("server.@port"), 80);
+
+        let mut keys = c.keys();
+        keys.sort();
+        assert_eq!(keys, vec!["cache.dir", "cache.size", "server.@port"]);
+
+        let conditions = vec!["macos".to_string()];
+        let c = Conditional::with_conditions(&cfg, &conditions);
+        assert_eq!(c.string("cache.dir"), "/var/cache");
+        assert!(c.get("cache.size").is_none());
+
+        let c = Conditional::new(&cfg);
+        assert!(c.conditions().iter().any(|x| x == std::env::consts::OS));
+
+        assert_eq!(split("a.b@linux"), Some(("a.b", "linux")));
+        assert_eq!(split("a@host:x.y"), Some(("a", "host:x.y")));
+        assert_eq!(split("a.@b"), None);
+    }
+}
@@ -0,0 +1,452 @@
+//! Write the values of a config in one of the supported formats,
+//! which is useful for migrating between formats or printing the
+//! effective configuration.
+
+use std::collections::BTreeMap;
+
+use crate::{Config, Format};
+
+/// The dot-notation keys of a config arranged as a tree.
+#[derive(Default)]
+struct Node {
+    value: Option<String>,
+    children: BTreeMap<String, Node>,
+}
+
+impl Node {
+    fn new(input: &dyn Config) -> Self {
+        let mut root = Node::default();
+        for key in input.keys() {
+            let value = match input.get(&key) {
+                Some(v) => v,
+                None => continue,
+            };
+            let mut node = &mut root;
+            for part in key.split('.') {
+                node = node.children.entry(part.to_string()).or_default();
+            }
+            node.value = Some(value);
+        }
+        root
+    }
+
+    /// The children of this node as either values or nested tables.
+    /// Hierarchical formats can't give a key both a value and
+    /// children, so the children of such a key are written at this
+    /// level with their full (quoted) dot-notation key instead, which
+    /// is flattened back into the same key when parsed.
+    fn items(&self) -> Vec<(String, Item<'_>)> {
+        let mut items = vec![];
+        for (name, child) in self.children.iter() {
+            match (&child.value, child.children.is_empty()) {
+                (Some(v), true) => items.push((name.to_string(), Item::Value(v))),
+                (None, _) => items.push((name.to_string(), Item::Table(child))),
+                (Some(v), false) => {
+                    items.push((name.to_string(), Item::Value(v)));
+                    for (key, v) in child.leaves(name) {
+                        items.push((key, Item::Value(v)));
+                    }
+                }
+            }
+        }
+        items
+    }
+
+    /// Every value below this node with its dot-notation key.
+    fn leaves(&self, prefix: &str) -> Vec<(String, &str)> {
+        let mut leaves = vec![];
+        for (name, child) in self.children.iter() {
+            let key = format!("{}.{}", prefix, name);
+            if let Some(v) = &child.value {
+                leaves.push((key.clone(), v.as_str()));
+            }
+            leaves.extend(child.leaves(&key));
+        }
+        leaves
+    }
+}
+
+enum Item<'a> {
+    Value(&'a str),
+    Table(&'a Node),
+}
+
+/// Quote a string using the escapes common to JSON, TOML and YAML.
+/// RON and KDL write unicode escapes with braces (`\u{7}`).
+pub(crate) fn quote(s: &str, braced: bool) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() && braced => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Whether the key can be written without quotes.
+fn bare(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Convert the values of the given config into the given format.
+/// Every value is written as a string, since that's how configs
+/// provide them, and nested keys are written as nested objects or
+/// sections where the format has them. The result can be parsed by
+/// the matching source to get the same values for the same keys.
+///
+/// Some formats can't represent everything: `Simple` and `Ini` trim
+/// values and can't contain newlines, `Env` can't tell `.` and `_`
+/// apart in keys and `Xml` wraps keys without a common root element
+/// in a `<config>` element.
+///
+/// ```
+/// use dinglebit_config::{convert, Format};
+/// use std::collections::HashMap;
+///
+/// let mut m = HashMap::new();
+/// m.insert("mongo.uri", "mongodb://localhost/");
+/// assert_eq!(
+///     convert(&m, Format::Env),
+///     "MONGO_URI=\"mongodb://localhost/\"\n"
+/// );
+/// ```
+pub fn convert(input: &dyn Config, format: Format) -> String {
+    let mut keys: Vec<String> = input.keys();
+    keys.sort();
+    let values: Vec<(String, String)> = keys
+        .into_iter()
+        .filter_map(|k| input.get(&k).map(|v| (k, v)))
+        .collect();
+
+    let mut out = String::new();
+    match format {
+        Format::Simple => {
+            for (k, v) in values.iter() {
+                out.push_str(&format!("{} = {}\n", k, v));
+            }
+        }
+        Format::Ini => ini(&values, &mut out),
+        Format::Env => {
+            for (k, v) in values.iter() {
+                let name = k.replace(['.', '/'], "_").to_uppercase();
+                out.push_str(&format!("{}={}\n", name, quote(v, false)));
+            }
+        }
+        Format::Properties => {
+            for (k, v) in values.iter() {
+                out.push_str(&format!(
+                    "{} = {}\n",
+                    properties_escape(k, true),
+                    properties_escape(v, false)
+                ));
+            }
+        }
+        Format::Json | Format::Hocon => {
+            json(&Node::new(input), 0, &mut out);
+            out.push('\n');
+        }
+        Format::Ron => {
+            ron(&Node::new(input), 0, &mut out);
+            out.push('\n');
+        }
+        Format::Toml => toml(&Node::new(input), &[], &mut out),
+        Format::Yaml => {
+            let root = Node::new(input);
+            match root.children.is_empty() {
+                true => out.push_str("{}\n"),
+                false => yaml(&root, 0, &mut out),
+            }
+        }
+        Format::Kdl => kdl(&Node::new(input), 0, &mut out),
+        Format::Xml => {
+            let root = Node::new(input);
+            match root.children.len() {
+                1 if root.children.keys().all(|k| bare(k) && !k.starts_with('@')) => {
+                    let (name, node) = root.children.iter().next().unwrap();
+                    xml(name, node, 0, &mut out);
+                }
+                _ => xml("config", &root, 0, &mut out),
+            }
+        }
+    }
+    out
+}
+
+fn ini(values: &[(String, String)], out: &mut String) {
+    // Quotes are only needed to keep whitespace or quotes that would
+    // otherwise be removed.
+    let value = |v: &str| {
+        let quoted =
+            (v.starts_with('"') && v.ends_with('"')) || (v.starts_with('\'') && v.ends_with('\''));
+        match v.trim() != v || (quoted && v.len() >= 2) {
+            true => format!("\"{}\"", v),
+            false => v.to_string(),
+        }
+    };
+
+    let mut sections: BTreeMap<&str, Vec<(&str, &str)>> = BTreeMap::new();
+    for (k, v) in values.iter() {
+        let (section, key) = k.rsplit_once('.').unwrap_or(("", k));
+        sections.entry(section).or_default().push((key, v));
+    }
+    for (i, (section, pairs)) in sections.iter().enumerate() {
+        if !section.is_empty() {
+            if i > 0 {
+                out.push('\n');
+            }
+            out.push_str(&format!("[{}]\n", section));
+        }
+        for (k, v) in pairs.iter() {
+            out.push_str(&format!("{} = {}\n", k, value(v)));
+        }
+    }
+}
+
+pub(crate) fn properties_escape(s: &str, key: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for (i, c) in s.chars().enumerate() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ' ' if key || i == 0 => out.push_str("\\ "),
+            '=' | ':' | '#' | '!' if key => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn json(node: &Node, indent: usize, out: &mut String) {
+    let items = node.items();
+    if items.is_empty() {
+        out.push_str("{}");
+        return;
+    }
+    out.push_str("{\n");
+    for (i, (name, item)) in items.iter().enumerate() {
+        out.push_str(&format!("{:1$}{2}: ", "", indent + 2, quote(name, false)));
+        match item {
+            Item::Value(v) => out.push_str(&quote(v, false)),
+            Item::Table(t) => json(t, indent + 2, out),
+        }
+        if i + 1 < items.len() {
+            out.push(',');
+        }
+        out.push('\n');
+    }
+    out.push_str(&format!("{:1$}}}", "", indent));
+}
+
+fn ron(node: &Node, indent: usize, out: &mut String) {
+    let items = node.items();
+    if items.is_empty() {
+        out.push_str("{}");
+        return;
+    }
+    out.push_str("{\n");
+    for (name, item) in items.iter() {
+        out.push_str(&format!("{:1$}{2}: ", "", indent + 4, quote(name, true)));
+        match item {
+            Item::Value(v) => out.push_str(&quote(v, true)),
+            Item::Table(t) => ron(t, indent + 4, out),
+        }
+        out.push_str(",\n");
+    }
+    out.push_str(&format!("{:1$}}}", "", indent));
+}
+
+fn toml(node: &Node, path: &[String], out: &mut String) {
+    let key = |k: &str| match bare(k) {
+        true => k.to_string(),
+        false => quote(k, false),
+    };
+
+    let items = node.items();
+    let mut tables = vec![];
+    let mut header = !path.is_empty();
+    for (name, item) in items.iter() {
+        match item {
+            Item::Value(v) => {
+                if header {
+                    if !out.is_empty() {
+                        out.push('\n');
+                    }
+                    out.push_str(&format!("[{}]\n", path.join(".")));
+                    header = false;
+                }
+                out.push_str(&format!("{} = {}\n", key(name), quote(v, false)));
+            }
+            Item::Table(t) => tables.push((name, t)),
+        }
+    }
+    for (name, t) in tables {
+        let mut path = path.to_vec();
+        path.push(key(name));
+        toml(t, &path, out);
+    }
+}
+
+fn yaml(node: &Node, indent: usize, out: &mut String) {
+    // Plain keys that would be read as something other than a string
+    // (e.g. `null` or `1e3`) are quoted too.
+    let key = |k: &str| {
+        let special = matches!(k.to_lowercase().as_str(), "true" | "false" | "null");
+        match bare(k) && !k.starts_with(|c: char| c.is_ascii_digit() || c == '-') && !special {
+            true => k.to_string(),
+            false => quote(k, false),
+        }
+    };
+
+    for (name, item) in node.items() {
+        out.push_str(&format!("{:1$}{2}:", "", indent, key(&name)));
+        match item {
+            Item::Value(v) => out.push_str(&format!(" {}\n", quote(v, false))),
+            Item::Table(t) => {
+                out.push('\n');
+                yaml(t, indent + 2, out);
+            }
+        }
+    }
+}
+
+fn kdl(node: &Node, indent: usize, out: &mut String) {
+    for (name, item) in node.items() {
+        out.push_str(&format!("{:1$}{2}", "", indent, quote(&name, true)));
+        match item {
+            Item::Value(v) => out.push_str(&format!(" {}\n", quote(v, true))),
+            Item::Table(t) => {
+                out.push_str(" {\n");
+                kdl(t, indent + 4, out);
+                out.push_str(&format!("{:1$}}}\n", "", indent));
+            }
+        }
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn xml(name: &str, node: &Node, indent: usize, out: &mut String) {
+    // Indexed children (`listener.0`, `listener.1`) are repeated
+    // elements with the same name.
+    let indexed = node.value.is_none()
+        && node.children.len() > 1
+        && (0..node.children.len()).all(|i| node.children.contains_key(&i.to_string()));
+    if indexed {
+        for i in 0..node.children.len() {
+            xml(name, &node.children[&i.to_string()], indent, out);
+        }
+        return;
+    }
+
+    out.push_str(&format!("{:1$}<{2}", "", indent, name));
+    let mut elements = vec![];
+    for (k, child) in node.children.iter() {
+        match (k.strip_prefix('@'), &child.value) {
+            (Some(attr), Some(v)) if child.children.is_empty() => {
+                out.push_str(&format!(" {}=\"{}\"", attr, xml_escape(v)));
+            }
+            _ => elements.push((k, child)),
+        }
+    }
+
+    let text = node.value.as_deref().map(xml_escape);
+    match (text, elements.is_empty()) {
+        (None, true) => out.push_str("/>\n"),
+        (Some(text), true) => out.push_str(&format!(">{}</{}>\n", text, name)),
+        (text, false) => {
+            out.push_str(">\n");
+            if let Some(text) = text {
+                out.push_str(&format!("{:1$}{2}\n", "", indent + 2, text));
+            }
+            for (k, child) in elements {
+                xml(k, child, indent + 2, out);
+            }
+            out.push_str(&format!("{:1$}</{2}>\n", "", indent, name));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::convert::convert;
+    use crate::{Error, Format};
+    use std::collections::HashMap;
+
+    #[test]
+    fn json() {
+        let m = HashMap::from([("a", "1"), ("b.c", "x \"y\""), ("b.d", "[1, 2]")]);
+        assert_eq!(
+            convert(&m, Format::Json),
+            "{\n  \"a\": \"1\",\n  \"b\": {\n    \"c\": \"x \\\"y\\\"\",\n    \"d\": \"[1, 2]\"\n  }\n}\n"
+        );
+        assert_eq!(convert(&HashMap::<&str, &str>::new(), Format::Json), "{}\n");
+    }
+
+    #[test]
+    fn round_trip() {
+        let m = HashMap::from([
+            ("app.name", "main"),
+            ("app.mongo", "shadowed"),
+            ("app.mongo.uri", "mongodb://localhost/"),
+            ("app.mongo.port", "27017"),
+            ("app.@version", "2"),
+            ("app.tags.0", "a"),
+            ("app.tags.1", "b"),
+            ("app.motd", "say \"hi\" & \\ <bye>"),
+        ]);
+
+        for format in [
+            Format::Simple,
+            Format::Ini,
+            Format::Env,
+            Format::Properties,
+            Format::Json,
+            Format::Toml,
+            Format::Yaml,
+            Format::Hocon,
+            Format::Ron,
+            Format::Kdl,
+            Format::Xml,
+        ] {
+            let s = convert(&m, format);
+            let cfg = match format.load_str(&s) {
+                Ok(cfg) => cfg,
+                Err(Error::Unsupported(_)) => continue,
+                Err(e) => panic!("{}: {:?}\n{}", format.name(), e, s),
+            };
+            for (k, v) in m.iter() {
+                assert_eq!(
+                    cfg.get(k).as_deref(),
+                    Some(*v),
+                    "{}: {}\n{}",
+                    format.name(),
+                    k,
+                    s
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Configuration from systemd credentials (`LoadCredential=`,
+//! `SetCredential=` and friends).
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs::{read_dir, read_to_string};
+use std::path::Path;
+
+use crate::{redact, Config, Error};
+
+/// The credentials systemd passed to the service, one file per key in
+/// `$CREDENTIALS_DIRECTORY`. A unit with
+/// `LoadCredential=db.password:/etc/secrets/db` has the key
+/// `db.password`. A single trailing newline is removed from values.
+///
+/// Credentials keep secrets out of the environment, where they'd be
+/// inherited by child processes and visible in `/proc`, so prefer them
+/// over environment variables (see `Builder::env_with_credentials`).
+#[derive(PartialEq, Clone)]
+pub struct SystemdCredentials {
+    values: HashMap<String, String>,
+}
+
+impl SystemdCredentials {
+    /// Read the credentials in `$CREDENTIALS_DIRECTORY`. When it isn't
+    /// set (e.g. the process wasn't started by systemd), there are
+    /// none.
+    pub fn new() -> Result<Self, Error> {
+        match env::var_os("CREDENTIALS_DIRECTORY") {
+            Some(dir) => Self::from_dir(dir),
+            None => Ok(Self {
+                values: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Read the credentials in the given directory.
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Result<Self, Error> {
+        let dir = dir.as_ref();
+        let mut values = HashMap::new();
+        for entry in read_dir(dir).map_err(|e| Error::file(dir, &e))? {
+            let path = entry.map_err(|e| Error::file(dir, &e))?.path();
+            if !path.is_file() {
+                continue;
+            }
+            let value = read_to_string(&path).map_err(|e| Error::file(&path, &e))?;
+            let value = value
+                .strip_suffix('\n')
+                .map(|v| v.strip_suffix('\r').unwrap_or(v))
+                .unwrap_or(&value);
+            if let Some(name) = path.file_name() {
+                values.insert(name.to_string_lossy().to_string(), value.to_string());
+            }
+        }
+        Ok(Self { values })
+    }
+}
+
+impl Config for SystemdCredentials {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values.get(key).cloned()
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.values.get(key).map(|v| Cow::Borrowed(v.as_str()))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+}
+
+impl fmt::Debug for SystemdCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redact::debug_secrets(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::TempDir;
+    use crate::{Config, MultiConfig, SystemdCredentials};
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn from_dir() {
+        let dir = TempDir::new("credentials");
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("db.password"), "hunter2\n").unwrap();
+        fs::write(dir.join("api.key"), "abc\r\n").unwrap();
+        fs::write(dir.join("cert"), "line 1\nline 2\n\n").unwrap();
+
+        let cfg = SystemdCredentials::from_dir(dir.path()).unwrap();
+        assert_eq!(cfg.string("db.password"), "hunter2");
+        assert_eq!(cfg.string("api.key"), "abc");
+        assert_eq!(cfg.string("cert"), "line 1\nline 2\n");
+        assert_eq!(cfg.keys().len(), 3);
+        assert!(SystemdCredentials::from_dir(dir.join("missing")).is_err());
+
+        env::set_var("CREDENTIALS_DIRECTORY", dir.path());
+        env::set_var("CREDS_TEST_DB_PASSWORD", "from-env");
+        env::set_var("CREDS_TEST_DB_USER", "app");
+        let cfg = MultiConfig::builder()
+            .env_with_credentials("creds_test")
+            .build()
+            .unwrap();
+        assert_eq!(cfg.string("db.password"), "hunter2");
+        assert_eq!(cfg.string("db.user"), "app");
+    }
+}
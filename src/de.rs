@@ -0,0 +1,709 @@
+//! A serde `Deserializer` over a `Value`, used to bind the keys under
+//! a prefix to a struct. Errors name the full key of the offending
+//! field.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use serde::Serialize;
+
+use crate::ser::to_value;
+use crate::value::Value;
+use crate::{Config, ValueError};
+
+/// Deserialize everything under the given prefix (e.g. `mongo` for
+/// `mongo.uri` and `mongo.pool.size`). See `Config::section`.
+pub(crate) fn section<T: DeserializeOwned>(
+    config: &dyn Config,
+    prefix: &str,
+) -> Result<T, ValueError> {
+    let root = collect(config, prefix).ok_or_else(|| ValueError::Missing(prefix.to_string()))?;
+    T::deserialize(ValueDeserializer {
+        path: prefix.to_string(),
+        value: root,
+    })
+    .map_err(|e| e.locate(prefix, None))
+}
+
+/// Deserialize the prefix again and update the fields of the target
+/// that changed, except the fixed ones, which keep their values. The
+/// names of the updated fields are returned. See `Config::rebind_into`.
+pub(crate) fn rebind<T: Serialize + DeserializeOwned>(
+    config: &dyn Config,
+    target: &mut T,
+    prefix: &str,
+    fixed: &[&str],
+) -> Result<Vec<String>, ValueError> {
+    let fresh: T = section(config, prefix)?;
+    let serialize = |value: &T| {
+        to_value(value).map_err(|e| ValueError::invalid(prefix, e.0, "serializable value"))
+    };
+    let (old, new) = (serialize(target)?, serialize(&fresh)?);
+    let (old, mut new) = match (old, new) {
+        (Value::Map(old), Value::Map(new)) => (old, new),
+        (old, new) => {
+            return match old == new {
+                true => Ok(vec![]),
+                false => {
+                    *target = fresh;
+                    Ok(vec![prefix.to_string()])
+                }
+            };
+        }
+    };
+    let mut changed = vec![];
+    let mut kept = false;
+    for (field, value) in new.iter_mut() {
+        if old.get(field) == Some(value) {
+            continue;
+        }
+        match fixed.contains(&field.as_str()) {
+            true => {
+                kept = true;
+                *value = old.get(field).cloned().unwrap_or(Value::Null);
+            }
+            false => changed.push(field.clone()),
+        }
+    }
+    if changed.is_empty() {
+        return Ok(changed);
+    }
+    *target = match kept {
+        false => fresh,
+        true => T::deserialize(ValueDeserializer {
+            path: prefix.to_string(),
+            value: Value::Map(new),
+        })
+        .map_err(|e| e.locate(prefix, None))?,
+    };
+    Ok(changed)
+}
+
+/// Build the tree of values under the prefix. Structured sources
+/// provide it directly and the keys of every source are layered on top
+/// so overrides (e.g. from the environment) apply.
+fn collect(config: &dyn Config, prefix: &str) -> Option<Value> {
+    let mut root = match prefix.is_empty() {
+        true => None,
+        false => config.get_value(prefix),
+    };
+    let start = match prefix.is_empty() {
+        true => String::new(),
+        false => format!("{}.", prefix),
+    };
+    for key in config.keys() {
+        let rest = match key.strip_prefix(&start) {
+            Some(rest) if !rest.is_empty() => rest,
+            _ => continue,
+        };
+        let value = match config.get_ref(&key) {
+            Some(value) => value,
+            None => continue,
+        };
+        // Keep plain strings as they are so `07` stays `07` for a
+        // string field; numbers are parsed when a field asks for one.
+        let value = match value.starts_with('[') || value.starts_with('{') {
+            true => Value::parse(&value),
+            false => Value::String(value.into_owned()),
+        };
+        insert(
+            root.get_or_insert_with(|| Value::Map(BTreeMap::new())),
+            rest,
+            value,
+        );
+    }
+    root
+}
+
+fn insert(node: &mut Value, key: &str, value: Value) {
+    if !matches!(node, Value::Map(_)) {
+        *node = Value::Map(BTreeMap::new());
+    }
+    if let Value::Map(m) = node {
+        match key.split_once('.') {
+            Some((first, rest)) => insert(
+                m.entry(first.to_string()).or_insert(Value::Null),
+                rest,
+                value,
+            ),
+            None => {
+                m.insert(key.to_string(), value);
+            }
+        }
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    match path.is_empty() {
+        true => key.to_string(),
+        false => format!("{}.{}", path, key),
+    }
+}
+
+/// Errors raised while deserializing. They are turned into a
+/// `ValueError` by the deserializer for the value they happened at,
+/// which knows the key.
+#[derive(Debug)]
+pub(crate) enum DeError {
+    Missing(String),
+    Expected(String),
+    Custom(String),
+    Located(ValueError),
+}
+
+impl DeError {
+    fn locate(self, path: &str, value: Option<&Value>) -> ValueError {
+        let value = || value.map(|v| v.to_string()).unwrap_or_default();
+        match self {
+            DeError::Located(e) => e,
+            DeError::Missing(field) => ValueError::Missing(join(path, &field)),
+            DeError::Expected(expected) | DeError::Custom(expected) => ValueError::Invalid {
+                key: path.to_string(),
+                value: value(),
+                expected,
+            },
+        }
+    }
+}
+
+impl fmt::Display for DeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeError::Missing(field) => write!(f, "missing field '{}'", field),
+            DeError::Expected(expected) => write!(f, "expected {}", expected),
+            DeError::Custom(msg) => write!(f, "{}", msg),
+            DeError::Located(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for DeError {}
+
+impl de::Error for DeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        DeError::Custom(msg.to_string())
+    }
+
+    fn invalid_type(_unexp: de::Unexpected, exp: &dyn de::Expected) -> Self {
+        DeError::Expected(exp.to_string())
+    }
+
+    fn invalid_value(_unexp: de::Unexpected, exp: &dyn de::Expected) -> Self {
+        DeError::Expected(exp.to_string())
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        DeError::Missing(field.to_string())
+    }
+}
+
+struct ValueDeserializer {
+    path: String,
+    value: Value,
+}
+
+impl ValueDeserializer {
+    fn child(&self, key: &str, value: Value) -> Self {
+        Self {
+            path: join(&self.path, key),
+            value,
+        }
+    }
+
+    /// Give errors raised here the key and value.
+    fn run<'de, V, F>(self, f: F) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+        F: FnOnce(Self) -> Result<V::Value, DeError>,
+    {
+        let (path, value) = (self.path.clone(), self.value.clone());
+        f(self).map_err(|e| DeError::Located(e.locate(&path, Some(&value))))
+    }
+
+    fn any<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.value {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::Int(i) => visitor.visit_i64(i),
+            Value::Float(f) => visitor.visit_f64(f),
+            Value::String(s) => visitor.visit_string(s),
+            Value::List(_) => self.seq(visitor),
+            Value::Map(m) => visitor.visit_map(MapAccess {
+                parent: self.path,
+                entries: m.into_iter(),
+                value: None,
+            }),
+        }
+    }
+
+    fn int<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        if let Value::String(s) = &self.value {
+            let s = s.trim();
+            if let Ok(i) = s.parse::<i64>() {
+                return visitor.visit_i64(i);
+            }
+            if let Ok(u) = s.parse::<u64>() {
+                return visitor.visit_u64(u);
+            }
+        }
+        self.any(visitor)
+    }
+
+    fn float<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match &self.value {
+            Value::Int(i) => visitor.visit_f64(*i as f64),
+            Value::String(s) => match s.trim().parse::<f64>() {
+                Ok(f) => visitor.visit_f64(f),
+                Err(_) => self.any(visitor),
+            },
+            _ => self.any(visitor),
+        }
+    }
+
+    /// Lists, maps keyed by index (e.g. `servers.0.host` from a flat
+    /// source) and comma-delimited strings are all sequences.
+    fn seq<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let items = match self.value {
+            Value::List(l) => l,
+            Value::Map(m) if m.keys().all(|k| k.parse::<usize>().is_ok()) => {
+                let mut entries: Vec<(usize, Value)> = m
+                    .into_iter()
+                    .map(|(k, v)| (k.parse().unwrap_or(0), v))
+                    .collect();
+                entries.sort_by_key(|(i, _)| *i);
+                entries.into_iter().map(|(_, v)| v).collect()
+            }
+            Value::String(s) => match Value::parse(&format!("[{}]", s)) {
+                Value::List(l) => l,
+                _ => vec![],
+            },
+            value => {
+                return Self { value, ..self }.any(visitor);
+            }
+        };
+        visitor.visit_seq(SeqAccess {
+            parent: self.path,
+            items: items.into_iter().enumerate(),
+        })
+    }
+}
+
+macro_rules! forward {
+    ($($method:ident => $via:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+                self.run::<V, _>(|d| d.$via(visitor))
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer {
+    type Error = DeError;
+
+    forward! {
+        deserialize_any => any,
+        deserialize_i8 => int,
+        deserialize_i16 => int,
+        deserialize_i32 => int,
+        deserialize_i64 => int,
+        deserialize_i128 => int,
+        deserialize_u8 => int,
+        deserialize_u16 => int,
+        deserialize_u32 => int,
+        deserialize_u64 => int,
+        deserialize_u128 => int,
+        deserialize_f32 => float,
+        deserialize_f64 => float,
+        deserialize_seq => seq,
+        deserialize_map => any,
+        deserialize_ignored_any => any,
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.run::<V, _>(|d| match &d.value {
+            Value::String(s) => match crate::parse_bool(s.trim()) {
+                Some(b) => visitor.visit_bool(b),
+                None => d.any(visitor),
+            },
+            _ => d.any(visitor),
+        })
+    }
+
+    /// Scalars of any kind can be read as strings.
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.run::<V, _>(|d| match d.value {
+            Value::Bool(_) | Value::Int(_) | Value::Float(_) => {
+                visitor.visit_string(d.value.to_string())
+            }
+            _ => d.any(visitor),
+        })
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.run::<V, _>(|d| match d.value {
+            Value::Null => visitor.visit_unit(),
+            _ => d.any(visitor),
+        })
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_map(visitor)
+    }
+
+    /// Unit variants are strings and variants with data are maps with
+    /// a single key, the way `Defaults::from_struct` writes them.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.run::<V, _>(|d| match d.value {
+            Value::String(s) => visitor.visit_enum(s.trim().to_string().into_deserializer()),
+            Value::Map(ref m) if m.len() == 1 => {
+                let (variant, value) = m
+                    .iter()
+                    .next()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .unwrap();
+                let value = d.child(&variant, value);
+                visitor.visit_enum(EnumAccess { variant, value })
+            }
+            _ => d.any(visitor),
+        })
+    }
+}
+
+struct SeqAccess {
+    parent: String,
+    items: std::iter::Enumerate<std::vec::IntoIter<Value>>,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess {
+    type Error = DeError;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, DeError> {
+        match self.items.next() {
+            Some((i, value)) => seed
+                .deserialize(ValueDeserializer {
+                    path: join(&self.parent, &i.to_string()),
+                    value,
+                })
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+struct MapAccess {
+    parent: String,
+    entries: std::collections::btree_map::IntoIter<String, Value>,
+    value: Option<(String, Value)>,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess {
+    type Error = DeError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, DeError> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some((key.clone(), value));
+                seed.deserialize(key.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, DeError> {
+        match self.value.take() {
+            Some((key, value)) => seed.deserialize(ValueDeserializer {
+                path: join(&self.parent, &key),
+                value,
+            }),
+            None => Err(DeError::Custom("map value without a key".to_string())),
+        }
+    }
+}
+
+struct EnumAccess {
+    variant: String,
+    value: ValueDeserializer,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess {
+    type Error = DeError;
+    type Variant = ValueDeserializer;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, ValueDeserializer), DeError> {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((variant, self.value))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for ValueDeserializer {
+    type Error = DeError;
+
+    fn unit_variant(self) -> Result<(), DeError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, DeError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, DeError> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Config, MultiConfig, ValueError};
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Pool {
+        size: u16,
+        timeout: Option<f64>,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Mode {
+        Primary,
+        Secondary,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Mongo {
+        uri: String,
+        db: String,
+        debug: bool,
+        hosts: Vec<String>,
+        mode: Mode,
+        pool: Pool,
+    }
+
+    #[test]
+    fn section() {
+        let env = HashMap::from([("mongo.pool.size", "20")]);
+        let file = HashMap::from([
+            ("mongo.uri", "mongodb://"),
+            ("mongo.db", "07"),
+            ("mongo.debug", "yes"),
+            ("mongo.hosts", "[a, b]"),
+            ("mongo.mode", "secondary"),
+            ("mongo.pool.size", "10"),
+            ("other", "x"),
+        ]);
+        let cfg = MultiConfig::new(vec![Box::new(env), Box::new(file)]);
+        assert_eq!(
+            cfg.section::<Mongo>("mongo"),
+            Ok(Mongo {
+                uri: "mongodb://".to_string(),
+                db: "07".to_string(),
+                debug: true,
+                hosts: vec!["a".to_string(), "b".to_string()],
+                mode: Mode::Secondary,
+                pool: Pool {
+                    size: 20,
+                    timeout: None,
+                },
+            })
+        );
+
+        let flat = HashMap::from([("pool.size", "1"), ("pool.timeout", "1.5")]);
+        assert_eq!(
+            flat.section::<Pool>("pool"),
+            Ok(Pool {
+                size: 1,
+                timeout: Some(1.5),
+            })
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let cfg = HashMap::from([("mongo.pool.size", "big"), ("mongo.pool.timeout", "1")]);
+        assert_eq!(
+            cfg.section::<Pool>("mongo.pool"),
+            Err(ValueError::Invalid {
+                key: "mongo.pool.size".to_string(),
+                value: "big".to_string(),
+                expected: "u16".to_string(),
+            })
+        );
+        let partial = HashMap::from([
+            ("mongo.uri", "mongodb://"),
+            ("mongo.db", "test"),
+            ("mongo.debug", "no"),
+            ("mongo.hosts", "a"),
+            ("mongo.mode", "primary"),
+            ("mongo.pool.timeout", "1"),
+        ]);
+        assert_eq!(
+            partial.section::<Mongo>("mongo"),
+            Err(ValueError::Missing("mongo.pool.size".to_string()))
+        );
+        assert_eq!(
+            HashMap::from([("pool.size", "70000")]).section::<Pool>("pool"),
+            Err(ValueError::Invalid {
+                key: "pool.size".to_string(),
+                value: "70000".to_string(),
+                expected: "u16".to_string(),
+            })
+        );
+        assert_eq!(
+            cfg.section::<Pool>("missing"),
+            Err(ValueError::Missing("missing".to_string()))
+        );
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Server {
+        port: u16,
+        log_level: String,
+        limits: Limits,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Limits {
+        rps: u32,
+        burst: u32,
+    }
+
+    #[test]
+    fn rebind() {
+        let before = HashMap::from([
+            ("server.port", "8080"),
+            ("server.log_level", "info"),
+            ("server.limits.rps", "100"),
+            ("server.limits.burst", "10"),
+        ]);
+        let mut server: Server = before.section("server").unwrap();
+        assert_eq!(before.rebind_into(&mut server, "server"), Ok(vec![]));
+
+        let after = HashMap::from([
+            ("server.port", "9090"),
+            ("server.log_level", "debug"),
+            ("server.limits.rps", "200"),
+            ("server.limits.burst", "10"),
+        ]);
+        assert_eq!(
+            after.rebind_into_except(&mut server, "server", &["port"]),
+            Ok(vec!["limits".to_string(), "log_level".to_string()])
+        );
+        assert_eq!(
+            server,
+            Server {
+                port: 8080,
+                log_level: "debug".to_string(),
+                limits: Limits {
+                    rps: 200,
+                    burst: 10
+                },
+            }
+        );
+
+        assert_eq!(
+            after.rebind_into(&mut server, "server"),
+            Ok(vec!["port".to_string()])
+        );
+        assert_eq!(server.port, 9090);
+
+        let bad = HashMap::from([("server.port", "many")]);
+        assert!(bad.rebind_into(&mut server, "server").is_err());
+        assert_eq!(server.port, 9090);
+    }
+}
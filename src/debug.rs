@@ -0,0 +1,225 @@
+//! A `/debug/config` endpoint for `axum` services.
+
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde_json::{json, Map, Value};
+
+use crate::redact::redact;
+use crate::{ChangeLog, Config, Schema};
+
+/// Serves the resolved config (with secrets redacted, see
+/// `redact::redact`), the health of its sources and the recent changes
+/// as JSON:
+///
+/// ```json
+/// {
+///   "config": {"db.host": "localhost", "db.password": "[redacted]"},
+///   "sources": [{"name": "Environment", "healthy": true, "error": null}],
+///   "changes": [
+///     {"at": "2024-01-01T00:00:00Z", "change": "~ db.host = a -> b", "restart_required": false}
+///   ]
+/// }
+/// ```
+///
+/// The endpoint shows how the service is configured, so only expose it
+/// where that's acceptable (e.g. on an internal admin port).
+///
+/// ```
+/// use dinglebit_config::debug::DebugEndpoint;
+/// use dinglebit_config::{ChangeLog, Reloadable};
+/// use std::sync::Arc;
+///
+/// let cfg = Arc::new(Reloadable::file("example.cfg").unwrap());
+/// let log = ChangeLog::new(100);
+/// let recorder = log.clone();
+/// cfg.on_change(move |changes| recorder.record(changes));
+///
+/// let app: axum::Router = axum::Router::new()
+///     .nest("/debug/config", DebugEndpoint::new(cfg).changes(log).router());
+/// ```
+pub struct DebugEndpoint {
+    config: Arc<dyn Config + Send + Sync>,
+    sources: Vec<Arc<dyn Config + Send + Sync>>,
+    changes: Option<ChangeLog>,
+    schema: Option<Schema>,
+}
+
+impl DebugEndpoint {
+    /// Serve the given config.
+    pub fn new(config: Arc<dyn Config + Send + Sync>) -> Self {
+        Self {
+            config,
+            sources: vec![],
+            changes: None,
+            schema: None,
+        }
+    }
+
+    /// Report the health of the given source, such as a layer of the
+    /// config. Without any, the health of the config itself is
+    /// reported.
+    pub fn source(mut self, source: Arc<dyn Config + Send + Sync>) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    /// Include the changes recorded in the given log.
+    pub fn changes(mut self, log: ChangeLog) -> Self {
+        self.changes = Some(log);
+        self
+    }
+
+    /// Label the changes to keys the schema marks as requiring a
+    /// restart (see `Schema::restart_required`) and redact the keys it
+    /// marks as secrets (see `Schema::sensitive`). Without a schema, no
+    /// change is labeled.
+    pub fn schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// The JSON the endpoint serves.
+    pub fn report(&self) -> Value {
+        let mut config = Map::new();
+        for key in self.config.keys() {
+            if let Some(value) = self.config.get_ref(&key) {
+                let value = match &self.schema {
+                    Some(schema) => schema.redact(&key, &value).into_owned(),
+                    None => redact(&key, &value).into_owned(),
+                };
+                config.insert(key, Value::String(value));
+            }
+        }
+
+        let sources = match self.sources.is_empty() {
+            true => vec![self.config.clone()],
+            false => self.sources.clone(),
+        };
+        let sources: Vec<Value> = sources
+            .iter()
+            .map(|source| {
+                let error = source.health().err().map(|e| e.to_string());
+                json!({
+                    "name": source.source_name(),
+                    "healthy": error.is_none(),
+                    "error": error,
+                })
+            })
+            .collect();
+
+        let changes: Vec<Value> = self
+            .changes
+            .iter()
+            .flat_map(ChangeLog::entries)
+            .map(|(at, change)| {
+                json!({
+                    "at": at.to_rfc3339(),
+                    "change": match &self.schema {
+                        Some(schema) => change.redacted_with(schema),
+                        None => change.redacted(),
+                    }
+                    .to_string(),
+                    "restart_required": self
+                        .schema
+                        .as_ref()
+                        .is_some_and(|s| change.requires_restart(s)),
+                })
+            })
+            .collect();
+
+        json!({
+            "config": config,
+            "sources": sources,
+            "changes": changes,
+        })
+    }
+
+    /// A router serving the report at its root, to be nested at a path
+    /// such as `/debug/config`.
+    pub fn router<S>(self) -> Router<S> {
+        Router::new()
+            .route("/", get(serve))
+            .with_state(Arc::new(self))
+    }
+}
+
+async fn serve(State(endpoint): State<Arc<DebugEndpoint>>) -> Json<Value> {
+    Json(endpoint.report())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::debug::DebugEndpoint;
+    use crate::schema::{Schema, Type};
+    use crate::{Change, ChangeLog, Config, SourceHealth};
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    struct Down;
+
+    impl Config for Down {
+        fn get(&self, _key: &str) -> Option<String> {
+            None
+        }
+
+        fn source_health(&self) -> SourceHealth {
+            SourceHealth::Down("unreachable".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn endpoint() {
+        let config: HashMap<String, String> = [
+            ("db.host", "localhost"),
+            ("db.password", "hunter2"),
+            ("db.salt", "pepper"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let log = ChangeLog::new(10);
+        log.record(&[Change::Changed {
+            key: "db.password".to_string(),
+            old: "a".to_string(),
+            new: "b".to_string(),
+        }]);
+        let endpoint = DebugEndpoint::new(Arc::new(config.clone()))
+            .source(Arc::new(config))
+            .source(Arc::new(Down))
+            .changes(log)
+            .schema(
+                Schema::new()
+                    .required("db.password", Type::String)
+                    .restart_required("db.password")
+                    .optional("db.salt", Type::String)
+                    .sensitive("db.salt"),
+            );
+
+        let response = endpoint
+            .router::<()>()
+            .oneshot(Request::get("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(report["config"]["db.host"], "localhost");
+        assert_eq!(report["config"]["db.password"], "[redacted]");
+        assert_eq!(report["config"]["db.salt"], "[redacted]");
+        assert_eq!(report["sources"][0]["healthy"], true);
+        assert_eq!(report["sources"][1]["name"], "Down");
+        assert_eq!(report["sources"][1]["healthy"], false);
+        assert_eq!(
+            report["changes"][0]["change"],
+            "~ db.password = [redacted] -> [redacted]"
+        );
+        assert_eq!(report["changes"][0]["restart_required"], true);
+    }
+}
@@ -0,0 +1,172 @@
+//! Default values, usually used as the last config of a
+//! `MultiConfig`.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{redact, Config};
+#[cfg(feature = "serde")]
+use crate::{value::Value, Error};
+
+/// Values defined in code. `MultiConfig::builder` always puts these
+/// below every other layer.
+#[derive(PartialEq, Clone, Default)]
+pub struct Defaults {
+    values: HashMap<String, String>,
+}
+
+impl Defaults {
+    /// Create an empty set of defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the default for the given key.
+    ///
+    /// ```
+    /// use dinglebit_config::{Config, Defaults};
+    ///
+    /// let defaults = Defaults::new()
+    ///     .set("mongo.uri", "mongodb://localhost/")
+    ///     .set("mongo.pool", 10.to_string());
+    /// assert_eq!(defaults.int("mongo.pool"), 10);
+    /// ```
+    pub fn set(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.values.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// Create defaults from any value that can be serialized (usually
+    /// a struct with `#[derive(Serialize)]`). Fields are flattened
+    /// into dot-notation keys, so a `mongo` field holding a struct
+    /// with a `uri` field is available as `mongo.uri`. Sequences are
+    /// rendered using the syntax `Config::list` expects, `None`
+    /// fields are skipped and unit enum variants are their name.
+    ///
+    /// ```
+    /// use dinglebit_config::{Config, Defaults, MultiConfig};
+    /// use serde::Serialize;
+    /// use std::collections::HashMap;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Mongo {
+    ///     uri: String,
+    ///     pool: u32,
+    /// }
+    ///
+    /// let defaults = Defaults::from_struct(&Mongo {
+    ///     uri: "mongodb://localhost/".to_string(),
+    ///     pool: 10,
+    /// })
+    /// .unwrap();
+    /// let cfg = MultiConfig::new(vec![
+    ///     Box::new(HashMap::from([("pool", "20")])),
+    ///     Box::new(defaults),
+    /// ]);
+    /// assert_eq!(cfg.int("pool"), 20);
+    /// assert_eq!(cfg.string("uri"), "mongodb://localhost/");
+    /// ```
+    ///
+    /// The value must serialize as a struct or map, otherwise
+    /// `Error::Serialize` is returned.
+    #[cfg(feature = "serde")]
+    pub fn from_struct<T: serde::Serialize + ?Sized>(value: &T) -> Result<Self, Error> {
+        match crate::ser::to_value(value) {
+            Ok(root @ Value::Map(_)) => Ok(Self {
+                values: root.flatten(),
+            }),
+            Ok(_) => Err(Error::Serialize("expected a struct or map".to_string())),
+            Err(e) => Err(Error::Serialize(e.to_string())),
+        }
+    }
+}
+
+impl Config for Defaults {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values.get(key).map(|value| value.to_string())
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.values
+            .get(key)
+            .map(|value| Cow::Borrowed(value.as_str()))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+}
+
+impl fmt::Debug for Defaults {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redact::debug(self, f)
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use crate::defaults::Defaults;
+    use crate::{Config, Error};
+    use serde::Serialize;
+    use std::collections::HashMap;
+
+    #[derive(Serialize)]
+    enum Mode {
+        Fast,
+        Limited(u32),
+    }
+
+    #[derive(Serialize)]
+    struct Mongo {
+        uri: String,
+        pool: Option<u32>,
+    }
+
+    #[derive(Serialize)]
+    struct Settings {
+        mongo: Mongo,
+        ratio: f64,
+        debug: bool,
+        tags: Vec<&'static str>,
+        mode: Mode,
+        limit: Mode,
+        labels: HashMap<u8, &'static str>,
+    }
+
+    #[test]
+    fn from_struct() {
+        let cfg = Defaults::from_struct(&Settings {
+            mongo: Mongo {
+                uri: "mongodb://".to_string(),
+                pool: None,
+            },
+            ratio: 0.5,
+            debug: true,
+            tags: vec!["a", "b"],
+            mode: Mode::Fast,
+            limit: Mode::Limited(3),
+            labels: HashMap::from([(1, "one")]),
+        })
+        .unwrap();
+
+        assert_eq!(cfg.get("mongo.uri"), Some("mongodb://".to_string()));
+        assert_eq!(cfg.get("mongo.pool"), None);
+        assert_eq!(cfg.float("ratio"), 0.5);
+        assert!(cfg.bool("debug"));
+        assert_eq!(cfg.list("tags"), vec!["a", "b"]);
+        assert_eq!(cfg.get("mode"), Some("Fast".to_string()));
+        assert_eq!(cfg.int("limit.Limited"), 3);
+        assert_eq!(cfg.get("labels.1"), Some("one".to_string()));
+        assert_eq!(cfg.keys().len(), 7);
+
+        assert!(matches!(
+            Defaults::from_struct(&vec![1, 2]),
+            Err(Error::Serialize(_))
+        ));
+        assert!(matches!(
+            Defaults::from_struct(&HashMap::from([((1, 2), 3)])),
+            Err(Error::Serialize(_))
+        ));
+    }
+}
@@ -0,0 +1,142 @@
+//! Compare the values of two configs.
+
+use crate::redact::redact;
+use crate::{Config, Schema};
+
+/// A difference between two configs.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Change {
+    /// The key is only in the new config.
+    Added { key: String, value: String },
+
+    /// The key is only in the old config.
+    Removed { key: String, value: String },
+
+    /// The key is in both configs with different values.
+    Changed {
+        key: String,
+        old: String,
+        new: String,
+    },
+}
+
+impl Change {
+    /// The key that changed.
+    pub fn key(&self) -> &str {
+        match self {
+            Change::Added { key, .. }
+            | Change::Removed { key, .. }
+            | Change::Changed { key, .. } => key,
+        }
+    }
+
+    /// Whether the schema marks the key as only taking effect after a
+    /// restart, so applying the change needs one.
+    pub fn requires_restart(&self, schema: &Schema) -> bool {
+        schema.requires_restart(self.key())
+    }
+
+    /// The change with its values redacted (see `redact::redact`), for
+    /// showing it in logs or on a terminal.
+    pub fn redacted(&self) -> Change {
+        self.map_values(|key, value| redact(key, value).into_owned())
+    }
+
+    /// Similar to `redacted` but the keys the schema marks as secrets
+    /// (see `Schema::sensitive`) are redacted too.
+    pub fn redacted_with(&self, schema: &Schema) -> Change {
+        self.map_values(|key, value| schema.redact(key, value).into_owned())
+    }
+
+    fn map_values<F: Fn(&str, &str) -> String>(&self, r: F) -> Change {
+        match self {
+            Change::Added { key, value } => Change::Added {
+                key: key.clone(),
+                value: r(key, value),
+            },
+            Change::Removed { key, value } => Change::Removed {
+                key: key.clone(),
+                value: r(key, value),
+            },
+            Change::Changed { key, old, new } => Change::Changed {
+                key: key.clone(),
+                old: r(key, old),
+                new: r(key, new),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Change::Added { key, value } => write!(f, "+ {} = {}", key, value),
+            Change::Removed { key, value } => write!(f, "- {} = {}", key, value),
+            Change::Changed { key, old, new } => write!(f, "~ {} = {} -> {}", key, old, new),
+        }
+    }
+}
+
+/// Compare the keys of the two configs, returning the changes sorted
+/// by key. Both configs must be able to list their keys.
+pub fn diff(old: &dyn Config, new: &dyn Config) -> Vec<Change> {
+    let mut keys = old.keys();
+    keys.extend(new.keys());
+    keys.sort();
+    keys.dedup();
+
+    keys.into_iter()
+        .filter_map(|key| match (old.get(&key), new.get(&key)) {
+            (None, Some(value)) => Some(Change::Added { key, value }),
+            (Some(value), None) => Some(Change::Removed { key, value }),
+            (Some(old), Some(new)) if old != new => Some(Change::Changed { key, old, new }),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diff::{diff, Change};
+    use crate::schema::{Schema, Type};
+    use std::collections::HashMap;
+
+    #[test]
+    fn changes() {
+        let old = HashMap::from([("a", "1"), ("b", "2"), ("c", "3")]);
+        let new = HashMap::from([("a", "1"), ("b", "20"), ("d", "4")]);
+        let changes = diff(&old, &new);
+        assert_eq!(
+            changes,
+            vec![
+                Change::Changed {
+                    key: "b".to_string(),
+                    old: "2".to_string(),
+                    new: "20".to_string()
+                },
+                Change::Removed {
+                    key: "c".to_string(),
+                    value: "3".to_string()
+                },
+                Change::Added {
+                    key: "d".to_string(),
+                    value: "4".to_string()
+                },
+            ]
+        );
+        assert_eq!(changes[0].to_string(), "~ b = 2 -> 20");
+        assert_eq!(changes[2].key(), "d");
+        assert!(diff(&old, &old).is_empty());
+
+        let schema = Schema::new().required("b", Type::Int).restart_required("b");
+        assert!(changes[0].requires_restart(&schema));
+        assert!(!changes[2].requires_restart(&schema));
+
+        let old = HashMap::from([("db.password", "hunter2")]);
+        let new = HashMap::from([("db.password", "hunter3")]);
+        assert_eq!(
+            diff(&old, &new)[0].redacted().to_string(),
+            "~ db.password = [redacted] -> [redacted]"
+        );
+    }
+}
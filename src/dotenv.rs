@@ -0,0 +1,187 @@
+//! Configuration from `.env` files.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::format::read_file;
+use crate::{redact, Config, Error};
+
+#[derive(PartialEq, Clone)]
+pub struct DotEnv {
+    prefix: String,
+    values: HashMap<String, String>,
+}
+
+/// Parse a double-quoted value, replacing the common escapes.
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('r') => out.push('\r'),
+                Some(c) => out.push(c),
+                None => out.push('\\'),
+            },
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// The inside of a value quoted with `q`, if the closing quote is
+/// followed by nothing but an optional comment.
+fn quoted(value: &str, q: char) -> Option<&str> {
+    let rest = value.strip_prefix(q)?;
+    let mut escaped = false;
+    let end = rest.char_indices().find_map(|(i, c)| {
+        match (escaped, c) {
+            (false, '\\') if q == '"' => escaped = true,
+            (false, c) if c == q => return Some(i),
+            _ => escaped = false,
+        }
+        None
+    })?;
+    let after = rest[end + 1..].trim_start();
+    match after.is_empty() || after.starts_with('#') {
+        true => Some(&rest[..end]),
+        false => None,
+    }
+}
+
+fn parse_line(line: &str) -> Result<Option<(String, String)>, Error> {
+    let line = line.trim();
+    if line.starts_with('#') || line.is_empty() {
+        return Ok(None);
+    }
+    let line = line.strip_prefix("export ").unwrap_or(line);
+
+    let (key, value) = match line.split_once('=') {
+        Some((k, v)) => (k.trim(), v.trim()),
+        None => return Err(Error::InvalidKeyValuePair),
+    };
+    let value = if let Some(inner) = quoted(value, '"') {
+        unescape(inner)
+    } else if let Some(inner) = quoted(value, '\'') {
+        inner.to_string()
+    } else {
+        // Unquoted values can have a trailing comment.
+        match value.find(" #") {
+            Some(i) => value[..i].trim_end().to_string(),
+            None => value.to_string(),
+        }
+    };
+
+    Ok(Some((key.to_string(), value)))
+}
+
+impl DotEnv {
+    /// Create a new configuration from the given `.env` contents.
+    /// Each line is a `NAME=value` pair (optionally preceded by
+    /// `export`), values may be single or double quoted and lines
+    /// starting with `#` are comments:
+    ///
+    /// ```sh
+    /// # i am a comment
+    /// export MONGO_URI="mongodb://localhost/"
+    /// MONGO_DB=test # trailing comment
+    /// ```
+    ///
+    /// Keys are looked up the same way `Environment` does, so a get
+    /// for `mongo.uri` looks for `MONGO_URI`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Self, Error> {
+        let mut values = HashMap::new();
+        for line in s.lines() {
+            if let Some((k, v)) = parse_line(line)? {
+                values.insert(k, v);
+            }
+        }
+        Ok(Self {
+            prefix: "".to_string(),
+            values,
+        })
+    }
+
+    /// Similar to `from_str` except that the given path is used as
+    /// the contents for the string to parse.
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let file = read_file(path)?;
+        Self::from_str(&file)
+    }
+
+    /// Prefix keys with the given prefix and an underscore, the same
+    /// way `Environment::new` does.
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = match !prefix.is_empty() {
+            true => prefix.to_owned() + "_",
+            false => "".to_string(),
+        };
+        self
+    }
+}
+
+impl Config for DotEnv {
+    fn get(&self, key: &str) -> Option<String> {
+        let key = self.prefix.to_owned() + key;
+        let key = key.replace(".", "_").replace("/", "_");
+        let key = key.to_uppercase();
+        self.values.get(&key).map(|value| value.to_string())
+    }
+
+    /// The variable names without the prefix, lower-cased and with
+    /// '_' replaced by '.' (e.g. `MONGO_URI` => `mongo.uri`).
+    fn keys(&self) -> Vec<String> {
+        let prefix = self.prefix.to_uppercase();
+        self.values
+            .keys()
+            .filter_map(|k| k.strip_prefix(&prefix))
+            .map(|k| k.to_lowercase().replace('_', "."))
+            .collect()
+    }
+}
+
+impl fmt::Debug for DotEnv {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redact::debug(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dotenv::{parse_line, DotEnv};
+    use crate::{Config, Error};
+
+    #[test]
+    fn test_parse_line() {
+        let tests = [
+            ("# comment", None),
+            ("FOO=bar", Some(("FOO", "bar"))),
+            ("export FOO = bar # comment", Some(("FOO", "bar"))),
+            ("FOO=\"a\\nb # c\"", Some(("FOO", "a\nb # c"))),
+            ("FOO=\"a\" # c", Some(("FOO", "a"))),
+            ("FOO=\"a \\\" b\"#c", Some(("FOO", "a \" b"))),
+            ("FOO='a' # c", Some(("FOO", "a"))),
+            ("FOO='a\\nb'", Some(("FOO", "a\\nb"))),
+            ("FOO=", Some(("FOO", ""))),
+        ];
+        for (line, exp) in tests.iter() {
+            let exp = exp.map(|(k, v)| (k.to_string(), v.to_string()));
+            assert_eq!(parse_line(line), Ok(exp));
+        }
+        assert_eq!(parse_line("FOO"), Err(Error::InvalidKeyValuePair));
+    }
+
+    #[test]
+    fn get() {
+        let cfg = DotEnv::from_str("MONGO_URI=mongodb://\nAPP_PORT=80").unwrap();
+        assert_eq!(cfg.get("mongo.uri"), Some("mongodb://".to_string()));
+        assert_eq!(cfg.get("mongo/uri"), Some("mongodb://".to_string()));
+
+        let cfg = cfg.with_prefix("app");
+        assert_eq!(cfg.int("port"), 80);
+        assert_eq!(cfg.get("mongo.uri"), None);
+    }
+}
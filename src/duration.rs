@@ -0,0 +1,93 @@
+//! Durations written with units (e.g. `30s` or `1h30m`).
+
+use alloc::string::String;
+use core::fmt::Write;
+
+use chrono::Duration;
+
+use crate::NumberFormat;
+
+/// What the errors for durations say was expected.
+pub(crate) const EXPECTED: &str = "duration like `30s`";
+
+/// Parse a duration. A plain integer is a number of seconds. Otherwise
+/// it's one or more numbers, each followed by a unit: `ms`, `s`, `m`,
+/// `h` or `d` (e.g. `250ms`, `90s` or `1h30m`).
+pub(crate) fn parse(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    if let Some(seconds) = NumberFormat::default().parse_int(s) {
+        return Duration::try_seconds(seconds);
+    }
+    if s.is_empty() {
+        return None;
+    }
+    let mut total = Duration::zero();
+    let mut rest = s;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let (n, tail) = rest.split_at(digits);
+        let n: i64 = n.parse().ok()?;
+        let unit = tail
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(tail.len());
+        let part = match &tail[..unit] {
+            "ms" => Duration::try_milliseconds(n)?,
+            "s" => Duration::try_seconds(n)?,
+            "m" => Duration::try_minutes(n)?,
+            "h" => Duration::try_hours(n)?,
+            "d" => Duration::try_days(n)?,
+            _ => return None,
+        };
+        total = total.checked_add(&part)?;
+        rest = &tail[unit..];
+    }
+    Some(total)
+}
+
+/// Render a duration the way `parse` reads it, with the largest units
+/// first (e.g. `1h30m`). Zero is `0s`.
+pub(crate) fn format(d: Duration) -> String {
+    let mut s = String::new();
+    if d < Duration::zero() {
+        s.push('-');
+    }
+    let mut ms = d.num_milliseconds().unsigned_abs();
+    for (unit, size) in [
+        ("d", 86_400_000),
+        ("h", 3_600_000),
+        ("m", 60_000),
+        ("s", 1_000),
+        ("ms", 1),
+    ] {
+        if ms >= size {
+            let _ = write!(s, "{}{}", ms / size, unit);
+            ms %= size;
+        }
+    }
+    if s.is_empty() || s == "-" {
+        s = String::from("0s");
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::duration::{format, parse};
+    use chrono::Duration;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse("50"), Some(Duration::seconds(50)));
+        assert_eq!(parse("250ms"), Some(Duration::milliseconds(250)));
+        assert_eq!(parse(" 1h30m "), Some(Duration::minutes(90)));
+        assert_eq!(parse("2d"), Some(Duration::days(2)));
+        for s in &["", "fast", "s", "30x", "1h 30m", "-5s", "1.5h"] {
+            assert_eq!(parse(s), None, "{}", s);
+        }
+
+        assert_eq!(format(Duration::minutes(90)), "1h30m");
+        assert_eq!(format(Duration::milliseconds(1_250)), "1s250ms");
+        assert_eq!(format(Duration::zero()), "0s");
+        assert_eq!(format(Duration::seconds(-5)), "-5s");
+    }
+}
@@ -0,0 +1,114 @@
+//! Configuration compiled into the binary, so the defaults always ship
+//! with it.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::{Config, Error, Format, Value, ValueError};
+
+/// A config file included at compile time, usually as the defaults
+/// layer. Use `embedded!` to include and parse one:
+///
+/// ```
+/// use dinglebit_config::{embedded, Config};
+///
+/// let defaults = embedded!("../example.cfg").unwrap();
+/// assert_eq!(defaults.string("foo"), "bar");
+/// ```
+///
+/// The format comes from the name, or is sniffed if the name doesn't
+/// say.
+pub struct Embedded {
+    name: &'static str,
+    config: Box<dyn Config + Send + Sync>,
+}
+
+impl Embedded {
+    /// Parse the contents of the file with the given name (e.g. from
+    /// `include_bytes!`).
+    pub fn from_bytes(name: &'static str, bytes: &'static [u8]) -> Result<Self, Error> {
+        let s = std::str::from_utf8(bytes).map_err(|e| Error::Parse(format!("{}: {}", name, e)))?;
+        let format = Format::from_path(name).unwrap_or_else(|| Format::sniff(s));
+        Ok(Self {
+            name,
+            config: format.load_str(s)?,
+        })
+    }
+
+    /// The name of the file the config was included from.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// Include the config file at the given path, relative to the current
+/// file like `include_bytes!`, and parse it into an `Embedded`. A
+/// missing file fails the build; a file that can't be parsed is an
+/// error when the macro runs.
+#[macro_export]
+macro_rules! embedded {
+    ($path:expr) => {
+        $crate::Embedded::from_bytes($path, include_bytes!($path))
+    };
+}
+
+impl Config for Embedded {
+    fn get(&self, key: &str) -> Option<String> {
+        self.config.get(key)
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.config.get_ref(key)
+    }
+
+    fn get_value(&self, key: &str) -> Option<Value> {
+        self.config.get_value(key)
+    }
+
+    fn try_get_ref(&self, key: &str) -> Result<Cow<'_, str>, ValueError> {
+        self.config.try_get_ref(key)
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.config.keys()
+    }
+
+    /// The name includes the file (e.g. `Embedded(defaults.toml)`).
+    fn source_name(&self) -> String {
+        format!("Embedded({})", self.name)
+    }
+}
+
+impl fmt::Debug for Embedded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Embedded")
+            .field("name", &self.name)
+            .field("source", &self.config.source_name())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::embedded::Embedded;
+    use crate::{Config, Error};
+
+    #[test]
+    fn embedded() {
+        let cfg = embedded!("../example.cfg").unwrap();
+        assert_eq!(cfg.name(), "../example.cfg");
+        assert_eq!(cfg.string("foo"), "bar");
+        assert_eq!(cfg.source_name(), "Embedded(../example.cfg)");
+
+        let cfg = Embedded::from_bytes("defaults", b"{\"pool\": {\"size\": 10}}");
+        #[cfg(feature = "json")]
+        assert_eq!(cfg.unwrap().int("pool.size"), 10);
+        #[cfg(not(feature = "json"))]
+        assert!(cfg.is_err());
+
+        assert!(matches!(
+            Embedded::from_bytes("bad.cfg", &[0xff]),
+            Err(Error::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,241 @@
+//! Encrypted values, so secrets can live in committed config files.
+//!
+//! Two kinds of values are understood: SOPS-style values
+//! (`ENC[AES256_GCM,data:...,iv:...,tag:...,type:str]`) encrypted with
+//! a 256-bit data key and age-armored values (`-----BEGIN AGE
+//! ENCRYPTED FILE-----`) encrypted to an age recipient. Use `encrypt`
+//! and `encrypt_age` to create them.
+
+use std::borrow::Cow;
+use std::io::Read;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::{Config, Error, ValueError};
+
+const AGE_ARMOR: &str = "-----BEGIN AGE ENCRYPTED FILE-----";
+const TAG_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Decrypts encrypted values of the wrapped config before returning
+/// them. Other values are returned as they are.
+///
+/// ```
+/// use dinglebit_config::encryption::encrypt;
+/// use dinglebit_config::{Config, Decrypting};
+/// use std::collections::HashMap;
+///
+/// let key = [7; 32];
+/// let secret = encrypt("db.password", "hunter2", &key);
+/// let cfg = Decrypting::new(HashMap::from([("db.password", secret.as_str())])).with_key(key);
+/// assert_eq!(cfg.string("db.password"), "hunter2");
+/// ```
+///
+/// A value that can't be decrypted (e.g. without the right key) is
+/// never returned as is: `get` returns `None` and the fallible getters
+/// return `ValueError::Invalid`.
+pub struct Decrypting<C> {
+    config: C,
+    key: Option<[u8; 32]>,
+    identities: Vec<age::x25519::Identity>,
+}
+
+impl<C: Config> Decrypting<C> {
+    /// Wrap the given config. Add a key or identities to decrypt with
+    /// `with_key` and `with_identity`.
+    pub fn new(config: C) -> Self {
+        Self {
+            config,
+            key: None,
+            identities: vec![],
+        }
+    }
+
+    /// Use the given data key for `ENC[...]` values.
+    pub fn with_key(mut self, key: [u8; 32]) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// Add an identity for age-armored values (e.g. parsed from an
+    /// `AGE-SECRET-KEY-1...` string). Each identity is tried in turn.
+    pub fn with_identity(mut self, identity: age::x25519::Identity) -> Self {
+        self.identities.push(identity);
+        self
+    }
+
+    /// Decrypt the value of the given key if it's encrypted.
+    fn reveal<'a>(&self, key: &str, value: Cow<'a, str>) -> Result<Cow<'a, str>, ValueError> {
+        let trimmed = value.trim();
+        let plain = match trimmed
+            .strip_prefix("ENC[")
+            .and_then(|v| v.strip_suffix(']'))
+        {
+            Some(inner) => self.decrypt_enc(key, inner),
+            None if trimmed.starts_with(AGE_ARMOR) => self.decrypt_age(trimmed),
+            None => return Ok(value),
+        };
+        match plain {
+            Some(plain) => Ok(Cow::Owned(plain)),
+            None => Err(ValueError::invalid(
+                key,
+                value.into_owned(),
+                "a value that can be decrypted",
+            )),
+        }
+    }
+
+    fn decrypt_enc(&self, key: &str, inner: &str) -> Option<String> {
+        let mut fields = inner.split(',');
+        if fields.next()? != "AES256_GCM" {
+            return None;
+        }
+        let (mut data, mut iv, mut tag) = (None, None, None);
+        for field in fields {
+            match field.split_once(':')? {
+                ("data", v) => data = Some(STANDARD.decode(v).ok()?),
+                ("iv", v) => iv = Some(STANDARD.decode(v).ok()?),
+                ("tag", v) => tag = Some(STANDARD.decode(v).ok()?),
+                _ => {}
+            }
+        }
+        let (mut msg, iv, tag) = (data?, iv?, tag?);
+        if iv.len() != NONCE_LEN || tag.len() != TAG_LEN {
+            return None;
+        }
+        msg.extend_from_slice(&tag);
+
+        let cipher = Aes256Gcm::new(self.key.as_ref()?.into());
+        let payload = Payload {
+            msg: &msg,
+            aad: key.as_bytes(),
+        };
+        let plain = cipher.decrypt(Nonce::from_slice(&iv), payload).ok()?;
+        String::from_utf8(plain).ok()
+    }
+
+    fn decrypt_age(&self, armored: &str) -> Option<String> {
+        if self.identities.is_empty() {
+            return None;
+        }
+        let reader = age::armor::ArmoredReader::new(armored.as_bytes());
+        let decryptor = age::Decryptor::new_buffered(reader).ok()?;
+        let identities = self.identities.iter().map(|i| i as &dyn age::Identity);
+        let mut plain = String::new();
+        decryptor
+            .decrypt(identities)
+            .ok()?
+            .read_to_string(&mut plain)
+            .ok()?;
+        Some(plain)
+    }
+}
+
+impl<C: Config> Config for Decrypting<C> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.get_ref(key).map(Cow::into_owned)
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.reveal(key, self.config.get_ref(key)?).ok()
+    }
+
+    fn try_get_ref(&self, key: &str) -> Result<Cow<'_, str>, ValueError> {
+        match self.config.get_ref(key) {
+            Some(value) => self.reveal(key, value),
+            None => Err(ValueError::Missing(key.to_string())),
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.config.keys()
+    }
+}
+
+/// Encrypt the value of the given key with the data key, for use with
+/// `Decrypting::with_key`. The config key is authenticated along with
+/// the value, so the result can't be moved to a different key.
+pub fn encrypt(key: &str, value: &str, data_key: &[u8; 32]) -> String {
+    let cipher = Aes256Gcm::new(data_key.into());
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let payload = Payload {
+        msg: value.as_bytes(),
+        aad: key.as_bytes(),
+    };
+    // Encrypting into memory can't fail.
+    let mut data = cipher.encrypt(&nonce, payload).unwrap_or_default();
+    let tag = data.split_off(data.len().saturating_sub(TAG_LEN));
+    format!(
+        "ENC[AES256_GCM,data:{},iv:{},tag:{},type:str]",
+        STANDARD.encode(data),
+        STANDARD.encode(nonce),
+        STANDARD.encode(tag)
+    )
+}
+
+/// Encrypt the value to the given age recipient (an `age1...` public
+/// key), for use with `Decrypting::with_identity`.
+pub fn encrypt_age(value: &str, recipient: &age::x25519::Recipient) -> Result<String, Error> {
+    age::encrypt_and_armor(recipient, value.as_bytes())
+        .map_err(|e| Error::Unsupported(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encryption::{encrypt, encrypt_age, Decrypting};
+    use crate::{Config, ValueError};
+    use std::collections::HashMap;
+
+    #[test]
+    fn enc() {
+        let key = [1; 32];
+        let secret = encrypt("db.password", "hunter2", &key);
+        assert!(secret.starts_with("ENC[AES256_GCM,data:"));
+        let moved = encrypt("other", "hunter2", &key);
+        let values: HashMap<String, String> = [
+            ("db.password", secret.clone()),
+            ("db.moved", moved),
+            ("db.host", "localhost".to_string()),
+            ("db.broken", "ENC[AES256_GCM,data:x]".to_string()),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.clone()))
+        .collect();
+
+        let cfg = Decrypting::new(values.clone()).with_key(key);
+        assert_eq!(cfg.get("db.password"), Some("hunter2".to_string()));
+        assert_eq!(cfg.get("db.host"), Some("localhost".to_string()));
+        assert_eq!(cfg.get("db.moved"), None);
+        assert_eq!(cfg.get("db.broken"), None);
+        assert_eq!(cfg.keys().len(), 4);
+
+        let wrong = Decrypting::new(values).with_key([2; 32]);
+        assert_eq!(wrong.get("db.password"), None);
+        assert!(matches!(
+            wrong.try_get("db.password"),
+            Err(ValueError::Invalid { value, .. }) if value == secret
+        ));
+        assert_eq!(
+            wrong.try_get("missing"),
+            Err(ValueError::Missing("missing".to_string()))
+        );
+    }
+
+    #[test]
+    fn age() {
+        let identity = age::x25519::Identity::generate();
+        let secret = encrypt_age("hunter2", &identity.to_public()).unwrap();
+        let values = HashMap::from([("db.password".to_string(), secret)]);
+
+        let cfg = Decrypting::new(values.clone())
+            .with_identity(age::x25519::Identity::generate())
+            .with_identity(identity);
+        assert_eq!(cfg.string("db.password"), "hunter2");
+
+        let other = Decrypting::new(values).with_identity(age::x25519::Identity::generate());
+        assert_eq!(other.get("db.password"), None);
+    }
+}
@@ -1,10 +1,13 @@
 //! Configuration from the environment variables.
 
 use std::env;
+use std::ffi::OsString;
+use std::process::Command;
 
-use crate::Config;
+use crate::schema::Field;
+use crate::{Config, Schema};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Environment {
     prefix: String,
 }
@@ -20,11 +23,131 @@ impl Environment {
     /// then a get for 'my.app.secret' would look for
     /// 'FOO_MY_APP_SECRET'.
     pub fn new(prefix: &str) -> Self {
-        let prefix = match prefix.len() > 0 {
+        let prefix = match !prefix.is_empty() {
             true => prefix.to_owned() + "_",
             false => "".to_string(),
         };
-        Self { prefix: prefix }
+        Self { prefix }
+    }
+
+    /// The name of the environment variable used for the given key
+    /// (e.g. 'my.app.secret' => 'FOO_MY_APP_SECRET').
+    pub fn var_name(&self, key: &str) -> String {
+        let mut name = String::with_capacity(self.prefix.len() + key.len());
+        for c in self.prefix.chars().chain(key.chars()) {
+            match c {
+                '.' | '/' => name.push('_'),
+                c => name.extend(c.to_uppercase()),
+            }
+        }
+        name
+    }
+
+    /// Work out the environment variable for the given key once, so
+    /// code that reads the same key repeatedly (e.g. in a hot loop)
+    /// can skip the conversion by using `get_prepared`.
+    ///
+    /// ```
+    /// use dinglebit_config::Environment;
+    ///
+    /// let env = Environment::new("app");
+    /// let key = env.prepare("worker.threads");
+    /// assert_eq!(key.name(), "APP_WORKER_THREADS");
+    /// assert_eq!(env.get_prepared(&key), std::env::var("APP_WORKER_THREADS").ok());
+    /// ```
+    pub fn prepare(&self, key: &str) -> EnvKey {
+        EnvKey {
+            name: self.var_name(key).into(),
+        }
+    }
+
+    /// Similar to `get` but uses a key from `prepare`.
+    pub fn get_prepared(&self, key: &EnvKey) -> Option<String> {
+        env::var_os(&key.name).and_then(|v| v.into_string().ok())
+    }
+
+    /// Document the environment variables for the keys of the schema
+    /// as a Markdown table, in the order of the schema, so the docs
+    /// for operators can be generated rather than kept in sync by
+    /// hand.
+    ///
+    /// ```
+    /// use dinglebit_config::schema::Type;
+    /// use dinglebit_config::{Environment, Schema};
+    ///
+    /// let schema = Schema::new()
+    ///     .required("db.uri", Type::String)
+    ///     .describe("db.uri", "Where the database is.");
+    /// assert_eq!(
+    ///     Environment::new("app").markdown(&schema),
+    ///     "| Variable | Type | Required | Description |\n\
+    ///      | --- | --- | --- | --- |\n\
+    ///      | `APP_DB_URI` | string | yes | Where the database is. |\n"
+    /// );
+    /// ```
+    pub fn markdown(&self, schema: &Schema) -> String {
+        let mut out = String::from("| Variable | Type | Required | Description |\n");
+        out.push_str("| --- | --- | --- | --- |\n");
+        for field in schema.fields() {
+            out.push_str(&format!(
+                "| `{}` | {} | {} | {} |\n",
+                self.var_name(&field.key),
+                field.kind.name(),
+                if field.required { "yes" } else { "no" },
+                describe(field).replace('|', "\\|"),
+            ));
+        }
+        out
+    }
+
+    /// Similar to `markdown` but as a fragment of a man page (e.g. for
+    /// the ENVIRONMENT section), with a tagged paragraph per variable.
+    pub fn man_page(&self, schema: &Schema) -> String {
+        let escape = |s: &str| s.replace('\\', "\\e").replace('-', "\\-");
+        let mut out = String::new();
+        for field in schema.fields() {
+            let required = if field.required {
+                "required"
+            } else {
+                "optional"
+            };
+            let description = escape(&describe(field));
+            out.push_str(&format!(
+                ".TP\n.B {}\n({}, {}) {}\n",
+                escape(&self.var_name(&field.key)),
+                field.kind.name(),
+                required,
+                description.trim(),
+            ));
+        }
+        out
+    }
+}
+
+/// The description of the field, noting when changing it needs a
+/// restart.
+fn describe(field: &Field) -> String {
+    let description = field.description.as_deref().unwrap_or_default();
+    match field.restart_required {
+        true => format!("{} Changes need a restart.", description)
+            .trim()
+            .to_string(),
+        false => description.to_string(),
+    }
+}
+
+/// A key whose environment variable name has already been worked out
+/// by `Environment::prepare`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct EnvKey {
+    name: OsString,
+}
+
+impl EnvKey {
+    /// The name of the environment variable.
+    pub fn name(&self) -> &str {
+        // The name was built from a `String`.
+        self.name.to_str().unwrap_or_default()
     }
 }
 
@@ -34,23 +157,44 @@ impl Config for Environment {
     /// prefix is 'foo', then a get for 'my.app.secret' would look for
     /// 'FOO_MY_APP_SECRET'.
     fn get(&self, key: &str) -> Option<String> {
-        // Make the key more environment variable like.
-        let key = self.prefix.to_owned() + key;
-        let key = key.replace(".", "_").replace("/", "_");
-        let key = key.to_uppercase();
-
-        match env::var(key) {
-            Ok(value) => Some(value),
-            Err(_) => None,
-        }
+        env::var(self.var_name(key)).ok()
+    }
+
+    /// The names of the environment variables starting with the
+    /// prefix, without the prefix, lower-cased and with '_' replaced
+    /// by '.'. Without a prefix, this is every variable.
+    fn keys(&self) -> Vec<String> {
+        let prefix = self.prefix.to_uppercase();
+        env::vars()
+            .filter_map(|(k, _)| k.strip_prefix(&prefix).map(|k| k.to_string()))
+            .map(|k| k.to_lowercase().replace('_', "."))
+            .collect()
+    }
+}
+
+/// Pass the values of a config to a child process as environment
+/// variables.
+pub trait CommandExt {
+    /// Set an environment variable for each of the config's values,
+    /// named the way `Environment` with the given prefix would look
+    /// for it. See `Config::to_env_vars`.
+    fn config_envs(&mut self, config: &dyn Config, prefix: &str) -> &mut Self;
+}
+
+impl CommandExt for Command {
+    fn config_envs(&mut self, config: &dyn Config, prefix: &str) -> &mut Self {
+        self.envs(config.to_env_vars(prefix))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::env::Environment;
+    use crate::env::{CommandExt, Environment};
+    use crate::schema::{Schema, Type};
     use crate::Config;
+    use std::collections::HashMap;
     use std::env;
+    use std::ffi::OsString;
 
     #[test]
     fn new() {
@@ -77,4 +221,69 @@ mod tests {
         env::remove_var("TEST_GET_FOO_BAR");
         assert_eq!(e.get("foo.bar"), None);
     }
+
+    #[test]
+    fn prepared() {
+        let e = Environment::new("test_prepared");
+        let key = e.prepare("foo.bar/baz");
+        assert_eq!(key.name(), "TEST_PREPARED_FOO_BAR_BAZ");
+        assert_eq!(e.get_prepared(&key), None);
+        env::set_var("TEST_PREPARED_FOO_BAR_BAZ", "qux");
+        assert_eq!(e.get_prepared(&key), Some("qux".to_string()));
+        env::remove_var("TEST_PREPARED_FOO_BAR_BAZ");
+    }
+
+    #[test]
+    fn docs() {
+        let schema = Schema::new()
+            .required("db.uri", Type::String)
+            .describe("db.uri", "Where the database is.")
+            .optional("log-level", Type::String)
+            .describe("log-level", "One of error|warn|info.")
+            .required("listen.port", Type::Int)
+            .restart_required("listen.port");
+        let env = Environment::new("app");
+        assert_eq!(
+            env.markdown(&schema),
+            "| Variable | Type | Required | Description |\n\
+             | --- | --- | --- | --- |\n\
+             | `APP_DB_URI` | string | yes | Where the database is. |\n\
+             | `APP_LOG-LEVEL` | string | no | One of error\\|warn\\|info. |\n\
+             | `APP_LISTEN_PORT` | int | yes | Changes need a restart. |\n"
+        );
+        assert_eq!(
+            env.man_page(&schema),
+            ".TP\n.B APP_DB_URI\n(string, required) Where the database is.\n\
+             .TP\n.B APP_LOG\\-LEVEL\n(string, optional) One of error|warn|info.\n\
+             .TP\n.B APP_LISTEN_PORT\n(int, required) Changes need a restart.\n"
+        );
+    }
+
+    #[test]
+    fn to_env_vars() {
+        let m = HashMap::from([("mongo.uri", "mongodb://"), ("port", "80")]);
+        assert_eq!(
+            m.to_env_vars("app"),
+            vec![
+                (
+                    OsString::from("APP_MONGO_URI"),
+                    OsString::from("mongodb://")
+                ),
+                (OsString::from("APP_PORT"), OsString::from("80")),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn config_envs() {
+        let m = HashMap::from([("mongo.uri", "mongodb://")]);
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg("echo $APP_MONGO_URI")
+            .config_envs(&m, "app")
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "mongodb://\n");
+    }
 }
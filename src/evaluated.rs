@@ -0,0 +1,294 @@
+//! Configuration from programs (Dhall or Jsonnet) that are evaluated
+//! into a record and flattened into keys.
+//!
+//! The programs are evaluated with the standard command line tools
+//! (`dhall-to-json` and `jsonnet`), which need to be on the `PATH`.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use crate::value::Value;
+use crate::{redact, Config, Error};
+
+/// Run the given evaluator, optionally feeding it a program on
+/// standard input, and flatten the JSON record it prints.
+fn evaluate(mut cmd: Command, stdin: Option<&str>) -> Result<HashMap<String, String>, Error> {
+    let name = cmd.get_program().to_string_lossy().to_string();
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::Eval(format!("running {}: {}", name, e)))?;
+
+    // The program is written from another thread while the output is
+    // read, so neither side blocks on a full pipe. Dropping the handle
+    // after writing closes the pipe.
+    let writer = child.stdin.take().map(|mut pipe| {
+        let program = stdin.unwrap_or_default().to_string();
+        thread::spawn(move || pipe.write_all(program.as_bytes()))
+    });
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| Error::Eval(format!("running {}: {}", name, e)))?;
+    if !output.status.success() {
+        return Err(Error::Eval(format!(
+            "{} failed: {}",
+            name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    if let Some(Ok(Err(e))) = writer.map(|w| w.join()) {
+        return Err(Error::Eval(format!("running {}: {}", name, e)));
+    }
+
+    match serde_json::from_slice::<serde_json::Value>(&output.stdout) {
+        Ok(v @ serde_json::Value::Object(_)) => Ok(Value::from(v).flatten()),
+        Ok(_) => Err(Error::Eval(format!("{} didn't produce a record", name))),
+        Err(e) => Err(Error::Parse(e.to_string())),
+    }
+}
+
+/// A configuration evaluated from a Dhall program.
+#[derive(PartialEq, Clone)]
+#[cfg(feature = "dhall")]
+pub struct Dhall {
+    values: HashMap<String, String>,
+}
+
+#[cfg(feature = "dhall")]
+impl Dhall {
+    /// Evaluate the given Dhall program, which must produce a record.
+    /// Nested records are flattened into dot-notation keys and lists
+    /// are rendered using the syntax `Config::list` expects.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Self, Error> {
+        Ok(Self {
+            values: evaluate(Command::new("dhall-to-json"), Some(s))?,
+        })
+    }
+
+    /// Similar to `from_str` except that the program is read from
+    /// the given path, so relative imports are resolved from there.
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let mut cmd = Command::new("dhall-to-json");
+        cmd.arg("--file").arg(path);
+        Ok(Self {
+            values: evaluate(cmd, None)?,
+        })
+    }
+}
+
+#[cfg(feature = "dhall")]
+impl Config for Dhall {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values.get(key).map(|value| value.to_string())
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.values
+            .get(key)
+            .map(|value| Cow::Borrowed(value.as_str()))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+}
+
+#[cfg(feature = "dhall")]
+impl fmt::Debug for Dhall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redact::debug(self, f)
+    }
+}
+
+/// A configuration evaluated from a Jsonnet program.
+#[derive(PartialEq, Clone)]
+#[cfg(feature = "jsonnet")]
+pub struct Jsonnet {
+    values: HashMap<String, String>,
+}
+
+#[cfg(feature = "jsonnet")]
+impl Jsonnet {
+    /// Evaluate the given Jsonnet program, which must produce an
+    /// object. Nested objects are flattened into dot-notation keys
+    /// and arrays are rendered using the syntax `Config::list`
+    /// expects. External variables (`std.extVar`) can be given as
+    /// key/value pairs.
+    pub fn from_str(s: &str, ext_vars: &[(&str, &str)]) -> Result<Self, Error> {
+        let mut cmd = jsonnet(ext_vars);
+        cmd.arg("-");
+        Ok(Self {
+            values: evaluate(cmd, Some(s))?,
+        })
+    }
+
+    /// Similar to `from_str` except that the program is read from
+    /// the given path, so relative imports are resolved from there.
+    pub fn from_file(path: &str, ext_vars: &[(&str, &str)]) -> Result<Self, Error> {
+        // After `--`, a path starting with `-` can't be taken for an
+        // option.
+        let mut cmd = jsonnet(ext_vars);
+        cmd.arg("--").arg(path);
+        Ok(Self {
+            values: evaluate(cmd, None)?,
+        })
+    }
+}
+
+/// The `jsonnet` command with the given external variables.
+#[cfg(feature = "jsonnet")]
+fn jsonnet(ext_vars: &[(&str, &str)]) -> Command {
+    let mut cmd = Command::new("jsonnet");
+    for (k, v) in ext_vars.iter() {
+        cmd.arg("--ext-str").arg(format!("{}={}", k, v));
+    }
+    cmd
+}
+
+#[cfg(feature = "jsonnet")]
+impl Config for Jsonnet {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values.get(key).map(|value| value.to_string())
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.values
+            .get(key)
+            .map(|value| Cow::Borrowed(value.as_str()))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+}
+
+#[cfg(feature = "jsonnet")]
+impl fmt::Debug for Jsonnet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redact::debug(self, f)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use crate::evaluated::evaluate;
+    use crate::Error;
+    use std::process::Command;
+
+    #[test]
+    fn evaluate_json() {
+        let mut cmd = Command::new("cat");
+        cmd.arg("-");
+        let values = evaluate(
+            cmd,
+            Some(r#"{"mongo": {"uri": "mongodb://", "port": 27017}, "tags": ["a", "b"]}"#),
+        )
+        .unwrap();
+        assert_eq!(values["mongo.uri"], "mongodb://");
+        assert_eq!(values["mongo.port"], "27017");
+        assert_eq!(values["tags"], "[a, b]");
+    }
+
+    #[test]
+    fn evaluate_large() {
+        // Bigger than a pipe's buffer in both directions.
+        let big = "x".repeat(1 << 20);
+        let mut cmd = Command::new("cat");
+        cmd.arg("-");
+        let values = evaluate(cmd, Some(&format!(r#"{{"big": "{}"}}"#, big))).unwrap();
+        assert_eq!(values["big"].len(), big.len());
+    }
+
+    #[test]
+    #[cfg(feature = "jsonnet")]
+    fn jsonnet_ext_vars() {
+        let cmd = crate::evaluated::jsonnet(&[("env", "prod"), ("region", "eu")]);
+        let args: Vec<_> = cmd.get_args().collect();
+        assert_eq!(args, ["--ext-str", "env=prod", "--ext-str", "region=eu"]);
+    }
+
+    /// Whether the given evaluator is on the `PATH`; the tests that run
+    /// one are skipped when it isn't.
+    fn installed(program: &str) -> bool {
+        Command::new(program).arg("--version").output().is_ok()
+    }
+
+    #[test]
+    #[cfg(feature = "dhall")]
+    fn dhall() {
+        use crate::testing::TempDir;
+        use crate::{Config, Dhall};
+        if !installed("dhall-to-json") {
+            return;
+        }
+
+        let program = r#"{ db = { host = "localhost", port = 5432 }, tags = [ "a", "b" ] }"#;
+        let cfg = Dhall::from_str(program).unwrap();
+        assert_eq!(cfg.string("db.host"), "localhost");
+        assert_eq!(cfg.int("db.port"), 5432);
+        assert_eq!(cfg.list("tags"), vec!["a", "b"]);
+
+        let dir = TempDir::new("dhall");
+        let path = dir.join("config.dhall");
+        std::fs::write(&path, program).unwrap();
+        assert!(Dhall::from_file(&path.to_string_lossy()).unwrap() == cfg);
+
+        assert!(matches!(Dhall::from_str("{ db = "), Err(Error::Eval(_))));
+    }
+
+    #[test]
+    #[cfg(feature = "jsonnet")]
+    fn jsonnet() {
+        use crate::testing::TempDir;
+        use crate::{Config, Jsonnet};
+        if !installed("jsonnet") {
+            return;
+        }
+
+        let program = r#"{
+            db: { host: "db." + std.extVar("region"), port: 5432 },
+            env: std.extVar("env"),
+            tags: ["a", "b"],
+        }"#;
+        let ext_vars = [("env", "prod"), ("region", "eu")];
+        let cfg = Jsonnet::from_str(program, &ext_vars).unwrap();
+        assert_eq!(cfg.string("db.host"), "db.eu");
+        assert_eq!(cfg.int("db.port"), 5432);
+        assert_eq!(cfg.string("env"), "prod");
+        assert_eq!(cfg.list("tags"), vec!["a", "b"]);
+
+        let dir = TempDir::new("jsonnet");
+        let path = dir.join("config.jsonnet");
+        std::fs::write(&path, program).unwrap();
+        assert!(Jsonnet::from_file(&path.to_string_lossy(), &ext_vars).unwrap() == cfg);
+
+        // Without the external variables the program doesn't evaluate.
+        assert!(matches!(
+            Jsonnet::from_str(program, &[]),
+            Err(Error::Eval(_))
+        ));
+    }
+
+    #[test]
+    fn evaluate_errors() {
+        let mut cmd = Command::new("cat");
+        cmd.arg("-");
+        assert!(matches!(evaluate(cmd, Some("[1, 2]")), Err(Error::Eval(_))));
+        assert!(matches!(
+            evaluate(Command::new("false"), None),
+            Err(Error::Eval(_))
+        ));
+        assert!(matches!(
+            evaluate(Command::new("/i/hope/i/do/not/exist"), None),
+            Err(Error::Eval(_))
+        ));
+    }
+}
@@ -0,0 +1,190 @@
+//! A/B experiments whose variants and weights are read from a config.
+
+use crate::hash::fnv1a;
+use crate::{weighted, Config, ValueError};
+
+type Listener = Box<dyn Fn(&Exposure) + Send + Sync>;
+
+/// An ID being assigned a variant of an experiment, passed to the
+/// listeners added with `Experiments::on_exposure` (e.g. to log it
+/// for analysis).
+#[derive(Debug, PartialEq, Clone)]
+pub struct Exposure {
+    /// The name of the experiment (e.g. `experiment.checkout`).
+    pub experiment: String,
+
+    /// The ID that was bucketed (e.g. a user ID).
+    pub id: String,
+
+    /// The variant the ID was assigned.
+    pub variant: String,
+}
+
+/// Experiments backed by a config. Each experiment lists its variants
+/// and their weights under `<experiment>.variants`:
+///
+/// ```ini
+/// experiment.checkout.variants.control = 80
+/// experiment.checkout.variants.one_page = 20
+/// ```
+///
+/// An ID is assigned a variant by a stable hash of the experiment and
+/// the ID, so the same ID always gets the same variant as long as the
+/// weights don't change. The config must be able to list its keys.
+///
+/// ```
+/// use dinglebit_config::{Config, Experiments};
+/// use std::collections::HashMap;
+///
+/// let cfg = HashMap::from([
+///     ("experiment.checkout.variants.control", "80"),
+///     ("experiment.checkout.variants.one_page", "20"),
+/// ]);
+/// let experiments = Experiments::new(&cfg).on_exposure(|e| {
+///     println!("{} saw {} of {}", e.id, e.variant, e.experiment);
+/// });
+/// let variant = experiments.bucket("experiment.checkout", "user-42");
+/// assert_eq!(cfg.bucket("experiment.checkout", "user-42"), variant);
+/// ```
+pub struct Experiments<'a> {
+    config: &'a dyn Config,
+    listeners: Vec<Listener>,
+}
+
+impl<'a> Experiments<'a> {
+    /// Read experiments from the given config.
+    pub fn new(config: &'a dyn Config) -> Self {
+        Self {
+            config,
+            listeners: vec![],
+        }
+    }
+
+    /// Call the given function whenever an ID is assigned a variant.
+    pub fn on_exposure<F: Fn(&Exposure) + Send + Sync + 'static>(mut self, f: F) -> Self {
+        self.listeners.push(Box::new(f));
+        self
+    }
+
+    /// The variants of the experiment and their weights, sorted by
+    /// name so the assignments don't depend on the order of the keys.
+    pub fn variants(&self, experiment: &str) -> Result<Vec<(String, u64)>, ValueError> {
+        let prefix = format!("{}.variants.", experiment);
+        let mut keys: Vec<String> = self
+            .config
+            .keys()
+            .into_iter()
+            .filter(|k| k.len() > prefix.len() && k.starts_with(&prefix))
+            .collect();
+        keys.sort();
+        keys.dedup();
+
+        let mut variants = vec![];
+        for key in keys.iter() {
+            let weight = self.config.try_get_ref(key)?;
+            match weight.trim().parse::<u64>() {
+                Ok(w) => variants.push((key[prefix.len()..].to_string(), w)),
+                Err(_) => {
+                    return Err(ValueError::invalid(
+                        key,
+                        weight.into_owned(),
+                        "weight (a whole number)",
+                    ))
+                }
+            }
+        }
+        let total = variants
+            .iter()
+            .try_fold(0u64, |t, (_, w)| t.checked_add(*w));
+        match (variants.is_empty(), total) {
+            (true, _) => Err(ValueError::Missing(format!("{}.variants", experiment))),
+            (false, Some(t)) if t > 0 => Ok(variants),
+            (false, _) => Err(ValueError::invalid(
+                &format!("{}.variants", experiment),
+                variants
+                    .iter()
+                    .map(|(v, w)| format!("{}:{}", v, w))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                "weights with a total above 0",
+            )),
+        }
+    }
+
+    /// The variant of the experiment for the given ID, or panics if
+    /// the experiment can't be read.
+    pub fn bucket(&self, experiment: &str, id: &str) -> String {
+        self.try_bucket(experiment, id).unwrap()
+    }
+
+    /// Similar to `bucket` but returns an error instead of panicking.
+    /// Listeners are only told about successful assignments.
+    pub fn try_bucket(&self, experiment: &str, id: &str) -> Result<String, ValueError> {
+        let variants = self.variants(experiment)?;
+        let options: Vec<(&str, u64)> = variants.iter().map(|(v, w)| (v.as_str(), *w)).collect();
+        let variant = weighted::pick(&options, fnv1a(&[experiment, id])).to_string();
+        let exposure = Exposure {
+            experiment: experiment.to_string(),
+            id: id.to_string(),
+            variant,
+        };
+        for listener in self.listeners.iter() {
+            listener(&exposure);
+        }
+        Ok(exposure.variant)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Config, Experiments, Exposure, ValueError};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn bucket() {
+        let cfg = HashMap::from([
+            ("checkout.variants.control", "50"),
+            ("checkout.variants.treatment", "50"),
+            ("off.variants.control", "1"),
+            ("off.variants.treatment", "0"),
+            ("broken.variants.a", "lots"),
+            ("zero.variants.a", "0"),
+        ]);
+        let seen = Arc::new(Mutex::new(vec![]));
+        let record = seen.clone();
+        let experiments =
+            Experiments::new(&cfg).on_exposure(move |e| record.lock().unwrap().push(e.clone()));
+
+        let ids: Vec<String> = (0..1000).map(|i| format!("user-{}", i)).collect();
+        let control = ids
+            .iter()
+            .filter(|id| experiments.bucket("checkout", id) == "control")
+            .count();
+        assert!(control > 400 && control < 600, "{}", control);
+        assert_eq!(seen.lock().unwrap().len(), 1000);
+        assert_eq!(
+            seen.lock().unwrap()[0],
+            Exposure {
+                experiment: "checkout".to_string(),
+                id: "user-0".to_string(),
+                variant: cfg.bucket("checkout", "user-0"),
+            }
+        );
+        assert!(ids.iter().all(|id| cfg.bucket("off", id) == "control"));
+
+        assert_eq!(
+            experiments.try_bucket("missing", "user-1"),
+            Err(ValueError::Missing("missing.variants".to_string()))
+        );
+        assert!(matches!(
+            experiments.try_bucket("broken", "user-1"),
+            Err(ValueError::Invalid { key, .. }) if key == "broken.variants.a"
+        ));
+        assert!(matches!(
+            cfg.try_bucket("zero", "user-1"),
+            Err(ValueError::Invalid { .. })
+        ));
+        assert_eq!(seen.lock().unwrap().len(), 1000);
+    }
+}
@@ -0,0 +1,518 @@
+//! Values computed from other keys, so relationships between settings
+//! can be written down instead of duplicating numbers.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::{Config, NumberFormat, SourceHealth, Value, ValueError};
+
+const PREFIX: &str = "expr:";
+
+/// Wraps a config so values starting with `expr:` are evaluated as
+/// expressions. An expression can use:
+///
+/// - integers, floats and double-quoted strings (e.g. `"-replica"`),
+/// - the value of another key with `${key}`, which may itself be an
+///   expression,
+/// - `+`, `-`, `*`, `/` and `%` with the usual precedence and
+///   parentheses, where `+` joins strings,
+/// - `min(a, b, ...)` and `max(a, b, ...)`.
+///
+/// Values of other keys that look like numbers are numbers. Integer
+/// division rounds towards zero, as in Rust.
+///
+/// ```
+/// use dinglebit_config::{Config, Expressions};
+/// use std::collections::HashMap;
+///
+/// let cfg = Expressions::new(HashMap::from([
+///     ("cpu.count", "8"),
+///     ("pool.max", "expr: ${cpu.count} * 4"),
+///     ("pool.min", "expr: max(${pool.max} / 10, 2)"),
+///     ("name", "expr: \"worker-\" + ${cpu.count}"),
+/// ]));
+/// assert_eq!(cfg.int("pool.max"), 32);
+/// assert_eq!(cfg.int("pool.min"), 3);
+/// assert_eq!(cfg.string("name"), "worker-8");
+/// ```
+///
+/// An expression that can't be evaluated (e.g. it refers to a missing
+/// key or keys refer to each other in a cycle) has no value from
+/// `get`, and `try_get_ref` returns a `ValueError::Invalid` saying why.
+pub struct Expressions<C> {
+    config: C,
+}
+
+impl<C: Config> Expressions<C> {
+    /// Wrap the given config.
+    pub fn new(config: C) -> Self {
+        Self { config }
+    }
+
+    /// The value of the key, evaluated if it's an expression. `stack`
+    /// holds the keys being evaluated, to catch cycles.
+    fn evaluate(&self, key: &str, stack: &mut Vec<String>) -> Result<Option<String>, ValueError> {
+        let value = match self.config.get_ref(key) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        let expr = match value.trim_start().strip_prefix(PREFIX) {
+            Some(expr) => expr,
+            None => return Ok(Some(value.into_owned())),
+        };
+        let invalid = |reason: &str| {
+            ValueError::invalid(key, value.to_string(), &format!("expression ({})", reason))
+        };
+        if stack.iter().any(|k| k == key) {
+            stack.push(key.to_string());
+            return Err(invalid(&format!("cycle {}", stack.join(" -> "))));
+        }
+
+        stack.push(key.to_string());
+        let result = tokenize(expr).map_err(Failure::Reason).and_then(|tokens| {
+            let mut parser = Parser {
+                tokens,
+                pos: 0,
+                resolve: &mut |k: &str| self.evaluate(k, stack),
+            };
+            let v = parser.expr()?;
+            match parser.tokens.get(parser.pos) {
+                None => Ok(v),
+                Some(t) => Err(Failure::Reason(format!("unexpected {}", t))),
+            }
+        });
+        stack.pop();
+        match result {
+            Ok(v) => Ok(Some(v.to_string())),
+            Err(Failure::Reason(reason)) => Err(invalid(&reason)),
+            Err(Failure::Value(e)) => Err(e),
+        }
+    }
+}
+
+impl<C: Config> Config for Expressions<C> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.evaluate(key, &mut vec![]).ok().flatten()
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.get(key).map(Cow::Owned)
+    }
+
+    fn get_value(&self, key: &str) -> Option<Value> {
+        match self.config.get_ref(key) {
+            Some(v) if v.trim_start().starts_with(PREFIX) => {
+                self.get(key).map(|v| Value::parse(&v))
+            }
+            _ => self.config.get_value(key),
+        }
+    }
+
+    fn try_get_ref(&self, key: &str) -> Result<Cow<'_, str>, ValueError> {
+        match self.evaluate(key, &mut vec![])? {
+            Some(v) => Ok(Cow::Owned(v)),
+            None => Err(ValueError::Missing(key.to_string())),
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.config.keys()
+    }
+
+    fn source_health(&self) -> SourceHealth {
+        self.config.source_health()
+    }
+
+    fn source_name(&self) -> String {
+        self.config.source_name()
+    }
+
+    fn source_of(&self, key: &str) -> Option<String> {
+        self.config.source_of(key)
+    }
+}
+
+/// Why an expression couldn't be evaluated: a problem with the
+/// expression itself, or with a key it refers to.
+enum Failure {
+    Reason(String),
+    Value(ValueError),
+}
+
+impl From<String> for Failure {
+    fn from(reason: String) -> Self {
+        Failure::Reason(reason)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Val {
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+impl Val {
+    /// A value of another key: a number if it looks like one.
+    fn from_value(s: &str) -> Self {
+        let format = NumberFormat::default();
+        if let Some(i) = format.parse_int(s) {
+            return Val::Int(i);
+        }
+        match format.parse_float(s) {
+            Some(f) if f.is_finite() => Val::Float(f),
+            _ => Val::Str(s.to_string()),
+        }
+    }
+
+    fn float(&self) -> Option<f64> {
+        match self {
+            Val::Int(i) => Some(*i as f64),
+            Val::Float(f) => Some(*f),
+            Val::Str(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Val {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Val::Int(i) => write!(f, "{}", i),
+            Val::Float(x) => write!(f, "{}", x),
+            Val::Str(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+    Val(Val),
+    Ref(String),
+    Name(String),
+    Op(char),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Val(Val::Str(s)) => write!(f, "\"{}\"", s),
+            Token::Val(v) => write!(f, "'{}'", v),
+            Token::Ref(key) => write!(f, "'${{{}}}'", key),
+            Token::Name(name) => write!(f, "'{}'", name),
+            Token::Op(c) => write!(f, "'{}'", c),
+        }
+    }
+}
+
+fn tokenize(expr: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = expr.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '0'..='9' | '.' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_ascii_alphanumeric() || "._".contains(chars[i]))
+                {
+                    i += 1;
+                }
+                let s: String = chars[start..i].iter().collect();
+                match Val::from_value(&s) {
+                    Val::Str(_) => return Err(format!("invalid number '{}'", s)),
+                    v => tokens.push(Token::Val(v)),
+                }
+            }
+            '"' => {
+                let mut s = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err("unclosed string".to_string()),
+                        Some('"') => break,
+                        Some('\\') if i + 1 < chars.len() => {
+                            s.push(chars[i + 1]);
+                            i += 2;
+                        }
+                        Some(c) => {
+                            s.push(*c);
+                            i += 1;
+                        }
+                    }
+                }
+                i += 1;
+                tokens.push(Token::Val(Val::Str(s)));
+            }
+            '$' if chars.get(i + 1) == Some(&'{') => {
+                let end = chars[i..]
+                    .iter()
+                    .position(|&c| c == '}')
+                    .ok_or_else(|| "unclosed '${'".to_string())?;
+                let key: String = chars[i + 2..i + end].iter().collect();
+                tokens.push(Token::Ref(key.trim().to_string()));
+                i += end + 1;
+            }
+            '+' | '-' | '*' | '/' | '%' | '(' | ')' | ',' => {
+                tokens.push(Token::Op(c));
+                i += 1;
+            }
+            c if c.is_ascii_alphabetic() => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token::Name(chars[start..i].iter().collect()));
+            }
+            c => return Err(format!("unexpected '{}'", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+type Resolve<'a> = dyn FnMut(&str) -> Result<Option<String>, ValueError> + 'a;
+
+/// A recursive descent parser that evaluates as it goes:
+///
+/// ```text
+/// expr    = term (("+" | "-") term)*
+/// term    = unary (("*" | "/" | "%") unary)*
+/// unary   = "-" unary | primary
+/// primary = value | "${" key "}" | "(" expr ")" | name "(" expr ("," expr)* ")"
+/// ```
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    resolve: &'a mut Resolve<'a>,
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek_op(&self) -> Option<char> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(c)) => Some(*c),
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, op: char) -> Result<(), Failure> {
+        match self.next() {
+            Some(Token::Op(c)) if c == op => Ok(()),
+            Some(t) => Err(format!("expected '{}', got {}", op, t).into()),
+            None => Err(format!("expected '{}'", op).into()),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Val, Failure> {
+        let mut left = self.term()?;
+        while let Some(op @ ('+' | '-')) = self.peek_op() {
+            self.pos += 1;
+            let right = self.term()?;
+            left = apply(op, left, right)?;
+        }
+        Ok(left)
+    }
+
+    fn term(&mut self) -> Result<Val, Failure> {
+        let mut left = self.unary()?;
+        while let Some(op @ ('*' | '/' | '%')) = self.peek_op() {
+            self.pos += 1;
+            let right = self.unary()?;
+            left = apply(op, left, right)?;
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Val, Failure> {
+        if self.peek_op() == Some('-') {
+            self.pos += 1;
+            let v = self.unary()?;
+            return apply('-', Val::Int(0), v);
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Val, Failure> {
+        match self.next() {
+            Some(Token::Val(v)) => Ok(v),
+            Some(Token::Ref(key)) => match (self.resolve)(&key).map_err(Failure::Value)? {
+                Some(v) => Ok(Val::from_value(v.trim())),
+                None => Err(format!("no value for '{}'", key).into()),
+            },
+            Some(Token::Op('(')) => {
+                let v = self.expr()?;
+                self.expect(')')?;
+                Ok(v)
+            }
+            Some(Token::Name(name)) => {
+                self.expect('(')?;
+                let mut args = vec![self.expr()?];
+                while self.peek_op() == Some(',') {
+                    self.pos += 1;
+                    args.push(self.expr()?);
+                }
+                self.expect(')')?;
+                call(&name, args)
+            }
+            Some(t) => Err(format!("unexpected {}", t).into()),
+            None => Err("unexpected end".to_string().into()),
+        }
+    }
+}
+
+fn apply(op: char, left: Val, right: Val) -> Result<Val, Failure> {
+    if op == '+' && (matches!(left, Val::Str(_)) || matches!(right, Val::Str(_))) {
+        return Ok(Val::Str(format!("{}{}", left, right)));
+    }
+    let overflow = || Failure::Reason("overflow".to_string());
+    match (&left, &right) {
+        (Val::Int(a), Val::Int(b)) => {
+            if (op == '/' || op == '%') && *b == 0 {
+                return Err("division by zero".to_string().into());
+            }
+            let v = match op {
+                '+' => a.checked_add(*b),
+                '-' => a.checked_sub(*b),
+                '*' => a.checked_mul(*b),
+                '/' => a.checked_div(*b),
+                _ => a.checked_rem(*b),
+            };
+            v.map(Val::Int).ok_or_else(overflow)
+        }
+        _ => match (left.float(), right.float()) {
+            (Some(a), Some(b)) => {
+                let v = match op {
+                    '+' => a + b,
+                    '-' => a - b,
+                    '*' => a * b,
+                    '/' => a / b,
+                    _ => a % b,
+                };
+                match v.is_finite() {
+                    true => Ok(Val::Float(v)),
+                    false => Err("division by zero".to_string().into()),
+                }
+            }
+            _ => Err(format!("can't use '{}' with a string", op).into()),
+        },
+    }
+}
+
+fn call(name: &str, args: Vec<Val>) -> Result<Val, Failure> {
+    let pick = |want: std::cmp::Ordering| -> Result<Val, Failure> {
+        let mut best: Option<Val> = None;
+        for arg in args.iter() {
+            let f = arg
+                .float()
+                .ok_or_else(|| format!("{} needs numbers", name))?;
+            match &best {
+                Some(b) if f.partial_cmp(&b.float().unwrap_or(f)) != Some(want) => {}
+                _ => best = Some(arg.clone()),
+            }
+        }
+        Ok(best.unwrap_or(Val::Int(0)))
+    };
+    match name {
+        "min" => pick(std::cmp::Ordering::Less),
+        "max" => pick(std::cmp::Ordering::Greater),
+        _ => Err(format!("unknown function '{}'", name).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::Expressions;
+    use crate::{Config, ValueError};
+    use std::collections::HashMap;
+
+    fn eval(expr: &str) -> Result<String, ValueError> {
+        let x = format!("expr: {}", expr);
+        let cfg = Expressions::new(HashMap::from([
+            ("n", "10"),
+            ("ratio", "0.5"),
+            ("name", "app"),
+            ("x", x.as_str()),
+        ]));
+        cfg.try_get("x")
+    }
+
+    #[test]
+    fn evaluate() {
+        for (expr, want) in &[
+            ("1 + 2 * 3", "7"),
+            ("(1 + 2) * 3", "9"),
+            ("-${n} + 1", "-9"),
+            ("${n} / 3", "3"),
+            ("${n} % 3", "1"),
+            ("${n} * ${ratio}", "5"),
+            ("${n} / 4.0", "2.5"),
+            ("1_000 * 2", "2000"),
+            ("min(${n}, 4, 7)", "4"),
+            ("max(${ratio}, 0.25)", "0.5"),
+            ("${name} + \"-\" + ${n}", "app-10"),
+            ("\"say \\\"hi\\\"\"", "say \"hi\""),
+        ] {
+            assert_eq!(eval(expr), Ok(want.to_string()), "{}", expr);
+        }
+
+        for (expr, reason) in &[
+            ("${n} / 0", "division by zero"),
+            ("${n} % 0.0", "division by zero"),
+            ("${name} * 2", "can't use '*' with a string"),
+            ("${missing} + 1", "no value for 'missing'"),
+            ("1 +", "unexpected end"),
+            ("(1 + 2", "expected ')'"),
+            ("1 2", "unexpected '2'"),
+            ("abs(1)", "unknown function 'abs'"),
+            ("9223372036854775807 + 1", "overflow"),
+            ("1 # 2", "unexpected '#'"),
+        ] {
+            assert_eq!(
+                eval(expr),
+                Err(ValueError::Invalid {
+                    key: "x".to_string(),
+                    value: format!("expr: {}", expr),
+                    expected: format!("expression ({})", reason),
+                }),
+                "{}",
+                expr
+            );
+        }
+    }
+
+    #[test]
+    fn nested() {
+        let cfg = Expressions::new(HashMap::from([
+            ("cpu.count", "4"),
+            ("workers", "expr: ${cpu.count} * 2"),
+            ("pool.max", "expr: ${workers} * 4"),
+            ("plain", "expr is not at the start: expr: 1"),
+            ("a", "expr: ${b} + 1"),
+            ("b", "expr: ${c} + 1"),
+            ("c", "expr: ${a} + 1"),
+        ]));
+        assert_eq!(cfg.int("pool.max"), 32);
+        assert_eq!(cfg.string("plain"), "expr is not at the start: expr: 1");
+        assert_eq!(cfg.get_value("workers"), Some(crate::Value::Int(8)));
+
+        assert_eq!(cfg.get("a"), None);
+        assert_eq!(
+            cfg.try_get("a"),
+            Err(ValueError::Invalid {
+                key: "a".to_string(),
+                value: "expr: ${b} + 1".to_string(),
+                expected: "expression (cycle a -> b -> c -> a)".to_string(),
+            })
+        );
+        assert_eq!(
+            cfg.try_get("missing"),
+            Err(ValueError::Missing("missing".to_string()))
+        );
+    }
+}
@@ -0,0 +1,291 @@
+//! Feature flags read from a config, and flag services used as a
+//! config.
+
+use std::borrow::Cow;
+
+use crate::hash::fnv1a;
+use crate::{parse_bool, Config};
+
+/// Feature flags backed by a config. Each flag is a key whose value
+/// is made up of `;`-separated rules:
+///
+/// ```ini
+/// dark_mode = on
+/// new_checkout = 25%
+/// beta_search = allow: alice, bob; deny: mallory; 10%
+/// ```
+///
+/// A flag is enabled for an ID if the ID is allowed, and disabled if
+/// it's denied (deny wins). Otherwise a percentage enables it for
+/// that share of IDs, chosen by a stable hash of the flag name and ID
+/// so the same ID always gets the same answer. Plain bool values
+/// (`on`, `true`, `0`, ...) turn the flag on or off for everyone.
+/// Missing flags and values that can't be understood are disabled.
+pub struct Flags<'a> {
+    config: &'a dyn Config,
+    prefix: String,
+}
+
+/// The parsed rules of a flag.
+#[derive(Debug, PartialEq, Default)]
+struct Rules {
+    allow: Vec<String>,
+    deny: Vec<String>,
+    percent: Option<f64>,
+    on: Option<bool>,
+}
+
+impl Rules {
+    fn parse(s: &str) -> Option<Self> {
+        let mut rules = Rules::default();
+        for rule in s.split(';').map(str::trim).filter(|r| !r.is_empty()) {
+            let list = |l: &str| -> Vec<String> {
+                l.split(',')
+                    .map(|id| id.trim().to_string())
+                    .filter(|id| !id.is_empty())
+                    .collect()
+            };
+            if let Some(ids) = rule.strip_prefix("allow:") {
+                rules.allow.extend(list(ids));
+            } else if let Some(ids) = rule.strip_prefix("deny:") {
+                rules.deny.extend(list(ids));
+            } else if let Some(p) = rule.strip_suffix('%') {
+                match p.trim().parse::<f64>() {
+                    Ok(p) if (0.0..=100.0).contains(&p) => rules.percent = Some(p),
+                    _ => return None,
+                }
+            } else {
+                rules.on = Some(parse_bool(rule)?);
+            }
+        }
+        Some(rules)
+    }
+
+    fn enabled(&self, name: &str, id: Option<&str>) -> bool {
+        if let Some(id) = id {
+            if self.deny.iter().any(|d| d == id) {
+                return false;
+            }
+            if self.allow.iter().any(|a| a == id) {
+                return true;
+            }
+        }
+        match (self.percent, id) {
+            (Some(p), _) if p >= 100.0 => true,
+            (Some(p), Some(id)) => ((fnv1a(&[name, id]) % 10_000) as f64) < p * 100.0,
+            (Some(_), None) => false,
+            (None, _) => self.on.unwrap_or(false),
+        }
+    }
+}
+
+impl<'a> Flags<'a> {
+    /// Read flags from the given config, using the flag names as
+    /// keys.
+    pub fn new(config: &'a dyn Config) -> Self {
+        Self {
+            config,
+            prefix: String::new(),
+        }
+    }
+
+    /// Look flags up under the given prefix (e.g. with the prefix
+    /// `flags`, the flag `new_checkout` is read from
+    /// `flags.new_checkout`).
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = match prefix.is_empty() {
+            true => String::new(),
+            false => format!("{}.", prefix),
+        };
+        self
+    }
+
+    fn rules(&self, name: &str) -> Option<Rules> {
+        let value = self.config.get_ref(&format!("{}{}", self.prefix, name))?;
+        Rules::parse(&value)
+    }
+
+    /// Whether the flag is on for everyone. Flags that depend on an ID
+    /// (lists or percentages under 100%) are off.
+    pub fn enabled(&self, name: &str) -> bool {
+        self.rules(name).is_some_and(|r| r.enabled(name, None))
+    }
+
+    /// Whether the flag is on for the given ID (e.g. a user or
+    /// account ID).
+    pub fn enabled_for(&self, name: &str, id: &str) -> bool {
+        self.rules(name).is_some_and(|r| r.enabled(name, Some(id)))
+    }
+}
+
+/// A feature flag service (e.g. Unleash) that can be used as a config
+/// layer with `FlagLayer`.
+pub trait FlagService {
+    /// Whether the flag is on, or `None` if there is no such flag.
+    fn enabled(&self, name: &str) -> Option<bool>;
+
+    /// The name of the variant of the flag, if it has one.
+    fn variant(&self, _name: &str) -> Option<String> {
+        None
+    }
+
+    /// The names of the flags.
+    fn names(&self) -> Vec<String>;
+}
+
+/// Exposes a flag service as a config, so its flags resolve through
+/// the same interface as the rest of the configuration. With the
+/// prefix `flags`, the flag `new_ui` is `flags.new_ui` (`true` or
+/// `false`) and its variant is `flags.new_ui.variant`.
+///
+/// ```
+/// use dinglebit_config::flags::{FlagLayer, FlagService};
+/// use dinglebit_config::Config;
+///
+/// struct Service;
+///
+/// impl FlagService for Service {
+///     fn enabled(&self, name: &str) -> Option<bool> {
+///         match name {
+///             "new_ui" => Some(true),
+///             _ => None,
+///         }
+///     }
+///
+///     fn names(&self) -> Vec<String> {
+///         vec!["new_ui".to_string()]
+///     }
+/// }
+///
+/// let cfg = FlagLayer::new(Service, "flags");
+/// assert!(cfg.bool("flags.new_ui"));
+/// ```
+pub struct FlagLayer<S> {
+    service: S,
+    prefix: String,
+}
+
+impl<S: FlagService> FlagLayer<S> {
+    /// Expose the flags of the given service under the given prefix.
+    pub fn new(service: S, prefix: &str) -> Self {
+        Self {
+            service,
+            prefix: match prefix.is_empty() {
+                true => String::new(),
+                false => format!("{}.", prefix),
+            },
+        }
+    }
+
+    /// The flag service.
+    pub fn service(&self) -> &S {
+        &self.service
+    }
+}
+
+impl<S: FlagService> Config for FlagLayer<S> {
+    fn get(&self, key: &str) -> Option<String> {
+        let name = key.strip_prefix(self.prefix.as_str())?;
+        match name.strip_suffix(".variant") {
+            Some(name) => self.service.variant(name),
+            None => self.service.enabled(name).map(|on| on.to_string()),
+        }
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.get(key).map(Cow::Owned)
+    }
+
+    fn keys(&self) -> Vec<String> {
+        let mut keys = vec![];
+        for name in self.service.names() {
+            if self.service.variant(&name).is_some() {
+                keys.push(format!("{}{}.variant", self.prefix, name));
+            }
+            keys.push(format!("{}{}", self.prefix, name));
+        }
+        keys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::flags::{FlagLayer, FlagService, Flags};
+    use crate::Config;
+    use std::collections::HashMap;
+
+    #[test]
+    fn enabled() {
+        let cfg = HashMap::from([
+            ("flags.dark_mode", "on"),
+            ("flags.legacy", "false"),
+            ("flags.everyone", "100%"),
+            ("flags.half", "50%"),
+            ("flags.beta", "allow: alice, bob; deny: mallory; 10%"),
+            ("flags.broken", "sometimes"),
+        ]);
+        let flags = Flags::new(&cfg).with_prefix("flags");
+
+        assert!(flags.enabled("dark_mode"));
+        assert!(!flags.enabled("legacy"));
+        assert!(flags.enabled("everyone"));
+        assert!(!flags.enabled("half"));
+        assert!(!flags.enabled("missing"));
+        assert!(!flags.enabled_for("broken", "alice"));
+
+        assert!(flags.enabled_for("beta", "alice"));
+        assert!(!flags.enabled_for("beta", "mallory"));
+        assert!(!flags.enabled("beta"));
+
+        // The same ID always gets the same answer and roughly the
+        // right share of IDs are enabled.
+        let on = (0..10_000)
+            .filter(|i| flags.enabled_for("half", &i.to_string()))
+            .count();
+        assert!((4_500..5_500).contains(&on), "{}", on);
+        assert_eq!(
+            flags.enabled_for("half", "user-1"),
+            flags.enabled_for("half", "user-1")
+        );
+    }
+
+    #[test]
+    fn layer() {
+        struct Service(HashMap<&'static str, (bool, Option<&'static str>)>);
+
+        impl FlagService for Service {
+            fn enabled(&self, name: &str) -> Option<bool> {
+                self.0.get(name).map(|f| f.0)
+            }
+
+            fn variant(&self, name: &str) -> Option<String> {
+                self.0.get(name)?.1.map(str::to_string)
+            }
+
+            fn names(&self) -> Vec<String> {
+                self.0.keys().map(|n| n.to_string()).collect()
+            }
+        }
+
+        let service = Service(HashMap::from([
+            ("new_ui", (true, Some("blue"))),
+            ("legacy", (false, None)),
+        ]));
+        let cfg = FlagLayer::new(service, "flags");
+        assert!(cfg.bool("flags.new_ui"));
+        assert!(!cfg.bool("flags.legacy"));
+        assert_eq!(cfg.string("flags.new_ui.variant"), "blue");
+        assert_eq!(cfg.get("flags.legacy.variant"), None);
+        assert_eq!(cfg.get("flags.missing"), None);
+        assert_eq!(cfg.get("new_ui"), None);
+        let mut keys = cfg.keys();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec!["flags.legacy", "flags.new_ui", "flags.new_ui.variant"]
+        );
+
+        // Flags from the service work with `Flags` as well.
+        assert!(Flags::new(&cfg).with_prefix("flags").enabled("new_ui"));
+    }
+}
@@ -0,0 +1,361 @@
+//! Pick a parser based on a file's extension or contents.
+
+use std::fs::read_to_string;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::{Config, DotEnv, Error, Ini, Properties, Simple};
+
+/// The file formats this package can parse. Some of them require a
+/// feature to be enabled.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    Simple,
+    Ini,
+    Env,
+    Properties,
+    Json,
+    Toml,
+    Yaml,
+    Hocon,
+    Ron,
+    Kdl,
+    Xml,
+}
+
+impl Format {
+    /// The lowercase name of the format (e.g. `json`).
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Simple => "simple",
+            Format::Ini => "ini",
+            Format::Env => "env",
+            Format::Properties => "properties",
+            Format::Json => "json",
+            Format::Toml => "toml",
+            Format::Yaml => "yaml",
+            Format::Hocon => "hocon",
+            Format::Ron => "ron",
+            Format::Kdl => "kdl",
+            Format::Xml => "xml",
+        }
+    }
+
+    /// The format with the given name (as returned by `name`), if
+    /// there is one.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "simple" => Some(Format::Simple),
+            "ini" => Some(Format::Ini),
+            "env" => Some(Format::Env),
+            "properties" => Some(Format::Properties),
+            "json" => Some(Format::Json),
+            "toml" => Some(Format::Toml),
+            "yaml" | "yml" => Some(Format::Yaml),
+            "hocon" => Some(Format::Hocon),
+            "ron" => Some(Format::Ron),
+            "kdl" => Some(Format::Kdl),
+            "xml" => Some(Format::Xml),
+            _ => None,
+        }
+    }
+
+    /// Guess the format from the extension of the given path. Files
+    /// named `.env` (or `.env.local`, etc.) are environment files.
+    /// `None` is returned for unknown extensions.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let path = Path::new(path);
+        if compression(path).is_some() {
+            return Format::from_path(&path.with_extension("").to_string_lossy());
+        }
+        let name = path.file_name()?.to_string_lossy();
+        if name == ".env" || name.starts_with(".env.") {
+            return Some(Format::Env);
+        }
+        let ext = path.extension()?.to_string_lossy().to_lowercase();
+        match ext.as_str() {
+            "cfg" => Some(Format::Simple),
+            "ini" => Some(Format::Ini),
+            "env" => Some(Format::Env),
+            "properties" => Some(Format::Properties),
+            "json" => Some(Format::Json),
+            "toml" => Some(Format::Toml),
+            "yaml" | "yml" => Some(Format::Yaml),
+            "hocon" => Some(Format::Hocon),
+            #[cfg(feature = "hocon")]
+            "conf" => Some(Format::Hocon),
+            "ron" => Some(Format::Ron),
+            "kdl" => Some(Format::Kdl),
+            "xml" => Some(Format::Xml),
+            _ => None,
+        }
+    }
+
+    /// Guess the format from the contents of a file. This only
+    /// distinguishes between the most common formats and falls back
+    /// to `Simple`.
+    pub fn sniff(s: &str) -> Self {
+        let trimmed = s.trim_start();
+        if trimmed.starts_with('<') {
+            return Format::Xml;
+        } else if trimmed.starts_with('{') {
+            return Format::Json;
+        } else if trimmed.starts_with("---") {
+            return Format::Yaml;
+        }
+
+        let lines: Vec<&str> = s
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#') && !l.starts_with(';'))
+            .collect();
+
+        if lines.iter().any(|l| l.starts_with('[') && l.ends_with(']')) {
+            #[cfg(feature = "toml")]
+            {
+                if s.parse::<::toml::Table>().is_ok() {
+                    return Format::Toml;
+                }
+            }
+            return Format::Ini;
+        }
+
+        let env_name = |l: &str| match l.split_once('=') {
+            Some((k, _)) => {
+                let k = k.trim();
+                !k.is_empty()
+                    && k.chars()
+                        .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+            }
+            None => false,
+        };
+        if !lines.is_empty()
+            && lines
+                .iter()
+                .all(|l| l.starts_with("export ") || env_name(l))
+        {
+            return Format::Env;
+        }
+
+        if !lines.is_empty()
+            && lines.iter().all(|l| {
+                !l.contains('=') && (l.contains(": ") || l.ends_with(':') || l.starts_with("- "))
+            })
+        {
+            return Format::Yaml;
+        }
+
+        Format::Simple
+    }
+
+    /// Parse the given string using this format.
+    pub fn load_str(&self, s: &str) -> Result<Box<dyn Config + Send + Sync>, Error> {
+        #[allow(unreachable_patterns)]
+        match self {
+            Format::Simple => Ok(Box::new(Simple::from_str(s)?)),
+            Format::Ini => Ok(Box::new(Ini::from_str(s)?)),
+            Format::Env => Ok(Box::new(DotEnv::from_str(s)?)),
+            Format::Properties => Ok(Box::new(Properties::from_str(s)?)),
+            #[cfg(feature = "json")]
+            Format::Json => Ok(Box::new(crate::Json::from_str(s)?)),
+            #[cfg(feature = "toml")]
+            Format::Toml => Ok(Box::new(crate::Toml::from_str(s)?)),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => Ok(Box::new(crate::Yaml::from_str(s)?)),
+            #[cfg(feature = "hocon")]
+            Format::Hocon => Ok(Box::new(crate::Hocon::from_str(s)?)),
+            #[cfg(feature = "ron")]
+            Format::Ron => Ok(Box::new(crate::Ron::from_str(s)?)),
+            #[cfg(feature = "kdl")]
+            Format::Kdl => Ok(Box::new(crate::Kdl::from_str(s)?)),
+            #[cfg(feature = "xml")]
+            Format::Xml => Ok(Box::new(crate::Xml::from_str(s)?)),
+            f => Err(Error::Unsupported(f.name().to_string())),
+        }
+    }
+
+    /// Parse the file at the given path using this format.
+    pub fn load_file(&self, path: &str) -> Result<Box<dyn Config + Send + Sync>, Error> {
+        match self {
+            // Includes are relative to the file, so it has to be
+            // parsed from its path.
+            #[cfg(feature = "hocon")]
+            Format::Hocon => Ok(Box::new(crate::Hocon::from_file(path)?)),
+            _ => self.load_str(&read_file(path)?),
+        }
+    }
+}
+
+/// Load the configuration at the given path, choosing the parser
+/// from the file's extension (`.cfg`, `.ini`, `.env`, `.properties`,
+/// `.json`, `.toml`, `.yaml`, etc.). If the extension isn't
+/// recognized, the format is guessed from the contents. Formats whose
+/// feature isn't enabled result in `Error::Unsupported`. A path of `-`
+/// reads standard input (see `load_auto_stdin`).
+pub fn load_auto(path: &str) -> Result<Box<dyn Config + Send + Sync>, Error> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!("config.load", path).entered();
+    let loaded = load_path(path);
+    #[cfg(feature = "tracing")]
+    match &loaded {
+        Ok(config) => tracing::debug!(
+            source = %config.source_name(),
+            keys = config.keys().len(),
+            "loaded config"
+        ),
+        Err(e) => tracing::warn!(error = %e, "failed to load config"),
+    }
+    loaded
+}
+
+fn load_path(path: &str) -> Result<Box<dyn Config + Send + Sync>, Error> {
+    if path == "-" {
+        return load_auto_stdin(None);
+    }
+    match Format::from_path(path) {
+        Some(format) => format.load_file(path),
+        None => {
+            let s = read_file(path)?;
+            Format::sniff(&s).load_str(&s)
+        }
+    }
+}
+
+/// The compression of a file, from its extension.
+fn compression(path: &Path) -> Option<&'static str> {
+    match path.extension()?.to_string_lossy().to_lowercase().as_str() {
+        "gz" => Some("gz"),
+        "zst" => Some("zst"),
+        _ => None,
+    }
+}
+
+/// Read the file at the given path. With the `compression` feature,
+/// `.gz` and `.zst` files are decompressed (e.g. `app.toml.gz`);
+/// without it they are `Error::Unsupported`.
+pub(crate) fn read_file<P: AsRef<Path>>(path: P) -> Result<String, Error> {
+    let path = path.as_ref();
+    match compression(path) {
+        Some(kind) => decompress(path, kind),
+        None => read_to_string(path).map_err(|e| Error::file(path, &e)),
+    }
+}
+
+#[cfg(feature = "compression")]
+fn decompress(path: &Path, kind: &str) -> Result<String, Error> {
+    let file = std::fs::File::open(path).map_err(|e| Error::file(path, &e))?;
+    let mut s = String::new();
+    let read = match kind {
+        "gz" => flate2::read::GzDecoder::new(file).read_to_string(&mut s),
+        _ => zstd::Decoder::new(file).and_then(|mut d| d.read_to_string(&mut s)),
+    };
+    match read {
+        Ok(_) => Ok(s),
+        Err(e) => Err(Error::file(path, &e)),
+    }
+}
+
+#[cfg(not(feature = "compression"))]
+fn decompress(_path: &Path, kind: &str) -> Result<String, Error> {
+    Err(Error::Unsupported(kind.to_string()))
+}
+
+/// Load the configuration from standard input (e.g. for
+/// `generate-config | app --config -`), using the given format or
+/// guessing it from the contents.
+pub fn load_auto_stdin(hint: Option<Format>) -> Result<Box<dyn Config + Send + Sync>, Error> {
+    load_reader(io::stdin().lock(), hint)
+}
+
+/// Similar to `load_auto_stdin` but reads from the given reader.
+pub fn load_reader<R: Read>(
+    mut reader: R,
+    hint: Option<Format>,
+) -> Result<Box<dyn Config + Send + Sync>, Error> {
+    let mut s = String::new();
+    if let Err(e) = reader.read_to_string(&mut s) {
+        return Err(Error::file("-", &e));
+    }
+    hint.unwrap_or_else(|| Format::sniff(&s)).load_str(&s)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::{load_auto, load_reader, Format};
+    use crate::Error;
+
+    #[test]
+    fn from_path() {
+        assert_eq!(Format::from_path("example.cfg"), Some(Format::Simple));
+        assert_eq!(Format::from_path("/etc/app/config.YML"), Some(Format::Yaml));
+        assert_eq!(Format::from_path(".env"), Some(Format::Env));
+        assert_eq!(Format::from_path("dir/.env.local"), Some(Format::Env));
+        assert_eq!(Format::from_path("app.toml"), Some(Format::Toml));
+        assert_eq!(Format::from_path("app"), None);
+        assert_eq!(Format::from_path("app.txt"), None);
+
+        assert_eq!(Format::from_name("YML"), Some(Format::Yaml));
+        assert_eq!(Format::from_name("txt"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn compressed() {
+        use crate::Config;
+        use std::io::Write;
+
+        assert_eq!(Format::from_path("app.toml.gz"), Some(Format::Toml));
+        assert_eq!(Format::from_path("bundle.cfg.ZST"), Some(Format::Simple));
+        assert_eq!(Format::from_path("app.gz"), None);
+
+        let dir = crate::testing::TempDir::new("compressed");
+        let gz = dir.join("app.cfg.gz");
+        let zst = dir.join("app.cfg.zst");
+        let contents = "foo = bar\n";
+        let mut enc = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        enc.write_all(contents.as_bytes()).unwrap();
+        std::fs::write(&gz, enc.finish().unwrap()).unwrap();
+        std::fs::write(&zst, zstd::encode_all(contents.as_bytes(), 0).unwrap()).unwrap();
+
+        for path in [gz, zst].iter() {
+            let cfg = load_auto(path.to_str().unwrap()).unwrap();
+            assert_eq!(cfg.get("foo"), Some("bar".to_string()));
+            let cfg = crate::Simple::from_file(path.to_str().unwrap()).unwrap();
+            assert_eq!(cfg.get("foo"), Some("bar".to_string()));
+        }
+    }
+
+    #[test]
+    fn sniff() {
+        assert_eq!(Format::sniff("  <config/>"), Format::Xml);
+        assert_eq!(Format::sniff("{\"a\": 1}"), Format::Json);
+        assert_eq!(Format::sniff("---\na: 1"), Format::Yaml);
+        assert_eq!(Format::sniff("a:\n  b: 1\n- c"), Format::Yaml);
+        assert_eq!(Format::sniff("# c\nexport A=1\nB_2=x"), Format::Env);
+        assert_eq!(Format::sniff("; c\n[section]\na=1"), Format::Ini);
+        assert_eq!(Format::sniff("a.b = 1\nc = 2"), Format::Simple);
+    }
+
+    #[test]
+    fn load() {
+        let cfg = load_auto("example.cfg").unwrap();
+        assert_eq!(cfg.get("foo"), Some("bar".to_string()));
+
+        let cfg = Format::Env.load_str("FOO_BAR=baz").unwrap();
+        assert_eq!(cfg.get("foo.bar"), Some("baz".to_string()));
+
+        assert!(matches!(
+            load_auto("/i/hope/i/do/not/exist.cfg"),
+            Err(Error::File { .. })
+        ));
+
+        let cfg = load_reader("FOO_BAR=baz".as_bytes(), None).unwrap();
+        assert_eq!(cfg.get("foo.bar"), Some("baz".to_string()));
+        let cfg = load_reader("FOO_BAR=baz".as_bytes(), Some(Format::Simple)).unwrap();
+        assert_eq!(cfg.get("FOO_BAR"), Some("baz".to_string()));
+        assert!(matches!(
+            load_reader(&[0xff, 0xfe][..], None),
+            Err(Error::File { .. })
+        ));
+    }
+}
@@ -0,0 +1,416 @@
+//! Secrets from Google Cloud Secret Manager.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs::read_to_string;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use ring::rand::SystemRandom;
+use ring::signature::{RsaKeyPair, RSA_PKCS1_SHA256};
+
+use crate::http::{encode, find_json, get_json, post_form};
+use crate::{Config, Error, SourceHealth};
+
+const ENDPOINT: &str = "https://secretmanager.googleapis.com";
+const METADATA: &str =
+    "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+const TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+const SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+
+/// How to authenticate with Google Cloud.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Credential {
+    /// Application Default Credentials: the key file named by
+    /// `GOOGLE_APPLICATION_CREDENTIALS`, then the one written by
+    /// `gcloud auth application-default login`, then the service
+    /// account of the host from the metadata server.
+    Default,
+    /// A bearer token that was acquired elsewhere (e.g. with `gcloud
+    /// auth print-access-token`).
+    Token(String),
+}
+
+impl Credential {
+    /// A token and how long it can be used for.
+    fn token(&self) -> Result<(String, Duration), Error> {
+        if let Credential::Token(token) = self {
+            return Ok((token.clone(), Duration::MAX));
+        }
+        match adc_file() {
+            Some(path) => {
+                let s = read_to_string(&path).map_err(|e| Error::file(&path, &e))?;
+                let key: serde_json::Value =
+                    serde_json::from_str(&s).map_err(|e| Error::Parse(e.to_string()))?;
+                match key.get("type").and_then(|t| t.as_str()) {
+                    Some("service_account") => service_account(&key),
+                    Some("authorized_user") => authorized_user(&key, TOKEN_URI),
+                    t => Err(Error::Unsupported(format!("credentials type {:?}", t))),
+                }
+            }
+            None => access_token(get_json(METADATA, &[("Metadata-Flavor", "Google")])?),
+        }
+    }
+}
+
+/// The path of the Application Default Credentials file, if any.
+fn adc_file() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("GOOGLE_APPLICATION_CREDENTIALS") {
+        return Some(PathBuf::from(path));
+    }
+    let config = match env::var_os("APPDATA") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    let path = config
+        .join("gcloud")
+        .join("application_default_credentials.json");
+    if path.exists() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+fn field<'a>(v: &'a serde_json::Value, name: &str) -> Result<&'a str, Error> {
+    match v.get(name).and_then(|f| f.as_str()) {
+        Some(f) => Ok(f),
+        None => Err(Error::Parse(format!("expected {}", name))),
+    }
+}
+
+fn access_token(v: serde_json::Value) -> Result<(String, Duration), Error> {
+    let expires_in = v.get("expires_in").and_then(|e| e.as_u64()).unwrap_or(0);
+    Ok((
+        field(&v, "access_token")?.to_string(),
+        Duration::from_secs(expires_in),
+    ))
+}
+
+/// Exchange a signed JWT for a token (a service account key).
+fn service_account(key: &serde_json::Value) -> Result<(String, Duration), Error> {
+    let token_uri = key
+        .get("token_uri")
+        .and_then(|t| t.as_str())
+        .unwrap_or(TOKEN_URI);
+    let pem: String = field(key, "private_key")?
+        .lines()
+        .filter(|l| !l.starts_with("-----"))
+        .collect();
+    let der = STANDARD
+        .decode(pem.trim())
+        .map_err(|e| Error::Parse(e.to_string()))?;
+    let pair = RsaKeyPair::from_pkcs8(&der).map_err(|e| Error::Parse(e.to_string()))?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let claims = serde_json::json!({
+        "iss": field(key, "client_email")?,
+        "scope": SCOPE,
+        "aud": token_uri,
+        "iat": now,
+        "exp": now + 3600,
+    });
+    let message = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256","typ":"JWT"}"#),
+        URL_SAFE_NO_PAD.encode(claims.to_string())
+    );
+    let mut signature = vec![0; pair.public().modulus_len()];
+    pair.sign(
+        &RSA_PKCS1_SHA256,
+        &SystemRandom::new(),
+        message.as_bytes(),
+        &mut signature,
+    )
+    .map_err(|_| Error::Signature("signing the token request".to_string()))?;
+    let assertion = format!("{}.{}", message, URL_SAFE_NO_PAD.encode(signature));
+
+    access_token(post_form(
+        token_uri,
+        &[
+            ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+            ("assertion", &assertion),
+        ],
+    )?)
+}
+
+/// Exchange a refresh token for a token (a `gcloud` login).
+fn authorized_user(key: &serde_json::Value, token_uri: &str) -> Result<(String, Duration), Error> {
+    access_token(post_form(
+        token_uri,
+        &[
+            ("grant_type", "refresh_token"),
+            ("client_id", field(key, "client_id")?),
+            ("client_secret", field(key, "client_secret")?),
+            ("refresh_token", field(key, "refresh_token")?),
+        ],
+    )?)
+}
+
+/// Secrets of a Google Cloud project, fetched from Secret Manager
+/// when they are first asked for.
+///
+/// Secret names can't contain dots, so `__` is used as a separator
+/// (`db__password` is `db.password`). The latest version of a secret
+/// is used unless it's pinned with `version`. Secrets, and secrets that
+/// don't exist, are cached for the lifetime of the source unless a
+/// `ttl` is set. Errors aren't cached, so the next lookup tries again.
+///
+/// ```no_run
+/// use dinglebit_config::gcp::{Credential, GcpSecrets};
+/// use dinglebit_config::Config;
+///
+/// let secrets = GcpSecrets::new("my-project", Credential::Default).version("api.key", "3");
+/// let password = secrets.string("db.password");
+/// ```
+pub struct GcpSecrets {
+    project: String,
+    endpoint: String,
+    credential: Credential,
+    versions: HashMap<String, String>,
+    ttl: Option<Duration>,
+    token: Mutex<Option<(String, Instant)>>,
+    cache: Mutex<HashMap<String, (Option<String>, Instant)>>,
+}
+
+impl GcpSecrets {
+    /// Use the secrets of the given project.
+    pub fn new(project: &str, credential: Credential) -> Self {
+        Self {
+            project: project.to_string(),
+            endpoint: ENDPOINT.to_string(),
+            credential,
+            versions: HashMap::new(),
+            ttl: None,
+            token: Mutex::new(None),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Use the given version of the secret for the given key instead
+    /// of the latest one.
+    pub fn version(mut self, key: &str, version: &str) -> Self {
+        self.versions.insert(key.to_string(), version.to_string());
+        self
+    }
+
+    /// Fetch secrets again once they've been cached for the given
+    /// duration, so rotated secrets are picked up.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Use the given Secret Manager endpoint, such as a regional one
+    /// (`https://secretmanager.{region}.rep.googleapis.com`).
+    pub fn endpoint(mut self, url: &str) -> Self {
+        self.endpoint = url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// A token, fetching a new one when the current one has expired.
+    fn token(&self) -> Result<String, Error> {
+        let mut current = self.token.lock().unwrap();
+        if let Some((token, expires)) = current.as_ref() {
+            if Instant::now() < *expires {
+                return Ok(token.clone());
+            }
+        }
+        let (token, valid) = self.credential.token()?;
+        // Refresh a little early so a token doesn't expire in flight.
+        let valid = valid.saturating_sub(Duration::from_secs(60));
+        let expires = Instant::now()
+            .checked_add(valid)
+            .unwrap_or_else(|| Instant::now() + Duration::from_secs(u32::MAX as u64));
+        *current = Some((token.clone(), expires));
+        Ok(token)
+    }
+
+    /// Fetch the secret for the given key.
+    fn access(&self, key: &str) -> Result<Option<String>, Error> {
+        let url = format!(
+            "{}/v1/projects/{}/secrets/{}/versions/{}:access",
+            self.endpoint,
+            encode(&self.project),
+            encode(&key.replace('.', "__")),
+            encode(self.versions.get(key).map_or("latest", |v| v.as_str()))
+        );
+        let auth = format!("Bearer {}", self.token()?);
+        let v = match find_json(&url, &[("Authorization", &auth)])? {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        let data = v
+            .pointer("/payload/data")
+            .and_then(|d| d.as_str())
+            .unwrap_or("");
+        let data = STANDARD
+            .decode(data)
+            .map_err(|e| Error::Parse(e.to_string()))?;
+        match String::from_utf8(data) {
+            Ok(s) => Ok(Some(s)),
+            Err(e) => Err(Error::Parse(e.to_string())),
+        }
+    }
+
+    /// The names of the secrets of the project.
+    fn list(&self) -> Result<Vec<String>, Error> {
+        let auth = format!("Bearer {}", self.token()?);
+        let mut names = vec![];
+        let mut page = String::new();
+        loop {
+            let url = format!(
+                "{}/v1/projects/{}/secrets?pageToken={}",
+                self.endpoint,
+                encode(&self.project),
+                encode(&page)
+            );
+            let v = get_json(&url, &[("Authorization", &auth)])?;
+            let secrets = v.get("secrets").and_then(|s| s.as_array());
+            for secret in secrets.into_iter().flatten() {
+                if let Some(name) = secret.get("name").and_then(|n| n.as_str()) {
+                    let name = name.rsplit('/').next().unwrap_or(name);
+                    names.push(name.replace("__", "."));
+                }
+            }
+            match v.get("nextPageToken").and_then(|t| t.as_str()) {
+                Some(token) if !token.is_empty() => page = token.to_string(),
+                _ => return Ok(names),
+            }
+        }
+    }
+}
+
+impl Config for GcpSecrets {
+    fn get(&self, key: &str) -> Option<String> {
+        self.lookup(key).ok().flatten()
+    }
+
+    /// Failures to fetch a secret aren't cached, so the next lookup
+    /// tries again.
+    fn lookup(&self, key: &str) -> Result<Option<String>, Error> {
+        if let Some((value, fetched)) = self.cache.lock().unwrap().get(key) {
+            if !matches!(self.ttl, Some(ttl) if fetched.elapsed() >= ttl) {
+                return Ok(value.clone());
+            }
+        }
+        let value = self.access(key)?;
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), (value.clone(), Instant::now()));
+        Ok(value)
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.get(key).map(Cow::Owned)
+    }
+
+    /// The secrets of the project. If they can't be listed, there are
+    /// none.
+    fn keys(&self) -> Vec<String> {
+        self.list().unwrap_or_default()
+    }
+
+    /// None, as listing the secrets is a request per page.
+    fn similar_keys(&self, _key: &str) -> Vec<String> {
+        vec![]
+    }
+
+    /// Down when a token can't be acquired.
+    fn source_health(&self) -> SourceHealth {
+        match self.token() {
+            Ok(_) => SourceHealth::Ok,
+            Err(e) => SourceHealth::Down(e.to_string()),
+        }
+    }
+}
+
+/// Shows the keys of the secrets, never their values.
+impl fmt::Debug for GcpSecrets {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut keys = self.keys();
+        keys.sort();
+        f.debug_struct("GcpSecrets")
+            .field("project", &self.project)
+            .field("endpoint", &self.endpoint)
+            .field("keys", &keys)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gcp::{authorized_user, Credential, GcpSecrets};
+    use crate::http::serve;
+    use crate::Config;
+    use std::time::Duration;
+
+    #[test]
+    fn secrets() {
+        let (url, server) = serve(4, |request| {
+            if request.contains("/secrets/db__password/versions/latest:access") {
+                (200, r#"{"payload": {"data": "aHVudGVyMg=="}}"#.to_string())
+            } else if request.contains("/secrets/api__key/versions/3:access") {
+                (200, r#"{"payload": {"data": "YWJj"}}"#.to_string())
+            } else if request.contains("/secrets?") {
+                let body = r#"{"secrets": [{"name": "projects/p/secrets/db__password"},
+                    {"name": "projects/p/secrets/api__key"}]}"#;
+                (200, body.to_string())
+            } else {
+                (404, "{}".to_string())
+            }
+        });
+        let secrets = GcpSecrets::new("p", Credential::Token("t".to_string()))
+            .endpoint(&url)
+            .version("api.key", "3");
+        assert_eq!(secrets.health(), Ok(()));
+        assert_eq!(secrets.string("db.password"), "hunter2");
+        assert_eq!(secrets.string("api.key"), "abc");
+        assert_eq!(secrets.get("missing"), None);
+        assert_eq!(secrets.keys(), vec!["db.password", "api.key"]);
+
+        // Cached, including the missing one.
+        assert_eq!(secrets.string("db.password"), "hunter2");
+        assert_eq!(secrets.get("missing"), None);
+        let requests = server.join().unwrap();
+        assert!(requests.iter().all(|r| r
+            .iter()
+            .any(|h| h.to_lowercase() == "authorization: bearer t")));
+
+        // Expired entries are fetched again; errors aren't cached.
+        let secrets = GcpSecrets::new("p", Credential::Token("t".to_string()))
+            .endpoint(&url)
+            .ttl(Duration::from_secs(0));
+        assert_eq!(secrets.get("db.password"), None);
+    }
+
+    #[test]
+    fn refresh() {
+        let (url, server) = serve(1, |_| {
+            (
+                200,
+                r#"{"access_token": "t", "expires_in": 3599}"#.to_string(),
+            )
+        });
+        let key = serde_json::json!({
+            "type": "authorized_user",
+            "client_id": "id",
+            "client_secret": "secret",
+            "refresh_token": "refresh",
+        });
+        assert_eq!(
+            authorized_user(&key, &url),
+            Ok(("t".to_string(), Duration::from_secs(3599)))
+        );
+        assert_eq!(server.join().unwrap()[0][0], "POST / HTTP/1.1");
+    }
+}
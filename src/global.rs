@@ -0,0 +1,92 @@
+//! A process-wide config that binaries install at startup and
+//! libraries can read without having it passed around.
+
+use std::sync::{Arc, OnceLock, RwLock};
+
+use crate::multi::{Miss, MissHook};
+use crate::Config;
+
+static GLOBAL: OnceLock<Box<dyn Config + Send + Sync>> = OnceLock::new();
+static MISS_HOOKS: RwLock<Vec<MissHook>> = RwLock::new(Vec::new());
+
+/// Install the global config. This can only be done once; later
+/// calls return the given config back as an error.
+///
+/// ```
+/// use dinglebit_config::{global, Config, MultiConfig, Environment};
+///
+/// let cfg = MultiConfig::new(vec![Box::new(Environment::new("app"))]);
+/// global::init(Box::new(cfg)).ok();
+/// assert!(global::try_global().is_some());
+/// ```
+pub fn init(config: Box<dyn Config + Send + Sync>) -> Result<(), Box<dyn Config + Send + Sync>> {
+    GLOBAL.set(config)
+}
+
+/// Get the global config or panics if `init` hasn't been called.
+pub fn global() -> &'static (dyn Config + Send + Sync) {
+    try_global().expect("global config used before global::init")
+}
+
+/// Get the global config if `init` has been called.
+pub fn try_global() -> Option<&'static (dyn Config + Send + Sync)> {
+    GLOBAL.get().map(|config| config.as_ref())
+}
+
+/// Call the given function whenever a key isn't found in any layer of
+/// any `MultiConfig` in the process, e.g. to log lookups for keys that
+/// don't exist anywhere, which is usually a deployment mistake. See
+/// `MultiConfig::on_miss` for a single config.
+pub fn on_miss<F: Fn(&Miss) + Send + Sync + 'static>(f: F) {
+    MISS_HOOKS.write().unwrap().push(Arc::new(f));
+}
+
+/// Whether `on_miss` has been called, so misses aren't described for
+/// nobody.
+pub(crate) fn has_miss_hooks() -> bool {
+    !MISS_HOOKS.read().unwrap().is_empty()
+}
+
+pub(crate) fn missed(miss: &Miss) {
+    for hook in MISS_HOOKS.read().unwrap().iter() {
+        hook(miss);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::global::{global, init, on_miss, try_global};
+    use crate::{Config, MultiConfig};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn init_once() {
+        let first: HashMap<String, String> = HashMap::from([("a".to_string(), "1".to_string())]);
+        let second: HashMap<String, String> = HashMap::from([("a".to_string(), "2".to_string())]);
+
+        assert!(init(Box::new(first)).is_ok());
+        let rejected = init(Box::new(second)).unwrap_err();
+        assert_eq!(rejected.get("a"), Some("2".to_string()));
+
+        assert_eq!(global().int("a"), 1);
+        let handle = std::thread::spawn(|| try_global().map(|c| c.int("a")));
+        assert_eq!(handle.join().unwrap(), Some(1));
+    }
+
+    #[test]
+    fn miss_hooks() {
+        let misses = Arc::new(Mutex::new(vec![]));
+        let record = misses.clone();
+        // Other tests miss keys too, so only this one's are kept.
+        on_miss(move |miss| {
+            if miss.key.starts_with("global_miss.") {
+                record.lock().unwrap().push(miss.key.clone());
+            }
+        });
+        let mc = MultiConfig::new(vec![Box::new(HashMap::from([("a", "1")]))]);
+        assert_eq!(mc.get("global_miss.b"), None);
+        assert_eq!(mc.get("a"), Some("1".to_string()));
+        assert_eq!(*misses.lock().unwrap(), vec!["global_miss.b".to_string()]);
+    }
+}
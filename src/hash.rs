@@ -0,0 +1,75 @@
+//! A small, stable hash for bucketing and fingerprints. Unlike
+//! `DefaultHasher`, its output doesn't change between releases or
+//! processes, so the same input always lands in the same bucket and
+//! the same config always has the same fingerprint.
+
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::hash::{BuildHasherDefault, Hasher};
+
+const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const PRIME: u64 = 0x0100_0000_01b3;
+
+/// Hash the given parts with 64-bit FNV-1a. The parts are separated
+/// by a zero byte so `("ab", "c")` and `("a", "bc")` differ.
+pub(crate) fn fnv1a(parts: &[&str]) -> u64 {
+    let mut hash = OFFSET;
+    for (i, part) in parts.iter().enumerate() {
+        if i > 0 {
+            // Hashing a zero byte; the xor would be a no-op.
+            hash = hash.wrapping_mul(PRIME);
+        }
+        for b in part.bytes() {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+    hash
+}
+
+/// FNV-1a as a `Hasher` for maps with short string keys, which it
+/// hashes several times faster than the default SipHash. It isn't
+/// resistant to collision attacks, so it's only used for maps whose
+/// keys come from configuration, not from requests.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy)]
+pub(crate) struct Fnv(u64);
+
+#[cfg(feature = "std")]
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(OFFSET)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Hasher for Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// A `HashMap` using `Fnv`.
+#[cfg(feature = "std")]
+pub(crate) type FnvHashMap<K, V> = HashMap<K, V, BuildHasherDefault<Fnv>>;
+
+#[cfg(test)]
+mod tests {
+    use crate::hash::fnv1a;
+
+    #[test]
+    fn stable() {
+        // Reference values for 64-bit FNV-1a.
+        assert_eq!(fnv1a(&[""]), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(&["a"]), 0xaf63_dc4c_8601_ec8c);
+        assert_ne!(fnv1a(&["ab", "c"]), fnv1a(&["a", "bc"]));
+    }
+}
@@ -0,0 +1,117 @@
+//! HTTP headers read from a config.
+
+use ::http::header::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::redact::REDACTED;
+use crate::{Config, Value, ValueError};
+
+/// Read the headers at the key. The value can be a map (e.g.
+/// `{Accept => application/json}`) or a list of `Name: value` entries,
+/// or the entries can be in indexed keys (`key.0`, `key.1`, ...).
+pub(crate) fn header_map<C: Config + ?Sized>(
+    config: &C,
+    key: &str,
+) -> Result<HeaderMap, ValueError> {
+    // The key of each entry (for errors), its name and its value.
+    let mut entries: Vec<(String, String, String)> = vec![];
+    match config.get_value(key) {
+        Some(Value::Map(m)) => {
+            for (name, value) in m.iter() {
+                let value = value.rendered().unwrap_or_default();
+                entries.push((format!("{}.{}", key, name), name.clone(), value));
+            }
+        }
+        Some(Value::List(l)) => {
+            for (i, line) in l.iter().enumerate() {
+                let line = line.rendered().unwrap_or_default();
+                entries.push(entry(&format!("{}.{}", key, i), &line)?);
+            }
+        }
+        Some(v) => return Err(ValueError::invalid(key, v.to_string(), "header map")),
+        None => {
+            for i in 0.. {
+                let k = format!("{}.{}", key, i);
+                match config.get_ref(&k) {
+                    Some(line) => entries.push(entry(&k, &line)?),
+                    None => break,
+                }
+            }
+            if entries.is_empty() {
+                return Err(ValueError::Missing(key.to_string()));
+            }
+        }
+    }
+
+    let mut headers = HeaderMap::new();
+    for (k, name, value) in entries.into_iter() {
+        let name = HeaderName::from_bytes(name.trim().as_bytes())
+            .map_err(|_| ValueError::invalid(&k, name.clone(), "HTTP header name"))?;
+        // Headers often carry credentials, so bad values aren't shown.
+        let value = HeaderValue::from_str(value.trim())
+            .map_err(|_| ValueError::invalid(&k, REDACTED.to_string(), "HTTP header value"))?;
+        headers.append(name, value);
+    }
+    Ok(headers)
+}
+
+/// Split a `Name: value` entry.
+fn entry(key: &str, line: &str) -> Result<(String, String, String), ValueError> {
+    match line.split_once(':') {
+        Some((name, value)) => Ok((key.to_string(), name.to_string(), value.to_string())),
+        None => Err(ValueError::invalid(
+            key,
+            line.to_string(),
+            "header like `Name: value`",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Config, ValueError};
+    use std::collections::HashMap;
+
+    #[test]
+    fn headers() {
+        let cfg = HashMap::from([
+            (
+                "client.headers",
+                "{X-Api-Key => abc, Accept => application/json}",
+            ),
+            (
+                "other.headers",
+                "[Accept: text/html, Cache-Control: no-cache]",
+            ),
+            ("repeated.headers.0", "Accept: text/html"),
+            ("repeated.headers.1", "Accept: application/json"),
+            ("bad_name.headers", "{Bad Name => x}"),
+            ("bad_value.headers.0", "Authorization: Bearer \u{7f}secret"),
+            ("bad_entry.headers", "[Accept]"),
+        ]);
+        let headers = cfg.headers("client.headers");
+        assert_eq!(headers["x-api-key"], "abc");
+        assert_eq!(headers["accept"], "application/json");
+        assert_eq!(cfg.headers("other.headers")["cache-control"], "no-cache");
+        let repeated = cfg.headers("repeated.headers");
+        assert_eq!(repeated.get_all("accept").iter().count(), 2);
+
+        assert_eq!(
+            cfg.try_headers("bad_name.headers").unwrap_err().to_string(),
+            "invalid value for 'bad_name.headers.Bad Name': expected HTTP header name, got 'Bad Name'"
+        );
+        assert_eq!(
+            cfg.try_headers("bad_value.headers")
+                .unwrap_err()
+                .to_string(),
+            "invalid value for 'bad_value.headers.0': expected HTTP header value, got '[redacted]'"
+        );
+        assert!(matches!(
+            cfg.try_headers("bad_entry.headers"),
+            Err(ValueError::Invalid { .. })
+        ));
+        assert_eq!(
+            cfg.try_headers("missing"),
+            Err(ValueError::Missing("missing".to_string()))
+        );
+    }
+}
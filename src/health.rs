@@ -0,0 +1,51 @@
+//! The health of config sources, for readiness probes.
+
+use std::fmt;
+
+/// How well a source can currently provide values, from
+/// `Config::source_health`.
+///
+/// ```
+/// use dinglebit_config::{Config, MultiConfig, SourceHealth};
+/// use std::collections::HashMap;
+///
+/// let cfg = MultiConfig::new(vec![Box::new(HashMap::from([("foo", "bar")]))]);
+/// assert_eq!(cfg.source_health(), SourceHealth::Ok);
+/// assert!(cfg.source_health().is_ready());
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SourceHealth {
+    /// The source is working.
+    Ok,
+
+    /// The source provides values but something is wrong, e.g. they
+    /// come from a cache because the source can't be reached, or some
+    /// layers of a `MultiConfig` are down.
+    Degraded(String),
+
+    /// The source can't provide values.
+    Down(String),
+}
+
+impl SourceHealth {
+    /// Whether the source is working.
+    pub fn is_ok(&self) -> bool {
+        matches!(self, SourceHealth::Ok)
+    }
+
+    /// Whether the source can provide values, even if it's degraded.
+    pub fn is_ready(&self) -> bool {
+        !matches!(self, SourceHealth::Down(_))
+    }
+}
+
+/// Renders the health as `ok`, `degraded: <reason>` or `down: <reason>`.
+impl fmt::Display for SourceHealth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SourceHealth::Ok => write!(f, "ok"),
+            SourceHealth::Degraded(reason) => write!(f, "degraded: {}", reason),
+            SourceHealth::Down(reason) => write!(f, "down: {}", reason),
+        }
+    }
+}
@@ -0,0 +1,847 @@
+//! Configuration from HOCON documents (e.g. the `application.conf`
+//! files used by JVM services).
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fmt;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use crate::format::read_file;
+use crate::value::Value;
+use crate::{redact, Config, Error};
+
+#[derive(PartialEq, Clone)]
+pub struct Hocon {
+    root: Value,
+    values: HashMap<String, String>,
+}
+
+impl Hocon {
+    /// Create a new configuration from the given HOCON document. The
+    /// document is flattened into dot-notation keys, so a value at
+    /// `mongo { uri = "..." }` is available as `mongo.uri`. Arrays are
+    /// rendered using the syntax `Config::list` expects.
+    ///
+    /// Objects with the same key are merged, `+=` appends to arrays
+    /// and substitutions (`${a.b}` and `${?a.b}`) are resolved against
+    /// the whole document, falling back to environment variables. A
+    /// substitution of a field's own path refers to its previous
+    /// value (e.g. `path = ${path}":/opt/bin"`).
+    ///
+    /// ```ini
+    /// mongo {
+    ///   host = localhost
+    ///   uri = "mongodb://"${mongo.host}"/"
+    /// }
+    /// mongo.db = test
+    /// ```
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Self, Error> {
+        Self::parse(s, None)
+    }
+
+    /// Similar to `from_str` except that the given path is used as
+    /// the contents for the string to parse. Includes are resolved
+    /// relative to the directory of the file.
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let path = Path::new(path);
+        let s = read_file(path)?;
+        let mut parser = Parser::new(&s, path.parent());
+        parser.including.extend(path.canonicalize());
+        Self::resolve(parser)
+    }
+
+    fn parse(s: &str, dir: Option<&Path>) -> Result<Self, Error> {
+        Self::resolve(Parser::new(s, dir))
+    }
+
+    fn resolve(mut parser: Parser) -> Result<Self, Error> {
+        let root = parser.document()?;
+        let mut resolver = Resolver {
+            root: &root,
+            visiting: vec![],
+        };
+        let root = resolver.object(&root)?;
+        Ok(Self {
+            values: root.flatten(),
+            root,
+        })
+    }
+}
+
+impl Config for Hocon {
+    /// Elements of arrays are available by index (e.g.
+    /// `servers.0.host`).
+    fn get(&self, key: &str) -> Option<String> {
+        match self.values.get(key) {
+            Some(value) => Some(value.to_string()),
+            None => self.root.lookup(key).and_then(Value::rendered),
+        }
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        match self.values.get(key) {
+            Some(value) => Some(Cow::Borrowed(value.as_str())),
+            None => self
+                .root
+                .lookup(key)
+                .and_then(Value::rendered)
+                .map(Cow::Owned),
+        }
+    }
+
+    fn get_value(&self, key: &str) -> Option<Value> {
+        self.root.lookup(key).cloned()
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+}
+
+impl fmt::Debug for Hocon {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redact::debug(self, f)
+    }
+}
+
+type Object = BTreeMap<String, Node>;
+
+/// A parsed but unresolved value.
+#[derive(Debug, Clone)]
+enum Node {
+    Scalar(Value),
+
+    /// An unquoted string that is part of a concatenation.
+    Unquoted(String),
+
+    /// Whitespace between the parts of a concatenation.
+    Space(String),
+
+    Subst {
+        path: Vec<String>,
+        optional: bool,
+    },
+    Concat(Vec<Node>),
+    Object(Object),
+    Array(Vec<Node>),
+}
+
+/// Characters that can't appear in unquoted strings or keys.
+const FORBIDDEN: &str = "$\"{}[]:=,+#`^?!@*&\\";
+
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    dir: Option<&'a Path>,
+    /// The files being included, outermost first, to detect cycles.
+    including: Vec<PathBuf>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(s: &str, dir: Option<&'a Path>) -> Self {
+        Self {
+            chars: s.chars().collect(),
+            pos: 0,
+            line: 1,
+            dir,
+            including: vec![],
+        }
+    }
+
+    fn error(&self, msg: &str) -> Error {
+        Error::Parse(format!("line {}: {}", self.line, msg))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), Error> {
+        match self.bump() {
+            Some(got) if got == c => Ok(()),
+            _ => Err(self.error(&format!("expected '{}'", c))),
+        }
+    }
+
+    fn at_comment(&self) -> bool {
+        self.starts_with("#") || self.starts_with("//")
+    }
+
+    /// Skip spaces and tabs, returning what was skipped.
+    fn inline_space(&mut self) -> String {
+        let mut space = String::new();
+        while let Some(c) = self.peek() {
+            if c == '\n' || !c.is_whitespace() {
+                break;
+            }
+            space.push(c);
+            self.bump();
+        }
+        space
+    }
+
+    /// Skip the rest of the line if it's a comment.
+    fn comment(&mut self) {
+        if self.at_comment() {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.bump();
+            }
+        }
+    }
+
+    /// Skip whitespace, newlines and comments.
+    fn skip_all(&mut self) {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.bump();
+                }
+                Some(_) if self.at_comment() => self.comment(),
+                _ => break,
+            }
+        }
+    }
+
+    fn document(&mut self) -> Result<Object, Error> {
+        self.skip_all();
+        let root = match self.peek() {
+            Some('{') => {
+                self.bump();
+                self.fields(Some('}'), &[])?
+            }
+            _ => self.fields(None, &[])?,
+        };
+        self.skip_all();
+        match self.peek() {
+            None => Ok(root),
+            Some(_) => Err(self.error("unexpected content after the root object")),
+        }
+    }
+
+    /// Parse the fields of an object until the closing brace (or the
+    /// end of the input for a root without braces). The prefix is the
+    /// path of the object and is used to find self-references.
+    fn fields(&mut self, close: Option<char>, prefix: &[String]) -> Result<Object, Error> {
+        let mut obj = Object::new();
+        loop {
+            self.skip_all();
+            match self.peek() {
+                None if close.is_none() => return Ok(obj),
+                None => return Err(self.error("expected '}'")),
+                Some(c) if Some(c) == close => {
+                    self.bump();
+                    return Ok(obj);
+                }
+                _ => {}
+            }
+
+            if self.starts_with("include") && matches!(self.chars.get(self.pos + 7), Some(' ')) {
+                self.pos += 7;
+                for (k, v) in self.include()? {
+                    set(&mut obj, prefix, &[k], v);
+                }
+            } else {
+                let path = self.key()?;
+                self.inline_space();
+                let append = self.starts_with("+=");
+                if append {
+                    self.pos += 2;
+                } else if matches!(self.peek(), Some('=') | Some(':')) {
+                    self.bump();
+                } else if self.peek() != Some('{') {
+                    return Err(self.error("expected '=', ':' or '{' after the key"));
+                }
+
+                let mut full = prefix.to_vec();
+                full.extend(path.iter().cloned());
+                let mut node = self.value(&full)?;
+                if append {
+                    node = Node::Concat(vec![
+                        Node::Subst {
+                            path: full,
+                            optional: true,
+                        },
+                        Node::Array(vec![node]),
+                    ]);
+                }
+                set(&mut obj, prefix, &path, node);
+            }
+
+            // A field ends with a newline, a comma or the closing brace.
+            self.inline_space();
+            self.comment();
+            match self.peek() {
+                Some(',') | Some('\n') | Some('\r') => {
+                    self.bump();
+                }
+                Some(c) if Some(c) == close => {}
+                None => {}
+                Some(c) => return Err(self.error(&format!("unexpected '{}'", c))),
+            }
+        }
+    }
+
+    /// Parse an include statement (after the `include` keyword) and
+    /// return the fields of the included file.
+    fn include(&mut self) -> Result<Object, Error> {
+        self.inline_space();
+        let mut required = false;
+        let mut parens = 0;
+        loop {
+            if self.starts_with("required(") {
+                required = true;
+                self.pos += 9;
+            } else if self.starts_with("file(") {
+                self.pos += 5;
+            } else if self.starts_with("url(") || self.starts_with("classpath(") {
+                return Err(self.error("only file includes are supported"));
+            } else {
+                break;
+            }
+            parens += 1;
+        }
+        let name = self.quoted()?;
+        for _ in 0..parens {
+            self.expect(')')?;
+        }
+
+        let path = match self.dir {
+            Some(dir) => dir.join(&name),
+            None => Path::new(&name).to_path_buf(),
+        };
+        let s = match read_to_string(&path) {
+            Ok(s) => s,
+            Err(_) if !required => return Ok(Object::new()),
+            Err(e) => return Err(Error::file(&path, &e)),
+        };
+        let canonical = path.canonicalize().map_err(|e| Error::file(&path, &e))?;
+        if let Some(start) = self.including.iter().position(|p| *p == canonical) {
+            let cycle: Vec<String> = self.including[start..]
+                .iter()
+                .chain(Some(&canonical))
+                .map(|p| p.display().to_string())
+                .collect();
+            return Err(self.error(&format!("include cycle: {}", cycle.join(" -> "))));
+        }
+        let mut parser = Parser::new(&s, path.parent());
+        parser.including = self.including.clone();
+        parser.including.push(canonical);
+        parser.document()
+    }
+
+    /// Parse a path expression like `a.b."c.d"`.
+    fn key(&mut self) -> Result<Vec<String>, Error> {
+        let mut path = vec![];
+        loop {
+            let segment = match self.peek() {
+                Some('"') => self.quoted()?,
+                _ => {
+                    let mut s = String::new();
+                    while let Some(c) = self.peek() {
+                        if c.is_whitespace()
+                            || c == '.'
+                            || FORBIDDEN.contains(c)
+                            || self.at_comment()
+                        {
+                            break;
+                        }
+                        s.push(c);
+                        self.bump();
+                    }
+                    if s.is_empty() {
+                        return Err(self.error("expected a key"));
+                    }
+                    s
+                }
+            };
+            path.push(segment);
+            match self.peek() {
+                Some('.') => {
+                    self.bump();
+                }
+                _ => return Ok(path),
+            }
+        }
+    }
+
+    /// Parse a value, which may be a concatenation of several values
+    /// on the same line.
+    fn value(&mut self, path: &[String]) -> Result<Node, Error> {
+        let mut pieces = vec![];
+        loop {
+            let space = self.inline_space();
+            match self.peek() {
+                None | Some('\n') | Some('\r') | Some(',') | Some('}') | Some(']') => break,
+                _ if self.at_comment() => break,
+                _ => {}
+            }
+            if !pieces.is_empty() && !space.is_empty() {
+                pieces.push(Node::Space(space));
+            }
+            let piece = match self.peek() {
+                Some('{') => {
+                    self.bump();
+                    Node::Object(self.fields(Some('}'), path)?)
+                }
+                Some('[') => {
+                    self.bump();
+                    self.array()?
+                }
+                Some('"') => Node::Scalar(Value::String(self.quoted()?)),
+                Some('$') => self.substitution()?,
+                _ => Node::Unquoted(self.unquoted()?),
+            };
+            pieces.push(piece);
+        }
+
+        match pieces.len() {
+            0 => Err(self.error("expected a value")),
+            1 => Ok(match pieces.remove(0) {
+                Node::Unquoted(s) => Node::Scalar(classify(&s)),
+                piece => piece,
+            }),
+            _ => Ok(Node::Concat(pieces)),
+        }
+    }
+
+    fn array(&mut self) -> Result<Node, Error> {
+        let mut items = vec![];
+        loop {
+            self.skip_all();
+            match self.peek() {
+                Some(']') => {
+                    self.bump();
+                    return Ok(Node::Array(items));
+                }
+                None => return Err(self.error("expected ']'")),
+                _ => {}
+            }
+            items.push(self.value(&[])?);
+            self.inline_space();
+            self.comment();
+            match self.peek() {
+                Some(',') | Some('\n') | Some('\r') => {
+                    self.bump();
+                }
+                Some(']') => {}
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn unquoted(&mut self) -> Result<String, Error> {
+        let mut s = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || FORBIDDEN.contains(c) || self.at_comment() {
+                break;
+            }
+            s.push(c);
+            self.bump();
+        }
+        match s.is_empty() {
+            true => Err(self.error("expected a value")),
+            false => Ok(s),
+        }
+    }
+
+    fn quoted(&mut self) -> Result<String, Error> {
+        if self.starts_with("\"\"\"") {
+            self.pos += 3;
+            let mut s = String::new();
+            loop {
+                // Extra quotes before the closing ones belong to the string.
+                if self.starts_with("\"\"\"") && !self.starts_with("\"\"\"\"") {
+                    self.pos += 3;
+                    return Ok(s);
+                }
+                match self.bump() {
+                    Some(c) => s.push(c),
+                    None => return Err(self.error("unterminated string")),
+                }
+            }
+        }
+
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                None | Some('\n') => return Err(self.error("unterminated string")),
+                Some('"') => return Ok(s),
+                Some('\\') => match self.bump() {
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some('r') => s.push('\r'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('u') => {
+                        let hex: String = (0..4).filter_map(|_| self.bump()).collect();
+                        match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                            Some(c) => s.push(c),
+                            None => return Err(self.error("invalid unicode escape")),
+                        }
+                    }
+                    Some(c) if c == '"' || c == '\\' || c == '/' => s.push(c),
+                    _ => return Err(self.error("invalid escape")),
+                },
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn substitution(&mut self) -> Result<Node, Error> {
+        self.expect('$')?;
+        self.expect('{')?;
+        let optional = self.peek() == Some('?');
+        if optional {
+            self.bump();
+        }
+        self.inline_space();
+        let path = self.key()?;
+        self.inline_space();
+        self.expect('}')?;
+        Ok(Node::Subst { path, optional })
+    }
+}
+
+/// Determine the type of a lone unquoted value.
+fn classify(s: &str) -> Value {
+    match s {
+        "true" => return Value::Bool(true),
+        "false" => return Value::Bool(false),
+        "null" => return Value::Null,
+        _ => {}
+    }
+    if let Ok(i) = s.parse::<i64>() {
+        return Value::Int(i);
+    }
+    let numeric = s
+        .chars()
+        .all(|c| c.is_ascii_digit() || c == '.' || c == '-' || c == 'e' || c == 'E');
+    match s.parse::<f64>() {
+        Ok(f) if numeric => Value::Float(f),
+        _ => Value::String(s.to_string()),
+    }
+}
+
+/// Set the node at the given path of the object (whose own path is
+/// prefix), creating intermediate objects as needed.
+fn set(obj: &mut Object, prefix: &[String], path: &[String], node: Node) {
+    let mut full = prefix.to_vec();
+    let mut obj = obj;
+    let (last, parents) = path.split_last().unwrap();
+    for p in parents {
+        full.push(p.clone());
+        let entry = obj
+            .entry(p.clone())
+            .or_insert_with(|| Node::Object(Object::new()));
+        if !matches!(entry, Node::Object(_)) {
+            *entry = Node::Object(Object::new());
+        }
+        obj = match entry {
+            Node::Object(o) => o,
+            _ => unreachable!(),
+        };
+    }
+    full.push(last.clone());
+    assign(obj, last, full, node);
+}
+
+/// Assign a field, merging objects with existing objects and
+/// replacing anything else. Self-references are replaced with the
+/// previous value.
+fn assign(obj: &mut Object, key: &str, full: Vec<String>, node: Node) {
+    let node = splice(node, &full, obj.get(key));
+    match (obj.get_mut(key), node) {
+        (Some(Node::Object(existing)), Node::Object(new)) => {
+            for (k, v) in new {
+                let mut path = full.clone();
+                path.push(k.clone());
+                assign(existing, &k, path, v);
+            }
+        }
+        (_, node) => {
+            obj.insert(key.to_string(), node);
+        }
+    }
+}
+
+fn splice(node: Node, full: &[String], prior: Option<&Node>) -> Node {
+    match (node, prior) {
+        (Node::Subst { ref path, .. }, Some(prior)) if path.as_slice() == full => prior.clone(),
+        (Node::Concat(pieces), _) => {
+            Node::Concat(pieces.into_iter().map(|p| splice(p, full, prior)).collect())
+        }
+        (node, _) => node,
+    }
+}
+
+struct Resolver<'a> {
+    root: &'a Object,
+    visiting: Vec<Vec<String>>,
+}
+
+impl<'a> Resolver<'a> {
+    fn object(&mut self, obj: &Object) -> Result<Value, Error> {
+        let mut m = BTreeMap::new();
+        for (k, v) in obj.iter() {
+            if let Some(v) = self.node(v)? {
+                m.insert(k.to_string(), v);
+            }
+        }
+        Ok(Value::Map(m))
+    }
+
+    /// Resolve a node. `None` means the node is undefined (e.g. an
+    /// optional substitution that wasn't found).
+    fn node(&mut self, node: &Node) -> Result<Option<Value>, Error> {
+        match node {
+            Node::Scalar(v) => Ok(Some(v.clone())),
+            Node::Unquoted(s) | Node::Space(s) => Ok(Some(Value::String(s.to_string()))),
+            Node::Object(obj) => self.object(obj).map(Some),
+            Node::Array(items) => {
+                let mut l = vec![];
+                for item in items.iter() {
+                    if let Some(v) = self.node(item)? {
+                        l.push(v);
+                    }
+                }
+                Ok(Some(Value::List(l)))
+            }
+            Node::Subst { path, optional } => {
+                if let Some(v) = self.lookup(path)? {
+                    return Ok(Some(v));
+                }
+                match env::var(path.join(".")) {
+                    Ok(v) => Ok(Some(Value::String(v))),
+                    Err(_) if *optional => Ok(None),
+                    Err(_) => Err(Error::Parse(format!(
+                        "could not resolve substitution ${{{}}}",
+                        path.join(".")
+                    ))),
+                }
+            }
+            Node::Concat(pieces) => self.concat(pieces),
+        }
+    }
+
+    /// Find the value at the given path. Paths that are currently
+    /// being resolved (i.e. cycles) are treated as not found.
+    fn lookup(&mut self, path: &[String]) -> Result<Option<Value>, Error> {
+        let mut obj = self.root;
+        for (i, segment) in path.iter().enumerate() {
+            let node = match obj.get(segment) {
+                None => return Ok(None),
+                Some(node) => node,
+            };
+            match node {
+                Node::Object(o) if i + 1 < path.len() => {
+                    obj = o;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let here = path[..=i].to_vec();
+            if self.visiting.contains(&here) {
+                return Ok(None);
+            }
+            self.visiting.push(here);
+            let value = self.node(node);
+            self.visiting.pop();
+
+            let mut value = value?;
+            for segment in path[i + 1..].iter() {
+                value = match value {
+                    Some(Value::Map(mut m)) => m.remove(segment),
+                    _ => None,
+                };
+            }
+            return Ok(value);
+        }
+        Ok(None)
+    }
+
+    fn concat(&mut self, pieces: &[Node]) -> Result<Option<Value>, Error> {
+        let mut values = vec![];
+        for piece in pieces.iter() {
+            if let Some(v) = self.node(piece)? {
+                values.push((v, matches!(piece, Node::Space(_))));
+            }
+        }
+        let mut solid: Vec<Value> = values
+            .iter()
+            .filter(|(_, space)| !space)
+            .map(|(v, _)| v.clone())
+            .collect();
+
+        if solid.len() < 2 {
+            return Ok(solid.pop());
+        }
+        if solid.iter().all(|v| matches!(v, Value::Map(_))) {
+            let mut merged = BTreeMap::new();
+            for v in solid {
+                if let Value::Map(m) = v {
+                    merge(&mut merged, m);
+                }
+            }
+            return Ok(Some(Value::Map(merged)));
+        }
+        if solid.iter().all(|v| matches!(v, Value::List(_))) {
+            let mut joined = vec![];
+            for v in solid {
+                if let Value::List(l) = v {
+                    joined.extend(l);
+                }
+            }
+            return Ok(Some(Value::List(joined)));
+        }
+        if solid
+            .iter()
+            .any(|v| matches!(v, Value::Map(_) | Value::List(_)))
+        {
+            return Err(Error::Parse(
+                "cannot concatenate objects or arrays with strings".to_string(),
+            ));
+        }
+        Ok(Some(Value::String(
+            values.iter().map(|(v, _)| v.to_string()).collect(),
+        )))
+    }
+}
+
+/// Deep merge the second map into the first.
+fn merge(into: &mut BTreeMap<String, Value>, from: BTreeMap<String, Value>) {
+    for (k, v) in from {
+        match (into.get_mut(&k), v) {
+            (Some(Value::Map(existing)), Value::Map(m)) => merge(existing, m),
+            (_, v) => {
+                into.insert(k, v);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hocon::Hocon;
+    use crate::testing::TempDir;
+    use crate::{Config, Error};
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn parse() {
+        let cfg = Hocon::from_str(
+            r#"
+            # comment
+            mongo {
+              host = localhost // another comment
+              port: 27017
+              uri = "mongodb://"${mongo.host}":"${mongo.port}
+            }
+            mongo { db = test }
+            mongo.db = prod
+            list = [1, 2,
+              3]
+            list += 4
+            path = /usr/bin
+            path = ${path}":/opt/bin"
+            quoted."a.b" = """multi
+line"""
+            words = hello there world
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(cfg.get("mongo.host"), Some("localhost".to_string()));
+        assert_eq!(cfg.int("mongo.port"), 27017);
+        assert_eq!(
+            cfg.get("mongo.uri"),
+            Some("mongodb://localhost:27017".to_string())
+        );
+        assert_eq!(cfg.get("mongo.db"), Some("prod".to_string()));
+        assert_eq!(cfg.list("list"), vec!["1", "2", "3", "4"]);
+        assert_eq!(cfg.get("path"), Some("/usr/bin:/opt/bin".to_string()));
+        assert_eq!(cfg.get("quoted.a.b"), Some("multi\nline".to_string()));
+        assert_eq!(cfg.get("words"), Some("hello there world".to_string()));
+    }
+
+    #[test]
+    fn substitutions() {
+        env::set_var("HOCON_TEST_HOME", "/home/test");
+        let cfg = Hocon::from_str(
+            r#"
+            home = ${HOCON_TEST_HOME}
+            missing = ${?NOT_A_REAL_HOCON_VAR}
+            base = { a = 1, b = 2 }
+            derived = ${base} { b = 3 }
+            "#,
+        )
+        .unwrap();
+        env::remove_var("HOCON_TEST_HOME");
+
+        assert_eq!(cfg.get("home"), Some("/home/test".to_string()));
+        assert_eq!(cfg.get("missing"), None);
+        assert_eq!(cfg.get("derived.a"), Some("1".to_string()));
+        assert_eq!(cfg.get("derived.b"), Some("3".to_string()));
+
+        assert!(matches!(Hocon::from_str("a = ${b}"), Err(Error::Parse(_))));
+        assert!(matches!(Hocon::from_str("a = ${a}"), Err(Error::Parse(_))));
+        assert!(matches!(Hocon::from_str("a = {"), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn include() {
+        let dir = TempDir::new("hocon-include");
+        fs::write(dir.join("base.conf"), "db { host = localhost, port = 1 }").unwrap();
+        fs::write(
+            dir.join("app.conf"),
+            "include \"base.conf\"\ninclude \"optional.conf\"\ndb.port = 2",
+        )
+        .unwrap();
+
+        let cfg = Hocon::from_file(dir.join("app.conf").to_str().unwrap()).unwrap();
+        assert_eq!(cfg.get("db.host"), Some("localhost".to_string()));
+        assert_eq!(cfg.get("db.port"), Some("2".to_string()));
+
+        fs::write(dir.join("required.conf"), "include required(\"nope.conf\")").unwrap();
+        assert!(matches!(
+            Hocon::from_file(dir.join("required.conf").to_str().unwrap()),
+            Err(Error::File { .. })
+        ));
+
+        fs::write(dir.join("a.conf"), "include \"b.conf\"\na = 1").unwrap();
+        fs::write(dir.join("b.conf"), "include \"a.conf\"\nb = 1").unwrap();
+        match Hocon::from_file(dir.join("a.conf").to_str().unwrap()) {
+            Err(Error::Parse(msg)) => {
+                assert!(msg.contains("include cycle"), "{}", msg);
+                assert!(msg.contains("b.conf"), "{}", msg);
+            }
+            other => panic!("expected a cycle error, got {:?}", other),
+        }
+        fs::write(dir.join("self.conf"), "include \"self.conf\"").unwrap();
+        assert!(matches!(
+            Hocon::from_str(&format!("include \"{}\"", dir.join("self.conf").display())),
+            Err(Error::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,152 @@
+//! A small HTTP client shared by the remote sources.
+
+use std::time::Duration;
+
+use crate::Error;
+
+/// How long to wait for a remote source before giving up.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// GET the given URL with the given headers and parse the JSON body.
+pub(crate) fn get_json(url: &str, headers: &[(&str, &str)]) -> Result<serde_json::Value, Error> {
+    #[cfg(feature = "tracing")]
+    let _span = span("GET", url).entered();
+    json(url, get(url, headers).call())
+}
+
+/// Similar to `get_json` but a `404 Not Found` is `None`.
+#[cfg(feature = "gcp")]
+pub(crate) fn find_json(
+    url: &str,
+    headers: &[(&str, &str)],
+) -> Result<Option<serde_json::Value>, Error> {
+    #[cfg(feature = "tracing")]
+    let _span = span("GET", url).entered();
+    match get(url, headers).call() {
+        Err(ureq::Error::Status(404, _)) => Ok(None),
+        result => json(url, result).map(Some),
+    }
+}
+
+/// POST the given form to the URL and parse the JSON body.
+#[cfg(feature = "gcp")]
+pub(crate) fn post_form(url: &str, form: &[(&str, &str)]) -> Result<serde_json::Value, Error> {
+    #[cfg(feature = "tracing")]
+    let _span = span("POST", url).entered();
+    json(url, ureq::post(url).timeout(TIMEOUT).send_form(form))
+}
+
+/// The span of a request to a remote source, with any password in the
+/// URL redacted.
+#[cfg(feature = "tracing")]
+fn span(method: &str, url: &str) -> tracing::Span {
+    tracing::info_span!(
+        "config.fetch",
+        method,
+        url = %crate::redact::redact("url", url)
+    )
+}
+
+fn get(url: &str, headers: &[(&str, &str)]) -> ureq::Request {
+    let mut request = ureq::get(url).timeout(TIMEOUT);
+    for (name, value) in headers {
+        request = request.set(name, value);
+    }
+    request
+}
+
+fn json(
+    url: &str,
+    result: Result<ureq::Response, ureq::Error>,
+) -> Result<serde_json::Value, Error> {
+    let parsed = parse(url, result);
+    #[cfg(feature = "tracing")]
+    if let Err(e) = &parsed {
+        tracing::warn!(error = %e, "failed to fetch config");
+    }
+    parsed
+}
+
+fn parse(
+    url: &str,
+    result: Result<ureq::Response, ureq::Error>,
+) -> Result<serde_json::Value, Error> {
+    match result {
+        Ok(response) => {
+            let body = response
+                .into_string()
+                .map_err(|e| Error::Remote(format!("{}: {}", url, e)))?;
+            serde_json::from_str(&body).map_err(|e| Error::Parse(e.to_string()))
+        }
+        Err(ureq::Error::Status(status, response)) => Err(Error::Remote(format!(
+            "{}: {} {}",
+            url,
+            status,
+            response.into_string().unwrap_or_default().trim()
+        ))),
+        Err(e) => Err(Error::Remote(e.to_string())),
+    }
+}
+
+/// Percent-encode the given string for use in a query string.
+#[cfg(any(feature = "azure", feature = "gcp"))]
+pub(crate) fn encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Serve the given number of requests on a local port, answering each
+/// with the status and body the handler returns for its request line
+/// (e.g. `GET /app/prod HTTP/1.1`). `{url}` in a body is replaced with
+/// the server's URL, for links back to it. Returns the server's URL and a
+/// handle that joins with the requests, as their request and header
+/// lines.
+#[cfg(test)]
+pub(crate) fn serve<F>(
+    requests: usize,
+    handler: F,
+) -> (String, std::thread::JoinHandle<Vec<Vec<String>>>)
+where
+    F: Fn(&str) -> (u16, String) + Send + 'static,
+{
+    use std::io::{BufRead, BufReader, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let base = url.clone();
+    let handle = std::thread::spawn(move || {
+        let mut seen = vec![];
+        for _ in 0..requests {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut lines = vec![];
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                lines.push(line.trim().to_string());
+            }
+            let (status, body) = handler(&lines[0]);
+            let body = body.replace("{url}", &base);
+            write!(
+                &stream,
+                "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+            .unwrap();
+            seen.push(lines);
+        }
+        seen
+    });
+    (url, handle)
+}
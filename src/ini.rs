@@ -0,0 +1,136 @@
+//! Configuration from INI files.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::format::read_file;
+use crate::{redact, Config, Error};
+
+#[derive(PartialEq, Clone)]
+pub struct Ini {
+    values: HashMap<String, String>,
+}
+
+/// Parse a section header like `[mongo]`, `[mongo.replica]` or
+/// `[mongo "replica"]` into a key prefix.
+fn parse_section(line: &str) -> Option<String> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?.trim();
+    let section = match inner.split_once(char::is_whitespace) {
+        Some((name, sub)) => format!("{}.{}", name, sub.trim().trim_matches('"')),
+        None => inner.to_string(),
+    };
+    Some(section)
+}
+
+fn parse(s: &str) -> Result<HashMap<String, String>, Error> {
+    let mut values = HashMap::new();
+    let mut section = String::new();
+
+    for line in s.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            section = match parse_section(line) {
+                Some(s) => s,
+                None => return Err(Error::Parse(format!("invalid section '{}'", line))),
+            };
+            continue;
+        }
+
+        let (key, value) = match line.find(['=', ':']) {
+            Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+            None => return Err(Error::InvalidKeyValuePair),
+        };
+        let value = match value.len() >= 2
+            && ((value.starts_with('"') && value.ends_with('"'))
+                || (value.starts_with('\'') && value.ends_with('\'')))
+        {
+            true => &value[1..value.len() - 1],
+            false => value,
+        };
+        let key = match section.is_empty() {
+            true => key.to_string(),
+            false => format!("{}.{}", section, key),
+        };
+        values.insert(key, value.to_string());
+    }
+
+    Ok(values)
+}
+
+impl Ini {
+    /// Create a new configuration from the given INI contents. Keys
+    /// are prefixed with their section, so `uri` in the `[mongo]`
+    /// section is available as `mongo.uri`. Keys and values are
+    /// separated by `=` or `:`, surrounding quotes are removed from
+    /// values and lines starting with `;` or `#` are comments:
+    ///
+    /// ```ini
+    /// ; i am a comment
+    /// [mongo]
+    /// uri = "mongodb://localhost/"
+    ///
+    /// [mongo "replica"]
+    /// name: rs0
+    /// ```
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Self, Error> {
+        Ok(Self { values: parse(s)? })
+    }
+
+    /// Similar to `from_str` except that the given path is used as
+    /// the contents for the string to parse.
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let file = read_file(path)?;
+        Self::from_str(&file)
+    }
+}
+
+impl Config for Ini {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values.get(key).map(|value| value.to_string())
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.values
+            .get(key)
+            .map(|value| Cow::Borrowed(value.as_str()))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+}
+
+impl fmt::Debug for Ini {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redact::debug(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ini::Ini;
+    use crate::{Config, Error};
+
+    #[test]
+    fn parse() {
+        let cfg = Ini::from_str(
+            "top = 1\n; comment\n[mongo]\nuri = \"mongodb://\"\n\n[mongo \"replica\"]\n# comment\nname: rs0\n[a.b]\nc='d'",
+        )
+        .unwrap();
+        assert_eq!(cfg.get("top"), Some("1".to_string()));
+        assert_eq!(cfg.get("mongo.uri"), Some("mongodb://".to_string()));
+        assert_eq!(cfg.get("mongo.replica.name"), Some("rs0".to_string()));
+        assert_eq!(cfg.get("a.b.c"), Some("d".to_string()));
+
+        assert_eq!(
+            Ini::from_str("[mongo]\nbad"),
+            Err(Error::InvalidKeyValuePair)
+        );
+        assert!(matches!(Ini::from_str("[mongo"), Err(Error::Parse(_))));
+    }
+}
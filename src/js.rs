@@ -0,0 +1,130 @@
+//! Configuration handed over by JavaScript, for code compiled to
+//! `wasm32-unknown-unknown` (e.g. edge workers), where there are no
+//! environment variables or files to read.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use wasm_bindgen::prelude::*;
+
+use crate::value::Value;
+use crate::{redact, Config, Error};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = Object, js_name = keys)]
+    fn object_keys(object: &JsValue) -> Vec<JsValue>;
+
+    #[wasm_bindgen(js_namespace = Reflect, js_name = get, catch)]
+    fn reflect_get(target: &JsValue, key: &JsValue) -> Result<JsValue, JsValue>;
+}
+
+/// The values of a JavaScript object, such as the `env` a worker is
+/// given. Nested objects are flattened into dot-notation keys, so
+/// `{db: {host: "..."}}` is available as `db.host`, arrays are
+/// rendered using the syntax `Config::list` expects, and `null` and
+/// `undefined` are skipped. The object is read once, when the config
+/// is created.
+///
+/// ```ignore
+/// #[wasm_bindgen]
+/// pub fn start(env: JsValue) -> Result<(), JsValue> {
+///     let cfg = JsObject::new(&env).map_err(|e| format!("{:?}", e))?;
+///     let port = cfg.int("listen.port");
+///     // ...
+/// }
+/// ```
+#[derive(PartialEq)]
+pub struct JsObject {
+    root: Value,
+    values: HashMap<String, String>,
+}
+
+impl JsObject {
+    /// Read the given object. Anything other than an object is an
+    /// error.
+    pub fn new(object: &JsValue) -> Result<Self, Error> {
+        if !object.is_object() || object.is_array() {
+            return Err(Error::Parse("expected an object".to_string()));
+        }
+        let root = convert(object)?;
+        Ok(Self {
+            values: root.flatten(),
+            root,
+        })
+    }
+}
+
+/// The JavaScript value as a `Value`. Values that aren't data (e.g.
+/// functions) are rendered the way JavaScript would show them.
+fn convert(v: &JsValue) -> Result<Value, Error> {
+    if v.is_null() || v.is_undefined() {
+        return Ok(Value::Null);
+    }
+    if let Some(b) = v.as_bool() {
+        return Ok(Value::Bool(b));
+    }
+    if let Some(n) = v.as_f64() {
+        return Ok(match n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+            true => Value::Int(n as i64),
+            false => Value::Float(n),
+        });
+    }
+    if let Some(s) = v.as_string() {
+        return Ok(Value::String(s));
+    }
+    let get = |key: &JsValue| {
+        reflect_get(v, key).map_err(|e| Error::Parse(format!("reading {:?}: {:?}", key, e)))
+    };
+    if v.is_array() {
+        return object_keys(v)
+            .iter()
+            .map(|i| convert(&get(i)?))
+            .collect::<Result<Vec<Value>, Error>>()
+            .map(Value::List);
+    }
+    if v.is_object() {
+        let mut m = BTreeMap::new();
+        for key in object_keys(v) {
+            if let Some(name) = key.as_string() {
+                m.insert(name, convert(&get(&key)?)?);
+            }
+        }
+        return Ok(Value::Map(m));
+    }
+    Ok(Value::String(format!("{:?}", v)))
+}
+
+impl Config for JsObject {
+    /// Elements of arrays are available by index (e.g.
+    /// `servers.0.host`).
+    fn get(&self, key: &str) -> Option<String> {
+        self.get_ref(key).map(Cow::into_owned)
+    }
+
+    fn get_value(&self, key: &str) -> Option<Value> {
+        self.root.lookup(key).cloned()
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        match self.values.get(key) {
+            Some(value) => Some(Cow::Borrowed(value.as_str())),
+            None => self
+                .root
+                .lookup(key)
+                .and_then(Value::rendered)
+                .map(Cow::Owned),
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+}
+
+impl fmt::Debug for JsObject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redact::debug(self, f)
+    }
+}
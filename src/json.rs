@@ -0,0 +1,136 @@
+//! Configuration from JSON documents.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::format::read_file;
+use crate::value::Value;
+use crate::{redact, Config, Error};
+
+#[derive(PartialEq, Clone)]
+pub struct Json {
+    root: Value,
+    values: HashMap<String, String>,
+}
+
+impl Json {
+    /// Create a new configuration from the given JSON document. The
+    /// document must be an object and is flattened into dot-notation
+    /// keys, so `{"mongo": {"uri": "..."}}` is available as
+    /// `mongo.uri`. Arrays are rendered using the syntax
+    /// `Config::list` expects and nulls are skipped.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Self, Error> {
+        match serde_json::from_str::<serde_json::Value>(s) {
+            Ok(v @ serde_json::Value::Object(_)) => {
+                let root = Value::from(v);
+                Ok(Self {
+                    values: root.flatten(),
+                    root,
+                })
+            }
+            Ok(_) => Err(Error::Parse("expected an object".to_string())),
+            Err(e) => Err(Error::Parse(e.to_string())),
+        }
+    }
+
+    /// Similar to `from_str` except that the given path is used as
+    /// the contents for the string to parse.
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let file = read_file(path)?;
+        Self::from_str(&file)
+    }
+}
+
+impl Config for Json {
+    /// Elements of arrays are available by index (e.g.
+    /// `servers.0.host`).
+    fn get(&self, key: &str) -> Option<String> {
+        match self.values.get(key) {
+            Some(value) => Some(value.to_string()),
+            None => self.root.lookup(key).and_then(Value::rendered),
+        }
+    }
+
+    fn get_value(&self, key: &str) -> Option<Value> {
+        self.root.lookup(key).cloned()
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        match self.values.get(key) {
+            Some(value) => Some(Cow::Borrowed(value.as_str())),
+            None => self
+                .root
+                .lookup(key)
+                .and_then(Value::rendered)
+                .map(Cow::Owned),
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+}
+
+impl fmt::Debug for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redact::debug(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json::Json;
+    use crate::{Config, Error, Value};
+
+    #[test]
+    fn parse() {
+        let cfg = Json::from_str(
+            r#"{"mongo": {"uri": "mongodb://", "port": 27017}, "ratio": 0.5,
+                "tags": ["a", "b"], "debug": true, "none": null}"#,
+        )
+        .unwrap();
+        assert_eq!(cfg.get("mongo.uri"), Some("mongodb://".to_string()));
+        assert_eq!(cfg.int("mongo.port"), 27017);
+        assert_eq!(cfg.float("ratio"), 0.5);
+        assert_eq!(cfg.list("tags"), vec!["a", "b"]);
+        assert!(cfg.bool("debug"));
+        assert_eq!(cfg.get("none"), None);
+
+        assert!(matches!(Json::from_str("[1]"), Err(Error::Parse(_))));
+        assert!(matches!(Json::from_str("{"), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn get_value() {
+        let cfg = Json::from_str(r#"{"mongo": {"uri": "mongodb://", "port": 27017}}"#).unwrap();
+        let mongo = match cfg.get_value("mongo") {
+            Some(Value::Map(m)) => m,
+            v => panic!("expected a map: {:?}", v),
+        };
+        assert_eq!(mongo["port"], Value::Int(27017));
+        assert_eq!(
+            cfg.get_value("mongo.uri"),
+            Some(Value::String("mongodb://".to_string()))
+        );
+        assert_eq!(cfg.get_value("mongo.user"), None);
+
+        let cfg = Json::from_str(r#"{"servers": [{"host": "a", "ports": [80, 443]}]}"#).unwrap();
+        assert_eq!(cfg.list_len("servers"), Some(1));
+        assert_eq!(cfg.get("servers.0.host"), Some("a".to_string()));
+        assert_eq!(cfg.int("servers.0.ports.1"), 443);
+        assert_eq!(cfg.get("servers.0"), None);
+        assert_eq!(cfg.get("servers.1.host"), None);
+
+        // String sources parse their values.
+        let m = std::collections::HashMap::from([("tags", "[a, 2]")]);
+        assert_eq!(
+            m.get_value("tags"),
+            Some(Value::List(vec![
+                Value::String("a".to_string()),
+                Value::Int(2)
+            ]))
+        );
+    }
+}
@@ -0,0 +1,533 @@
+//! Configuration from KDL documents.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::format::read_file;
+use crate::value::Value;
+use crate::{redact, Config, Error};
+
+#[derive(PartialEq, Clone)]
+pub struct Kdl {
+    values: HashMap<String, String>,
+}
+
+impl Kdl {
+    /// Create a new configuration from the given KDL document. Node
+    /// names become dot-notation keys, so
+    ///
+    /// ```kdl
+    /// mongo {
+    ///     uri "mongodb://localhost/"
+    ///     pool min=1 max=10
+    /// }
+    /// tags "a" "b"
+    /// ```
+    ///
+    /// provides `mongo.uri`, `mongo.pool.min`, `mongo.pool.max` and
+    /// `tags` (as a list). A node with a single argument maps to that
+    /// value and several arguments become a list. When a node also
+    /// has properties or children, its arguments are available by
+    /// index (e.g. `server.0`). Later nodes with the same name are
+    /// merged into earlier ones.
+    ///
+    /// Both the v1 (`true`, `r"raw"`) and v2 (`#true`, `#"raw"#`)
+    /// keyword and raw string syntax are accepted. Type annotations
+    /// are ignored.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Self, Error> {
+        let nodes = Parser::new(s).nodes(false)?;
+        Ok(Self {
+            values: Value::Map(nodes).flatten(),
+        })
+    }
+
+    /// Similar to `from_str` except that the given path is used as
+    /// the contents for the string to parse.
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let file = read_file(path)?;
+        Self::from_str(&file)
+    }
+}
+
+impl Config for Kdl {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values.get(key).map(|value| value.to_string())
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.values
+            .get(key)
+            .map(|value| Cow::Borrowed(value.as_str()))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+}
+
+impl fmt::Debug for Kdl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redact::debug(self, f)
+    }
+}
+
+/// Characters that can't appear in bare identifiers.
+const RESERVED: &str = "\\/(){}<>;[]=,\"#";
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn new(s: &str) -> Self {
+        Self {
+            chars: s.chars().collect(),
+            pos: 0,
+            line: 1,
+        }
+    }
+
+    fn error(&self, msg: &str) -> Error {
+        Error::Parse(format!("line {}: {}", self.line, msg))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, n: usize) -> Option<char> {
+        self.chars.get(self.pos + n).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars()
+            .enumerate()
+            .all(|(i, c)| self.peek_at(i) == Some(c))
+    }
+
+    /// Skip whitespace, block comments and line continuations within
+    /// a node.
+    fn inline_space(&mut self) -> Result<(), Error> {
+        loop {
+            match self.peek() {
+                Some('\n') | Some(';') | None => return Ok(()),
+                Some(c) if c.is_whitespace() => {
+                    self.bump();
+                }
+                Some('/') if self.starts_with("/*") => self.block_comment()?,
+                Some('\\') => {
+                    // A line continuation, optionally followed by a comment.
+                    self.bump();
+                    while matches!(self.peek(), Some(c) if c != '\n' && c.is_whitespace()) {
+                        self.bump();
+                    }
+                    if self.starts_with("//") {
+                        self.line_comment();
+                    }
+                    if self.bump() != Some('\n') {
+                        return Err(self.error("expected a newline after '\\'"));
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Skip whitespace, newlines, semicolons and comments between
+    /// nodes.
+    fn space(&mut self) -> Result<(), Error> {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() || c == ';' => {
+                    self.bump();
+                }
+                Some('/') if self.starts_with("//") => self.line_comment(),
+                Some('/') if self.starts_with("/*") => self.block_comment()?,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn line_comment(&mut self) {
+        while !matches!(self.peek(), None | Some('\n')) {
+            self.bump();
+        }
+    }
+
+    fn block_comment(&mut self) -> Result<(), Error> {
+        self.pos += 2;
+        let mut depth = 1;
+        while depth > 0 {
+            if self.starts_with("/*") {
+                self.pos += 2;
+                depth += 1;
+            } else if self.starts_with("*/") {
+                self.pos += 2;
+                depth -= 1;
+            } else if self.bump().is_none() {
+                return Err(self.error("unterminated comment"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse nodes until the end of the input or, for children, the
+    /// closing brace.
+    fn nodes(&mut self, children: bool) -> Result<BTreeMap<String, Value>, Error> {
+        let mut nodes = BTreeMap::new();
+        loop {
+            self.space()?;
+            match self.peek() {
+                None if children => return Err(self.error("expected '}'")),
+                None => return Ok(nodes),
+                Some('}') if children => {
+                    self.bump();
+                    return Ok(nodes);
+                }
+                _ => {}
+            }
+
+            let skip = self.slashdash()?;
+            let (name, value) = self.node()?;
+            if !skip {
+                match (nodes.get_mut(&name), value) {
+                    (Some(Value::Map(existing)), Value::Map(m)) => merge(existing, m),
+                    (_, value) => {
+                        nodes.insert(name, value);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Consume a `/-` (which comments out the next node, entry or
+    /// children block) and report whether there was one.
+    fn slashdash(&mut self) -> Result<bool, Error> {
+        if !self.starts_with("/-") {
+            return Ok(false);
+        }
+        self.pos += 2;
+        self.inline_space()?;
+        Ok(true)
+    }
+
+    fn node(&mut self) -> Result<(String, Value), Error> {
+        self.annotation()?;
+        let name = self.identifier()?;
+        let mut args = vec![];
+        let mut props = BTreeMap::new();
+        let mut children = None;
+
+        loop {
+            self.inline_space()?;
+            match self.peek() {
+                None | Some('\n') | Some(';') | Some('}') => break,
+                Some('/') if self.starts_with("//") => {
+                    self.line_comment();
+                    break;
+                }
+                _ => {}
+            }
+
+            let skip = self.slashdash()?;
+            if self.peek() == Some('{') {
+                self.bump();
+                let nodes = self.nodes(true)?;
+                if !skip {
+                    children = Some(nodes);
+                }
+                continue;
+            }
+            if children.is_some() {
+                return Err(self.error("unexpected entry after children"));
+            }
+
+            // Properties are `identifier=value`; anything else is an argument.
+            let start = (self.pos, self.line);
+            match self.identifier().ok() {
+                Some(key) if self.peek() == Some('=') => {
+                    self.bump();
+                    let value = self.value()?;
+                    if !skip {
+                        props.insert(key, value);
+                    }
+                }
+                _ => {
+                    self.pos = start.0;
+                    self.line = start.1;
+                    let value = self.value()?;
+                    if !skip {
+                        args.push(value);
+                    }
+                }
+            }
+        }
+
+        if props.is_empty() && children.is_none() {
+            let value = match args.len() {
+                0 => Value::Null,
+                1 => args.remove(0),
+                _ => Value::List(args),
+            };
+            return Ok((name, value));
+        }
+
+        let mut m = children.unwrap_or_default();
+        m.extend(props);
+        for (i, arg) in args.into_iter().enumerate() {
+            m.insert(i.to_string(), arg);
+        }
+        Ok((name, Value::Map(m)))
+    }
+
+    /// Skip a type annotation like `(u8)`.
+    fn annotation(&mut self) -> Result<(), Error> {
+        if self.peek() == Some('(') {
+            while self.peek() != Some(')') {
+                if self.bump().is_none() {
+                    return Err(self.error("unterminated type annotation"));
+                }
+            }
+            self.bump();
+        }
+        Ok(())
+    }
+
+    fn identifier(&mut self) -> Result<String, Error> {
+        match self.peek() {
+            Some('"') => self.string(),
+            Some('r') if matches!(self.peek_at(1), Some('"') | Some('#')) => self.raw_string(),
+            Some('#') if matches!(self.peek_at(1), Some('"') | Some('#')) => self.raw_string(),
+            _ => {
+                let s = self.bare();
+                match s.is_empty() {
+                    true => Err(self.error("expected an identifier")),
+                    false => Ok(s),
+                }
+            }
+        }
+    }
+
+    fn bare(&mut self) -> String {
+        let mut s = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || RESERVED.contains(c) {
+                break;
+            }
+            s.push(c);
+            self.bump();
+        }
+        s
+    }
+
+    fn value(&mut self) -> Result<Value, Error> {
+        self.annotation()?;
+        match self.peek() {
+            Some('"') => return self.string().map(Value::String),
+            Some('r') | Some('#') if matches!(self.peek_at(1), Some('"') | Some('#')) => {
+                return self.raw_string().map(Value::String)
+            }
+            Some('#') => {
+                self.bump();
+                return match self.bare().as_str() {
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    "null" => Ok(Value::Null),
+                    "inf" => Ok(Value::Float(f64::INFINITY)),
+                    "-inf" => Ok(Value::Float(f64::NEG_INFINITY)),
+                    "nan" => Ok(Value::Float(f64::NAN)),
+                    k => Err(self.error(&format!("unknown keyword '#{}'", k))),
+                };
+            }
+            _ => {}
+        }
+
+        let s = self.bare();
+        match s.as_str() {
+            "" => return Err(self.error("expected a value")),
+            "true" => return Ok(Value::Bool(true)),
+            "false" => return Ok(Value::Bool(false)),
+            "null" => return Ok(Value::Null),
+            _ => {}
+        }
+        let digits = s.trim_start_matches(['+', '-']);
+        if !digits.starts_with(|c: char| c.is_ascii_digit()) {
+            // Bare identifiers are strings in KDL v2.
+            return Ok(Value::String(s));
+        }
+        match number(&s) {
+            Some(v) => Ok(v),
+            None => Err(self.error(&format!("invalid number '{}'", s))),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.bump();
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                None => return Err(self.error("unterminated string")),
+                Some('"') => return Ok(s),
+                Some('\\') => match self.bump() {
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some('r') => s.push('\r'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('s') => s.push(' '),
+                    Some('u') => {
+                        if self.bump() != Some('{') {
+                            return Err(self.error("expected '{' in unicode escape"));
+                        }
+                        let mut hex = String::new();
+                        while let Some(c) = self.bump() {
+                            if c == '}' {
+                                break;
+                            }
+                            hex.push(c);
+                        }
+                        match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                            Some(c) => s.push(c),
+                            None => return Err(self.error("invalid unicode escape")),
+                        }
+                    }
+                    Some(c) if c == '"' || c == '\\' || c == '/' => s.push(c),
+                    Some(c) if c.is_whitespace() => {
+                        // An escaped newline/whitespace is removed entirely.
+                        while matches!(self.peek(), Some(c) if c.is_whitespace()) {
+                            self.bump();
+                        }
+                    }
+                    _ => return Err(self.error("invalid escape")),
+                },
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    /// Parse `r"..."`, `r#"..."#` (v1) or `#"..."#` (v2).
+    fn raw_string(&mut self) -> Result<String, Error> {
+        if self.peek() == Some('r') {
+            self.bump();
+        }
+        let mut hashes = 0;
+        while self.peek() == Some('#') {
+            self.bump();
+            hashes += 1;
+        }
+        if self.bump() != Some('"') {
+            return Err(self.error("expected '\"'"));
+        }
+        let close = format!("\"{}", "#".repeat(hashes));
+        let mut s = String::new();
+        while !self.starts_with(&close) {
+            match self.bump() {
+                Some(c) => s.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+        self.pos += close.len();
+        Ok(s)
+    }
+}
+
+/// Parse decimal, hex (`0x`), octal (`0o`) and binary (`0b`) numbers,
+/// which may contain underscores.
+fn number(s: &str) -> Option<Value> {
+    let s = s.replace('_', "");
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(d) => (true, d),
+        None => (false, s.trim_start_matches('+')),
+    };
+    let radix = match digits.get(..2) {
+        Some("0x") => 16,
+        Some("0o") => 8,
+        Some("0b") => 2,
+        _ => 10,
+    };
+    if radix != 10 {
+        let i = i64::from_str_radix(&digits[2..], radix).ok()?;
+        return Some(Value::Int(if negative { -i } else { i }));
+    }
+    if let Ok(i) = s.parse::<i64>() {
+        return Some(Value::Int(i));
+    }
+    s.parse::<f64>().ok().map(Value::Float)
+}
+
+/// Deep merge the second map into the first.
+fn merge(into: &mut BTreeMap<String, Value>, from: BTreeMap<String, Value>) {
+    for (k, v) in from {
+        match (into.get_mut(&k), v) {
+            (Some(Value::Map(existing)), Value::Map(m)) => merge(existing, m),
+            (_, v) => {
+                into.insert(k, v);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kdl::Kdl;
+    use crate::{Config, Error};
+
+    #[test]
+    fn parse() {
+        let cfg = Kdl::from_str(
+            r##"
+            // a comment
+            mongo {
+                uri "mongodb://localhost/"
+                pool min=1 max=0x10 /-ignored=1
+            }
+            mongo { db test; }
+            tags "a" "b" \
+                 "c"
+            /- skipped "node"
+            server "main" port=8080 {
+                tls #true
+            }
+            raw r#"C:\path"#
+            ratio (f32)1_000.5
+            /* block /* nested */ comment */
+            debug true
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(
+            cfg.get("mongo.uri"),
+            Some("mongodb://localhost/".to_string())
+        );
+        assert_eq!(cfg.int("mongo.pool.min"), 1);
+        assert_eq!(cfg.int("mongo.pool.max"), 16);
+        assert_eq!(cfg.get("mongo.pool.ignored"), None);
+        assert_eq!(cfg.get("mongo.db"), Some("test".to_string()));
+        assert_eq!(cfg.list("tags"), vec!["a", "b", "c"]);
+        assert_eq!(cfg.get("skipped"), None);
+        assert_eq!(cfg.get("server.0"), Some("main".to_string()));
+        assert_eq!(cfg.int("server.port"), 8080);
+        assert!(cfg.bool("server.tls"));
+        assert_eq!(cfg.get("raw"), Some("C:\\path".to_string()));
+        assert_eq!(cfg.float("ratio"), 1000.5);
+        assert!(cfg.bool("debug"));
+
+        assert!(matches!(Kdl::from_str("a {"), Err(Error::Parse(_))));
+        assert!(matches!(Kdl::from_str("a \"b"), Err(Error::Parse(_))));
+        assert!(matches!(Kdl::from_str("a 1x"), Err(Error::Parse(_))));
+    }
+}
@@ -0,0 +1,79 @@
+//! Checking the syntax of keys, at compile time when they're literals.
+
+/// Whether the key is well formed: one or more parts separated by
+/// single dots, where each part is made of ASCII letters, digits, `_`,
+/// `-` or `/`. So `db.uri` and `servers.0.host` are valid, but an
+/// empty key, `db..uri`, `.db`, `db.` and `db uri` aren't.
+pub const fn is_valid(key: &str) -> bool {
+    let bytes = key.as_bytes();
+    if bytes.is_empty() {
+        return false;
+    }
+    let mut i = 0;
+    let mut previous_dot = true;
+    while i < bytes.len() {
+        let b = bytes[i];
+        match b {
+            b'.' => {
+                if previous_dot {
+                    return false;
+                }
+                previous_dot = true;
+            }
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b'-' | b'/' => previous_dot = false,
+            _ => return false,
+        }
+        i += 1;
+    }
+    !previous_dot
+}
+
+/// A key literal checked at compile time (see `key::is_valid`), so a
+/// typo like `db..uri` fails the build instead of reading nothing at
+/// runtime. It evaluates to the `&'static str`.
+///
+/// ```
+/// use dinglebit_config::{key, Config};
+/// use std::collections::HashMap;
+///
+/// const DB_URI: &str = key!("db.uri");
+/// let cfg = HashMap::from([("db.uri", "postgres://db/app")]);
+/// assert_eq!(cfg.string(DB_URI), "postgres://db/app");
+/// assert_eq!(cfg.string(key!("db.uri")), "postgres://db/app");
+/// ```
+///
+/// ```compile_fail
+/// let key = dinglebit_config::key!("db..uri");
+/// ```
+#[macro_export]
+macro_rules! key {
+    ($key:expr) => {{
+        const KEY: &str = $key;
+        const _: () = assert!($crate::key::is_valid(KEY), "invalid config key");
+        KEY
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::key::is_valid;
+
+    #[test]
+    fn valid() {
+        for key in &[
+            "db",
+            "db.uri",
+            "servers.0.host",
+            "HTTP_PORT",
+            "log-level",
+            "a/b",
+        ] {
+            assert!(is_valid(key), "{}", key);
+        }
+        for key in &[
+            "", ".", "db.", ".db", "db..uri", "db uri", "db.urí", "db=uri",
+        ] {
+            assert!(!is_valid(key), "{}", key);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Keyboard shortcuts for editor-like apps that keep keymaps in
+//! their config.
+
+use alloc::string::{String, ToString};
+use core::fmt;
+
+/// What the errors for key bindings say was expected.
+pub(crate) const EXPECTED: &str = "key binding like `ctrl+shift+p`";
+
+/// Keys that have names instead of being a single character.
+const NAMED: &[&str] = &[
+    "backspace",
+    "delete",
+    "down",
+    "end",
+    "enter",
+    "escape",
+    "home",
+    "insert",
+    "left",
+    "pagedown",
+    "pageup",
+    "right",
+    "space",
+    "tab",
+    "up",
+];
+
+/// The modifier keys held down for a `KeyBinding`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub struct Modifiers {
+    /// The Control key.
+    pub ctrl: bool,
+
+    /// The Alt key, or Option on macOS.
+    pub alt: bool,
+
+    /// The Shift key.
+    pub shift: bool,
+
+    /// The Command key on macOS, and the Windows or Super key
+    /// elsewhere.
+    pub meta: bool,
+}
+
+/// A keyboard shortcut read with `Config::keybinding`: modifiers and a
+/// key joined by `+` (e.g. `ctrl+shift+p`, `alt+enter` or `f5`).
+///
+/// The modifiers are `ctrl` (or `control`), `alt` (or `option`),
+/// `shift` and `meta` (or `cmd`, `command`, `super` or `win`). The key
+/// is a single character, `f1` to `f24`, or one of `enter`, `tab`,
+/// `space`, `escape` (`esc`), `backspace`, `delete` (`del`), `insert`,
+/// `home`, `end`, `pageup`, `pagedown`, `up`, `down`, `left` or
+/// `right`. Everything is case insensitive and `ctrl++` binds the `+`
+/// key.
+///
+/// ```
+/// use dinglebit_config::{Config, KeyBinding};
+/// use std::collections::HashMap;
+///
+/// let cfg = HashMap::from([("keys.palette", "Ctrl+Shift+P")]);
+/// let palette = cfg.keybinding("keys.palette");
+/// assert!(palette.modifiers.ctrl && palette.modifiers.shift);
+/// assert_eq!(palette.key, "p");
+/// assert_eq!(palette.to_string(), "ctrl+shift+p");
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct KeyBinding {
+    /// The modifiers that must be held.
+    pub modifiers: Modifiers,
+
+    /// The key, in lowercase (e.g. `p`, `f5` or `enter`).
+    pub key: String,
+}
+
+impl KeyBinding {
+    /// Parse a key binding, returning `None` if it's malformed or uses
+    /// an unknown key or modifier.
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim().to_lowercase();
+        let (modifiers, key) = match s.strip_suffix('+') {
+            Some(m) if m.is_empty() || m.ends_with('+') => (&m[..m.len().saturating_sub(1)], "+"),
+            _ => match s.rsplit_once('+') {
+                Some((m, k)) => (m, k),
+                None => ("", s.as_str()),
+            },
+        };
+
+        let mut binding = Self {
+            modifiers: Modifiers::default(),
+            key: key_name(key.trim())?,
+        };
+        for m in modifiers.split('+').map(str::trim) {
+            let flag = match m {
+                "" if modifiers.is_empty() => continue,
+                "ctrl" | "control" => &mut binding.modifiers.ctrl,
+                "alt" | "option" => &mut binding.modifiers.alt,
+                "shift" => &mut binding.modifiers.shift,
+                "meta" | "cmd" | "command" | "super" | "win" => &mut binding.modifiers.meta,
+                _ => return None,
+            };
+            *flag = true;
+        }
+        Some(binding)
+    }
+}
+
+/// The canonical name of the key.
+fn key_name(key: &str) -> Option<String> {
+    let key = match key {
+        "esc" => "escape",
+        "del" => "delete",
+        "return" => "enter",
+        k => k,
+    };
+    if key.chars().count() == 1 || NAMED.contains(&key) {
+        return Some(key.to_string());
+    }
+    match key.strip_prefix('f').map(str::parse::<u8>) {
+        Some(Ok(n)) if (1..=24).contains(&n) => Some(key.to_string()),
+        _ => None,
+    }
+}
+
+/// Renders the binding the way `parse` reads it, with the modifiers
+/// in a fixed order (e.g. `ctrl+alt+shift+meta+k`).
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let m = self.modifiers;
+        for (held, name) in [
+            (m.ctrl, "ctrl"),
+            (m.alt, "alt"),
+            (m.shift, "shift"),
+            (m.meta, "meta"),
+        ] {
+            if held {
+                write!(f, "{}+", name)?;
+            }
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keybinding::{KeyBinding, Modifiers};
+
+    #[test]
+    fn parse() {
+        let binding = |ctrl, alt, shift, meta, key: &str| {
+            Some(KeyBinding {
+                modifiers: Modifiers {
+                    ctrl,
+                    alt,
+                    shift,
+                    meta,
+                },
+                key: key.to_string(),
+            })
+        };
+        assert_eq!(
+            KeyBinding::parse("ctrl+shift+p"),
+            binding(true, false, true, false, "p")
+        );
+        assert_eq!(
+            KeyBinding::parse(" Control + Option + Del "),
+            binding(true, true, false, false, "delete")
+        );
+        assert_eq!(
+            KeyBinding::parse("cmd+s"),
+            binding(false, false, false, true, "s")
+        );
+        assert_eq!(
+            KeyBinding::parse("F12"),
+            binding(false, false, false, false, "f12")
+        );
+        assert_eq!(
+            KeyBinding::parse("ctrl++"),
+            binding(true, false, false, false, "+")
+        );
+        assert_eq!(
+            KeyBinding::parse("+"),
+            binding(false, false, false, false, "+")
+        );
+
+        assert_eq!(KeyBinding::parse(""), None);
+        assert_eq!(KeyBinding::parse("ctrl+"), None);
+        assert_eq!(KeyBinding::parse("hyper+p"), None);
+        assert_eq!(KeyBinding::parse("ctrl+pp"), None);
+        assert_eq!(KeyBinding::parse("f25"), None);
+        assert_eq!(KeyBinding::parse("ctrl++p"), None);
+
+        let b = KeyBinding::parse("shift+win+alt+ctrl+k").unwrap();
+        assert_eq!(b.to_string(), "ctrl+alt+shift+meta+k");
+        assert_eq!(KeyBinding::parse(&b.to_string()), Some(b));
+    }
+}
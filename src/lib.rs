@@ -23,16 +23,461 @@
 //!     assert_eq!(cfg.must_get("baz"), "foo".to_string());
 //!     assert!(cfg.get("bar").is_none());
 //! }
+//! ```
+//!
+//! Without the default `std` feature, the crate is `no_std` (it needs
+//! `alloc`) and only provides the `Config` trait with its typed
+//! getters, `Value`, `NumberFormat` and the `Config` implementations
+//! for `BTreeMap` and lists of pairs, so firmware can implement
+//! `Config` for its own storage (e.g. flash). The sources, which read
+//! the environment, files or the network, need `std`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
 use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::ffi::OsString;
 
+#[cfg(feature = "azure")]
+pub mod azure;
+#[cfg(feature = "bundle")]
+pub mod bundle;
+pub mod color;
+#[cfg(feature = "std")]
+pub mod combinators;
+#[cfg(feature = "std")]
+pub mod conditional;
+#[cfg(feature = "std")]
+pub mod conn;
+#[cfg(feature = "std")]
+pub mod convert;
+#[cfg(feature = "std")]
+pub mod credentials;
+#[cfg(feature = "serde")]
+mod de;
+#[cfg(feature = "axum")]
+pub mod debug;
+#[cfg(feature = "std")]
+pub mod defaults;
+#[cfg(feature = "std")]
+pub mod diff;
+#[cfg(feature = "std")]
+pub mod dotenv;
+mod duration;
+#[cfg(feature = "std")]
+pub mod embedded;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+#[cfg(feature = "std")]
 pub mod env;
+#[cfg(any(feature = "dhall", feature = "jsonnet"))]
+pub mod evaluated;
+#[cfg(feature = "std")]
+pub mod experiment;
+#[cfg(feature = "std")]
+pub mod expr;
+#[cfg(feature = "std")]
+pub mod flags;
+#[cfg(feature = "std")]
+pub mod format;
+#[cfg(feature = "gcp")]
+pub mod gcp;
+#[cfg(feature = "git")]
+pub mod git;
+#[cfg(feature = "std")]
+pub mod global;
+mod hash;
+#[cfg(feature = "headers")]
+mod headers;
+#[cfg(feature = "std")]
+pub mod health;
+#[cfg(feature = "hocon")]
+pub mod hocon;
+#[cfg(any(
+    feature = "azure",
+    feature = "gcp",
+    feature = "spring",
+    feature = "unleash"
+))]
+mod http;
+#[cfg(feature = "std")]
+pub mod ini;
+#[cfg(feature = "js")]
+pub mod js;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "kdl")]
+pub mod kdl;
+pub mod key;
+pub mod keybinding;
+#[cfg(feature = "std")]
+pub mod mapped;
+#[cfg(feature = "mobile")]
+pub mod mobile;
+#[cfg(feature = "std")]
 pub mod multi;
+#[cfg(feature = "std")]
+pub mod namespace;
+#[cfg(feature = "std")]
+pub mod normalized;
+pub mod number;
+#[cfg(feature = "std")]
+pub mod overlay;
+#[cfg(feature = "std")]
+pub mod paas;
+#[cfg(feature = "std")]
+mod pem;
+#[cfg(feature = "persisted")]
+pub mod persisted;
+#[cfg(feature = "std")]
+pub mod policy;
+#[cfg(feature = "std")]
+pub mod properties;
+pub mod rate;
+#[cfg(feature = "std")]
+pub mod readonly;
+#[cfg(feature = "std")]
+pub mod redact;
+#[cfg(feature = "std")]
+pub mod reload;
+#[cfg(feature = "std")]
+pub mod report;
+#[cfg(feature = "std")]
+pub mod resilient;
+#[cfg(feature = "std")]
+pub mod restricted;
+#[cfg(feature = "rhai")]
+pub mod rhai;
+#[cfg(feature = "ron")]
+pub mod ron;
+#[cfg(feature = "std")]
+pub mod scheduled;
+#[cfg(feature = "std")]
+pub mod schema;
+#[cfg(feature = "serde")]
+mod ser;
+mod shell;
+#[cfg(feature = "signing")]
+pub mod signing;
+#[cfg(feature = "std")]
 pub mod simple;
+#[cfg(feature = "std")]
+pub mod snapshot;
+#[cfg(feature = "spring")]
+pub mod spring;
+#[cfg(feature = "std")]
+pub mod stats;
+mod suggest;
+#[cfg(feature = "std")]
+pub mod sys;
+#[cfg(feature = "std")]
+pub mod template;
+#[cfg(all(test, feature = "std"))]
+mod testing;
+#[cfg(feature = "std")]
+pub mod timeout;
+#[cfg(feature = "toml")]
+pub mod toml;
+#[cfg(feature = "unleash")]
+pub mod unleash;
+pub mod value;
+mod weighted;
+#[cfg(feature = "xml")]
+pub mod xml;
+#[cfg(feature = "yaml")]
+pub mod yaml;
+#[cfg(feature = "zookeeper")]
+pub mod zookeeper;
 
-pub use env::Environment;
-pub use multi::MultiConfig;
+#[cfg(feature = "rhai")]
+pub use crate::rhai::Rhai;
+#[cfg(feature = "ron")]
+pub use crate::ron::Ron;
+#[cfg(feature = "toml")]
+pub use crate::toml::Toml;
+#[cfg(feature = "azure")]
+pub use azure::{AppConfiguration, KeyVault};
+#[cfg(feature = "bundle")]
+pub use bundle::Bundle;
+pub use color::Color;
+#[cfg(feature = "std")]
+pub use combinators::{Filtered, MappedKeys, Or, Prefixed, WithDefault};
+#[cfg(feature = "std")]
+pub use conditional::Conditional;
+#[cfg(feature = "std")]
+pub use conn::ConnParts;
+#[cfg(feature = "std")]
+pub use convert::convert;
+#[cfg(feature = "std")]
+pub use credentials::SystemdCredentials;
+#[cfg(feature = "std")]
+pub use defaults::Defaults;
+#[cfg(feature = "std")]
+pub use diff::{diff, Change};
+#[cfg(feature = "std")]
+pub use dotenv::DotEnv;
+#[cfg(feature = "std")]
+pub use embedded::Embedded;
+#[cfg(feature = "encryption")]
+pub use encryption::Decrypting;
+#[cfg(feature = "std")]
+pub use env::{CommandExt, EnvKey, Environment};
+#[cfg(feature = "dhall")]
+pub use evaluated::Dhall;
+#[cfg(feature = "jsonnet")]
+pub use evaluated::Jsonnet;
+#[cfg(feature = "std")]
+pub use experiment::{Experiments, Exposure};
+#[cfg(feature = "std")]
+pub use expr::Expressions;
+#[cfg(feature = "std")]
+pub use flags::{FlagLayer, Flags};
+#[cfg(feature = "std")]
+pub use format::{load_auto, load_auto_stdin, load_reader, Format};
+#[cfg(feature = "gcp")]
+pub use gcp::GcpSecrets;
+#[cfg(feature = "git")]
+pub use git::GitSource;
+#[cfg(feature = "std")]
+pub use global::{global, try_global};
+#[cfg(feature = "std")]
+pub use health::SourceHealth;
+#[cfg(feature = "hocon")]
+pub use hocon::Hocon;
+#[cfg(feature = "std")]
+pub use ini::Ini;
+#[cfg(feature = "js")]
+pub use js::JsObject;
+#[cfg(feature = "json")]
+pub use json::Json;
+#[cfg(feature = "kdl")]
+pub use kdl::Kdl;
+pub use keybinding::{KeyBinding, Modifiers};
+#[cfg(feature = "std")]
+pub use mapped::Mapped;
+#[cfg(feature = "std")]
+pub use multi::{Builder, CloneableConfig, Degraded, Miss, MultiConfig, Policy, Resolution};
+#[cfg(feature = "std")]
+pub use namespace::Namespaced;
+#[cfg(feature = "std")]
+pub use normalized::Normalized;
+pub use number::NumberFormat;
+#[cfg(feature = "std")]
+pub use overlay::Overlay;
+#[cfg(feature = "std")]
+pub use paas::Paas;
+#[cfg(feature = "persisted")]
+pub use persisted::{Persisted, Stale};
+#[cfg(feature = "std")]
+pub use policy::{Collector, GetterPolicy, ValueErrors, WithPolicy};
+#[cfg(feature = "std")]
+pub use properties::Properties;
+pub use rate::Rate;
+#[cfg(feature = "std")]
+pub use readonly::ReadOnly;
+#[cfg(feature = "std")]
+pub use reload::{ChangeLog, Reloadable};
+#[cfg(feature = "std")]
+pub use report::StartupReport;
+#[cfg(feature = "std")]
+pub use resilient::{Resilient, Retry};
+#[cfg(feature = "std")]
+pub use restricted::Restricted;
+#[cfg(feature = "std")]
+pub use scheduled::Scheduled;
+#[cfg(feature = "std")]
+pub use schema::Schema;
+#[cfg(feature = "std")]
 pub use simple::{Error, Simple};
+#[cfg(feature = "std")]
+pub use snapshot::Snapshot;
+#[cfg(feature = "spring")]
+pub use spring::SpringCloud;
+#[cfg(feature = "std")]
+pub use stats::SourceStats;
+#[cfg(feature = "std")]
+pub use sys::SystemInfo;
+#[cfg(feature = "std")]
+pub use template::{render, render_file};
+#[cfg(feature = "std")]
+pub use timeout::Timeout;
+#[cfg(feature = "unleash")]
+pub use unleash::Unleash;
+pub use value::Value;
+#[cfg(feature = "xml")]
+pub use xml::Xml;
+#[cfg(feature = "yaml")]
+pub use yaml::Yaml;
+#[cfg(feature = "zookeeper")]
+pub use zookeeper::ZooKeeper;
+
+/// Unwrap the result of a fallible getter, suggesting similar keys in
+/// the panic message when the key is missing.
+fn found<T, C: Config + ?Sized>(config: &C, result: Result<T, ValueError>) -> T {
+    result.map_err(|e| e.with_suggestions(config)).unwrap()
+}
+
+/// The reasons a fallible (`try_*`) getter can fail.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ValueError {
+    /// No value was found for the key.
+    Missing(String),
+
+    /// A value was found but couldn't be parsed as the expected type.
+    Invalid {
+        key: String,
+        value: String,
+        expected: String,
+    },
+
+    /// The key isn't one the config may read (see `Restricted`).
+    AccessDenied(String),
+
+    /// No value was found for the key, but the config has keys with
+    /// similar names that may have been meant (e.g. `db.uri` for
+    /// `db.uir`), from `ValueError::with_suggestions`. Treat it the
+    /// same as `Missing`; see `is_missing`.
+    Misspelled {
+        key: String,
+        suggestions: Vec<String>,
+    },
+}
+
+impl ValueError {
+    /// Whether no value was found for the key (`Missing` or
+    /// `Misspelled`).
+    pub fn is_missing(&self) -> bool {
+        matches!(self, ValueError::Missing(_) | ValueError::Misspelled { .. })
+    }
+
+    /// Turn a `Missing` error into `Misspelled` if the config has keys
+    /// with similar names (see `Config::similar_keys`). Listing and
+    /// comparing the keys is slow for large configs, so the fallible
+    /// getters don't do it themselves; call this when reporting the
+    /// error.
+    ///
+    /// ```
+    /// use dinglebit_config::Config;
+    /// use std::collections::HashMap;
+    ///
+    /// let cfg = HashMap::from([("db.uri", "postgres://db/app")]);
+    /// let e = cfg.try_get_ref("db.uir").unwrap_err().with_suggestions(&cfg);
+    /// assert_eq!(
+    ///     e.to_string(),
+    ///     "missing value for 'db.uir', did you mean 'db.uri'?"
+    /// );
+    /// ```
+    pub fn with_suggestions<C: Config + ?Sized>(self, config: &C) -> Self {
+        match self {
+            ValueError::Missing(key) => {
+                let suggestions = config.similar_keys(&key);
+                match suggestions.is_empty() {
+                    true => ValueError::Missing(key),
+                    false => ValueError::Misspelled { key, suggestions },
+                }
+            }
+            e => e,
+        }
+    }
+
+    fn invalid(key: &str, value: String, expected: &str) -> Self {
+        ValueError::Invalid {
+            key: key.to_string(),
+            value,
+            expected: expected.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValueError::Missing(key) => write!(f, "missing value for '{}'", key),
+            ValueError::AccessDenied(key) => write!(f, "access denied to '{}'", key),
+            ValueError::Misspelled { key, suggestions } => write!(
+                f,
+                "missing value for '{}', did you mean '{}'?",
+                key,
+                suggestions.join("' or '")
+            ),
+            ValueError::Invalid {
+                key,
+                value,
+                expected,
+            } => write!(
+                f,
+                "invalid value for '{}': expected {}, got '{}'",
+                key, expected, value
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ValueError {}
+
+/// The keys `Config::require` found without a value, in the order
+/// they were given.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MissingKeys(pub Vec<String>);
+
+impl fmt::Display for MissingKeys {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "missing values for: {}", self.0.join(", "))
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MissingKeys {}
+
+/// Find an element within a list value, as it's written, by looking up
+/// the longest key in front of an index (e.g. `servers` for
+/// `servers.0.host`) with `get`. Keys without an index aren't looked up
+/// at all.
+fn element<'a, F>(key: &str, get: F) -> Option<Cow<'a, str>>
+where
+    F: Fn(&str) -> Option<Cow<'a, str>>,
+{
+    // Maps aren't values of their own, as with `Value::rendered`.
+    element_or_map(key, get).filter(|v| !(v.starts_with('{') && v.ends_with('}')))
+}
+
+/// Similar to `element` but the element can be a map, for `Config::map`.
+fn element_or_map<'a, F>(key: &str, get: F) -> Option<Cow<'a, str>>
+where
+    F: Fn(&str) -> Option<Cow<'a, str>>,
+{
+    key.rmatch_indices('.')
+        .filter(|(i, _)| {
+            let next = key[i + 1..].split('.').next().unwrap_or_default();
+            next.parse::<usize>().is_ok()
+        })
+        .find_map(|(i, _)| {
+            let path: Vec<&str> = key[i + 1..].split('.').collect();
+            match get(&key[..i])? {
+                Cow::Borrowed(v) => value::element_text(v, &path).map(Cow::Borrowed),
+                Cow::Owned(v) => value::element_text(&v, &path).map(|e| Cow::Owned(e.to_string())),
+            }
+        })
+}
+
+/// Parse the truthy/falsey vocabulary used by `bool` and `try_bool`.
+fn parse_bool(s: &str) -> Option<bool> {
+    match s.trim().to_lowercase().as_str() {
+        "t" | "true" | "1" | "y" | "yes" | "on" | "enabled" => Some(true),
+        "f" | "false" | "0" | "n" | "no" | "off" | "disabled" => Some(false),
+        _ => None,
+    }
+}
 
 /// The main trait for this package. This should be implemented if you
 /// want to use this package with your configuration systems.
@@ -40,69 +485,1028 @@ pub trait Config {
     /// Returns the value associated with the given key.
     fn get(&self, key: &str) -> Option<String>;
 
+    /// Similar to `get` but can return a borrowed value, avoiding an
+    /// allocation for configs that already hold their values. The
+    /// typed getters use this, so configs that can borrow should
+    /// implement it. The default calls `get`.
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.get(key).map(Cow::Owned)
+    }
+
+    /// Get the value with its structure, so lists, maps and nested
+    /// keys don't have to be parsed out of strings. Structured sources
+    /// (e.g. JSON or TOML) return what they parsed, including the map
+    /// under a key like `mongo`; the default parses the string value
+    /// (see `Value::parse`). Elements of a list value are available by
+    /// index (e.g. `servers.0` when `servers` is `[a, b]`).
+    fn get_value(&self, key: &str) -> Option<Value> {
+        match self.get_ref(key) {
+            Some(v) => Some(Value::parse(&v)),
+            None => element(key, |k| self.get_ref(k)).map(|v| Value::parse(&v)),
+        }
+    }
+
+    /// The number of elements in the list at the given key, or `None`
+    /// if there isn't a list there. Together with indexed keys (e.g.
+    /// `servers.0.host`), this allows iterating over repeated blocks.
+    ///
+    /// ```
+    /// use dinglebit_config::Config;
+    /// use std::collections::HashMap;
+    ///
+    /// let cfg = HashMap::from([("servers", "[{host=>a}, {host=>b}]")]);
+    /// let hosts: Vec<String> = (0..cfg.list_len("servers").unwrap())
+    ///     .map(|i| cfg.string(&format!("servers.{}.host", i)))
+    ///     .collect();
+    /// assert_eq!(hosts, vec!["a", "b"]);
+    /// ```
+    fn list_len(&self, key: &str) -> Option<usize> {
+        match self.get_value(key)? {
+            Value::List(l) => Some(l.len()),
+            _ => None,
+        }
+    }
+
+    /// Get the values of several keys at once, in the same order as
+    /// the keys. Configs where each lookup is a round trip (e.g. a
+    /// remote source) can override this to fetch them in one batch;
+    /// the default gets them one at a time.
+    fn get_many(&self, keys: &[&str]) -> Vec<Option<String>> {
+        keys.iter().map(|key| self.get(key)).collect()
+    }
+
+    /// Load and cache every value under each of the given prefixes
+    /// (e.g. `db.`) ahead of time, so the first reads don't wait on a
+    /// slow source (e.g. on a cold start). The result for each prefix
+    /// is the number of values under it or why they couldn't be
+    /// loaded. Configs that already hold all of their values (the
+    /// default) only count them.
+    #[cfg(feature = "std")]
+    fn prefetch(&self, prefixes: &[&str]) -> Vec<Result<usize, Error>> {
+        let keys = self.keys();
+        prefixes
+            .iter()
+            .map(|prefix| Ok(keys.iter().filter(|k| k.starts_with(prefix)).count()))
+            .collect()
+    }
+
+    /// Returns the keys this config knows about, in no particular
+    /// order. Configs that can't enumerate their keys (the default)
+    /// return an empty list.
+    fn keys(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Up to three keys with names similar to the key (e.g. `db.uri`
+    /// for `db.uir`), closest first, for suggesting which key was
+    /// meant. See `ValueError::with_suggestions`. Sources that list
+    /// their keys remotely return none.
+    fn similar_keys(&self, key: &str) -> Vec<String> {
+        suggest::similar(key, &self.keys())
+    }
+
+    /// A hash of the keys and their values, which is the same across
+    /// processes and releases, so nodes can compare fingerprints to
+    /// detect config drift. The order of the keys doesn't matter.
+    /// Configs that can't list their keys all have the same
+    /// fingerprint.
+    fn fingerprint(&self) -> u64 {
+        let mut keys = self.keys();
+        keys.sort();
+        keys.dedup();
+        let values: Vec<String> = keys
+            .iter()
+            .map(|k| self.get(k).unwrap_or_default())
+            .collect();
+        let parts: Vec<&str> = keys
+            .iter()
+            .zip(&values)
+            .flat_map(|(k, v)| [k.as_str(), v.as_str()])
+            .collect();
+        hash::fnv1a(&parts)
+    }
+
+    /// Check whether the config can currently provide values (e.g. a
+    /// remote source can reach its server): an `Error::Unhealthy` with
+    /// the reason when `source_health` is down. `MultiConfig::try_new`
+    /// uses this to find layers to leave out. It's derived from
+    /// `source_health` so the two can't disagree; implement that
+    /// instead.
+    #[cfg(feature = "std")]
+    fn health(&self) -> Result<(), Error> {
+        match self.source_health() {
+            SourceHealth::Down(reason) => Err(Error::Unhealthy(reason)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Get the value of the key, telling a missing value (`Ok(None)`)
+    /// apart from a lookup that failed (e.g. a remote source that
+    /// couldn't be reached), which `get` treats the same. Configs that
+    /// hold their values (the default) never fail.
+    #[cfg(feature = "std")]
+    fn lookup(&self, key: &str) -> Result<Option<String>, Error> {
+        Ok(self.get(key))
+    }
+
+    /// How well the config can currently provide values, for readiness
+    /// probes. Unlike `health`, it can tell a source that still
+    /// provides values but has a problem (`SourceHealth::Degraded`)
+    /// apart from one that's down. The default is ok, for configs that
+    /// hold their values.
+    #[cfg(feature = "std")]
+    fn source_health(&self) -> SourceHealth {
+        SourceHealth::Ok
+    }
+
+    /// A short name for the config used in diagnostics. The default is
+    /// the name of the type (e.g. `Json`).
+    fn source_name(&self) -> String {
+        let name = core::any::type_name::<Self>();
+        let base = name.split('<').next().unwrap_or(name);
+        let start = base.rfind("::").map(|i| i + 2).unwrap_or(0);
+        name[start..].to_string()
+    }
+
+    /// The `source_name` of the config the value of the key comes
+    /// from, or `None` if there is no value. Configs made of other
+    /// configs (e.g. `MultiConfig`) name the one that provided it.
+    fn source_of(&self, key: &str) -> Option<String> {
+        self.get_ref(key).map(|_| self.source_name())
+    }
+
+    /// A table of the keys in the registry with their values (secrets
+    /// redacted), where they came from and whether it was a default,
+    /// for logging at startup. See `StartupReport`.
+    #[cfg(feature = "std")]
+    fn startup_report(&self, registry: &Schema) -> StartupReport {
+        report::startup_report(self, registry)
+    }
+
+    /// A copy of the values and where they came from, which doesn't
+    /// change when the config does. Its `Display` is a table with the
+    /// secrets redacted. See `Snapshot`.
+    #[cfg(feature = "std")]
+    fn snapshot(&self) -> Snapshot {
+        snapshot::snapshot(self)
+    }
+
+    /// The statistics of the config: how many keys it has and how long
+    /// it took to load and refresh, where that's known. Configs made
+    /// of other configs (e.g. `MultiConfig`) have an entry for each.
+    /// See `SourceStats`.
+    #[cfg(feature = "std")]
+    fn stats(&self) -> Vec<SourceStats> {
+        vec![SourceStats::new(self.source_name(), self.keys().len())]
+    }
+
+    /// Fall back to the other config for keys this one doesn't have,
+    /// like a two layer `MultiConfig` without the boxing.
+    ///
+    /// ```
+    /// use dinglebit_config::{Config, Environment, Simple};
+    ///
+    /// let cfg = Environment::new("app")
+    ///     .or(Simple::from_str("db.host = localhost").unwrap())
+    ///     .with_default("db.pool", "10");
+    /// assert_eq!(cfg.string("db.host"), "localhost");
+    /// assert_eq!(cfg.int("db.pool"), 10);
+    /// ```
+    #[cfg(feature = "std")]
+    fn or<C: Config>(self, other: C) -> Or<Self, C>
+    where
+        Self: Sized,
+    {
+        Or::new(self, other)
+    }
+
+    /// Make the keys of this config available under the prefix, so
+    /// e.g. with the prefix `db` the key `db.host` reads `host`.
+    #[cfg(feature = "std")]
+    fn prefixed(self, prefix: &str) -> Prefixed<Self>
+    where
+        Self: Sized,
+    {
+        Prefixed::new(self, prefix)
+    }
+
+    /// Only have the keys the function accepts.
+    ///
+    /// ```
+    /// use dinglebit_config::Config;
+    /// use std::collections::HashMap;
+    ///
+    /// let cfg = HashMap::from([("db.host", "localhost"), ("db.password", "hunter2")]);
+    /// let public = cfg.filtered(|key| !key.ends_with("password"));
+    /// assert_eq!(public.get("db.password"), None);
+    /// ```
+    #[cfg(feature = "std")]
+    fn filtered<F: Fn(&str) -> bool>(self, accept: F) -> Filtered<Self, F>
+    where
+        Self: Sized,
+    {
+        Filtered::new(self, accept)
+    }
+
+    /// Read each key from the key the function turns it into (e.g.
+    /// `db.host` from `DB_HOST`). The keys can't be listed. See
+    /// `Mapped` for translating only some keys.
+    #[cfg(feature = "std")]
+    fn mapped_keys<F: Fn(&str) -> String>(self, map: F) -> MappedKeys<Self, F>
+    where
+        Self: Sized,
+    {
+        MappedKeys::new(self, map)
+    }
+
+    /// Use the value for the key when this config doesn't have one.
+    #[cfg(feature = "std")]
+    fn with_default(self, key: &str, value: impl Into<String>) -> WithDefault<Self>
+    where
+        Self: Sized,
+    {
+        WithDefault::new(self, key, value.into())
+    }
+
+    /// Check that every one of the keys has a value, returning all of
+    /// the missing ones at once so they can be fixed in one go.
+    ///
+    /// ```
+    /// use dinglebit_config::{Config, MissingKeys};
+    /// use std::collections::HashMap;
+    ///
+    /// let cfg = HashMap::from([("listen.addr", ":8080")]);
+    /// assert_eq!(
+    ///     cfg.require(&["db.uri", "auth.secret", "listen.addr"]),
+    ///     Err(MissingKeys(vec!["db.uri".to_string(), "auth.secret".to_string()]))
+    /// );
+    /// ```
+    fn require(&self, keys: &[&str]) -> Result<(), MissingKeys> {
+        let missing: Vec<String> = keys
+            .iter()
+            .filter(|key| self.get_ref(key).is_none())
+            .map(|key| key.to_string())
+            .collect();
+        match missing.is_empty() {
+            true => Ok(()),
+            false => Err(MissingKeys(missing)),
+        }
+    }
+
     /// Similar to `get` but panics if there is no value.
     fn must_get(&self, key: &str) -> String {
         self.get(key).unwrap()
     }
 
-    /// Get the value as a string or panics if one isn't found.
+    /// Similar to `get` but returns an error naming the key if there
+    /// is no value.
+    fn try_get(&self, key: &str) -> Result<String, ValueError> {
+        self.try_get_ref(key).map(Cow::into_owned)
+    }
+
+    /// Similar to `get_ref` but returns an error naming the key if
+    /// there is no value. Elements of a list value are found by index
+    /// (e.g. `servers.0.host`), as they're written, so this and the
+    /// typed getters built on it work with indexed keys for every
+    /// config.
+    fn try_get_ref(&self, key: &str) -> Result<Cow<'_, str>, ValueError> {
+        if let Some(value) = self.get_ref(key) {
+            return Ok(value);
+        }
+        element(key, |k| self.get_ref(k)).ok_or_else(|| ValueError::Missing(key.to_string()))
+    }
+
+    /// Get the value as a string or panics if one isn't found. The
+    /// panicking getters suggest similar keys when the key is missing.
     fn string(&self, key: &str) -> String {
-        self.get(key).unwrap()
+        found(self, self.try_get_ref(key)).into_owned()
     }
 
     /// Get the value as an integer or panics if one isn't found or
-    /// cannot be parsed.
+    /// cannot be parsed. Underscores between digits and a leading `+`
+    /// are accepted (e.g. `+1_000_000`).
     fn int(&self, key: &str) -> i64 {
-        self.must_get(key).parse::<i64>().unwrap()
+        found(self, self.try_int(key))
+    }
+
+    /// Similar to `int` but returns an error instead of panicking.
+    fn try_int(&self, key: &str) -> Result<i64, ValueError> {
+        self.try_int_with(key, &NumberFormat::default())
+    }
+
+    /// Get the value as an integer using the given format, which
+    /// controls which group and decimal separators are accepted.
+    fn try_int_with(&self, key: &str, format: &NumberFormat) -> Result<i64, ValueError> {
+        let value = self.try_get_ref(key)?;
+        match format.parse_int(&value) {
+            Some(i) => Ok(i),
+            None => Err(ValueError::invalid(key, value.into_owned(), "integer")),
+        }
     }
 
     /// Get the value as a float or panics if one isn't found or
-    /// cannot be parsed.
+    /// cannot be parsed. Underscores between digits and a leading `+`
+    /// are accepted (e.g. `+1_000.5`).
     fn float(&self, key: &str) -> f64 {
-        self.must_get(key).parse::<f64>().unwrap()
+        found(self, self.try_float(key))
     }
 
-    /// Get the value as a bool or panics if one isn't found or cannot
-    /// be parsed. The following case-insensitive values are considered
-    /// true: t, true, 1, y, yes. All other values are considered
-    /// false.
+    /// Similar to `float` but returns an error instead of panicking.
+    fn try_float(&self, key: &str) -> Result<f64, ValueError> {
+        self.try_float_with(key, &NumberFormat::default())
+    }
+
+    /// Get the value as a float using the given format, which
+    /// controls which group and decimal separators are accepted.
+    fn try_float_with(&self, key: &str, format: &NumberFormat) -> Result<f64, ValueError> {
+        let value = self.try_get_ref(key)?;
+        match format.parse_float(&value) {
+            Some(f) => Ok(f),
+            None => Err(ValueError::invalid(key, value.into_owned(), "float")),
+        }
+    }
+
+    /// Get the value as a bool or panics if one isn't found. The
+    /// following case-insensitive values are considered true: t,
+    /// true, 1, y, yes, on, enabled. All other values are considered
+    /// false. Use `try_bool` if unrecognized values should be an
+    /// error instead.
     fn bool(&self, key: &str) -> bool {
-        match self.must_get(key).to_lowercase().as_str() {
-            "t" => true,
-            "true" => true,
-            "1" => true,
-            "y" => true,
-            "yes" => true,
-            _ => false,
+        parse_bool(&found(self, self.try_get_ref(key))).unwrap_or(false)
+    }
+
+    /// Get the value as a bool. The following case-insensitive values
+    /// are considered true: t, true, 1, y, yes, on, enabled. The
+    /// following are considered false: f, false, 0, n, no, off,
+    /// disabled. Anything else is an error.
+    fn try_bool(&self, key: &str) -> Result<bool, ValueError> {
+        let value = self.try_get_ref(key)?;
+        match parse_bool(&value) {
+            Some(b) => Ok(b),
+            None => Err(ValueError::invalid(key, value.into_owned(), "bool")),
         }
     }
 
     /// Get the value as a duration or panics if one isn't found or
-    /// can't be parsed. Thre doesn't appear to be a parsing function
-    /// for a duration, so it attempts to convert to an integer and use
-    /// that as the number of seconds.
+    /// can't be parsed. A plain integer is a number of seconds;
+    /// otherwise it's numbers followed by units (`ms`, `s`, `m`, `h` or
+    /// `d`), e.g. `250ms`, `30s` or `1h30m`.
     fn duration(&self, key: &str) -> chrono::Duration {
-        // There doesn't seem to be a parse function for
-        // chrono::Duration. We just assume i64 seconds.
-        chrono::Duration::seconds(self.int(key))
+        found(self, self.try_duration(key))
+    }
+
+    /// Similar to `duration` but returns an error instead of
+    /// panicking.
+    fn try_duration(&self, key: &str) -> Result<chrono::Duration, ValueError> {
+        let value = self.try_get_ref(key)?;
+        match duration::parse(&value) {
+            Some(d) => Ok(d),
+            None => Err(ValueError::invalid(
+                key,
+                value.into_owned(),
+                duration::EXPECTED,
+            )),
+        }
+    }
+
+    /// Get the value as a duration (see `duration`) or panics if one
+    /// isn't found, can't be parsed or is outside the given range
+    /// (inclusive). Useful for timeouts, where both `0` and days are
+    /// usually mistakes.
+    fn duration_between(
+        &self,
+        key: &str,
+        min: chrono::Duration,
+        max: chrono::Duration,
+    ) -> chrono::Duration {
+        found(self, self.try_duration_between(key, min, max))
+    }
+
+    /// Similar to `duration_between` but returns an error instead of
+    /// panicking.
+    fn try_duration_between(
+        &self,
+        key: &str,
+        min: chrono::Duration,
+        max: chrono::Duration,
+    ) -> Result<chrono::Duration, ValueError> {
+        let d = self.try_duration(key)?;
+        match d >= min && d <= max {
+            true => Ok(d),
+            false => Err(ValueError::invalid(
+                key,
+                duration::format(d),
+                &format!(
+                    "duration between {} and {}",
+                    duration::format(min),
+                    duration::format(max)
+                ),
+            )),
+        }
+    }
+
+    /// Get the value as a rate limit or quota (e.g. `100/second` or
+    /// `5k/minute`) or panics if one isn't found or can't be parsed.
+    /// See `Rate`.
+    fn rate(&self, key: &str) -> Rate {
+        found(self, self.try_rate(key))
+    }
+
+    /// Similar to `rate` but returns an error instead of panicking.
+    fn try_rate(&self, key: &str) -> Result<Rate, ValueError> {
+        let value = self.try_get_ref(key)?;
+        match Rate::parse(&value) {
+            Some(r) => Ok(r),
+            None => Err(ValueError::invalid(key, value.into_owned(), rate::EXPECTED)),
+        }
+    }
+
+    /// Get the value as a color (e.g. `#ff8800`, `rgb(255, 136, 0)` or
+    /// `orange`) or panics if one isn't found or can't be parsed. See
+    /// `Color`.
+    fn color(&self, key: &str) -> Color {
+        found(self, self.try_color(key))
+    }
+
+    /// Similar to `color` but returns an error instead of panicking.
+    fn try_color(&self, key: &str) -> Result<Color, ValueError> {
+        let value = self.try_get_ref(key)?;
+        match Color::parse(&value) {
+            Some(c) => Ok(c),
+            None => Err(ValueError::invalid(
+                key,
+                value.into_owned(),
+                color::EXPECTED,
+            )),
+        }
+    }
+
+    /// Get the value as a keyboard shortcut (e.g. `ctrl+shift+p`) or
+    /// panics if one isn't found or can't be parsed. See `KeyBinding`.
+    fn keybinding(&self, key: &str) -> KeyBinding {
+        found(self, self.try_keybinding(key))
+    }
+
+    /// Similar to `keybinding` but returns an error instead of
+    /// panicking.
+    fn try_keybinding(&self, key: &str) -> Result<KeyBinding, ValueError> {
+        let value = self.try_get_ref(key)?;
+        match KeyBinding::parse(&value) {
+            Some(b) => Ok(b),
+            None => Err(ValueError::invalid(
+                key,
+                value.into_owned(),
+                keybinding::EXPECTED,
+            )),
+        }
+    }
+
+    /// Get the value as a command line split into the program and its
+    /// arguments, or panics if one isn't found or it can't be split.
+    /// It uses the quoting rules of a POSIX shell (e.g. `notify-send
+    /// 'Build done' --icon=/opt/My\ Apps/icon.png`). The command isn't
+    /// run through a shell, so variables and globs are left as they
+    /// are.
+    ///
+    /// ```
+    /// use dinglebit_config::Config;
+    /// use std::collections::HashMap;
+    ///
+    /// let cfg = HashMap::from([(
+    ///     "hooks.post_build",
+    ///     r#""/opt/my tools/upload" --tag "v1 rc""#,
+    /// )]);
+    /// let (program, args) = cfg.command("hooks.post_build");
+    /// assert_eq!(program, "/opt/my tools/upload");
+    /// assert_eq!(args, vec!["--tag", "v1 rc"]);
+    /// ```
+    fn command(&self, key: &str) -> (String, Vec<String>) {
+        found(self, self.try_command(key))
+    }
+
+    /// Similar to `command` but returns an error instead of panicking.
+    fn try_command(&self, key: &str) -> Result<(String, Vec<String>), ValueError> {
+        let value = self.try_get_ref(key)?;
+        let mut words = shell::split(&value).unwrap_or_default();
+        match words.is_empty() {
+            true => Err(ValueError::invalid(key, value.into_owned(), "command")),
+            false => {
+                let program = words.remove(0);
+                Ok((program, words))
+            }
+        }
     }
 
     /// Get the value as a duration or panics if one isn't found or it
     /// can't be parsed. It uses RFC339 to parse it.
     fn datetime(&self, key: &str) -> chrono::DateTime<chrono::Utc> {
-        chrono::DateTime::<chrono::Utc>::from_utc(
-            chrono::DateTime::parse_from_rfc3339(self.must_get(key).as_str())
-                .unwrap()
-                .naive_utc(),
-            chrono::Utc,
-        )
+        found(self, self.try_datetime(key))
+    }
+
+    /// Similar to `datetime` but returns an error instead of
+    /// panicking.
+    fn try_datetime(&self, key: &str) -> Result<chrono::DateTime<chrono::Utc>, ValueError> {
+        let value = self.try_get_ref(key)?;
+        match chrono::DateTime::parse_from_rfc3339(&value) {
+            Ok(dt) => Ok(dt.with_timezone(&chrono::Utc)),
+            Err(_) => Err(ValueError::invalid(
+                key,
+                value.into_owned(),
+                "RFC 3339 datetime",
+            )),
+        }
+    }
+
+    /// Get the value as one of several options picked by weight, or
+    /// panics if one isn't found or it can't be parsed. A value like
+    /// `choose[control:80, treatment:20]` picks `control` for 80% of
+    /// the hash keys (e.g. user IDs) and `treatment` for the rest. The
+    /// same hash key always gets the same option, while different keys
+    /// split the hash keys independently. Any other value is returned
+    /// as is, so the choice can be fixed.
+    ///
+    /// ```
+    /// use dinglebit_config::Config;
+    /// use std::collections::HashMap;
+    ///
+    /// let cfg = HashMap::from([
+    ///     ("variant", "choose[control:80, treatment:20]"),
+    ///     ("theme", "dark"),
+    /// ]);
+    /// let variant = cfg.choose("variant", "user-42");
+    /// assert!(variant == "control" || variant == "treatment");
+    /// assert_eq!(cfg.choose("variant", "user-42"), variant);
+    /// assert_eq!(cfg.choose("theme", "user-42"), "dark");
+    /// ```
+    fn choose(&self, key: &str, hash_key: &str) -> String {
+        found(self, self.try_choose(key, hash_key))
+    }
+
+    /// Similar to `choose` but returns an error instead of panicking.
+    fn try_choose(&self, key: &str, hash_key: &str) -> Result<String, ValueError> {
+        let value = self.try_get_ref(key)?;
+        if !weighted::is_weighted(&value) {
+            return Ok(value.into_owned());
+        }
+        match weighted::parse(&value) {
+            Some(options) => {
+                Ok(weighted::pick(&options, hash::fnv1a(&[key, hash_key])).to_string())
+            }
+            None => Err(ValueError::invalid(
+                key,
+                value.into_owned(),
+                weighted::EXPECTED,
+            )),
+        }
+    }
+
+    /// The variant of the experiment for the given ID (e.g. a user ID),
+    /// or panics if the experiment can't be read. See `Experiments`,
+    /// which can also tell listeners about each assignment.
+    #[cfg(feature = "std")]
+    fn bucket(&self, experiment: &str, id: &str) -> String
+    where
+        Self: Sized,
+    {
+        found(self, self.try_bucket(experiment, id))
+    }
+
+    /// Similar to `bucket` but returns an error instead of panicking.
+    #[cfg(feature = "std")]
+    fn try_bucket(&self, experiment: &str, id: &str) -> Result<String, ValueError>
+    where
+        Self: Sized,
+    {
+        Experiments::new(self).try_bucket(experiment, id)
+    }
+
+    /// Get the value as a connection string split into its parts
+    /// (see `ConnParts`), or panics if one isn't found or it can't be
+    /// parsed.
+    #[cfg(feature = "std")]
+    fn conn_string(&self, key: &str) -> ConnParts {
+        found(self, self.try_conn_string(key))
+    }
+
+    /// Similar to `conn_string` but returns an error instead of
+    /// panicking. If the value can't be parsed, the value in the error
+    /// has its password removed.
+    #[cfg(feature = "std")]
+    fn try_conn_string(&self, key: &str) -> Result<ConnParts, ValueError> {
+        let value = self.try_get_ref(key)?;
+        match ConnParts::parse(&value) {
+            Some(parts) => Ok(parts),
+            None => Err(ValueError::invalid(
+                key,
+                redact::redact(key, &value).into_owned(),
+                "connection string",
+            )),
+        }
+    }
+
+    /// Get the value as a URL whose scheme is one of the given ones
+    /// (compared case-insensitively), e.g. `&["https"]` for a webhook,
+    /// or panics if one isn't found or it's not such a URL.
+    #[cfg(feature = "std")]
+    fn url_with(&self, key: &str, schemes: &[&str]) -> String {
+        found(self, self.try_url_with(key, schemes))
+    }
+
+    /// Similar to `url_with` but returns an error instead of panicking.
+    /// If the value can't be parsed, the value in the error has its
+    /// password removed.
+    #[cfg(feature = "std")]
+    fn try_url_with(&self, key: &str, schemes: &[&str]) -> Result<String, ValueError> {
+        let value = self.try_get_ref(key)?;
+        let invalid = |expected: &str| {
+            ValueError::invalid(key, redact::redact(key, &value).into_owned(), expected)
+        };
+        let parts = ConnParts::parse(&value).ok_or_else(|| invalid("URL"))?;
+        match schemes
+            .iter()
+            .any(|s| s.eq_ignore_ascii_case(parts.scheme()))
+        {
+            true => Ok(value.to_string()),
+            false => Err(invalid(&format!(
+                "URL with scheme {}",
+                schemes.join(" or ")
+            ))),
+        }
+    }
+
+    /// Similar to `url_with` but the host must also be public, so a
+    /// callback can't be pointed at `localhost`, the cloud metadata
+    /// service or other internal addresses (see
+    /// `ConnParts::is_private_host`).
+    #[cfg(feature = "std")]
+    fn public_url_with(&self, key: &str, schemes: &[&str]) -> String {
+        found(self, self.try_public_url_with(key, schemes))
+    }
+
+    /// Similar to `public_url_with` but returns an error instead of
+    /// panicking.
+    #[cfg(feature = "std")]
+    fn try_public_url_with(&self, key: &str, schemes: &[&str]) -> Result<String, ValueError> {
+        let url = self.try_url_with(key, schemes)?;
+        match ConnParts::parse(&url).map(|p| p.is_private_host()) {
+            Some(false) => Ok(url),
+            _ => Err(ValueError::invalid(
+                key,
+                redact::redact(key, &url).into_owned(),
+                "URL with a public host",
+            )),
+        }
+    }
+
+    /// Get the value as an email address (e.g. `ops@example.com`), or
+    /// panics if one isn't found or it's not valid. It's checked
+    /// against the syntax in RFC 5322. A display name (e.g. `Ops
+    /// <ops@example.com>`) isn't accepted.
+    #[cfg(feature = "validate")]
+    fn email(&self, key: &str) -> String {
+        found(self, self.try_email(key))
+    }
+
+    /// Similar to `email` but returns an error instead of panicking.
+    #[cfg(feature = "validate")]
+    fn try_email(&self, key: &str) -> Result<String, ValueError> {
+        let value = self.try_get_ref(key)?;
+        let options = email_address::Options::default().without_display_text();
+        match email_address::EmailAddress::parse_with_options(&value, options) {
+            Ok(_) => Ok(value.into_owned()),
+            Err(_) => Err(ValueError::invalid(
+                key,
+                value.into_owned(),
+                "email address",
+            )),
+        }
+    }
+
+    /// Get the value as a host name (e.g. `smtp.example.com`), or
+    /// panics if one isn't found or it's not valid. It's checked
+    /// against the syntax in RFC 1123: dot separated labels of at most
+    /// 63 letters, digits and `-`, not starting or ending with `-`.
+    #[cfg(feature = "validate")]
+    fn hostname(&self, key: &str) -> String {
+        found(self, self.try_hostname(key))
+    }
+
+    /// Similar to `hostname` but returns an error instead of panicking.
+    #[cfg(feature = "validate")]
+    fn try_hostname(&self, key: &str) -> Result<String, ValueError> {
+        let value = self.try_get_ref(key)?;
+        match hostname_validator::is_valid(&value) {
+            true => Ok(value.into_owned()),
+            false => Err(ValueError::invalid(key, value.into_owned(), "host name")),
+        }
+    }
+
+    /// Get the value as an IP network in CIDR notation (e.g.
+    /// `10.0.0.0/8` or `fd00::/8`), or panics if one isn't found or it
+    /// can't be parsed. A plain address is a network of just that
+    /// address.
+    #[cfg(feature = "cidr")]
+    fn cidr(&self, key: &str) -> ipnetwork::IpNetwork {
+        found(self, self.try_cidr(key))
+    }
+
+    /// Similar to `cidr` but returns an error instead of panicking.
+    #[cfg(feature = "cidr")]
+    fn try_cidr(&self, key: &str) -> Result<ipnetwork::IpNetwork, ValueError> {
+        let value = self.try_get_ref(key)?;
+        match value.trim().parse() {
+            Ok(network) => Ok(network),
+            Err(_) => Err(ValueError::invalid(key, value.into_owned(), "CIDR network")),
+        }
+    }
+
+    /// Get the value as a list of IP networks (see `cidr`), written
+    /// like `list` expects (e.g. `[10.0.0.0/8, 192.168.0.0/16]`), for
+    /// settings like trusted proxies, or panics if one isn't found or
+    /// an entry isn't a network.
+    #[cfg(feature = "cidr")]
+    fn cidr_list(&self, key: &str) -> Vec<ipnetwork::IpNetwork> {
+        found(self, self.try_cidr_list(key))
+    }
+
+    /// Similar to `cidr_list` but returns an error instead of
+    /// panicking. The error names the first entry that isn't a
+    /// network.
+    #[cfg(feature = "cidr")]
+    fn try_cidr_list(&self, key: &str) -> Result<Vec<ipnetwork::IpNetwork>, ValueError> {
+        let value = self.try_get_ref(key)?;
+        value
+            .trim_matches(|c| c == '[' || c == ']' || char::is_whitespace(c))
+            .split(',')
+            .map(str::trim)
+            .filter(|n| !n.is_empty())
+            .map(|n| {
+                n.parse()
+                    .map_err(|_| ValueError::invalid(key, n.to_string(), "CIDR network"))
+            })
+            .collect()
+    }
+
+    /// Get the value as a compiled glob pattern (e.g. `src/**/*.rs`),
+    /// or panics if one isn't found or it's invalid, so an invalid
+    /// pattern is reported when the config is read rather than when
+    /// files are matched.
+    #[cfg(feature = "glob")]
+    fn glob(&self, key: &str) -> globset::GlobMatcher {
+        found(self, self.try_glob(key))
+    }
+
+    /// Similar to `glob` but returns an error instead of panicking.
+    #[cfg(feature = "glob")]
+    fn try_glob(&self, key: &str) -> Result<globset::GlobMatcher, ValueError> {
+        let value = self.try_get_ref(key)?;
+        match globset::Glob::new(value.trim()) {
+            Ok(g) => Ok(g.compile_matcher()),
+            Err(_) => Err(ValueError::invalid(key, value.into_owned(), "glob pattern")),
+        }
+    }
+
+    /// Get the value as a set of glob patterns (see `glob`), written
+    /// like `list` expects (e.g. `[*.tmp, target/**]`), for include and
+    /// exclude filters, or panics if one isn't found or a pattern is
+    /// invalid. A path matches the set if it matches any of the
+    /// patterns. Since entries are split on commas, use separate
+    /// patterns instead of alternatives like `{a,b}`.
+    #[cfg(feature = "glob")]
+    fn glob_list(&self, key: &str) -> globset::GlobSet {
+        found(self, self.try_glob_list(key))
+    }
+
+    /// Similar to `glob_list` but returns an error instead of
+    /// panicking. The error names the first invalid pattern.
+    #[cfg(feature = "glob")]
+    fn try_glob_list(&self, key: &str) -> Result<globset::GlobSet, ValueError> {
+        let value = self.try_get_ref(key)?;
+        let mut set = globset::GlobSetBuilder::new();
+        for pattern in value
+            .trim_matches(|c| c == '[' || c == ']' || char::is_whitespace(c))
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+        {
+            match globset::Glob::new(pattern) {
+                Ok(g) => set.add(g),
+                Err(_) => {
+                    return Err(ValueError::invalid(
+                        key,
+                        pattern.to_string(),
+                        "glob pattern",
+                    ))
+                }
+            };
+        }
+        set.build()
+            .map_err(|_| ValueError::invalid(key, value.into_owned(), "glob patterns"))
+    }
+
+    /// Get the value as HTTP headers (e.g. for an outbound client), or
+    /// panics if none are found or they're invalid. They're written as
+    /// a map (`{X-Api-Key => abc, Accept => application/json}`), a list
+    /// of `Name: value` entries, or entries in indexed keys
+    /// (`headers.0`, `headers.1`, ...). Names may repeat in the list
+    /// forms.
+    #[cfg(feature = "headers")]
+    fn headers(&self, key: &str) -> ::http::HeaderMap {
+        found(self, self.try_headers(key))
+    }
+
+    /// Similar to `headers` but returns an error instead of panicking.
+    /// Invalid values aren't shown in the error since headers often
+    /// carry credentials.
+    #[cfg(feature = "headers")]
+    fn try_headers(&self, key: &str) -> Result<::http::HeaderMap, ValueError> {
+        headers::header_map(self, key)
+    }
+
+    /// Get the value as a media type (e.g. `text/html; charset=utf-8`),
+    /// or panics if one isn't found or it can't be parsed. A `charset`
+    /// parameter must be an encoding `charset` knows.
+    #[cfg(feature = "mime")]
+    fn mime(&self, key: &str) -> mime::Mime {
+        found(self, self.try_mime(key))
+    }
+
+    /// Similar to `mime` but returns an error instead of panicking.
+    #[cfg(feature = "mime")]
+    fn try_mime(&self, key: &str) -> Result<mime::Mime, ValueError> {
+        let value = self.try_get_ref(key)?;
+        let parsed = value.trim().parse::<mime::Mime>().ok().filter(|m| {
+            m.get_param(mime::CHARSET).map_or(true, |c| {
+                encoding_rs::Encoding::for_label(c.as_str().as_bytes()).is_some()
+            })
+        });
+        match parsed {
+            Some(m) => Ok(m),
+            None => Err(ValueError::invalid(key, value.into_owned(), "media type")),
+        }
+    }
+
+    /// Get the value as a character encoding, or panics if one isn't
+    /// found or it's unknown. It's given by any of the labels in the
+    /// WHATWG Encoding Standard (e.g. `utf-8`, `latin1` or
+    /// `shift_jis`). Use `name` on the result for the canonical name.
+    #[cfg(feature = "mime")]
+    fn charset(&self, key: &str) -> &'static encoding_rs::Encoding {
+        found(self, self.try_charset(key))
+    }
+
+    /// Similar to `charset` but returns an error instead of panicking.
+    #[cfg(feature = "mime")]
+    fn try_charset(&self, key: &str) -> Result<&'static encoding_rs::Encoding, ValueError> {
+        let value = self.try_get_ref(key)?;
+        match encoding_rs::Encoding::for_label(value.trim().as_bytes()) {
+            Some(e) => Ok(e),
+            None => Err(ValueError::invalid(key, value.into_owned(), "charset")),
+        }
+    }
+
+    /// Read the file at the path in the value and return its contents
+    /// if it's PEM encoded certificates or a private key (e.g. for
+    /// TLS), or panics if the value isn't found or the file can't be
+    /// read or isn't PEM. A missing or malformed file is reported as a
+    /// problem with the key, rather than as a failed handshake later
+    /// on.
+    #[cfg(feature = "std")]
+    fn pem_file(&self, key: &str) -> Vec<u8> {
+        found(self, self.try_pem_file(key))
+    }
+
+    /// Similar to `pem_file` but returns an error instead of panicking.
+    #[cfg(feature = "std")]
+    fn try_pem_file(&self, key: &str) -> Result<Vec<u8>, ValueError> {
+        let path = self.try_get_ref(key)?;
+        let bytes = std::fs::read(&*path)
+            .map_err(|_| ValueError::invalid(key, path.to_string(), "readable file"))?;
+        match core::str::from_utf8(&bytes).map(pem::is_cert_or_key) {
+            Ok(true) => Ok(bytes),
+            _ => Err(ValueError::invalid(
+                key,
+                path.into_owned(),
+                "PEM certificate or private key",
+            )),
+        }
+    }
+
+    /// The values of this config as environment variables, named the
+    /// way `Environment` with the given prefix would look for them
+    /// (e.g. `mongo.uri` => `APP_MONGO_URI`). This is useful for
+    /// passing the resolved configuration down to a child process.
+    /// The variables are sorted by name.
+    #[cfg(feature = "std")]
+    fn to_env_vars(&self, prefix: &str) -> Vec<(OsString, OsString)> {
+        let env = Environment::new(prefix);
+        let mut vars: Vec<(OsString, OsString)> = self
+            .keys()
+            .into_iter()
+            .filter_map(|k| self.get(&k).map(|v| (env.var_name(&k).into(), v.into())))
+            .collect();
+        vars.sort();
+        vars
+    }
+
+    /// Deserialize everything under the given prefix into a struct
+    /// (or any other type implementing `Deserialize`). Nested keys map
+    /// to nested fields, so `mongo.pool.size` is the `size` field of
+    /// the `pool` field when the prefix is `mongo`. String values are
+    /// converted to the type of the field, lists use the syntax
+    /// `Config::list` understands and an empty prefix uses every key.
+    ///
+    /// ```
+    /// use dinglebit_config::Config;
+    /// use serde::Deserialize;
+    /// use std::collections::HashMap;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Mongo {
+    ///     uri: String,
+    ///     pool: u32,
+    /// }
+    ///
+    /// let cfg = HashMap::from([("mongo.uri", "mongodb://"), ("mongo.pool", "10")]);
+    /// let mongo: Mongo = cfg.section("mongo").unwrap();
+    /// assert_eq!(mongo.pool, 10);
+    /// ```
+    ///
+    /// Errors name the full key of the field (e.g. `mongo.pool`), and
+    /// `ValueError::Missing` is returned if there's nothing under the
+    /// prefix.
+    #[cfg(feature = "serde")]
+    fn section<T: serde::de::DeserializeOwned>(&self, prefix: &str) -> Result<T, ValueError>
+    where
+        Self: Sized,
+    {
+        de::section(self, prefix)
+    }
+
+    /// Deserialize the prefix again (see `section`) into a struct that
+    /// was bound before, e.g. after a reload, and return the names of
+    /// the fields that changed. Nothing is updated if the values can't
+    /// be deserialized.
+    ///
+    /// ```
+    /// use dinglebit_config::Config;
+    /// use serde::{Deserialize, Serialize};
+    /// use std::collections::HashMap;
+    ///
+    /// #[derive(Deserialize, Serialize)]
+    /// struct Server {
+    ///     port: u16,
+    ///     log_level: String,
+    /// }
+    ///
+    /// let cfg = HashMap::from([("server.port", "8080"), ("server.log_level", "info")]);
+    /// let mut server: Server = cfg.section("server").unwrap();
+    ///
+    /// let reloaded = HashMap::from([("server.port", "8080"), ("server.log_level", "debug")]);
+    /// assert_eq!(reloaded.rebind_into(&mut server, "server").unwrap(), vec!["log_level"]);
+    /// assert_eq!(server.log_level, "debug");
+    /// ```
+    #[cfg(feature = "serde")]
+    fn rebind_into<T>(&self, target: &mut T, prefix: &str) -> Result<Vec<String>, ValueError>
+    where
+        Self: Sized,
+        T: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        de::rebind(self, target, prefix, &[])
+    }
+
+    /// Similar to `rebind_into` but the given fields keep their values
+    /// and aren't reported, for settings that can't change while
+    /// running (e.g. the port a server listens on).
+    #[cfg(feature = "serde")]
+    fn rebind_into_except<T>(
+        &self,
+        target: &mut T,
+        prefix: &str,
+        fixed: &[&str],
+    ) -> Result<Vec<String>, ValueError>
+    where
+        Self: Sized,
+        T: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        de::rebind(self, target, prefix, fixed)
+    }
+
+    /// Create a temporary layer of values over this config (e.g. for
+    /// request or tenant specific overrides). See `Overlay`.
+    #[cfg(feature = "std")]
+    fn overlay<L: Config>(&self, layer: L) -> Overlay<'_, L>
+    where
+        Self: Sized,
+    {
+        Overlay::new(self, layer)
     }
 
     /// Get a list or panics if one isn't found. The list should be a
     /// comma-delimited list surrouned by brackets (e.g. [1, 2, 3] =>
     /// vec!["1", "2", "3"].
     fn list(&self, key: &str) -> Vec<String> {
-        let s = self.must_get(key);
+        let s = found(self, self.try_get_ref(key));
         let s = s.trim_matches(|c| c == '[' || c == ']' || char::is_whitespace(c));
         s.split(',')
             .map(|p| p.trim().to_string())
@@ -113,8 +1517,16 @@ pub trait Config {
     /// comma-delimited list surrouned by braces with key/value pairs
     /// associated with => (e.g. {a=>1, b=>2, c=>3} => ((a,1), (b,2),
     /// (c,3))).
+    #[cfg(feature = "std")]
     fn map(&self, key: &str) -> HashMap<String, String> {
-        let s = self.must_get(key);
+        let s = match self.get_ref(key) {
+            Some(s) => s,
+            None => found(
+                self,
+                element_or_map(key, |k| self.get_ref(k))
+                    .ok_or_else(|| ValueError::Missing(key.to_string())),
+            ),
+        };
         let s = s.trim_matches(|c| c == '{' || c == '}' || char::is_whitespace(c));
         s.split(',')
             .map(|p| {
@@ -130,27 +1542,143 @@ pub trait Config {
     }
 }
 
-/// Create a config from a list of key/value pairs.
+/// Create a config from a list of key/value pairs. Keys and values
+/// can be any expression that converts `Into<String>`, so computed
+/// defaults work too. The result is a boxed `HashMap<String, String>`.
+///
+/// ```
+/// use dinglebit_config::{default_config, Config};
+///
+/// let port = 8080;
+/// let config = default_config! {
+///     "host" => "localhost",
+///     "port" => port.to_string(),
+/// };
+/// assert_eq!(config.int("port"), 8080);
+/// ```
+#[cfg(feature = "std")]
 #[macro_export]
 macro_rules! default_config(
-    { $($key:expr => $value:expr),+ } => {
+    {} => {
+        Box::new(::std::collections::HashMap::<String, String>::new())
+    };
+    { $($key:expr => $value:expr),+ $(,)? } => {
         {
-            let mut m: ::std::collections::HashMap<&str, &str> = ::std::collections::HashMap::new();
+            let mut m: ::std::collections::HashMap<String, String> = ::std::collections::HashMap::new();
             $(
-                m.insert($key, $value);
+                m.insert(::std::convert::Into::<String>::into($key), ::std::convert::Into::<String>::into($value));
             )+
             Box::new(m)
         }
      };
 );
 
+#[cfg(feature = "std")]
 impl Config for HashMap<&str, &str> {
+    /// Elements of lists are available by index (e.g. `servers.0`).
     fn get(&self, key: &str) -> Option<String> {
         match self.get(key) {
-            None => None,
             Some(v) => Some(v.to_string()),
+            None => element(key, |k| Config::get_ref(self, k)).map(Cow::into_owned),
         }
     }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.get(key).map(|v| Cow::Borrowed(*v))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        HashMap::keys(self).map(|k| k.to_string()).collect()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Config for HashMap<String, String> {
+    /// Elements of lists are available by index (e.g. `servers.0`).
+    fn get(&self, key: &str) -> Option<String> {
+        match self.get(key) {
+            Some(v) => Some(v.clone()),
+            None => element(key, |k| Config::get_ref(self, k)).map(Cow::into_owned),
+        }
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.get(key).map(|v| Cow::Borrowed(v.as_str()))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        HashMap::keys(self).cloned().collect()
+    }
+}
+
+impl Config for BTreeMap<&str, &str> {
+    /// Elements of lists are available by index (e.g. `servers.0`).
+    fn get(&self, key: &str) -> Option<String> {
+        match self.get(key) {
+            Some(v) => Some(v.to_string()),
+            None => element(key, |k| Config::get_ref(self, k)).map(Cow::into_owned),
+        }
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.get(key).map(|v| Cow::Borrowed(*v))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        BTreeMap::keys(self).map(|k| k.to_string()).collect()
+    }
+}
+
+impl Config for BTreeMap<String, String> {
+    /// Elements of lists are available by index (e.g. `servers.0`).
+    fn get(&self, key: &str) -> Option<String> {
+        match self.get(key) {
+            Some(v) => Some(v.clone()),
+            None => element(key, |k| Config::get_ref(self, k)).map(Cow::into_owned),
+        }
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.get(key).map(|v| Cow::Borrowed(v.as_str()))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        BTreeMap::keys(self).cloned().collect()
+    }
+}
+
+/// Lists of pairs behave like a map built from them, so when a key
+/// appears more than once the last pair wins.
+impl Config for Vec<(String, String)> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.clone())
+    }
+
+    fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.iter().map(|(k, _)| k.clone()).collect();
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+}
+
+impl Config for &[(&str, &str)] {
+    fn get(&self, key: &str) -> Option<String> {
+        self.iter()
+            .rev()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v.to_string())
+    }
+
+    fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.iter().map(|(k, _)| k.to_string()).collect();
+        keys.sort();
+        keys.dedup();
+        keys
+    }
 }
 
 #[cfg(test)]
@@ -158,7 +1686,8 @@ mod tests {
     use crate::*;
     use chrono::{TimeZone, Utc};
     use lazy_static::lazy_static;
-    use std::collections::HashMap;
+    use std::borrow::Cow;
+    use std::collections::{BTreeMap, HashMap};
 
     #[test]
     fn default() {
@@ -168,6 +1697,17 @@ mod tests {
             "baz" => "foo"
         };
         assert_eq!(config.string("foo"), "bar".to_string());
+
+        let name = String::from("computed");
+        let config = default_config! {
+            name.clone() => format!("{}-value", name),
+            "trailing" => "comma",
+        };
+        assert_eq!(config.string("computed"), "computed-value");
+        assert_eq!(config.string("trailing"), "comma");
+
+        let config = default_config! {};
+        assert!(config.get("foo").is_none());
     }
 
     #[test]
@@ -176,7 +1716,79 @@ mod tests {
         let mut m = HashMap::new();
         m.insert("foo", "bar");
         assert_eq!(m.must_get("foo"), "bar".to_string());
-        assert!(m.get("bar").is_none());
+        assert!(Config::get(&m, "bar").is_none());
+    }
+
+    #[test]
+    fn get_ref() {
+        let m = HashMap::from([("a", "1"), ("b", "x")]);
+        assert!(matches!(m.get_ref("a"), Some(Cow::Borrowed("1"))));
+        assert_eq!(
+            m.try_get_ref("c"),
+            Err(ValueError::Missing("c".to_string()))
+        );
+        assert_eq!(
+            m.try_int("b"),
+            Err(ValueError::Invalid {
+                key: "b".to_string(),
+                value: "x".to_string(),
+                expected: "integer".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn containers() {
+        let owned: HashMap<String, String> = HashMap::from([("a".to_string(), "1".to_string())]);
+        assert_eq!(owned.int("a"), 1);
+
+        let tree = BTreeMap::from([("a", "1"), ("b", "2")]);
+        assert_eq!(tree.int("b"), 2);
+        assert_eq!(Config::keys(&tree), vec!["a", "b"]);
+
+        let owned_tree: BTreeMap<String, String> =
+            BTreeMap::from([("a".to_string(), "x".to_string())]);
+        assert_eq!(owned_tree.string("a"), "x");
+
+        let pairs = vec![
+            ("a".to_string(), "1".to_string()),
+            ("a".to_string(), "2".to_string()),
+        ];
+        assert_eq!(pairs.int("a"), 2);
+        assert_eq!(pairs.keys(), vec!["a"]);
+
+        let slice: &[(&str, &str)] = &[("a", "1"), ("b", "2"), ("b", "3")];
+        assert_eq!(slice.int("b"), 3);
+        assert!(Config::get(&slice, "c").is_none());
+        assert_eq!(slice.keys(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn try_bool() {
+        let mut m = HashMap::new();
+        m.insert("on", "On");
+        m.insert("disabled", "disabled");
+        m.insert("n", "n");
+        m.insert("bogus", "maybe");
+        assert_eq!(m.try_bool("on"), Ok(true));
+        assert_eq!(m.try_bool("disabled"), Ok(false));
+        assert_eq!(m.try_bool("n"), Ok(false));
+        assert_eq!(
+            m.try_bool("bogus"),
+            Err(ValueError::Invalid {
+                key: "bogus".to_string(),
+                value: "maybe".to_string(),
+                expected: "bool".to_string(),
+            })
+        );
+        assert_eq!(
+            m.try_bool("missing"),
+            Err(ValueError::Missing("missing".to_string()))
+        );
+
+        // The legacy getter stays lenient.
+        assert!(m.bool("on"));
+        assert!(!m.bool("bogus"));
     }
 
     lazy_static! {
@@ -184,6 +1796,8 @@ mod tests {
             let mut m = HashMap::new();
             m.insert("foo", "bar");
             m.insert("int", "100");
+            m.insert("int_separated", "+1_000");
+            m.insert("float_grouped", "1,000.5");
             m.insert("float", "-2.4");
             m.insert("bool", "t");
             m.insert("duration", "50");
@@ -213,10 +1827,12 @@ mod tests {
     test_gets! {
         (string, HASHMAP.string("foo")): "bar".to_string(),
         (int, HASHMAP.int("int")): 100,
+        (int_separated, HASHMAP.int("int_separated")): 1000,
         (float, HASHMAP.float("float")): -2.4,
+        (float_grouped, HASHMAP.try_float_with("float_grouped", &NumberFormat::comma_grouped())): Ok(1000.5),
         (bool, HASHMAP.bool("bool")): true,
         (duration, HASHMAP.duration("duration")): chrono::Duration::seconds(50),
-        (datetime, HASHMAP.datetime("datetime")): Utc.ymd(2015, 5, 15).and_hms(5, 5, 5),
+        (datetime, HASHMAP.datetime("datetime")): Utc.with_ymd_and_hms(2015, 5, 15, 5, 5, 5).unwrap(),
         (list, HASHMAP.list("list")): vec!["1", "2", "3"],
         (map, HASHMAP.map("map")): {
             let mut m: HashMap<String, String> = HashMap::new();
@@ -226,4 +1842,341 @@ mod tests {
             m
         },
     }
+
+    #[test]
+    fn require() {
+        let cfg = HashMap::from([("db.uri", "postgres://db"), ("listen.addr", ":8080")]);
+        assert_eq!(cfg.require(&["db.uri", "listen.addr"]), Ok(()));
+        assert_eq!(cfg.require(&[]), Ok(()));
+
+        let err = cfg
+            .require(&["db.uri", "auth.secret", "listen.addr", "auth.issuer"])
+            .unwrap_err();
+        assert_eq!(err.0, vec!["auth.secret", "auth.issuer"]);
+        assert_eq!(
+            err.to_string(),
+            "missing values for: auth.secret, auth.issuer"
+        );
+    }
+
+    #[test]
+    fn choose() {
+        let cfg = HashMap::from([
+            ("a", "choose[x:50, y:50]"),
+            ("b", "choose[x:50, y:50]"),
+            ("never", "choose[x:0, y:1]"),
+            ("broken", "choose[x:50, y]"),
+            ("fixed", "x"),
+        ]);
+        let ids: Vec<String> = (0..1000).map(|i| format!("user-{}", i)).collect();
+        let xs = ids.iter().filter(|id| cfg.choose("a", id) == "x").count();
+        assert!(xs > 400 && xs < 600, "{}", xs);
+        // Keys split the IDs independently of each other.
+        assert!(ids
+            .iter()
+            .any(|id| cfg.choose("a", id) != cfg.choose("b", id)));
+        assert!(ids.iter().all(|id| cfg.choose("never", id) == "y"));
+        assert_eq!(cfg.choose("fixed", "user-1"), "x");
+        assert!(matches!(
+            cfg.try_choose("broken", "user-1"),
+            Err(ValueError::Invalid { .. })
+        ));
+        assert!(matches!(
+            cfg.try_choose("missing", "user-1"),
+            Err(ValueError::Missing(_))
+        ));
+    }
+
+    #[test]
+    fn command() {
+        let cfg = HashMap::from([
+            ("hook", "git commit -m 'Update config'"),
+            ("empty", "  "),
+            ("broken", "say \"hi"),
+        ]);
+        assert_eq!(
+            cfg.try_command("hook"),
+            Ok((
+                "git".to_string(),
+                vec![
+                    "commit".to_string(),
+                    "-m".to_string(),
+                    "Update config".to_string()
+                ]
+            ))
+        );
+        assert!(matches!(
+            cfg.try_command("empty"),
+            Err(ValueError::Invalid { .. })
+        ));
+        assert_eq!(
+            cfg.try_command("broken").unwrap_err().to_string(),
+            "invalid value for 'broken': expected command, got 'say \"hi'"
+        );
+        assert_eq!(cfg.command("hook").0, "git");
+        let e = std::panic::catch_unwind(|| cfg.command("empty")).unwrap_err();
+        assert!(e
+            .downcast_ref::<String>()
+            .unwrap()
+            .contains(r#"expected: "command""#));
+    }
+
+    #[test]
+    fn duration_between() {
+        let cfg = HashMap::from([("timeout", "1h30m"), ("retry", "fast"), ("zero", "0")]);
+        let (min, max) = (chrono::Duration::seconds(1), chrono::Duration::hours(2));
+        assert_eq!(
+            cfg.try_duration_between("timeout", min, max),
+            Ok(chrono::Duration::minutes(90))
+        );
+        assert_eq!(
+            cfg.try_duration_between("zero", min, max)
+                .unwrap_err()
+                .to_string(),
+            "invalid value for 'zero': expected duration between 1s and 2h, got '0s'"
+        );
+        assert_eq!(
+            cfg.try_duration("retry").unwrap_err().to_string(),
+            "invalid value for 'retry': expected duration like `30s`, got 'fast'"
+        );
+    }
+
+    #[test]
+    fn url_with() {
+        let cfg = HashMap::from([
+            ("hook", "https://hooks.example.com/alerts"),
+            ("plain", "http://hooks.example.com/alerts"),
+            ("metadata", "HTTPS://169.254.169.254/latest"),
+            ("relative", "/alerts"),
+        ]);
+        assert_eq!(
+            cfg.try_public_url_with("hook", &["https"]),
+            Ok("https://hooks.example.com/alerts".to_string())
+        );
+        assert_eq!(
+            cfg.try_url_with("plain", &["https"]).unwrap_err().to_string(),
+            "invalid value for 'plain': expected URL with scheme https, got 'http://hooks.example.com/alerts'"
+        );
+        assert!(cfg.try_url_with("plain", &["http", "https"]).is_ok());
+        assert!(cfg.try_url_with("metadata", &["https"]).is_ok());
+        assert_eq!(
+            cfg.try_public_url_with("metadata", &["https"])
+                .unwrap_err()
+                .to_string(),
+            "invalid value for 'metadata': expected URL with a public host, got 'HTTPS://169.254.169.254/latest'"
+        );
+        assert_eq!(
+            cfg.try_url_with("relative", &["https"])
+                .unwrap_err()
+                .to_string(),
+            "invalid value for 'relative': expected URL, got '/alerts'"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "cidr")]
+    fn cidr() {
+        let cfg = HashMap::from([
+            ("admin.network", "10.0.0.0/8"),
+            ("admin.host", "192.168.1.20"),
+            ("trusted_proxies", "[10.0.0.0/8, 192.168.0.0/16, fd00::/8]"),
+            ("none", "[]"),
+            ("broken", "[10.0.0.0/8, 10.0.0.0/33]"),
+        ]);
+        let network = cfg.cidr("admin.network");
+        assert!(network.contains("10.1.2.3".parse().unwrap()));
+        assert_eq!(cfg.cidr("admin.host").prefix(), 32);
+
+        let proxies = cfg.cidr_list("trusted_proxies");
+        assert_eq!(proxies.len(), 3);
+        assert!(proxies[2].contains("fd00::1".parse().unwrap()));
+        assert_eq!(cfg.try_cidr_list("none"), Ok(vec![]));
+        assert_eq!(
+            cfg.try_cidr_list("broken").unwrap_err().to_string(),
+            "invalid value for 'broken': expected CIDR network, got '10.0.0.0/33'"
+        );
+        assert!(matches!(
+            cfg.try_cidr("missing"),
+            Err(ValueError::Missing(_))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "glob")]
+    fn glob() {
+        let cfg = HashMap::from([
+            ("watch.include", "src/**/*.rs"),
+            ("watch.exclude", "[*.tmp, target/**, .git/**]"),
+            ("watch.none", "[]"),
+            ("broken.include", "src/[a-"),
+            ("broken.exclude", "[*.tmp, {a,b]"),
+        ]);
+        let include = cfg.glob("watch.include");
+        assert!(include.is_match("src/config/mod.rs"));
+        assert!(!include.is_match("benches/multi.rs"));
+        let exclude = cfg.glob_list("watch.exclude");
+        assert_eq!(exclude.len(), 3);
+        assert!(exclude.is_match("target/debug/app"));
+        assert!(exclude.is_match("notes.tmp"));
+        assert!(!exclude.is_match("src/lib.rs"));
+        assert!(cfg.glob_list("watch.none").is_empty());
+
+        assert!(matches!(
+            cfg.try_glob("broken.include"),
+            Err(ValueError::Invalid { .. })
+        ));
+        assert_eq!(
+            cfg.try_glob_list("broken.exclude").unwrap_err().to_string(),
+            "invalid value for 'broken.exclude': expected glob pattern, got '{a'"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "mime")]
+    fn mime_and_charset() {
+        let cfg = HashMap::from([
+            ("upload.type", "text/csv; charset=latin1"),
+            ("fallback.type", "application/octet-stream"),
+            ("bad.type", "text"),
+            ("bad.charset", "text/plain; charset=utf-9"),
+            ("output.charset", " UTF8 "),
+            ("input.charset", "ebcdic"),
+        ]);
+        let m = cfg.mime("upload.type");
+        assert_eq!(m.essence_str(), "text/csv");
+        assert_eq!(
+            cfg.try_mime("fallback.type"),
+            Ok(mime::APPLICATION_OCTET_STREAM)
+        );
+        assert!(matches!(
+            cfg.try_mime("bad.type"),
+            Err(ValueError::Invalid { .. })
+        ));
+        assert_eq!(
+            cfg.try_mime("bad.charset").unwrap_err().to_string(),
+            "invalid value for 'bad.charset': expected media type, got 'text/plain; charset=utf-9'"
+        );
+
+        assert_eq!(cfg.charset("output.charset").name(), "UTF-8");
+        assert_eq!(
+            cfg.try_charset("input.charset").unwrap_err().to_string(),
+            "invalid value for 'input.charset': expected charset, got 'ebcdic'"
+        );
+    }
+
+    #[test]
+    fn pem_file() {
+        let dir = crate::testing::TempDir::new("pem");
+        let cert = "-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n";
+        std::fs::write(dir.join("cert.pem"), cert).unwrap();
+        std::fs::write(dir.join("cert.der"), [0x30, 0x82, 0x01]).unwrap();
+
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+        let cfg = HashMap::from([
+            ("tls.cert".to_string(), path("cert.pem")),
+            ("tls.der".to_string(), path("cert.der")),
+            ("tls.key".to_string(), path("key.pem")),
+        ]);
+        assert_eq!(cfg.try_pem_file("tls.cert"), Ok(cert.as_bytes().to_vec()));
+        assert_eq!(
+            cfg.try_pem_file("tls.der"),
+            Err(ValueError::Invalid {
+                key: "tls.der".to_string(),
+                value: path("cert.der"),
+                expected: "PEM certificate or private key".to_string(),
+            })
+        );
+        assert_eq!(
+            cfg.try_pem_file("tls.key"),
+            Err(ValueError::Invalid {
+                key: "tls.key".to_string(),
+                value: path("key.pem"),
+                expected: "readable file".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "validate")]
+    fn email_and_hostname() {
+        let cfg = HashMap::from([
+            ("alerts.to", "ops+alerts@example.com"),
+            ("alerts.from", "Ops <ops@example.com>"),
+            ("smtp.host", "smtp.example.com"),
+            ("smtp.relay", "smtp_relay.example.com"),
+        ]);
+        assert_eq!(
+            cfg.try_email("alerts.to"),
+            Ok("ops+alerts@example.com".to_string())
+        );
+        assert_eq!(
+            cfg.try_email("alerts.from").unwrap_err().to_string(),
+            "invalid value for 'alerts.from': expected email address, got 'Ops <ops@example.com>'"
+        );
+        assert_eq!(
+            cfg.try_hostname("smtp.host"),
+            Ok("smtp.example.com".to_string())
+        );
+        assert_eq!(
+            cfg.try_hostname("smtp.relay").unwrap_err().to_string(),
+            "invalid value for 'smtp.relay': expected host name, got 'smtp_relay.example.com'"
+        );
+        assert!(matches!(
+            cfg.try_hostname("missing"),
+            Err(ValueError::Missing(_))
+        ));
+    }
+
+    #[test]
+    fn element() {
+        use std::cell::Cell;
+
+        let cfg = Simple::from_str(
+            "zips = [02134, 10001]\nvers = [1.10, 2.0]\nservers = [{host=>a}, {host=>b}]",
+        )
+        .unwrap();
+        assert_eq!(cfg.get("zips.0"), Some("02134".to_string()));
+        assert_eq!(cfg.string("vers.0"), "1.10");
+        assert_eq!(cfg.get("servers.0.host"), Some("a".to_string()));
+        assert_eq!(cfg.try_get("servers.1.host"), Ok("b".to_string()));
+        assert_eq!(cfg.get("servers.2.host"), None);
+
+        let cfg = HashMap::from([
+            ("flags", "[true, false]"),
+            ("groups", "[[a, b], [c]]"),
+            ("servers", "[{host=>a, port=>1}]"),
+        ]);
+        assert!(cfg.bool("flags.0"));
+        assert!(!cfg.bool("flags.1"));
+        assert_eq!(cfg.list("groups.0"), vec!["a", "b"]);
+        let server = HashMap::from([
+            ("host".to_string(), "a".to_string()),
+            ("port".to_string(), "1".to_string()),
+        ]);
+        assert_eq!(cfg.map("servers.0"), server);
+        let multi = MultiConfig::new(vec![Box::new(cfg.clone())]);
+        assert!(multi.bool("flags.0"));
+        assert_eq!(multi.list("groups.1"), vec!["c"]);
+        assert_eq!(multi.map("servers.0"), server);
+        for key in ["flags.2", "groups.2"] {
+            let e = std::panic::catch_unwind(|| cfg.bool(key)).unwrap_err();
+            assert!(e.downcast_ref::<String>().unwrap().contains(key));
+        }
+
+        /// Counts the lookups.
+        struct Counted(Cell<usize>);
+
+        impl Config for Counted {
+            fn get(&self, _key: &str) -> Option<String> {
+                self.0.set(self.0.get() + 1);
+                None
+            }
+        }
+
+        let counted = Counted(Cell::new(0));
+        assert!(counted.try_get("a.b.c.d").is_err());
+        assert_eq!(counted.0.get(), 1);
+        assert!(counted.try_get("a.0.c.1").is_err());
+        assert_eq!(counted.0.get(), 4);
+    }
 }
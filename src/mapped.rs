@@ -0,0 +1,120 @@
+//! Read some keys of a source under different names.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::normalized::canonical;
+use crate::{Config, SourceHealth, Value};
+
+/// Wraps a config so the given keys are read from other keys of it.
+/// This lets a source that follows someone else's conventions be
+/// layered without renaming the keys in code, such as the environment
+/// variables set by a platform:
+///
+/// ```
+/// use dinglebit_config::{Config, Environment, Mapped};
+///
+/// std::env::set_var("DATABASE_URL", "postgres://db.internal/app");
+/// let cfg = Mapped::new(Environment::new("")).translate("db.uri", "DATABASE_URL");
+/// assert_eq!(cfg.string("db.uri"), "postgres://db.internal/app");
+/// ```
+///
+/// Keys without a translation are read as they are.
+pub struct Mapped<C> {
+    config: C,
+    translations: BTreeMap<String, String>,
+}
+
+impl<C: Config> Mapped<C> {
+    /// Wrap the given config without any translations.
+    pub fn new(config: C) -> Self {
+        Self {
+            config,
+            translations: BTreeMap::new(),
+        }
+    }
+
+    /// Read the given key from the source key of the wrapped config.
+    pub fn translate(mut self, key: &str, source: &str) -> Self {
+        self.translations
+            .insert(key.to_string(), source.to_string());
+        self
+    }
+
+    /// The key to read from the wrapped config.
+    fn source<'k>(&'k self, key: &'k str) -> &'k str {
+        self.translations.get(key).map_or(key, |s| s.as_str())
+    }
+}
+
+impl<C: Config> Config for Mapped<C> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.config.get(self.source(key))
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.config.get_ref(self.source(key))
+    }
+
+    fn get_value(&self, key: &str) -> Option<Value> {
+        self.config.get_value(self.source(key))
+    }
+
+    /// The translated keys that have a value, followed by the keys of
+    /// the wrapped config that aren't the source of a translation.
+    fn keys(&self) -> Vec<String> {
+        let sources: HashSet<String> = self.translations.values().map(|s| canonical(s)).collect();
+        let mut keys: Vec<String> = self
+            .translations
+            .iter()
+            .filter(|(_, source)| self.config.get_ref(source).is_some())
+            .map(|(key, _)| key.clone())
+            .collect();
+        keys.extend(
+            self.config
+                .keys()
+                .into_iter()
+                .filter(|k| !sources.contains(&canonical(k))),
+        );
+        keys
+    }
+
+    fn source_health(&self) -> SourceHealth {
+        self.config.source_health()
+    }
+
+    fn source_name(&self) -> String {
+        self.config.source_name()
+    }
+
+    fn source_of(&self, key: &str) -> Option<String> {
+        self.config.source_of(self.source(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mapped::Mapped;
+    use crate::Config;
+    use std::collections::HashMap;
+
+    #[test]
+    fn mapped() {
+        let cfg = Mapped::new(HashMap::from([
+            ("DATABASE_URL", "postgres://db/app"),
+            ("PORT", "8080"),
+            ("log.level", "debug"),
+        ]))
+        .translate("db.uri", "DATABASE_URL")
+        .translate("listen.port", "PORT")
+        .translate("redis.uri", "REDIS_URL");
+        assert_eq!(cfg.string("db.uri"), "postgres://db/app");
+        assert_eq!(cfg.int("listen.port"), 8080);
+        assert_eq!(cfg.string("log.level"), "debug");
+        assert_eq!(cfg.get("redis.uri"), None);
+
+        let mut keys = cfg.keys();
+        keys.sort();
+        assert_eq!(keys, vec!["db.uri", "listen.port", "log.level"]);
+    }
+}
@@ -0,0 +1,165 @@
+//! Config files bundled with a mobile app, so a Rust core embedded in
+//! an Android or iOS app can load the same layers as the server side.
+//!
+//! Only the loader for the platform being built for is available. On
+//! Android the assets are read with the `ndk` crate.
+
+#[cfg(target_vendor = "apple")]
+use std::ffi::c_void;
+#[cfg(any(target_os = "android", target_vendor = "apple", test))]
+use std::io;
+#[cfg(any(target_vendor = "apple", all(test, unix)))]
+use std::path::PathBuf;
+
+#[cfg(any(target_os = "android", test))]
+use crate::Format;
+#[cfg(any(target_os = "android", target_vendor = "apple", test))]
+use crate::{Config, Error};
+
+/// Load the config file with the given name from the app's assets
+/// (e.g. `config/defaults.toml`). The format comes from the name, or
+/// is sniffed if the name doesn't say. The manager usually comes from
+/// `ndk::asset::AssetManager::from_ptr` with the pointer Java passes
+/// in, or from the `AndroidApp` of `android-activity`.
+#[cfg(target_os = "android")]
+pub fn android_asset(
+    manager: &ndk::asset::AssetManager,
+    name: &str,
+) -> Result<Box<dyn Config + Send + Sync>, Error> {
+    use std::io::Read;
+
+    let not_found = || Error::File {
+        path: name.into(),
+        kind: io::ErrorKind::NotFound,
+    };
+    let c_name = std::ffi::CString::new(name).map_err(|_| not_found())?;
+    let mut asset = manager.open(&c_name).ok_or_else(not_found)?;
+    let mut bytes = Vec::with_capacity(asset.length());
+    asset
+        .read_to_end(&mut bytes)
+        .map_err(|e| Error::file(name, &e))?;
+    load_asset(name, bytes)
+}
+
+/// Parse the contents of the asset with the given name. Assets that
+/// aren't valid UTF-8 are an error rather than patched up, so a
+/// corrupt file isn't loaded with some of its values mangled.
+#[cfg(any(target_os = "android", test))]
+fn load_asset(name: &str, bytes: Vec<u8>) -> Result<Box<dyn Config + Send + Sync>, Error> {
+    let contents = String::from_utf8(bytes).map_err(|_| Error::File {
+        path: name.into(),
+        kind: io::ErrorKind::InvalidData,
+    })?;
+    let format = Format::from_path(name).unwrap_or_else(|| Format::sniff(&contents));
+    format.load_str(&contents)
+}
+
+#[cfg(target_vendor = "apple")]
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFBundleGetMainBundle() -> *const c_void;
+    fn CFBundleCopyResourcesDirectoryURL(bundle: *const c_void) -> *const c_void;
+    fn CFURLGetFileSystemRepresentation(
+        url: *const c_void,
+        resolve_against_base: u8,
+        buffer: *mut u8,
+        max_len: isize,
+    ) -> u8;
+    fn CFRelease(cf: *const c_void);
+}
+
+/// The resources directory of the app's main bundle, if there is one.
+#[cfg(target_vendor = "apple")]
+pub fn resources_dir() -> Option<PathBuf> {
+    let mut buffer = vec![0u8; 4096];
+    // SAFETY: the main bundle isn't owned by the caller; the URL is
+    // released once its path has been copied out.
+    let ok = unsafe {
+        let bundle = CFBundleGetMainBundle();
+        if bundle.is_null() {
+            return None;
+        }
+        let url = CFBundleCopyResourcesDirectoryURL(bundle);
+        if url.is_null() {
+            return None;
+        }
+        let ok =
+            CFURLGetFileSystemRepresentation(url, 1, buffer.as_mut_ptr(), buffer.len() as isize);
+        CFRelease(url);
+        ok
+    };
+    match ok {
+        0 => None,
+        _ => Some(nul_terminated_path(&buffer)),
+    }
+}
+
+/// The path in a buffer filled in by C, which ends at the first NUL.
+#[cfg(any(target_vendor = "apple", all(test, unix)))]
+fn nul_terminated_path(buffer: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+
+    let len = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+    std::ffi::OsStr::from_bytes(&buffer[..len]).into()
+}
+
+/// Load the config file with the given name from the resources of the
+/// app's main bundle (e.g. `defaults.toml`), choosing the parser with
+/// `load_auto`.
+#[cfg(target_vendor = "apple")]
+pub fn bundle_resource(name: &str) -> Result<Box<dyn Config + Send + Sync>, Error> {
+    let dir = resources_dir().ok_or_else(|| Error::File {
+        path: name.into(),
+        kind: io::ErrorKind::NotFound,
+    })?;
+    crate::load_auto(&dir.join(name).to_string_lossy())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mobile::load_asset;
+    use crate::Error;
+    use std::io;
+
+    #[test]
+    fn assets() {
+        let cfg = load_asset(
+            "config/defaults.toml",
+            b"[db]\nhost = \"localhost\"\n".to_vec(),
+        );
+        #[cfg(feature = "toml")]
+        assert_eq!(cfg.unwrap().get("db.host"), Some("localhost".to_string()));
+        #[cfg(not(feature = "toml"))]
+        assert!(matches!(cfg, Err(Error::Unsupported(_))));
+
+        // Without an extension the format is sniffed.
+        let cfg = load_asset("defaults", b"db.host = localhost\n".to_vec()).unwrap();
+        assert_eq!(cfg.get("db.host"), Some("localhost".to_string()));
+
+        assert_eq!(
+            load_asset("defaults.cfg", b"db.host = caf\xe9\n".to_vec()).err(),
+            Some(Error::File {
+                path: "defaults.cfg".into(),
+                kind: io::ErrorKind::InvalidData,
+            })
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn paths() {
+        use crate::mobile::nul_terminated_path;
+        use std::path::PathBuf;
+
+        let mut buffer = b"/App.app/Resources\0garbage".to_vec();
+        assert_eq!(
+            nul_terminated_path(&buffer),
+            PathBuf::from("/App.app/Resources")
+        );
+        buffer.truncate(18);
+        assert_eq!(
+            nul_terminated_path(&buffer),
+            PathBuf::from("/App.app/Resources")
+        );
+    }
+}
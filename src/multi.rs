@@ -1,10 +1,118 @@
 //! Combine multiple configs to get configuration values from various
 //! places.
 
-use crate::Config;
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::fmt;
+use std::ops::Deref;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::atomic::AtomicUsize;
+use std::sync::{mpsc, Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::hash::FnvHashMap;
+use crate::suggest;
+use crate::timeout::Running;
+
+use crate::{
+    element, global, load_auto, Config, Defaults, Environment, Error, Paas, SourceHealth,
+    SourceStats, SystemdCredentials, Value, ValueError,
+};
+
+/// A config that can be cloned behind a `Box`, so the layers given to
+/// `MultiConfig::cloneable` are copied when the `MultiConfig` is. It's
+/// implemented for every config that is `Clone + Send + Sync`, which
+/// includes the sources in this crate that hold their values (e.g.
+/// `Simple`, `Json` and `Environment`).
+pub trait CloneableConfig: Config + Send + Sync {
+    /// Clone the config into a new box.
+    fn boxed_clone(&self) -> Box<dyn CloneableConfig>;
+}
+
+impl<C: Config + Clone + Send + Sync + 'static> CloneableConfig for C {
+    fn boxed_clone(&self) -> Box<dyn CloneableConfig> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn CloneableConfig> {
+    fn clone(&self) -> Self {
+        self.boxed_clone()
+    }
+}
+
+pub(crate) type MissHook = Arc<dyn Fn(&Miss) + Send + Sync>;
+
+/// A key that wasn't found in any layer of a `MultiConfig`, given to
+/// the functions added with `MultiConfig::on_miss` and
+/// `global::on_miss`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Miss {
+    /// The key that was looked up.
+    pub key: String,
+    /// The `source_name` of each layer that was asked, in order.
+    pub layers: Vec<String>,
+}
+
+/// A layer of a `MultiConfig`: shared between clones, or copied for
+/// each clone.
+enum Layer {
+    Shared(Arc<dyn Config + Send + Sync>),
+    Owned(Arc<dyn CloneableConfig>),
+}
+
+impl Layer {
+    /// Another handle to the same layer, e.g. for a thread, rather than
+    /// a copy for a clone of the `MultiConfig`.
+    fn handle(&self) -> Layer {
+        match self {
+            Layer::Shared(config) => Layer::Shared(config.clone()),
+            Layer::Owned(config) => Layer::Owned(config.clone()),
+        }
+    }
+}
+
+impl Deref for Layer {
+    type Target = dyn Config + Send + Sync;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Layer::Shared(config) => config.as_ref(),
+            Layer::Owned(config) => config.as_ref(),
+        }
+    }
+}
+
+impl Clone for Layer {
+    fn clone(&self) -> Self {
+        match self {
+            Layer::Shared(config) => Layer::Shared(config.clone()),
+            Layer::Owned(config) => Layer::Owned(Arc::from(config.boxed_clone())),
+        }
+    }
+}
 
 pub struct MultiConfig {
-    configs: Vec<Box<dyn Config>>,
+    configs: Vec<Layer>,
+    /// How long each layer took to load, if the `Builder` loaded it.
+    load_times: Vec<Option<Duration>>,
+    merge: bool,
+    parallel: bool,
+    /// How many threads `parallel` lookups can have running at once.
+    in_flight: usize,
+    /// The threads of `parallel` lookups that are still running,
+    /// shared with clones.
+    running: Arc<AtomicUsize>,
+    index: RwLock<Option<FnvHashMap<String, usize>>>,
+    on_miss: Vec<MissHook>,
+    resolution: Resolution,
+    /// The positions of the layers in the order they're asked.
+    order: Vec<usize>,
+    /// The prefixes of `Resolution::Routed` with the positions of the
+    /// layers they're routed to.
+    routes: Vec<(String, Vec<usize>)>,
 }
 
 impl MultiConfig {
@@ -15,20 +123,870 @@ impl MultiConfig {
     //! creating a `MultiConfig` with `!vec[environment,
     //! instance-config-file, global-config-file, default-values]`
     //! would provide something like you'd expect in a 12-factor app.
-    pub fn new(configs: Vec<Box<dyn Config>>) -> Self {
-        Self { configs }
+    //! The configs must be `Send + Sync` so the result can be shared
+    //! between threads (e.g. installed with `global::init`). This is
+    //! the breaking change of 2.0, as any `Box<dyn Config>` would do
+    //! in 1.x: a config holding an `Rc` or a `RefCell` has to switch to
+    //! an `Arc` and a `Mutex` (or `RwLock`) to be a layer.
+    pub fn new(configs: Vec<Box<dyn Config + Send + Sync>>) -> Self {
+        Self::with_layers(
+            configs
+                .into_iter()
+                .map(|c| Layer::Shared(Arc::from(c)))
+                .collect(),
+        )
+    }
+
+    /// Similar to `new` but each clone of the `MultiConfig` gets its
+    /// own copy of the configs (see `CloneableConfig`).
+    ///
+    /// ```
+    /// use dinglebit_config::{Config, Environment, MultiConfig, Simple};
+    ///
+    /// let cfg = MultiConfig::cloneable(vec![
+    ///     Box::new(Environment::new("app")),
+    ///     Box::new(Simple::from_str("foo = bar").unwrap()),
+    /// ]);
+    /// let copy = cfg.clone();
+    /// drop(cfg);
+    /// assert_eq!(copy.string("foo"), "bar");
+    /// ```
+    pub fn cloneable(configs: Vec<Box<dyn CloneableConfig>>) -> Self {
+        Self::with_layers(
+            configs
+                .into_iter()
+                .map(|c| Layer::Owned(Arc::from(c)))
+                .collect(),
+        )
+    }
+
+    fn with_layers(configs: Vec<Layer>) -> Self {
+        Self {
+            load_times: vec![None; configs.len()],
+            merge: false,
+            parallel: false,
+            in_flight: 16,
+            running: Arc::new(AtomicUsize::new(0)),
+            index: RwLock::new(None),
+            on_miss: vec![],
+            resolution: Resolution::FirstMatch,
+            order: (0..configs.len()).collect(),
+            routes: vec![],
+            configs,
+        }
+    }
+
+    /// Similar to `new` but checks the health of each config first
+    /// (see `Config::health`). With `Policy::FailFast`, the first
+    /// unhealthy config is returned as an error. With
+    /// `Policy::SkipAndWarn`, unhealthy configs are left out and
+    /// returned alongside the `MultiConfig` so they can be reported,
+    /// which keeps a service running on a file fallback when a remote
+    /// source is down.
+    pub fn try_new(
+        configs: Vec<Box<dyn Config + Send + Sync>>,
+        policy: Policy,
+    ) -> Result<(Self, Vec<Degraded>), Degraded> {
+        let mut healthy = Vec::with_capacity(configs.len());
+        let mut degraded = vec![];
+        for (index, config) in configs.into_iter().enumerate() {
+            match config.health() {
+                Ok(()) => healthy.push(config),
+                Err(error) => {
+                    let d = Degraded {
+                        index,
+                        source: config.source_name(),
+                        error,
+                    };
+                    match policy {
+                        Policy::FailFast => return Err(d),
+                        Policy::SkipAndWarn => degraded.push(d),
+                    }
+                }
+            }
+        }
+        Ok((Self::new(healthy), degraded))
+    }
+
+    /// Merge lists and maps across layers instead of taking the first
+    /// one found. List values (`[a, b]`) are concatenated, starting
+    /// with the lowest layer, and map values (`{a=>1}`) are combined
+    /// with the higher layers winning for the same map key. Lists and
+    /// maps nested in maps are merged the same way, and the merged map
+    /// lists its keys in order. A value
+    /// starting with `!reset` (e.g. `plugins = !reset [a]`) stops the
+    /// merge, so the layers below it are ignored for that key. Layers
+    /// whose value isn't the same kind as the top one are ignored as
+    /// well. Scalars are unaffected.
+    ///
+    /// Nested keys of structured formats (e.g. TOML tables) are
+    /// already resolved per key, so this only matters for values
+    /// using the list and map syntax.
+    pub fn merging(mut self) -> Self {
+        self.merge = true;
+        self
+    }
+
+    /// Query the layers at the same time instead of one after the
+    /// other, for stacks with slow (e.g. remote) sources. The value of
+    /// the highest layer that has one still wins, and is returned as
+    /// soon as it and the layers above it answered, without waiting
+    /// for the layers below. The top layer is asked on the calling
+    /// thread and each layer below it on a thread of its own, so this
+    /// only pays off when the layers wait on I/O; use `get_many` to
+    /// read many keys in one round. A layer that panics panics the
+    /// query.
+    ///
+    /// The threads aren't waited for once the value is known, so a
+    /// layer that hangs keeps its thread. At most 16 threads (see
+    /// `in_flight`) are running at once, counting those and the ones
+    /// of clones; beyond that, layers are asked on the calling thread,
+    /// one after the other. Lookups of list elements (e.g.
+    /// `servers.0`) that aren't keys of their own query the layers
+    /// again for the list, so they use more threads.
+    pub fn parallel(mut self) -> Self {
+        self.parallel = true;
+        self
+    }
+
+    /// Set how many threads `parallel` lookups can have running at
+    /// once, including those for layers that haven't answered yet
+    /// after the lookup returned.
+    pub fn in_flight(mut self, limit: usize) -> Self {
+        self.in_flight = limit;
+        self
+    }
+
+    /// Call the given function whenever a key isn't found in any layer,
+    /// e.g. to log or alert on lookups for keys that don't exist
+    /// anywhere, which is usually a deployment mistake. The functions
+    /// added with `global::on_miss` are called too. Clones share the
+    /// functions added before they were made.
+    ///
+    /// ```
+    /// use dinglebit_config::{Config, Environment, MultiConfig};
+    ///
+    /// let cfg = MultiConfig::new(vec![Box::new(Environment::new("app"))]).on_miss(|miss| {
+    ///     eprintln!("no value for {} in {:?}", miss.key, miss.layers);
+    /// });
+    /// assert_eq!(cfg.get("db.uri"), None);
+    /// ```
+    pub fn on_miss<F: Fn(&Miss) + Send + Sync + 'static>(mut self, f: F) -> Self {
+        self.on_miss.push(Arc::new(f));
+        self
+    }
+
+    /// Tell the miss hooks, if there are any, that the key wasn't found.
+    fn missed(&self, key: &str) {
+        if self.on_miss.is_empty() && !global::has_miss_hooks() {
+            return;
+        }
+        let miss = Miss {
+            key: key.to_string(),
+            layers: self.layers_for(key).map(|c| c.source_name()).collect(),
+        };
+        for hook in self.on_miss.iter() {
+            hook(&miss);
+        }
+        global::missed(&miss);
+    }
+
+    /// Choose which layers are asked for a key and in what order. See
+    /// `Resolution`.
+    ///
+    /// ```
+    /// use dinglebit_config::{Config, Environment, MultiConfig, Resolution, Simple};
+    ///
+    /// let cfg = MultiConfig::new(vec![
+    ///     Box::new(Environment::new("app")),
+    ///     Box::new(Simple::from_str("secrets.token = leaked\nport = 80").unwrap()),
+    /// ])
+    /// .resolution(Resolution::Routed(vec![("secrets".to_string(), 0)]));
+    /// assert_eq!(cfg.get("secrets.token"), None);
+    /// assert_eq!(cfg.int("port"), 80);
+    /// ```
+    pub fn resolution(mut self, resolution: Resolution) -> Self {
+        self.order = (0..self.configs.len()).collect();
+        self.routes = vec![];
+        match &resolution {
+            Resolution::FirstMatch => {}
+            Resolution::Priority(priorities) => {
+                self.order
+                    .sort_by_key(|&i| Reverse(priorities.get(i).copied()));
+            }
+            Resolution::Routed(routes) => {
+                for (prefix, layer) in routes {
+                    let prefix = prefix.trim_end_matches('*').trim_end_matches('.');
+                    match self.routes.iter_mut().find(|(p, _)| p == prefix) {
+                        Some((_, layers)) => layers.push(*layer),
+                        None => self.routes.push((prefix.to_string(), vec![*layer])),
+                    }
+                }
+            }
+        }
+        self.resolution = resolution;
+        if self.index.read().unwrap().is_some() {
+            self.reindex();
+        }
+        self
+    }
+
+    /// The positions of the layers the key is routed to, if it's
+    /// under a routed prefix. The longest prefix wins.
+    fn route(&self, key: &str) -> Option<&[usize]> {
+        self.routes
+            .iter()
+            .filter(|(prefix, _)| match key.strip_prefix(prefix.as_str()) {
+                Some(rest) => rest.is_empty() || rest.starts_with('.'),
+                None => false,
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, layers)| layers.as_slice())
+    }
+
+    /// Whether the layer at the position may have a value for the key.
+    fn allows(&self, layer: usize, key: &str) -> bool {
+        match self.route(key) {
+            Some(layers) => layers.contains(&layer),
+            None => true,
+        }
+    }
+
+    /// The layers that may have a value for the key, in the order
+    /// they're asked.
+    fn layers_for<'a>(&'a self, key: &str) -> impl Iterator<Item = &'a Layer> {
+        let route = self.route(key);
+        self.order
+            .iter()
+            .filter(move |i| match route {
+                Some(layers) => layers.contains(i),
+                None => true,
+            })
+            .map(move |&i| &self.configs[i])
+    }
+
+    /// Index which layer has each key, so a lookup goes straight to the
+    /// layer with the value instead of probing every layer above it.
+    /// This pays off for deep stacks read on hot paths. The index is
+    /// built from `Config::keys` right away, so keys that show up in a
+    /// higher layer afterwards (e.g. a new environment variable or a
+    /// reload) are shadowed by the layer in the index until `reindex`
+    /// is called. Keys that aren't in the index, such as those of
+    /// layers that can't list their keys, are still looked up in
+    /// every layer. Merging doesn't use the index.
+    pub fn indexed(self) -> Self {
+        self.reindex();
+        self
+    }
+
+    /// Build the index again after the keys of the layers changed. See
+    /// `indexed`.
+    pub fn reindex(&self) {
+        let mut index = FnvHashMap::default();
+        for &i in self.order.iter() {
+            for key in self.configs[i].keys() {
+                if self.allows(i, &key) {
+                    index.entry(key).or_insert(i);
+                }
+            }
+        }
+        *self.index.write().unwrap() = Some(index);
+    }
+
+    /// Get the value for the key from the layer the index has for it.
+    fn indexed_get(&self, key: &str) -> Option<Cow<'_, str>> {
+        let layer = *self.index.read().unwrap().as_ref()?.get(key)?;
+        self.configs[layer].get_ref(key)
+    }
+
+    /// Get the value for the key from the first layer that has one.
+    fn first(&self, key: &str) -> Option<Cow<'_, str>> {
+        if let Some(value) = self.indexed_get(key) {
+            return Some(value);
+        }
+        if !self.parallel {
+            return self.layers_for(key).find_map(|config| config.get_ref(key));
+        }
+        // The threads aren't waited for once the highest layer with a
+        // value answered, so they get their own handles to the layers.
+        // The top layer, and those beyond the limit, are asked here.
+        let layers: Vec<&Layer> = self.layers_for(key).collect();
+        let (tx, rx) = mpsc::channel();
+        let mut spawned = vec![false; layers.len()];
+        for (n, layer) in layers.iter().enumerate().skip(1) {
+            let running = match Running::start(&self.running, self.in_flight) {
+                Some(running) => running,
+                None => break,
+            };
+            let (tx, layer, key) = (tx.clone(), layer.handle(), key.to_string());
+            thread::spawn(move || {
+                let _running = running;
+                let _ = tx.send((n, panic::catch_unwind(AssertUnwindSafe(|| layer.get(&key)))));
+            });
+            spawned[n] = true;
+        }
+        drop(tx);
+        let mut answers: Vec<Option<Option<String>>> = vec![None; layers.len()];
+        let mut next = 0;
+        while next < layers.len() {
+            match answers[next].take() {
+                Some(Some(value)) => return Some(Cow::Owned(value)),
+                Some(None) => next += 1,
+                None if !spawned[next] => answers[next] = Some(layers[next].get(key)),
+                None => {
+                    let (n, answer) = rx.recv().ok()?;
+                    answers[n] = Some(answer.unwrap_or_else(|e| panic::resume_unwind(e)));
+                }
+            }
+        }
+        None
+    }
+
+    /// Get the value for the key, or an element of a list (e.g.
+    /// `servers.0`), without telling the miss hooks.
+    fn resolve(&self, key: &str) -> Option<Cow<'_, str>> {
+        let value = match self.merge {
+            true => self.merged(key).map(Cow::Owned),
+            false => self.first(key),
+        };
+        value.or_else(|| element(key, |k| self.resolve(k)))
+    }
+
+    /// Get the value for the key, merging lists and maps.
+    fn merged(&self, key: &str) -> Option<String> {
+        let mut values: Vec<Value> = vec![];
+        for config in self.layers_for(key) {
+            if let Some(value) = config.get_ref(key) {
+                let value = value.trim();
+                match value.strip_prefix("!reset") {
+                    Some(rest) => {
+                        values.push(Value::parse_raw(rest));
+                        break;
+                    }
+                    None => values.push(Value::parse_raw(value)),
+                }
+            }
+        }
+        let mut values = values.into_iter();
+        let mut merged = values.next()?;
+        for lower in values {
+            match (&merged, &lower) {
+                (Value::List(_), Value::List(_)) | (Value::Map(_), Value::Map(_)) => {
+                    merged = merge(lower, merged)
+                }
+                _ => break,
+            }
+        }
+        Some(merged.to_string())
+    }
+
+    /// The `source_name` and `fingerprint` of each layer, in order, to
+    /// find which source differs when the fingerprints of two nodes
+    /// don't match.
+    pub fn fingerprints(&self) -> Vec<(String, u64)> {
+        self.configs
+            .iter()
+            .map(|config| (config.source_name(), config.fingerprint()))
+            .collect()
+    }
+
+    /// The `source_name` and `source_health` of each layer, in order,
+    /// for a readiness probe that lists the sources.
+    pub fn layer_health(&self) -> Vec<(String, SourceHealth)> {
+        self.configs
+            .iter()
+            .map(|config| (config.source_name(), config.source_health()))
+            .collect()
+    }
+
+    /// Start building a `MultiConfig` one layer at a time. See
+    /// `Builder`.
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+}
+
+/// Merge a value into the one of the layer below it: lists are
+/// concatenated and maps are merged key by key, recursively, with the
+/// higher value winning for anything else.
+fn merge(lower: Value, higher: Value) -> Value {
+    match (lower, higher) {
+        (Value::List(mut lower), Value::List(higher)) => {
+            lower.extend(higher);
+            Value::List(lower)
+        }
+        (Value::Map(mut lower), Value::Map(higher)) => {
+            for (k, v) in higher {
+                let v = match lower.remove(&k) {
+                    Some(below) => merge(below, v),
+                    None => v,
+                };
+                lower.insert(k, v);
+            }
+            Value::Map(lower)
+        }
+        (_, higher) => higher,
+    }
+}
+
+/// Clones have their own copy of the layers given to `cloneable`, while
+/// those given to `new` (or added with a `Builder`) are shared, as
+/// most can't be copied (e.g. a connection to a remote source). Either
+/// way, a clone sees the same values as the original.
+impl Clone for MultiConfig {
+    fn clone(&self) -> Self {
+        Self {
+            configs: self.configs.clone(),
+            load_times: self.load_times.clone(),
+            merge: self.merge,
+            parallel: self.parallel,
+            in_flight: self.in_flight,
+            running: self.running.clone(),
+            index: RwLock::new(self.index.read().unwrap().clone()),
+            on_miss: self.on_miss.clone(),
+            resolution: self.resolution.clone(),
+            order: self.order.clone(),
+            routes: self.routes.clone(),
+        }
+    }
+}
+
+/// What `MultiConfig::try_new` does with configs that aren't healthy.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Policy {
+    /// Return the first unhealthy config as an error.
+    FailFast,
+    /// Leave unhealthy configs out and report them.
+    SkipAndWarn,
+}
+
+/// How a `MultiConfig` decides which layer a value comes from, set
+/// with `MultiConfig::resolution`. The indexes, merging and `get_many`
+/// all follow it.
+///
+/// Layers are identified by their position in the list given to
+/// `MultiConfig::new` (or the order they were added to a `Builder`,
+/// with the defaults last). The layers `MultiConfig::try_new` leaves
+/// out aren't counted, so check the `Degraded` layers it returns
+/// before choosing positions.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub enum Resolution {
+    /// The first layer with a value wins, in the order they were given.
+    #[default]
+    FirstMatch,
+
+    /// The layer with the highest priority wins. The priorities are
+    /// given in the same order as the layers, layers with the same
+    /// priority keep their order and layers without one (e.g. the
+    /// defaults of a `Builder`) come last.
+    Priority(Vec<i32>),
+
+    /// Keys under a prefix (e.g. `secrets` for `secrets.*`) only come
+    /// from the layer at the given position, so a secret can't be
+    /// overridden or leaked by another layer. A prefix can be routed
+    /// to several layers, which are asked in their usual order. If there's no layer
+    /// at the position, the keys under the prefix have no value. The
+    /// longest matching prefix wins and other keys are first match.
+    Routed(Vec<(String, usize)>),
+}
+
+/// A config that failed its health check.
+#[derive(Debug, PartialEq)]
+pub struct Degraded {
+    /// The position of the config in the list given to `try_new`.
+    pub index: usize,
+    /// The config's `source_name`.
+    pub source: String,
+    /// Why the config isn't healthy.
+    pub error: Error,
+}
+
+/// Builds a `MultiConfig` from layers added in priority order (the
+/// first layer added wins), with the defaults always at the bottom:
+///
+/// ```
+/// use dinglebit_config::{Config, Defaults, MultiConfig};
+///
+/// let cfg = MultiConfig::builder()
+///     .env("app")
+///     .file("example.cfg")
+///     .defaults(Defaults::new().set("pool", "10"))
+///     .build()
+///     .unwrap();
+/// assert_eq!(cfg.string("foo"), "bar");
+/// assert_eq!(cfg.int("pool"), 10);
+/// ```
+#[derive(Default)]
+pub struct Builder {
+    configs: Vec<Box<dyn Config + Send + Sync>>,
+    load_times: Vec<Option<Duration>>,
+    defaults: Defaults,
+    error: Option<Error>,
+    merge: bool,
+    parallel: bool,
+    in_flight: Option<usize>,
+    indexed: bool,
+    on_miss: Vec<MissHook>,
+    resolution: Resolution,
+}
+
+impl Builder {
+    /// Add a layer.
+    pub fn layer<C: Config + Send + Sync + 'static>(mut self, config: C) -> Self {
+        self.configs.push(Box::new(config));
+        self.load_times.push(None);
+        self
+    }
+
+    /// Add the environment with the given prefix as a layer. See
+    /// `Environment::new`.
+    pub fn env(self, prefix: &str) -> Self {
+        self.layer(Environment::new(prefix))
+    }
+
+    /// Add the systemd credentials (see `SystemdCredentials::new`)
+    /// and then the environment with the given prefix, so a secret
+    /// delivered as a credential wins over an environment variable for
+    /// the same key. If the credentials can't be read, `build` returns
+    /// the error.
+    pub fn env_with_credentials(mut self, prefix: &str) -> Self {
+        let start = Instant::now();
+        match SystemdCredentials::new() {
+            Ok(credentials) => self = self.timed(Box::new(credentials), start),
+            Err(e) => {
+                self.error.get_or_insert(e);
+            }
+        }
+        self.env(prefix)
+    }
+
+    /// Add the platform variables from the environment (see
+    /// `Paas::from_env`) as a layer, so e.g. `db.host` is read from
+    /// `DATABASE_URL`. If a URL can't be parsed, `build` returns the
+    /// error.
+    pub fn paas(mut self) -> Self {
+        let start = Instant::now();
+        match Paas::from_env() {
+            Ok(paas) => self = self.timed(Box::new(paas), start),
+            Err(e) => {
+                self.error.get_or_insert(e);
+            }
+        }
+        self
+    }
+
+    /// Add the file at the given path as a layer, choosing the parser
+    /// with `load_auto`. If the file can't be loaded, `build` returns
+    /// the error.
+    pub fn file(mut self, path: &str) -> Self {
+        let start = Instant::now();
+        match load_auto(path) {
+            Ok(config) => self = self.timed(config, start),
+            Err(e) => {
+                self.error.get_or_insert(e);
+            }
+        }
+        self
+    }
+
+    /// Add a layer that took from `start` until now to load.
+    fn timed(mut self, config: Box<dyn Config + Send + Sync>, start: Instant) -> Self {
+        self.configs.push(config);
+        self.load_times.push(Some(start.elapsed()));
+        self
+    }
+
+    /// Similar to `file` but a file that doesn't exist is skipped, so
+    /// a stack like `/etc/app.toml`, `~/.config/app.toml` and
+    /// `./app.toml` can be given without checking for each one. A file
+    /// that exists but can't be read or parsed is still an error.
+    pub fn optional_file(self, path: &str) -> Self {
+        match Path::new(path).exists() {
+            true => self.file(path),
+            false => self,
+        }
+    }
+
+    /// Set the defaults, which are consulted after every other layer
+    /// no matter when they're set.
+    pub fn defaults(mut self, defaults: Defaults) -> Self {
+        self.defaults = defaults;
+        self
+    }
+
+    /// Merge lists and maps across layers. See `MultiConfig::merging`.
+    pub fn merging(mut self) -> Self {
+        self.merge = true;
+        self
+    }
+
+    /// Query the layers at the same time. See `MultiConfig::parallel`.
+    pub fn parallel(mut self) -> Self {
+        self.parallel = true;
+        self
+    }
+
+    /// Set how many threads `parallel` lookups can have running at
+    /// once. See `MultiConfig::in_flight`.
+    pub fn in_flight(mut self, limit: usize) -> Self {
+        self.in_flight = Some(limit);
+        self
+    }
+
+    /// Index which layer has each key. See `MultiConfig::indexed`.
+    pub fn indexed(mut self) -> Self {
+        self.indexed = true;
+        self
+    }
+
+    /// Call the given function whenever a key isn't found. See
+    /// `MultiConfig::on_miss`.
+    pub fn on_miss<F: Fn(&Miss) + Send + Sync + 'static>(mut self, f: F) -> Self {
+        self.on_miss.push(Arc::new(f));
+        self
+    }
+
+    /// Choose which layers are asked for a key and in what order. See
+    /// `MultiConfig::resolution`.
+    pub fn resolution(mut self, resolution: Resolution) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    /// Create the `MultiConfig` or return the first error from adding
+    /// the layers.
+    pub fn build(mut self) -> Result<MultiConfig, Error> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        self.configs.push(Box::new(self.defaults));
+        self.load_times.push(None);
+        let mut multi = MultiConfig::new(self.configs);
+        multi.load_times = self.load_times;
+        multi.merge = self.merge;
+        multi.parallel = self.parallel;
+        if let Some(limit) = self.in_flight {
+            multi.in_flight = limit;
+        }
+        multi.on_miss = self.on_miss;
+        let multi = multi.resolution(self.resolution);
+        Ok(match self.indexed {
+            true => multi.indexed(),
+            false => multi,
+        })
     }
 }
 
 impl Config for MultiConfig {
     fn get(&self, key: &str) -> Option<String> {
-        for config in self.configs.iter() {
-            match config.get(key) {
-                Some(value) => return Some(value),
-                None => continue,
+        self.get_ref(key).map(Cow::into_owned)
+    }
+
+    /// Elements of lists are available by index (e.g. `servers.0`)
+    /// from any layer, whether structured or using the list syntax.
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        let value = self.resolve(key);
+        if value.is_none() {
+            self.missed(key);
+        }
+        value
+    }
+
+    /// The same as `get_ref`, which already finds elements of lists.
+    fn try_get_ref(&self, key: &str) -> Result<Cow<'_, str>, ValueError> {
+        self.get_ref(key)
+            .ok_or_else(|| ValueError::Missing(key.to_string()))
+    }
+
+    fn get_value(&self, key: &str) -> Option<Value> {
+        if self.merge {
+            return match self.merged(key) {
+                Some(v) => Some(Value::parse(&v)),
+                None => element(key, |k| self.resolve(k)).map(|v| Value::parse(&v)),
             };
         }
-        None
+        self.layers_for(key)
+            .find_map(|config| config.get_value(key))
+    }
+
+    /// Each layer is asked for the keys it hasn't had an answer for
+    /// yet in one `get_many` call, or all layers at once with
+    /// `parallel`, so reading many keys from remote sources doesn't
+    /// need a round trip per key.
+    fn get_many(&self, keys: &[&str]) -> Vec<Option<String>> {
+        if self.merge {
+            return keys.iter().map(|key| self.get(key)).collect();
+        }
+        let mut values: Vec<Option<String>> = vec![None; keys.len()];
+        if self.parallel {
+            let layers: Vec<(usize, Vec<Option<String>>)> = thread::scope(|s| {
+                let handles: Vec<_> = self
+                    .order
+                    .iter()
+                    .map(|&l| (l, s.spawn(move || self.configs[l].get_many(keys))))
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|(l, h)| (l, h.join().unwrap_or_else(|e| panic::resume_unwind(e))))
+                    .collect()
+            });
+            for (l, layer) in layers {
+                for ((value, found), key) in values.iter_mut().zip(layer).zip(keys) {
+                    if value.is_none() && self.allows(l, key) {
+                        *value = found;
+                    }
+                }
+            }
+        } else {
+            for &l in self.order.iter() {
+                if values.iter().all(Option::is_some) {
+                    break;
+                }
+                let missing: Vec<usize> = (0..keys.len())
+                    .filter(|&i| values[i].is_none() && self.allows(l, keys[i]))
+                    .collect();
+                if missing.is_empty() {
+                    continue;
+                }
+                let wanted: Vec<&str> = missing.iter().map(|&i| keys[i]).collect();
+                for (i, found) in missing.into_iter().zip(self.configs[l].get_many(&wanted)) {
+                    values[i] = found;
+                }
+            }
+        }
+        // Indexed keys (e.g. `servers.0`) aren't found directly.
+        for (value, key) in values.iter_mut().zip(keys) {
+            if value.is_none() {
+                *value = element(key, |k| self.resolve(k)).map(Cow::into_owned);
+            }
+            if value.is_none() {
+                self.missed(key);
+            }
+        }
+        values
+    }
+
+    /// Every layer prefetches the prefixes (all at once with
+    /// `parallel`). A prefix fails with the first error from a layer,
+    /// otherwise it counts the distinct keys under it.
+    fn prefetch(&self, prefixes: &[&str]) -> Vec<Result<usize, Error>> {
+        let layers: Vec<Vec<Result<usize, Error>>> = match self.parallel {
+            true => thread::scope(|s| {
+                let handles: Vec<_> = self
+                    .configs
+                    .iter()
+                    .map(|config| s.spawn(move || config.prefetch(prefixes)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|h| h.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+                    .collect()
+            }),
+            false => self
+                .configs
+                .iter()
+                .map(|config| config.prefetch(prefixes))
+                .collect(),
+        };
+        let mut results: Vec<Option<Error>> = prefixes.iter().map(|_| None).collect();
+        for layer in layers {
+            for (result, found) in results.iter_mut().zip(layer) {
+                if let (None, Err(e)) = (&result, found) {
+                    *result = Some(e);
+                }
+            }
+        }
+        let keys = self.keys();
+        results
+            .into_iter()
+            .zip(prefixes)
+            .map(|(error, prefix)| match error {
+                Some(e) => Err(e),
+                None => Ok(keys.iter().filter(|k| k.starts_with(prefix)).count()),
+            })
+            .collect()
+    }
+
+    /// Ok when every layer is, down when every layer is, and degraded
+    /// otherwise, with the reasons of the layers that aren't ok.
+    fn source_health(&self) -> SourceHealth {
+        let layers = self.layer_health();
+        let reasons: Vec<String> = layers
+            .iter()
+            .filter(|(_, health)| !health.is_ok())
+            .map(|(name, health)| format!("{}: {}", name, health))
+            .collect();
+        if reasons.is_empty() {
+            return SourceHealth::Ok;
+        }
+        match layers.iter().all(|(_, health)| !health.is_ready()) {
+            true => SourceHealth::Down(reasons.join("; ")),
+            false => SourceHealth::Degraded(reasons.join("; ")),
+        }
+    }
+
+    /// The statistics of each layer, with the load time of the layers
+    /// the `Builder` loaded.
+    fn stats(&self) -> Vec<SourceStats> {
+        self.configs
+            .iter()
+            .zip(self.load_times.iter())
+            .flat_map(|(config, took)| {
+                let mut stats = config.stats();
+                if let [single] = stats.as_mut_slice() {
+                    single.load_time = single.load_time.or(*took);
+                }
+                stats
+            })
+            .collect()
+    }
+
+    /// The source of the first layer with a value for the key.
+    fn source_of(&self, key: &str) -> Option<String> {
+        self.layers_for(key)
+            .find_map(|config| config.source_of(key))
+    }
+
+    /// The similar keys of each layer, so layers that don't suggest
+    /// their keys (e.g. remote ones) aren't asked to list them.
+    fn similar_keys(&self, key: &str) -> Vec<String> {
+        let mut keys: Vec<String> = vec![];
+        for (i, config) in self.configs.iter().enumerate() {
+            for k in config.similar_keys(key) {
+                if self.allows(i, &k) && !keys.contains(&k) {
+                    keys.push(k);
+                }
+            }
+        }
+        suggest::similar(key, &keys)
+    }
+
+    /// The keys of all of the configs, without duplicates.
+    fn keys(&self) -> Vec<String> {
+        let mut keys = Vec::new();
+        for (i, config) in self.configs.iter().enumerate() {
+            for key in config.keys() {
+                if self.allows(i, &key) && !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        }
+        keys
+    }
+}
+
+/// Shows the `source_name` of each layer, never the values.
+impl fmt::Debug for MultiConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let layers: Vec<String> = self.configs.iter().map(|c| c.source_name()).collect();
+        f.debug_struct("MultiConfig")
+            .field("layers", &layers)
+            .field("merge", &self.merge)
+            .field("parallel", &self.parallel)
+            .field("in_flight", &self.in_flight)
+            .field("indexed", &self.index.read().unwrap().is_some())
+            .field("resolution", &self.resolution)
+            .finish()
     }
 }
 
@@ -52,5 +1010,546 @@ mod tests {
         assert_eq!(mc.get("foo"), Some("buz".to_string()));
         assert_eq!(mc.get("bar"), Some("baz".to_string()));
         assert_eq!(mc.get("buz"), Some("foo".to_string()));
+
+        let fingerprints = mc.fingerprints();
+        assert_eq!(fingerprints.len(), 2);
+        assert_ne!(fingerprints[0].1, fingerprints[1].1);
+        assert_ne!(mc.fingerprint(), fingerprints[0].1);
+    }
+
+    #[test]
+    fn index() {
+        use std::collections::HashMap;
+        use std::sync::{Arc, Mutex};
+
+        /// Values that can be changed from outside.
+        struct Shared(Arc<Mutex<HashMap<String, String>>>);
+
+        impl Config for Shared {
+            fn get(&self, key: &str) -> Option<String> {
+                self.0.lock().unwrap().get(key).cloned()
+            }
+
+            fn keys(&self) -> Vec<String> {
+                self.0.lock().unwrap().keys().cloned().collect()
+            }
+        }
+
+        let values = Arc::new(Mutex::new(HashMap::from([(
+            "a".to_string(),
+            "top".to_string(),
+        )])));
+        let mc = MultiConfig::new(vec![
+            Box::new(Shared(values.clone())),
+            Box::new(HashMap::from([("a", "bottom"), ("b", "bottom")])),
+        ])
+        .indexed();
+        assert_eq!(mc.get("a"), Some("top".to_string()));
+        assert_eq!(mc.get("b"), Some("bottom".to_string()));
+        assert_eq!(mc.get("c"), None);
+
+        // Keys that show up in a higher layer are shadowed until the
+        // index is rebuilt, while keys the index doesn't have are
+        // still found.
+        values
+            .lock()
+            .unwrap()
+            .insert("b".to_string(), "top".to_string());
+        values
+            .lock()
+            .unwrap()
+            .insert("c".to_string(), "top".to_string());
+        assert_eq!(mc.get("b"), Some("bottom".to_string()));
+        assert_eq!(mc.get("c"), Some("top".to_string()));
+        mc.reindex();
+        assert_eq!(mc.get("b"), Some("top".to_string()));
+
+        // A key that's gone from its layer falls back to the others.
+        values.lock().unwrap().remove("a");
+        assert_eq!(mc.get("a"), Some("bottom".to_string()));
+
+        // Clones share the layers given to `new` and keep the index.
+        let copy = mc.clone();
+        values
+            .lock()
+            .unwrap()
+            .insert("d".to_string(), "top".to_string());
+        assert_eq!(copy.get("d"), Some("top".to_string()));
+        assert_eq!(copy.get("b"), Some("top".to_string()));
+    }
+
+    #[test]
+    fn cloneable() {
+        use crate::{Environment, Simple};
+
+        let mc = MultiConfig::cloneable(vec![
+            Box::new(Environment::new("dinglebit_cloneable")),
+            Box::new(Simple::from_str("a = [1, 2]").unwrap()),
+            Box::new(std::collections::HashMap::from([(
+                "a".to_string(),
+                "[3]".to_string(),
+            )])),
+        ])
+        .merging();
+        let copy = mc.clone();
+        drop(mc);
+        assert_eq!(copy.list("a"), vec!["3", "1", "2"]);
+        assert_eq!(copy.fingerprints().len(), 3);
+    }
+
+    struct Down;
+
+    impl Config for Down {
+        fn get(&self, _key: &str) -> Option<String> {
+            None
+        }
+
+        fn source_health(&self) -> crate::SourceHealth {
+            crate::SourceHealth::Down("unreachable".to_string())
+        }
+    }
+
+    #[test]
+    fn try_new() {
+        use crate::{Degraded, Error, Policy};
+        use std::collections::HashMap;
+
+        let layers = || -> Vec<Box<dyn Config + Send + Sync>> {
+            vec![Box::new(Down), Box::new(HashMap::from([("foo", "bar")]))]
+        };
+        let (mc, degraded) = MultiConfig::try_new(layers(), Policy::SkipAndWarn).unwrap();
+        assert_eq!(mc.get("foo"), Some("bar".to_string()));
+        assert_eq!(
+            degraded,
+            vec![Degraded {
+                index: 0,
+                source: "Down".to_string(),
+                error: Error::Unhealthy("unreachable".to_string()),
+            }]
+        );
+        assert!(matches!(
+            MultiConfig::try_new(layers(), Policy::FailFast),
+            Err(Degraded { index: 0, .. })
+        ));
+        assert_eq!(
+            HashMap::<&str, &str>::new().source_name(),
+            "HashMap<&str, &str>"
+        );
+    }
+
+    #[test]
+    fn on_miss() {
+        use crate::Miss;
+        use std::collections::HashMap;
+        use std::sync::{Arc, Mutex};
+
+        let misses = Arc::new(Mutex::new(vec![]));
+        let record = misses.clone();
+        let mc = MultiConfig::builder()
+            .layer(HashMap::from([("a", "1"), ("list", "[x, y]")]))
+            .on_miss(move |miss| record.lock().unwrap().push(miss.clone()))
+            .build()
+            .unwrap();
+        assert_eq!(mc.get("a"), Some("1".to_string()));
+        assert_eq!(mc.get("list.1"), Some("y".to_string()));
+        assert_eq!(mc.get("b"), None);
+        assert_eq!(mc.get_many(&["a", "c"]), vec![Some("1".to_string()), None]);
+        // Only the keys asked for are misses, not the keys probed for
+        // lists along the way.
+        assert!(mc.try_int("db.port").is_err());
+        assert_eq!(mc.get("list.5"), None);
+        assert_eq!(mc.get("db.0.host"), None);
+        let layers = vec!["HashMap<&str, &str>".to_string(), "Defaults".to_string()];
+        let keys: Vec<String> = misses
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|m| m.key.clone())
+            .collect();
+        assert_eq!(keys, vec!["b", "c", "db.port", "list.5", "db.0.host"]);
+        assert_eq!(
+            misses.lock().unwrap()[0],
+            Miss {
+                key: "b".to_string(),
+                layers,
+            }
+        );
+    }
+
+    #[test]
+    fn source_health() {
+        use crate::SourceHealth;
+        use std::collections::HashMap;
+
+        let ok = MultiConfig::new(vec![Box::new(HashMap::from([("foo", "bar")]))]);
+        assert_eq!(ok.source_health(), SourceHealth::Ok);
+
+        let degraded = MultiConfig::new(vec![
+            Box::new(Down),
+            Box::new(HashMap::from([("foo", "bar")])),
+        ]);
+        assert_eq!(
+            degraded.source_health(),
+            SourceHealth::Degraded("Down: down: unreachable".to_string())
+        );
+        assert_eq!(degraded.layer_health()[1].1, SourceHealth::Ok);
+
+        let down = MultiConfig::new(vec![Box::new(Down)]);
+        assert!(!down.source_health().is_ready());
+    }
+
+    #[test]
+    fn parallel() {
+        use std::collections::HashMap;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        /// Counts the round trips it's asked to make.
+        struct Remote(HashMap<&'static str, &'static str>, Arc<AtomicUsize>);
+
+        impl Config for Remote {
+            fn get(&self, key: &str) -> Option<String> {
+                self.1.fetch_add(1, Ordering::SeqCst);
+                self.0.get(key).map(|v| v.to_string())
+            }
+
+            fn get_many(&self, keys: &[&str]) -> Vec<Option<String>> {
+                self.1.fetch_add(1, Ordering::SeqCst);
+                keys.iter()
+                    .map(|k| self.0.get(k).map(|v| v.to_string()))
+                    .collect()
+            }
+        }
+
+        for parallel in [false, true] {
+            let trips = Arc::new(AtomicUsize::new(0));
+            let top = Remote(HashMap::from([("a", "top")]), trips.clone());
+            let bottom = Remote(
+                HashMap::from([("a", "bottom"), ("b", "bottom")]),
+                trips.clone(),
+            );
+            let mut mc = MultiConfig::new(vec![Box::new(top), Box::new(bottom)]);
+            if parallel {
+                mc = mc.parallel();
+            }
+            assert_eq!(
+                mc.get_many(&["a", "b"]),
+                vec![Some("top".to_string()), Some("bottom".to_string())]
+            );
+            assert_eq!(trips.load(Ordering::SeqCst), 2);
+            assert_eq!(mc.get("a"), Some("top".to_string()));
+            assert_eq!(mc.get("b"), Some("bottom".to_string()));
+        }
+
+        /// Doesn't answer until the test is over.
+        struct Slow(Arc<std::sync::Mutex<()>>);
+
+        impl Config for Slow {
+            fn get(&self, _key: &str) -> Option<String> {
+                let _open = self.0.lock().unwrap();
+                Some("slow".to_string())
+            }
+        }
+
+        // The top layer's value doesn't wait for the layers below.
+        let gate = Arc::new(std::sync::Mutex::new(()));
+        let closed = gate.lock().unwrap();
+        let mc = MultiConfig::new(vec![
+            Box::new(HashMap::from([("a", "top")])),
+            Box::new(Slow(gate.clone())),
+        ])
+        .parallel();
+        assert_eq!(mc.get("a"), Some("top".to_string()));
+
+        // Layers that are still hung hold on to their threads, so no
+        // more are started past the limit.
+        let mc = MultiConfig::new(vec![
+            Box::new(HashMap::from([("a", "top")])),
+            Box::new(Slow(gate.clone())),
+            Box::new(Slow(gate.clone())),
+        ])
+        .parallel()
+        .in_flight(2);
+        for _ in 0..3 {
+            assert_eq!(mc.get("a"), Some("top".to_string()));
+        }
+        assert_eq!(mc.running.load(Ordering::SeqCst), 2);
+        drop(closed);
+        assert_eq!(mc.get("b"), Some("slow".to_string()));
+        while mc.running.load(Ordering::SeqCst) > 0 {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        /// Panics on every lookup.
+        struct Broken;
+
+        impl Config for Broken {
+            fn get(&self, _key: &str) -> Option<String> {
+                panic!("broken layer");
+            }
+        }
+
+        let mc = MultiConfig::new(vec![
+            Box::new(Broken),
+            Box::new(HashMap::from([("a", "bottom")])),
+        ])
+        .parallel();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| mc.get("a")));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn prefetch() {
+        use crate::Error;
+        use std::collections::HashMap;
+        use std::sync::Mutex;
+
+        /// Only knows its values once they've been prefetched.
+        struct Remote(Mutex<HashMap<String, String>>);
+
+        impl Config for Remote {
+            fn get(&self, key: &str) -> Option<String> {
+                self.0.lock().unwrap().get(key).cloned()
+            }
+
+            fn keys(&self) -> Vec<String> {
+                self.0.lock().unwrap().keys().cloned().collect()
+            }
+
+            fn prefetch(&self, prefixes: &[&str]) -> Vec<Result<usize, Error>> {
+                prefixes
+                    .iter()
+                    .map(|&prefix| match prefix {
+                        "db." => {
+                            let mut values = self.0.lock().unwrap();
+                            values.insert("db.host".to_string(), "remote".to_string());
+                            values.insert("db.port".to_string(), "5432".to_string());
+                            Ok(2)
+                        }
+                        _ => Err(Error::Unsupported(prefix.to_string())),
+                    })
+                    .collect()
+            }
+        }
+
+        let remote = Remote(Mutex::new(HashMap::new()));
+        let local = HashMap::from([("db.host", "local"), ("cache.size", "1")]);
+        let mc = MultiConfig::new(vec![Box::new(remote), Box::new(local)]).parallel();
+        assert_eq!(mc.get("db.host"), Some("local".to_string()));
+        assert_eq!(
+            mc.prefetch(&["db.", "cache."]),
+            vec![Ok(2), Err(Error::Unsupported("cache.".to_string()))]
+        );
+        assert_eq!(mc.get("db.host"), Some("remote".to_string()));
+        assert_eq!(
+            HashMap::from([("cache.size", "1")]).prefetch(&["cache.", "db."]),
+            vec![Ok(1), Ok(0)]
+        );
+    }
+
+    #[test]
+    fn builder() {
+        use crate::{Defaults, Error};
+        use std::collections::HashMap;
+
+        let mc = MultiConfig::builder()
+            .defaults(Defaults::new().set("foo", "default").set("pool", "10"))
+            .layer(HashMap::from([("foo", "layer")]))
+            .file("example.cfg")
+            .optional_file("/i/hope/i/do/not/exist.cfg")
+            .build()
+            .unwrap();
+        assert_eq!(mc.string("foo"), "layer");
+        assert_eq!(mc.int("pool"), 10);
+        assert_eq!(mc.string("list"), "one, two, three");
+
+        assert!(matches!(
+            MultiConfig::builder()
+                .file("/i/hope/i/do/not/exist.cfg")
+                .build(),
+            Err(Error::File { .. })
+        ));
+
+        // Optional files still have to parse.
+        let dir = crate::testing::TempDir::new("optional");
+        let bad = dir.join("bad.cfg");
+        std::fs::write(&bad, "no pair here").unwrap();
+        assert!(matches!(
+            MultiConfig::builder()
+                .optional_file(bad.to_str().unwrap())
+                .build(),
+            Err(Error::InvalidKeyValuePair)
+        ));
+    }
+
+    #[test]
+    fn merging() {
+        use std::collections::HashMap;
+        let env = HashMap::from([("plugins", "[c]"), ("name", "env")]);
+        let site = HashMap::from([
+            ("plugins", "[b]"),
+            ("labels", "{team=>web, tier=>2}"),
+            ("reset", "!reset [y]"),
+        ]);
+        let base = HashMap::from([
+            ("plugins", "[a]"),
+            ("labels", "{tier=>1, region=>eu}"),
+            ("reset", "[x]"),
+            ("name", "base"),
+        ]);
+
+        let mc = MultiConfig::new(vec![Box::new(env), Box::new(site), Box::new(base)]).merging();
+        assert_eq!(mc.list("plugins"), vec!["a", "b", "c"]);
+        assert_eq!(mc.string("labels"), "{region=>eu, team=>web, tier=>2}");
+        assert_eq!(mc.map("labels")["tier"], "2");
+        assert_eq!(mc.list("reset"), vec!["y"]);
+        assert_eq!(mc.string("name"), "env");
+        assert!(mc.get("missing").is_none());
+        assert_eq!(mc.get("plugins.1"), Some("b".to_string()));
+        assert_eq!(mc.list_len("plugins"), Some(3));
+
+        // Nested values are merged too, and scalars are kept as written.
+        let top = HashMap::from([("m", "{hosts=>[a, b], x=>{p=>1}, zips=>[02134]}")]);
+        let bottom = HashMap::from([("m", "{x=>{q=>2.50}, y=>1, zips=>[10001]}")]);
+        let mc = MultiConfig::new(vec![Box::new(top), Box::new(bottom)]).merging();
+        assert_eq!(
+            mc.string("m"),
+            "{hosts=>[a, b], x=>{p=>1, q=>2.50}, y=>1, zips=>[10001, 02134]}"
+        );
+    }
+
+    #[test]
+    fn resolution() {
+        use crate::{Defaults, Resolution};
+        use std::collections::HashMap;
+
+        struct Vault;
+
+        impl Config for Vault {
+            fn get(&self, key: &str) -> Option<String> {
+                match key {
+                    "secrets.db" => Some("from-vault".to_string()),
+                    _ => None,
+                }
+            }
+
+            fn keys(&self) -> Vec<String> {
+                vec!["secrets.db".to_string()]
+            }
+        }
+
+        let layers = || -> Vec<Box<dyn Config + Send + Sync>> {
+            vec![
+                Box::new(HashMap::from([("a", "env"), ("secrets.db", "env")])),
+                Box::new(Vault),
+                Box::new(HashMap::from([("a", "file"), ("b", "file")])),
+            ]
+        };
+
+        let mc = MultiConfig::new(layers()).resolution(Resolution::Priority(vec![1, 5, 10]));
+        assert_eq!(mc.string("a"), "file");
+        assert_eq!(mc.string("secrets.db"), "from-vault");
+        assert_eq!(
+            mc.get_many(&["a", "secrets.db"]),
+            vec![Some("file".to_string()), Some("from-vault".to_string())]
+        );
+
+        let mc = MultiConfig::new(layers())
+            .indexed()
+            .resolution(Resolution::Routed(vec![("secrets.*".to_string(), 1)]));
+        assert_eq!(mc.string("a"), "env");
+        assert_eq!(mc.string("secrets.db"), "from-vault");
+        assert_eq!(mc.source_of("secrets.db"), Some("Vault".to_string()));
+        assert_eq!(
+            mc.get_many(&["secrets.db", "b"]),
+            vec![Some("from-vault".to_string()), Some("file".to_string())]
+        );
+        assert_eq!(mc.keys().iter().filter(|k| *k == "secrets.db").count(), 1);
+        let parallel = mc.clone().parallel();
+        assert_eq!(parallel.string("secrets.db"), "from-vault");
+        assert_eq!(
+            parallel.get_many(&["secrets.db"]),
+            vec![Some("from-vault".to_string())]
+        );
+
+        // Routed to a layer that isn't there, so nothing answers.
+        let mc = MultiConfig::new(layers())
+            .resolution(Resolution::Routed(vec![("secrets".to_string(), 7)]));
+        assert_eq!(mc.get("secrets.db"), None);
+        assert_eq!(mc.string("a"), "env");
+
+        // Layers of the same type are told apart by position.
+        let mc = MultiConfig::new(layers()).resolution(Resolution::Routed(vec![
+            ("a".to_string(), 2),
+            ("secrets".to_string(), 1),
+            ("secrets".to_string(), 0),
+        ]));
+        assert_eq!(mc.string("a"), "file");
+        assert_eq!(mc.string("secrets.db"), "env");
+
+        // The defaults of a builder have no priority, so they come last.
+        let mc = MultiConfig::builder()
+            .layer(HashMap::from([("a", "low")]))
+            .layer(HashMap::from([("a", "high")]))
+            .defaults(Defaults::new().set("a", "default").set("c", "default"))
+            .resolution(Resolution::Priority(vec![-1, 0]))
+            .build()
+            .unwrap();
+        assert_eq!(mc.string("a"), "high");
+        assert_eq!(mc.string("c"), "default");
+    }
+
+    #[test]
+    fn similar_keys() {
+        use crate::ValueError;
+        use std::collections::HashMap;
+
+        /// A remote source that mustn't be listed for suggestions.
+        struct Remote;
+
+        impl Config for Remote {
+            fn get(&self, _key: &str) -> Option<String> {
+                None
+            }
+
+            fn keys(&self) -> Vec<String> {
+                panic!("listed the remote keys");
+            }
+
+            fn similar_keys(&self, _key: &str) -> Vec<String> {
+                vec![]
+            }
+        }
+
+        let mc = MultiConfig::new(vec![
+            Box::new(Remote),
+            Box::new(HashMap::from([("db.uri", "x"), ("db.url", "y")])),
+        ]);
+        let e = mc.try_int("db.ur").unwrap_err();
+        assert_eq!(e, ValueError::Missing("db.ur".to_string()));
+        assert_eq!(
+            e.with_suggestions(&mc),
+            ValueError::Misspelled {
+                key: "db.ur".to_string(),
+                suggestions: vec!["db.uri".to_string(), "db.url".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn indexed() {
+        use std::collections::HashMap;
+        let env = HashMap::from([("ports", "[80, 443]")]);
+        let file = HashMap::from([("servers", "[{host=>a}, {host=>b}]")]);
+
+        let mc = MultiConfig::new(vec![Box::new(env), Box::new(file)]);
+        assert_eq!(mc.list_len("servers"), Some(2));
+        assert_eq!(mc.get("servers.1.host"), Some("b".to_string()));
+        assert_eq!(mc.get("servers.2.host"), None);
+        assert_eq!(mc.get("servers.0"), None);
+        assert_eq!(mc.int("ports.1"), 443);
+        assert_eq!(mc.list_len("ports"), Some(2));
+        assert_eq!(mc.list_len("ports.0"), None);
+        assert_eq!(mc.list_len("missing"), None);
     }
 }
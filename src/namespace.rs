@@ -0,0 +1,122 @@
+//! Resolve keys per tenant (or any other namespace) with a fallback
+//! to the shared value.
+
+use std::borrow::Cow;
+
+use crate::Config;
+
+/// A view of a config for a single tenant. A get for `key` first
+/// tries `tenants.<id>.key` and then the shared `key`, so tenants only
+/// need to list the values they change:
+///
+/// ```ini
+/// mail.from = noreply@example.com
+/// tenants.acme.mail.from = support@acme.test
+/// ```
+///
+/// ```
+/// use dinglebit_config::{Config, Namespaced};
+/// use std::collections::HashMap;
+///
+/// let cfg = HashMap::from([
+///     ("mail.from", "noreply@example.com"),
+///     ("tenants.acme.mail.from", "support@acme.test"),
+/// ]);
+/// let acme = Namespaced::new(&cfg, "acme");
+/// assert_eq!(acme.string("mail.from"), "support@acme.test");
+/// let other = Namespaced::new(&cfg, "other");
+/// assert_eq!(other.string("mail.from"), "noreply@example.com");
+/// ```
+pub struct Namespaced<'a> {
+    config: &'a dyn Config,
+    root: String,
+    prefix: String,
+}
+
+impl<'a> Namespaced<'a> {
+    /// Create a view of the config for the given tenant, using the
+    /// `tenants` prefix.
+    pub fn new(config: &'a dyn Config, id: &str) -> Self {
+        Self::with_root(config, "tenants", id)
+    }
+
+    /// Create a view of the config for the given namespace under a
+    /// root other than `tenants` (e.g. `regions.<id>.key`).
+    pub fn with_root(config: &'a dyn Config, root: &str, id: &str) -> Self {
+        Self {
+            config,
+            root: format!("{}.", root),
+            prefix: format!("{}.{}.", root, id),
+        }
+    }
+
+    /// The key the namespaced value is read from.
+    fn scoped(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key)
+    }
+}
+
+impl Config for Namespaced<'_> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.config
+            .get(&self.scoped(key))
+            .or_else(|| self.config.get(key))
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.config
+            .get_ref(&self.scoped(key))
+            .or_else(|| self.config.get_ref(key))
+    }
+
+    /// The namespace's keys (without the prefix) and the shared keys.
+    /// Keys of other namespaces under the same root are left out.
+    fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = vec![];
+        for key in self.config.keys() {
+            let key = match key.strip_prefix(&self.prefix) {
+                Some(k) => k.to_string(),
+                None if key.starts_with(&self.root) => continue,
+                None => key,
+            };
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        keys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::namespace::Namespaced;
+    use crate::Config;
+    use std::collections::HashMap;
+
+    #[test]
+    fn namespaced() {
+        let cfg = HashMap::from([
+            ("limit", "10"),
+            ("name", "shared"),
+            ("tenants.acme.limit", "100"),
+            ("tenants.acme.extra", "yes"),
+            ("tenants.other.limit", "5"),
+            ("regions.eu.name", "europe"),
+        ]);
+
+        let acme = Namespaced::new(&cfg, "acme");
+        assert_eq!(acme.int("limit"), 100);
+        assert_eq!(acme.string("name"), "shared");
+        assert!(acme.bool("extra"));
+        let mut keys = acme.keys();
+        keys.sort();
+        assert_eq!(keys, vec!["extra", "limit", "name", "regions.eu.name"]);
+
+        let none = Namespaced::new(&cfg, "none");
+        assert_eq!(none.int("limit"), 10);
+        assert!(none.get("extra").is_none());
+
+        let eu = Namespaced::with_root(&cfg, "regions", "eu");
+        assert_eq!(eu.string("name"), "europe");
+    }
+}
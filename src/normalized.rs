@@ -0,0 +1,241 @@
+//! Resolve keys regardless of case and separator.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use crate::{Config, SourceHealth, Value};
+
+/// The canonical form of a key: lower case, with `-` and `_` turned
+/// into `.` (e.g. `HTTP_PORT` and `http-port` both become `http.port`).
+pub fn canonical(key: &str) -> String {
+    key.chars()
+        .map(|c| match c {
+            '-' | '_' => '.',
+            c => c.to_ascii_lowercase(),
+        })
+        .collect()
+}
+
+/// Wraps a config so keys that only differ in case or in their
+/// separators (`-`, `_` or `.`) resolve to the same value, e.g.
+/// `http-port`, `HTTP_PORT` and `http.port`.
+///
+/// A key is first looked up as given. If the config doesn't have it,
+/// the config's own keys are matched by their canonical form (see
+/// `canonical`). When more than one key of the config has the same
+/// canonical form, which one is used is unspecified. The keys are
+/// indexed the first time they are needed and again when the index
+/// points at a key the config no longer has, after `invalidate` or,
+/// for keys that aren't in the index, once it's older than
+/// `reindex_after` (10 seconds by default). Until then a miss is
+/// answered from the index, so looking up keys that don't exist
+/// doesn't list the config's keys every time.
+///
+/// ```
+/// use dinglebit_config::{Config, Normalized};
+/// use std::collections::HashMap;
+///
+/// let cfg = Normalized::new(HashMap::from([("HTTP_PORT", "8080")]));
+/// assert_eq!(cfg.int("http.port"), 8080);
+/// assert_eq!(cfg.int("http-port"), 8080);
+/// ```
+pub struct Normalized<C> {
+    config: C,
+    index: RwLock<Option<Index>>,
+    reindex_after: Duration,
+}
+
+/// The keys of the wrapped config by their canonical form.
+struct Index {
+    keys: HashMap<String, String>,
+    built: Instant,
+}
+
+impl<C: Config> Normalized<C> {
+    /// Wrap the given config.
+    pub fn new(config: C) -> Self {
+        Self {
+            config,
+            index: RwLock::new(None),
+            reindex_after: Duration::from_secs(10),
+        }
+    }
+
+    /// How long a key that isn't in the index is taken to be missing
+    /// before the keys are indexed again, for sources that gain keys
+    /// when they're reloaded.
+    pub fn reindex_after(mut self, after: Duration) -> Self {
+        self.reindex_after = after;
+        self
+    }
+
+    /// Index the keys again on the next lookup that needs the index,
+    /// e.g. after reloading the wrapped config.
+    pub fn invalidate(&self) {
+        *self.index.write().unwrap() = None;
+    }
+
+    /// The key of the wrapped config that has the given key's
+    /// canonical form, if there is one.
+    fn resolve(&self, key: &str) -> Option<String> {
+        let wanted = canonical(key);
+        if let Some(index) = self.index.read().unwrap().as_ref() {
+            match index.keys.get(&wanted) {
+                Some(k) if self.config.get_ref(k).is_some() => return Some(k.clone()),
+                // The config changed under the index.
+                Some(_) => (),
+                None if index.built.elapsed() < self.reindex_after => return None,
+                None => (),
+            }
+        }
+        let keys: HashMap<String, String> = self
+            .config
+            .keys()
+            .into_iter()
+            .map(|k| (canonical(&k), k))
+            .collect();
+        let found = keys.get(&wanted).cloned();
+        *self.index.write().unwrap() = Some(Index {
+            keys,
+            built: Instant::now(),
+        });
+        found
+    }
+
+    /// The key to read from the wrapped config.
+    fn key<'k>(&self, key: &'k str) -> Option<Cow<'k, str>> {
+        match self.config.get_ref(key).is_some() {
+            true => Some(Cow::Borrowed(key)),
+            false => self.resolve(key).map(Cow::Owned),
+        }
+    }
+}
+
+impl<C: Config> Config for Normalized<C> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.key(key).and_then(|k| self.config.get(&k))
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        match self.config.get_ref(key) {
+            Some(v) => Some(v),
+            None => self.resolve(key).and_then(|k| self.config.get_ref(&k)),
+        }
+    }
+
+    fn get_value(&self, key: &str) -> Option<Value> {
+        self.key(key).and_then(|k| self.config.get_value(&k))
+    }
+
+    /// The keys of the wrapped config in their canonical form.
+    fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.config.keys().iter().map(|k| canonical(k)).collect();
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+
+    fn source_health(&self) -> SourceHealth {
+        self.config.source_health()
+    }
+
+    fn source_name(&self) -> String {
+        self.config.source_name()
+    }
+
+    fn source_of(&self, key: &str) -> Option<String> {
+        self.key(key).and_then(|k| self.config.source_of(&k))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::normalized::{canonical, Normalized};
+    use crate::testing::TempDir;
+    use crate::{Config, Reloadable};
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// Counts how often its keys are listed.
+    struct Listed {
+        values: HashMap<&'static str, &'static str>,
+        listed: AtomicUsize,
+    }
+
+    impl Config for Listed {
+        fn get(&self, key: &str) -> Option<String> {
+            self.values.get(key).map(|v| v.to_string())
+        }
+
+        fn keys(&self) -> Vec<String> {
+            self.listed.fetch_add(1, Ordering::SeqCst);
+            self.values.keys().map(|k| k.to_string()).collect()
+        }
+    }
+
+    #[test]
+    fn normalized() {
+        assert_eq!(canonical("HTTP_PORT"), "http.port");
+        assert_eq!(canonical("Http-Port"), "http.port");
+
+        let cfg = Normalized::new(HashMap::from([
+            ("HTTP_PORT", "8080"),
+            ("db.host", "localhost"),
+            ("Log-Level", "debug"),
+        ]));
+        for key in &["http.port", "http-port", "HTTP_PORT", "Http_Port"] {
+            assert_eq!(cfg.get(key), Some("8080".to_string()), "{}", key);
+        }
+        assert_eq!(cfg.string("DB_HOST"), "localhost");
+        assert_eq!(cfg.string("log.level"), "debug");
+        assert_eq!(cfg.get("http.host"), None);
+        assert_eq!(
+            cfg.keys(),
+            vec!["db.host", "http.port", "log.level"]
+                .into_iter()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn reindexes() {
+        let dir = TempDir::new("normalized");
+        let path = dir.join("app.cfg");
+        std::fs::write(&path, "HTTP_PORT = 80\n").unwrap();
+        let cfg = Normalized::new(Reloadable::file(path.to_str().unwrap()).unwrap());
+        assert_eq!(cfg.int("http.port"), 80);
+
+        std::fs::write(&path, "http-port = 81\n").unwrap();
+        cfg.config.reload().unwrap();
+        assert_eq!(cfg.int("http.port"), 81);
+    }
+
+    #[test]
+    fn misses() {
+        let cfg = Normalized::new(Listed {
+            values: HashMap::from([("HTTP_PORT", "80")]),
+            listed: AtomicUsize::new(0),
+        });
+        let listed = || cfg.config.listed.load(Ordering::SeqCst);
+        assert_eq!(cfg.int("http.port"), 80);
+        assert_eq!(listed(), 1);
+        for _ in 0..3 {
+            assert_eq!(cfg.get("http.host"), None);
+            assert_eq!(cfg.int("http-port"), 80);
+        }
+        assert_eq!(listed(), 1);
+
+        cfg.invalidate();
+        assert_eq!(cfg.get("http.host"), None);
+        assert_eq!(listed(), 2);
+
+        let cfg = cfg.reindex_after(Duration::ZERO);
+        assert_eq!(cfg.get("http.host"), None);
+        assert_eq!(cfg.get("http.host"), None);
+        assert_eq!(cfg.config.listed.load(Ordering::SeqCst), 4);
+    }
+}
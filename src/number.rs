@@ -0,0 +1,138 @@
+//! Lenient number parsing for values copied out of docs and runbooks.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Describes the separators accepted when parsing numbers. Underscores
+/// between digits (e.g. `1_000_000`) and a leading `+` are always
+/// accepted. The default format has no group separator and uses a
+/// period for the decimal point.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct NumberFormat {
+    /// An optional separator between groups of digits (e.g. the `,`
+    /// in `1,000,000`).
+    pub group: Option<char>,
+
+    /// The character separating the integer and fractional parts.
+    pub decimal: char,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            group: None,
+            decimal: '.',
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Commas group digits and a period is the decimal point (e.g.
+    /// `1,000,000.5`).
+    pub fn comma_grouped() -> Self {
+        Self {
+            group: Some(','),
+            decimal: '.',
+        }
+    }
+
+    /// Periods group digits and a comma is the decimal point (e.g.
+    /// `1.000.000,5`).
+    pub fn period_grouped() -> Self {
+        Self {
+            group: Some('.'),
+            decimal: ',',
+        }
+    }
+
+    /// Parse an integer using this format.
+    pub fn parse_int(&self, s: &str) -> Option<i64> {
+        self.normalize(s)?.parse::<i64>().ok()
+    }
+
+    /// Parse a float using this format.
+    pub fn parse_float(&self, s: &str) -> Option<f64> {
+        self.normalize(s)?.parse::<f64>().ok()
+    }
+
+    /// Strip the separators from the given string so it can be
+    /// handed to the standard parsers. Separators are only accepted
+    /// between two digits.
+    fn normalize(&self, s: &str) -> Option<String> {
+        let s = s.trim();
+        let mut out = String::with_capacity(s.len());
+        let s = match s.strip_prefix('-') {
+            Some(rest) => {
+                out.push('-');
+                rest
+            }
+            None => s.strip_prefix('+').unwrap_or(s),
+        };
+        if s.starts_with(['+', '-']) {
+            return None;
+        }
+        let chars: Vec<char> = s.chars().collect();
+        for (i, &c) in chars.iter().enumerate() {
+            if c == '_' || Some(c) == self.group {
+                let between_digits = i > 0
+                    && chars[i - 1].is_ascii_digit()
+                    && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit());
+                if !between_digits {
+                    return None;
+                }
+            } else if c == self.decimal {
+                out.push('.');
+            } else {
+                out.push(c);
+            }
+        }
+        Some(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::number::NumberFormat;
+
+    #[test]
+    fn parse_int() {
+        let f = NumberFormat::default();
+        assert_eq!(f.parse_int("1_000_000"), Some(1_000_000));
+        assert_eq!(f.parse_int("+42"), Some(42));
+        assert_eq!(f.parse_int("-42"), Some(-42));
+        assert_eq!(f.parse_int("1,000"), None);
+        assert_eq!(f.parse_int("_1"), None);
+        assert_eq!(f.parse_int("1__0"), None);
+        assert_eq!(f.parse_int("+-5"), None);
+        assert_eq!(f.parse_int("++5"), None);
+        assert_eq!(f.parse_int("--5"), None);
+
+        assert_eq!(
+            NumberFormat::comma_grouped().parse_int("1,000,000"),
+            Some(1_000_000)
+        );
+        assert_eq!(
+            NumberFormat::period_grouped().parse_int("1.000.000"),
+            Some(1_000_000)
+        );
+    }
+
+    #[test]
+    fn parse_float() {
+        let f = NumberFormat::default();
+        assert_eq!(f.parse_float("1_000.5"), Some(1000.5));
+        assert_eq!(f.parse_float("+2.5"), Some(2.5));
+        assert_eq!(f.parse_float("1,000.5"), None);
+        assert_eq!(f.parse_float("-+2.5"), None);
+        assert_eq!(f.parse_float("1e-5"), Some(1e-5));
+
+        assert_eq!(
+            NumberFormat::comma_grouped().parse_float("1,000.5"),
+            Some(1000.5)
+        );
+        assert_eq!(
+            NumberFormat::period_grouped().parse_float("1.000,5"),
+            Some(1000.5)
+        );
+    }
+}
@@ -0,0 +1,81 @@
+//! Temporary overrides on top of an existing config.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::Config;
+
+/// A layer of values over a borrowed config, usually created with
+/// `Config::overlay`. Values in the layer win and everything else
+/// falls through to the base config. Nothing is boxed or copied from
+/// the base, so overlays are cheap enough to create per request.
+///
+/// ```
+/// use dinglebit_config::Config;
+/// use std::collections::HashMap;
+///
+/// let base = HashMap::from([("theme", "light"), ("lang", "en")]);
+/// let request = base.overlay(HashMap::from([("theme", "dark")]));
+/// assert_eq!(request.string("theme"), "dark");
+/// assert_eq!(request.string("lang"), "en");
+/// ```
+pub struct Overlay<'a, L = HashMap<String, String>> {
+    base: &'a dyn Config,
+    layer: L,
+}
+
+impl<'a, L: Config> Overlay<'a, L> {
+    /// Create an overlay of the given layer over the base config.
+    pub fn new(base: &'a dyn Config, layer: L) -> Self {
+        Self { base, layer }
+    }
+
+    /// The values that override the base config.
+    pub fn layer(&self) -> &L {
+        &self.layer
+    }
+}
+
+impl<L: Config> Config for Overlay<'_, L> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.layer.get(key).or_else(|| self.base.get(key))
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.layer.get_ref(key).or_else(|| self.base.get_ref(key))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        let mut keys = self.layer.keys();
+        for key in self.base.keys() {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        keys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Config;
+    use std::collections::HashMap;
+
+    #[test]
+    fn overlay() {
+        let base = HashMap::from([("a", "1"), ("b", "2")]);
+        let tenant: HashMap<String, String> = HashMap::from([("b".to_string(), "20".to_string())]);
+        let o = base.overlay(tenant);
+        assert_eq!(o.int("a"), 1);
+        assert_eq!(o.int("b"), 20);
+        assert!(o.get("c").is_none());
+
+        // Overlays can be stacked.
+        let o2 = o.overlay(HashMap::from([("a", "10")]));
+        assert_eq!(o2.int("a"), 10);
+        assert_eq!(o2.int("b"), 20);
+        let mut keys = o2.keys();
+        keys.sort();
+        assert_eq!(keys, vec!["a", "b"]);
+    }
+}
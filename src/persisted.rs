@@ -0,0 +1,272 @@
+//! Keeping the last config that loaded successfully on disk, for
+//! starting up when a remote source can't be reached.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{Config, Error, Snapshot, SourceHealth};
+
+/// What's written to the cache file.
+#[derive(Serialize, Deserialize)]
+struct Cached {
+    /// When the snapshot was taken, in RFC 3339.
+    saved_at: String,
+    snapshot: Snapshot,
+}
+
+/// Where the values of a `Persisted` come from when the source failed.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Stale {
+    /// When the cached snapshot was saved.
+    pub saved_at: DateTime<Utc>,
+
+    /// Why the source couldn't be used.
+    pub error: Error,
+}
+
+enum Inner {
+    Live(Box<dyn Config + Send + Sync>),
+    Cached(Snapshot),
+}
+
+/// A source whose values are saved to a cache file (as JSON, see
+/// `Snapshot`) whenever it loads, and read back from it when the source
+/// fails at startup, so a service can still start with its last known
+/// good config when e.g. the remote source is unreachable.
+///
+/// The source fails if it couldn't be created or isn't healthy (see
+/// `Config::health`). Whether the values came from the cache, and how
+/// old they are, is in `stale`.
+///
+/// ```
+/// use dinglebit_config::{Config, Error, Persisted, Simple};
+///
+/// let cache = std::env::temp_dir().join("dinglebit-config-doc-persisted.json");
+/// let cfg = Persisted::load(&cache, Simple::from_str("foo = bar")).unwrap();
+/// assert!(!cfg.is_stale());
+///
+/// let unreachable: Result<Simple, _> = Err(Error::Remote("unreachable".to_string()));
+/// let cfg = Persisted::load(&cache, unreachable).unwrap();
+/// assert!(cfg.is_stale());
+/// assert_eq!(cfg.get("foo"), Some("bar".to_string()));
+/// ```
+///
+/// The cache holds the secrets of the source as they are, so keep it
+/// somewhere only the service can read. On unix, the file is only
+/// readable and writable by its owner.
+pub struct Persisted {
+    inner: Inner,
+    path: PathBuf,
+    stale: Option<Stale>,
+}
+
+impl Persisted {
+    /// Use the source if it loaded and is healthy, saving it to the
+    /// cache file, or else the values in the cache file. It's an error
+    /// if the source failed and there's no cache to fall back on (the
+    /// error of the source is returned), or the cache can't be read.
+    ///
+    /// A cache that can't be written doesn't stop a healthy source
+    /// from being used; call `save` to find out why.
+    pub fn load<C, P>(path: P, source: Result<C, Error>) -> Result<Self, Error>
+    where
+        C: Config + Send + Sync + 'static,
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_path_buf();
+        let error = match source {
+            Ok(c) => match c.health() {
+                Ok(()) => {
+                    let persisted = Self {
+                        inner: Inner::Live(Box::new(c)),
+                        path,
+                        stale: None,
+                    };
+                    let _ = persisted.save();
+                    return Ok(persisted);
+                }
+                Err(e) => e,
+            },
+            Err(e) => e,
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(error),
+            Err(e) => return Err(Error::file(&path, &e)),
+        };
+        let cached: Cached = serde_json::from_str(&contents)
+            .map_err(|e| Error::Parse(format!("{}: {}", path.display(), e)))?;
+        let saved_at = DateTime::parse_from_rfc3339(&cached.saved_at)
+            .map_err(|e| Error::Parse(format!("{}: saved_at: {}", path.display(), e)))?;
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            path = %path.display(),
+            saved_at = %cached.saved_at,
+            error = %error,
+            "source failed, using the cached config"
+        );
+        Ok(Self {
+            inner: Inner::Cached(cached.snapshot),
+            path,
+            stale: Some(Stale {
+                saved_at: saved_at.with_timezone(&Utc),
+                error,
+            }),
+        })
+    }
+
+    /// Write the current values of the source to the cache file. The
+    /// file is replaced in one step so a crash can't leave half of it,
+    /// and on unix it's created with mode `0600`. Nothing is written
+    /// while the values come from the cache.
+    pub fn save(&self) -> Result<(), Error> {
+        let config = match &self.inner {
+            Inner::Live(c) => c,
+            Inner::Cached(_) => return Ok(()),
+        };
+        let cached = Cached {
+            saved_at: Utc::now().to_rfc3339(),
+            snapshot: config.snapshot(),
+        };
+        let json = serde_json::to_string(&cached).map_err(|e| Error::Parse(e.to_string()))?;
+        let tmp = self.path.with_extension("tmp");
+        // A leftover file would keep its mode, so it's replaced.
+        let _ = fs::remove_file(&tmp);
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options
+            .open(&tmp)
+            .and_then(|mut file| file.write_all(json.as_bytes()))
+            .map_err(|e| Error::file(&tmp, &e))?;
+        fs::rename(&tmp, &self.path).map_err(|e| Error::file(&self.path, &e))
+    }
+
+    /// Whether the values come from the cache because the source failed.
+    pub fn is_stale(&self) -> bool {
+        self.stale.is_some()
+    }
+
+    /// When the values come from the cache, when they were saved and
+    /// why the source couldn't be used.
+    pub fn stale(&self) -> Option<&Stale> {
+        self.stale.as_ref()
+    }
+
+    /// How old the cached values are, or `None` if the source is used.
+    pub fn age(&self) -> Option<chrono::Duration> {
+        self.stale.as_ref().map(|s| Utc::now() - s.saved_at)
+    }
+
+    fn config(&self) -> &dyn Config {
+        match &self.inner {
+            Inner::Live(c) => c.as_ref(),
+            Inner::Cached(s) => s,
+        }
+    }
+}
+
+impl Config for Persisted {
+    fn get(&self, key: &str) -> Option<String> {
+        self.config().get(key)
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.config().get_ref(key)
+    }
+
+    fn lookup(&self, key: &str) -> Result<Option<String>, Error> {
+        self.config().lookup(key)
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.config().keys()
+    }
+
+    fn source_of(&self, key: &str) -> Option<String> {
+        self.config().source_of(key)
+    }
+
+    /// Degraded while the values come from the cache.
+    fn source_health(&self) -> SourceHealth {
+        match &self.stale {
+            Some(stale) => SourceHealth::Degraded(format!(
+                "using the values saved at {}: {}",
+                stale.saved_at.to_rfc3339(),
+                stale.error
+            )),
+            None => self.config().source_health(),
+        }
+    }
+}
+
+impl fmt::Debug for Persisted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Persisted")
+            .field("source", &self.config().source_name())
+            .field("path", &self.path)
+            .field("stale", &self.stale)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::TempDir;
+    use crate::{Config, Error, Persisted, Simple};
+    use std::fs;
+
+    #[test]
+    fn persisted() {
+        let dir = TempDir::new("persisted");
+        let cache = dir.join("cache.json");
+        let down = || -> Result<Simple, Error> { Err(Error::Remote("unreachable".to_string())) };
+
+        assert_eq!(
+            Persisted::load(&cache, down()).unwrap_err(),
+            Error::Remote("unreachable".to_string())
+        );
+
+        let live = Persisted::load(&cache, Simple::from_str("db.host = a")).unwrap();
+        assert!(!live.is_stale());
+        assert_eq!(live.age(), None);
+        assert!(cache.exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&cache).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let cached = Persisted::load(&cache, down()).unwrap();
+        assert_eq!(cached.get("db.host"), Some("a".to_string()));
+        assert_eq!(cached.source_of("db.host"), Some("Simple".to_string()));
+        let stale = cached.stale().unwrap();
+        assert_eq!(stale.error, Error::Remote("unreachable".to_string()));
+        assert!(cached.age().unwrap() >= chrono::Duration::zero());
+
+        let updated = Persisted::load(&cache, Simple::from_str("db.host = b")).unwrap();
+        assert_eq!(updated.get("db.host"), Some("b".to_string()));
+        assert_eq!(
+            Persisted::load(&cache, down()).unwrap().get("db.host"),
+            Some("b".to_string())
+        );
+
+        fs::write(&cache, "not json").unwrap();
+        assert!(matches!(
+            Persisted::load(&cache, down()),
+            Err(Error::Parse(_))
+        ));
+    }
+}
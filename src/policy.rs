@@ -0,0 +1,390 @@
+//! Choose what the typed getters do when a value is missing or can't
+//! be parsed, without changing the code that calls them.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+use crate::{Config, SourceHealth, Value, ValueError};
+
+/// What a getter that can't return an error (e.g. `int`) does when the
+/// value is missing or invalid.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GetterPolicy {
+    /// Panic, the way the getters behave on their own.
+    Panic,
+    /// Return the type's `Default::default()` (e.g. `0` or an empty
+    /// string).
+    Default,
+    /// Return the default and record the error, so all of them can be
+    /// reported at once (see `WithPolicy::errors`).
+    Collect,
+}
+
+/// Wraps a config so the panicking getters (`must_get`, `string`,
+/// `int`, `float`, `bool`, `duration`, `datetime`, `list` and `map`)
+/// follow a `GetterPolicy`. The `try_*` getters are unaffected.
+///
+/// ```
+/// use dinglebit_config::{Config, GetterPolicy, WithPolicy};
+/// use std::collections::HashMap;
+///
+/// let cfg = WithPolicy::new(HashMap::from([("pool", "many")]), GetterPolicy::Collect);
+/// assert_eq!(cfg.int("pool"), 0);
+/// assert_eq!(cfg.string("db.uri"), "");
+/// assert_eq!(cfg.errors().len(), 2);
+/// ```
+///
+/// `bool` treats a value it doesn't recognize as an error under the
+/// `Default` and `Collect` policies, like `try_bool`, instead of as
+/// `false`.
+pub struct WithPolicy<C> {
+    config: C,
+    policy: GetterPolicy,
+    errors: Mutex<Vec<ValueError>>,
+}
+
+impl<C: Config> WithPolicy<C> {
+    /// Wrap the given config.
+    pub fn new(config: C, policy: GetterPolicy) -> Self {
+        Self {
+            config,
+            policy,
+            errors: Mutex::new(vec![]),
+        }
+    }
+
+    /// The policy the getters follow.
+    pub fn policy(&self) -> GetterPolicy {
+        self.policy
+    }
+
+    /// The errors recorded so far under `GetterPolicy::Collect`, in
+    /// the order they happened.
+    pub fn errors(&self) -> Vec<ValueError> {
+        self.errors.lock().unwrap().clone()
+    }
+
+    /// The value, or what the policy says to do instead of the error.
+    /// Only called for the `Default` and `Collect` policies; `Panic`
+    /// leaves the getters of the wrapped config to panic as usual.
+    fn or_policy<T: Default>(&self, result: Result<T, ValueError>) -> T {
+        match result {
+            Ok(v) => v,
+            Err(e) => {
+                if self.policy == GetterPolicy::Collect {
+                    self.errors.lock().unwrap().push(e);
+                }
+                T::default()
+            }
+        }
+    }
+
+    /// Check there's a value for a getter without a `try_*` version.
+    fn present(&self, key: &str) -> Result<(), ValueError> {
+        self.config.try_get_ref(key).map(|_| ())
+    }
+}
+
+impl<C: Config> Config for WithPolicy<C> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.config.get(key)
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.config.get_ref(key)
+    }
+
+    fn get_value(&self, key: &str) -> Option<Value> {
+        self.config.get_value(key)
+    }
+
+    fn try_get_ref(&self, key: &str) -> Result<Cow<'_, str>, ValueError> {
+        self.config.try_get_ref(key)
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.config.keys()
+    }
+
+    fn source_health(&self) -> SourceHealth {
+        self.config.source_health()
+    }
+
+    fn source_name(&self) -> String {
+        self.config.source_name()
+    }
+
+    fn source_of(&self, key: &str) -> Option<String> {
+        self.config.source_of(key)
+    }
+
+    fn must_get(&self, key: &str) -> String {
+        match self.policy {
+            GetterPolicy::Panic => self.config.must_get(key),
+            _ => self.or_policy(self.config.try_get(key)),
+        }
+    }
+
+    fn string(&self, key: &str) -> String {
+        match self.policy {
+            GetterPolicy::Panic => self.config.string(key),
+            _ => self.or_policy(self.config.try_get(key)),
+        }
+    }
+
+    fn int(&self, key: &str) -> i64 {
+        match self.policy {
+            GetterPolicy::Panic => self.config.int(key),
+            _ => self.or_policy(self.config.try_int(key)),
+        }
+    }
+
+    fn float(&self, key: &str) -> f64 {
+        match self.policy {
+            GetterPolicy::Panic => self.config.float(key),
+            _ => self.or_policy(self.config.try_float(key)),
+        }
+    }
+
+    fn bool(&self, key: &str) -> bool {
+        match self.policy {
+            GetterPolicy::Panic => self.config.bool(key),
+            _ => self.or_policy(self.config.try_bool(key)),
+        }
+    }
+
+    fn duration(&self, key: &str) -> chrono::Duration {
+        match self.policy {
+            GetterPolicy::Panic => self.config.duration(key),
+            _ => self.or_policy(self.config.try_duration(key)),
+        }
+    }
+
+    fn datetime(&self, key: &str) -> chrono::DateTime<chrono::Utc> {
+        match self.policy {
+            GetterPolicy::Panic => self.config.datetime(key),
+            _ => self.or_policy(self.config.try_datetime(key)),
+        }
+    }
+
+    fn list(&self, key: &str) -> Vec<String> {
+        match self.policy {
+            GetterPolicy::Panic => self.config.list(key),
+            _ => self.or_policy(self.present(key).map(|_| self.config.list(key))),
+        }
+    }
+
+    fn map(&self, key: &str) -> HashMap<String, String> {
+        match self.policy {
+            GetterPolicy::Panic => self.config.map(key),
+            _ => self.or_policy(self.present(key).map(|_| self.config.map(key))),
+        }
+    }
+}
+
+/// Every error a `Collector` recorded, in the order they happened.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ValueErrors(pub Vec<ValueError>);
+
+impl fmt::Display for ValueErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let errors: Vec<String> = self.0.iter().map(|e| e.to_string()).collect();
+        write!(f, "{}", errors.join("; "))
+    }
+}
+
+impl std::error::Error for ValueErrors {}
+
+/// Wraps a config so the typed getters never panic while settings are
+/// read at startup. Instead, every missing or invalid value is
+/// recorded and the getter returns the type's default. `finish` then
+/// reports all of them at once, so an operator can fix the whole
+/// config in one go instead of one key per restart.
+///
+/// ```
+/// use dinglebit_config::{Collector, Config};
+/// use std::collections::HashMap;
+///
+/// struct Settings {
+///     port: i64,
+///     db: String,
+/// }
+///
+/// let cfg = Collector::new(HashMap::from([("port", "eighty")]));
+/// let settings = Settings {
+///     port: cfg.int("port"),
+///     db: cfg.string("db.uri"),
+/// };
+/// let errors = cfg.finish().unwrap_err();
+/// assert_eq!(
+///     errors.to_string(),
+///     "invalid value for 'port': expected integer, got 'eighty'; missing value for 'db.uri'"
+/// );
+/// ```
+pub struct Collector<C> {
+    inner: WithPolicy<C>,
+}
+
+impl<C: Config> Collector<C> {
+    /// Wrap the given config.
+    pub fn new(config: C) -> Self {
+        Self {
+            inner: WithPolicy::new(config, GetterPolicy::Collect),
+        }
+    }
+
+    /// Return the errors recorded since the collector was created or
+    /// `finish` was last called, if there were any.
+    pub fn finish(&self) -> Result<(), ValueErrors> {
+        let errors = std::mem::take(&mut *self.inner.errors.lock().unwrap());
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(ValueErrors(errors)),
+        }
+    }
+}
+
+impl<C: Config> Config for Collector<C> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.inner.get(key)
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.inner.get_ref(key)
+    }
+
+    fn get_value(&self, key: &str) -> Option<Value> {
+        self.inner.get_value(key)
+    }
+
+    fn try_get_ref(&self, key: &str) -> Result<Cow<'_, str>, ValueError> {
+        self.inner.try_get_ref(key)
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.inner.keys()
+    }
+
+    fn source_health(&self) -> SourceHealth {
+        self.inner.source_health()
+    }
+
+    fn source_name(&self) -> String {
+        self.inner.source_name()
+    }
+
+    fn source_of(&self, key: &str) -> Option<String> {
+        self.inner.source_of(key)
+    }
+
+    fn must_get(&self, key: &str) -> String {
+        self.inner.must_get(key)
+    }
+
+    fn string(&self, key: &str) -> String {
+        self.inner.string(key)
+    }
+
+    fn int(&self, key: &str) -> i64 {
+        self.inner.int(key)
+    }
+
+    fn float(&self, key: &str) -> f64 {
+        self.inner.float(key)
+    }
+
+    fn bool(&self, key: &str) -> bool {
+        self.inner.bool(key)
+    }
+
+    fn duration(&self, key: &str) -> chrono::Duration {
+        self.inner.duration(key)
+    }
+
+    fn datetime(&self, key: &str) -> chrono::DateTime<chrono::Utc> {
+        self.inner.datetime(key)
+    }
+
+    fn list(&self, key: &str) -> Vec<String> {
+        self.inner.list(key)
+    }
+
+    fn map(&self, key: &str) -> HashMap<String, String> {
+        self.inner.map(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::policy::{Collector, GetterPolicy, ValueErrors, WithPolicy};
+    use crate::{Config, ValueError};
+    use std::collections::HashMap;
+
+    fn config(policy: GetterPolicy) -> WithPolicy<HashMap<&'static str, &'static str>> {
+        WithPolicy::new(
+            HashMap::from([("port", "8080"), ("pool", "many"), ("debug", "maybe")]),
+            policy,
+        )
+    }
+
+    #[test]
+    fn policies() {
+        for policy in [GetterPolicy::Default, GetterPolicy::Collect] {
+            let cfg = config(policy);
+            assert_eq!(cfg.int("port"), 8080);
+            assert_eq!(cfg.int("pool"), 0);
+            assert!(!cfg.bool("debug"));
+            assert_eq!(cfg.string("missing"), "");
+            assert_eq!(cfg.list("missing"), Vec::<String>::new());
+            assert_eq!(cfg.duration("missing"), chrono::Duration::zero());
+        }
+
+        let cfg = config(GetterPolicy::Default);
+        assert_eq!(cfg.errors(), vec![]);
+
+        let cfg = config(GetterPolicy::Collect);
+        cfg.int("pool");
+        cfg.string("missing");
+        assert_eq!(
+            cfg.errors(),
+            vec![
+                ValueError::Invalid {
+                    key: "pool".to_string(),
+                    value: "many".to_string(),
+                    expected: "integer".to_string(),
+                },
+                ValueError::Missing("missing".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn panic() {
+        config(GetterPolicy::Panic).int("pool");
+    }
+
+    #[test]
+    fn collector() {
+        let cfg = Collector::new(HashMap::from([("port", "8080"), ("debug", "maybe")]));
+        assert_eq!(cfg.int("port"), 8080);
+        assert_eq!(cfg.finish(), Ok(()));
+
+        assert!(!cfg.bool("debug"));
+        assert_eq!(cfg.float("ratio"), 0.0);
+        assert_eq!(
+            cfg.finish(),
+            Err(ValueErrors(vec![
+                ValueError::Invalid {
+                    key: "debug".to_string(),
+                    value: "maybe".to_string(),
+                    expected: "bool".to_string(),
+                },
+                ValueError::Missing("ratio".to_string()),
+            ]))
+        );
+        assert_eq!(cfg.finish(), Ok(()));
+    }
+}
@@ -0,0 +1,203 @@
+//! Configuration from Java `.properties` files.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::format::read_file;
+use crate::{redact, Config, Error};
+
+#[derive(PartialEq, Clone)]
+pub struct Properties {
+    values: HashMap<String, String>,
+}
+
+/// Join lines ending in an odd number of backslashes with the next
+/// line, dropping the leading whitespace of the continuation. Comments
+/// don't continue onto the next line.
+fn logical_lines(s: &str) -> Vec<String> {
+    let mut lines = vec![];
+    let mut current: Option<String> = None;
+    for line in s.lines() {
+        if current.is_none() && line.trim_start().starts_with(['#', '!']) {
+            lines.push(line.to_string());
+            continue;
+        }
+        let line = match current {
+            Some(_) => line.trim_start(),
+            None => line,
+        };
+        let mut joined = current.take().unwrap_or_default();
+        joined.push_str(line);
+
+        let trailing = joined.chars().rev().take_while(|c| *c == '\\').count();
+        if trailing % 2 == 1 {
+            joined.pop();
+            current = Some(joined);
+        } else {
+            lines.push(joined);
+        }
+    }
+    if let Some(last) = current {
+        lines.push(last);
+    }
+    lines
+}
+
+/// Replace the escape sequences in a key or value.
+fn unescape(s: &str) -> Result<String, Error> {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('f') => out.push('\u{c}'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    Some(c) => out.push(c),
+                    None => return Err(Error::Parse(format!("invalid escape '\\u{}'", hex))),
+                }
+            }
+            Some(c) => out.push(c),
+            None => {}
+        }
+    }
+    Ok(out)
+}
+
+fn parse_line(line: &str) -> Result<Option<(String, String)>, Error> {
+    let line = line.trim_start();
+    if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+        return Ok(None);
+    }
+
+    // The key ends at the first unescaped separator or whitespace.
+    let mut end = line.len();
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '=' || c == ':' || c.is_whitespace() {
+            end = i;
+            break;
+        }
+    }
+    let (key, rest) = line.split_at(end);
+
+    // Whitespace around a single separator is ignored.
+    let rest = rest.trim_start();
+    let rest = match rest.strip_prefix('=').or_else(|| rest.strip_prefix(':')) {
+        Some(r) => r.trim_start(),
+        None => rest,
+    };
+
+    Ok(Some((unescape(key)?, unescape(rest)?)))
+}
+
+fn parse(s: &str) -> Result<HashMap<String, String>, Error> {
+    let mut values = HashMap::new();
+    for line in logical_lines(s) {
+        if let Some((k, v)) = parse_line(&line)? {
+            values.insert(k, v);
+        }
+    }
+    Ok(values)
+}
+
+impl Properties {
+    /// Create a new configuration from the given `.properties`
+    /// contents. Keys and values are separated by `=`, `:` or
+    /// whitespace, lines starting with `#` or `!` are comments, a
+    /// trailing backslash continues the value on the next line and
+    /// `\uXXXX` escapes are decoded:
+    ///
+    /// ```ini
+    /// ! i am a comment
+    /// mongo.uri : mongodb://localhost/
+    /// greeting = hello, \
+    ///            world \u263A
+    /// ```
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Self, Error> {
+        Ok(Self { values: parse(s)? })
+    }
+
+    /// Similar to `from_str` except that the given path is used as
+    /// the contents for the string to parse.
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let file = read_file(path)?;
+        Self::from_str(&file)
+    }
+}
+
+impl Config for Properties {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values.get(key).map(|value| value.to_string())
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.values
+            .get(key)
+            .map(|value| Cow::Borrowed(value.as_str()))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+}
+
+impl fmt::Debug for Properties {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redact::debug(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::properties::{parse_line, Properties};
+    use crate::{Config, Error};
+
+    #[test]
+    fn test_parse_line() {
+        let tests = [
+            ("  # comment", None),
+            ("! comment", None),
+            ("foo=bar", Some(("foo", "bar"))),
+            ("foo : bar baz", Some(("foo", "bar baz"))),
+            ("foo bar", Some(("foo", "bar"))),
+            ("empty", Some(("empty", ""))),
+            ("a\\=b = c", Some(("a=b", "c"))),
+            ("tab = a\\tb", Some(("tab", "a\tb"))),
+            ("smile = \\u263A", Some(("smile", "\u{263A}"))),
+        ];
+        for (line, exp) in tests.iter() {
+            let exp = exp.map(|(k, v)| (k.to_string(), v.to_string()));
+            assert_eq!(parse_line(line), Ok(exp));
+        }
+        assert!(matches!(parse_line("bad = \\uZZZZ"), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn continuations() {
+        let cfg = Properties::from_str(
+            "greeting = hello, \\\n           world\npath = c:\\\\dir\\\\\nnext = 1",
+        )
+        .unwrap();
+        assert_eq!(cfg.get("greeting"), Some("hello, world".to_string()));
+        assert_eq!(cfg.get("path"), Some("c:\\dir\\".to_string()));
+        assert_eq!(cfg.get("next"), Some("1".to_string()));
+
+        let cfg = Properties::from_str("# note \\\nfoo=bar\n! also \\\nbaz=1").unwrap();
+        assert_eq!(cfg.get("foo"), Some("bar".to_string()));
+        assert_eq!(cfg.get("baz"), Some("1".to_string()));
+    }
+}
@@ -0,0 +1,108 @@
+//! Rate limits and quotas written as a count per window (e.g.
+//! `100/second`).
+
+use core::convert::TryFrom;
+use core::fmt;
+
+use chrono::Duration;
+
+use crate::{duration, NumberFormat};
+
+/// What the errors for rates say was expected.
+pub(crate) const EXPECTED: &str = "rate like `100/second`";
+
+/// A number of events allowed per window of time, read with
+/// `Config::rate`.
+///
+/// The count can have a `k` (thousand) or `M` (million) suffix, and
+/// the window is a unit (`second`, `minute`, `hour` or `day`, in full,
+/// plural or abbreviated as `sec`, `min`, `s`, `m`, `h` or `d`) or a
+/// duration like `10s` or `1h30m`.
+///
+/// ```
+/// use chrono::Duration;
+/// use dinglebit_config::{Config, Rate};
+/// use std::collections::HashMap;
+///
+/// let cfg = HashMap::from([("api.limit", "5k/minute"), ("login.limit", "10/15m")]);
+/// let limit = cfg.rate("api.limit");
+/// assert_eq!(limit.count, 5_000);
+/// assert_eq!(limit.window, Duration::minutes(1));
+/// assert_eq!(cfg.rate("login.limit").window, Duration::minutes(15));
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Rate {
+    /// The number of events allowed in each window.
+    pub count: u64,
+
+    /// The length of the window. It's always positive.
+    pub window: Duration,
+}
+
+impl Rate {
+    /// Parse a rate, returning `None` if it's malformed or the window
+    /// isn't positive.
+    pub fn parse(s: &str) -> Option<Self> {
+        let (count, window) = s.split_once('/')?;
+        let count = count.trim();
+        let (digits, multiplier) = match count.char_indices().last()? {
+            (i, 'k') | (i, 'K') => (&count[..i], 1_000),
+            (i, 'M') => (&count[..i], 1_000_000),
+            _ => (count, 1),
+        };
+        let count = u64::try_from(NumberFormat::default().parse_int(digits.trim())?).ok()?;
+        let window = match window.trim() {
+            "s" | "sec" | "second" | "seconds" => Duration::seconds(1),
+            "m" | "min" | "minute" | "minutes" => Duration::minutes(1),
+            "h" | "hour" | "hours" => Duration::hours(1),
+            "d" | "day" | "days" => Duration::days(1),
+            w => duration::parse(w)?,
+        };
+        match window > Duration::zero() {
+            true => Some(Self {
+                count: count.checked_mul(multiplier)?,
+                window,
+            }),
+            false => None,
+        }
+    }
+
+    /// The average number of events allowed per second.
+    pub fn per_second(&self) -> f64 {
+        self.count as f64 * 1000.0 / self.window.num_milliseconds() as f64
+    }
+}
+
+impl fmt::Display for Rate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.count, duration::format(self.window))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rate::Rate;
+    use chrono::Duration;
+
+    #[test]
+    fn parse() {
+        let rate = |count, window| Some(Rate { count, window });
+        assert_eq!(Rate::parse("100/second"), rate(100, Duration::seconds(1)));
+        assert_eq!(Rate::parse("5k/minute"), rate(5_000, Duration::minutes(1)));
+        assert_eq!(Rate::parse("2M / day"), rate(2_000_000, Duration::days(1)));
+        assert_eq!(Rate::parse("1_000/h"), rate(1_000, Duration::hours(1)));
+        assert_eq!(Rate::parse("10/15m"), rate(10, Duration::minutes(15)));
+        assert_eq!(Rate::parse("0/sec"), rate(0, Duration::seconds(1)));
+
+        assert_eq!(Rate::parse("100"), None);
+        assert_eq!(Rate::parse("-1/second"), None);
+        assert_eq!(Rate::parse("k/second"), None);
+        assert_eq!(Rate::parse("100/fortnight"), None);
+        assert_eq!(Rate::parse("100/0s"), None);
+
+        let r = Rate::parse("5k/minute").unwrap();
+        assert_eq!(r.to_string(), "5000/1m");
+        assert_eq!(Rate::parse(&r.to_string()), Some(r));
+        assert!((r.per_second() - 83.333).abs() < 0.001);
+    }
+}
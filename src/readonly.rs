@@ -0,0 +1,149 @@
+//! A config handed to code that must not change it.
+
+use std::borrow::Cow;
+
+use crate::{Config, Error, SourceHealth, SourceStats, Value, ValueError};
+
+/// Wraps a config so only the `Config` methods are reachable. The
+/// wrapped config can't be borrowed back out, so APIs of the wrapped
+/// type that change it (e.g. `Reloadable::reload` or `on_change`)
+/// can't be called by whoever holds the `ReadOnly`, such as plugins.
+/// Since that's checked at compile time, there's no attempt to change
+/// the config at run time to panic on or log. Every `Config` method is
+/// passed through, so the wrapped config answers the same as it would
+/// unwrapped.
+///
+/// ```
+/// use dinglebit_config::{Config, ReadOnly, Reloadable};
+///
+/// let cfg = ReadOnly::wrap(Reloadable::file("example.cfg").unwrap());
+/// assert_eq!(cfg.string("foo"), "bar");
+/// ```
+///
+/// ```compile_fail
+/// use dinglebit_config::{ReadOnly, Reloadable};
+///
+/// let cfg = ReadOnly::wrap(Reloadable::file("example.cfg").unwrap());
+/// cfg.reload();
+/// ```
+pub struct ReadOnly<C> {
+    config: C,
+}
+
+impl<C: Config> ReadOnly<C> {
+    /// Wrap the given config.
+    pub fn wrap(config: C) -> Self {
+        Self { config }
+    }
+}
+
+impl<C: Config> Config for ReadOnly<C> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.config.get(key)
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.config.get_ref(key)
+    }
+
+    fn get_value(&self, key: &str) -> Option<Value> {
+        self.config.get_value(key)
+    }
+
+    fn try_get_ref(&self, key: &str) -> Result<Cow<'_, str>, ValueError> {
+        self.config.try_get_ref(key)
+    }
+
+    fn list_len(&self, key: &str) -> Option<usize> {
+        self.config.list_len(key)
+    }
+
+    fn get_many(&self, keys: &[&str]) -> Vec<Option<String>> {
+        self.config.get_many(keys)
+    }
+
+    fn prefetch(&self, prefixes: &[&str]) -> Vec<Result<usize, Error>> {
+        self.config.prefetch(prefixes)
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.config.keys()
+    }
+
+    fn similar_keys(&self, key: &str) -> Vec<String> {
+        self.config.similar_keys(key)
+    }
+
+    fn fingerprint(&self) -> u64 {
+        self.config.fingerprint()
+    }
+
+    fn lookup(&self, key: &str) -> Result<Option<String>, Error> {
+        self.config.lookup(key)
+    }
+
+    fn source_health(&self) -> SourceHealth {
+        self.config.source_health()
+    }
+
+    fn source_name(&self) -> String {
+        self.config.source_name()
+    }
+
+    fn source_of(&self, key: &str) -> Option<String> {
+        self.config.source_of(key)
+    }
+
+    fn stats(&self) -> Vec<SourceStats> {
+        self.config.stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Config, Error, MultiConfig, ReadOnly, SourceHealth};
+    use std::collections::HashMap;
+
+    /// A remote source that can't be reached.
+    struct Down;
+
+    impl Config for Down {
+        fn get(&self, _key: &str) -> Option<String> {
+            None
+        }
+
+        fn prefetch(&self, prefixes: &[&str]) -> Vec<Result<usize, Error>> {
+            prefixes
+                .iter()
+                .map(|_| Err(Error::Remote("down".to_string())))
+                .collect()
+        }
+
+        fn lookup(&self, _key: &str) -> Result<Option<String>, Error> {
+            Err(Error::Remote("down".to_string()))
+        }
+
+        fn source_health(&self) -> SourceHealth {
+            SourceHealth::Down("down".to_string())
+        }
+    }
+
+    #[test]
+    fn forwards() {
+        let cfg = ReadOnly::wrap(Down);
+        assert_eq!(cfg.lookup("a"), Err(Error::Remote("down".to_string())));
+        assert_eq!(
+            cfg.prefetch(&["db."]),
+            vec![Err(Error::Remote("down".to_string()))]
+        );
+        assert!(matches!(cfg.source_health(), SourceHealth::Down(_)));
+
+        let cfg = ReadOnly::wrap(MultiConfig::new(vec![
+            Box::new(HashMap::from([("servers", "[a, b]")])),
+            Box::new(Down),
+        ]));
+        assert_eq!(cfg.stats().len(), 2);
+        assert_eq!(cfg.list_len("servers"), Some(2));
+        assert_eq!(cfg.lookup("servers"), Ok(Some("[a, b]".to_string())));
+    }
+}
@@ -0,0 +1,167 @@
+//! Hiding secrets when configuration is shown, such as in logs or on
+//! a debug endpoint.
+
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::RwLock;
+
+use crate::{Config, Schema};
+
+/// What a secret value is replaced with.
+pub const REDACTED: &str = "[redacted]";
+
+/// Words that mark the last part of a key as a secret.
+const SENSITIVE: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "apikey",
+    "api_key",
+    "private_key",
+    "credential",
+];
+
+/// Keys marked with `mark_sensitive`.
+static MARKED: RwLock<BTreeSet<String>> = RwLock::new(BTreeSet::new());
+
+/// Treat the key as a secret everywhere in the process, for secrets
+/// whose names don't give them away (e.g. `stripe.webhook` or
+/// `app.salt`), including the `Debug` output of every source. The mark
+/// can't be undone; to redact a key only where a schema is at hand,
+/// use `Schema::sensitive` instead.
+pub fn mark_sensitive(key: &str) {
+    MARKED.write().unwrap().insert(key.to_string());
+}
+
+/// Whether the key holds a secret: it was marked with `mark_sensitive`
+/// or it looks like it, going by the last part of it (e.g.
+/// `db.password`, `auth.client_secret` or `stripe.key`).
+pub fn is_sensitive(key: &str) -> bool {
+    if MARKED.read().unwrap().contains(key) {
+        return true;
+    }
+    let last = key.rsplit('.').next().unwrap_or(key).to_lowercase();
+    last == "key" || last == "pass" || SENSITIVE.iter().any(|s| last.contains(s))
+}
+
+/// The value of the key as it can be shown: `REDACTED` for secrets
+/// (see `is_sensitive`), and URLs with the password removed
+/// (`postgres://app:[redacted]@db/app`).
+pub fn redact<'a>(key: &str, value: &'a str) -> Cow<'a, str> {
+    if is_sensitive(key) {
+        return Cow::Borrowed(REDACTED);
+    }
+    let userinfo = value.find("://").and_then(|scheme| {
+        let start = scheme + 3;
+        let end = start
+            + value[start..]
+                .find(['/', '?', '#'])
+                .unwrap_or(value.len() - start);
+        let at = start + value[start..end].rfind('@')?;
+        let colon = start + value[start..at].find(':')?;
+        Some((colon + 1, at))
+    });
+    match userinfo {
+        Some((start, end)) => {
+            Cow::Owned(format!("{}{}{}", &value[..start], REDACTED, &value[end..]))
+        }
+        None => Cow::Borrowed(value),
+    }
+}
+
+/// Write the `source_name` and values of the config for `Debug`, with
+/// secrets redacted and the keys sorted (e.g. `Simple {"db.host":
+/// "localhost", "db.password": "[redacted]"}`), so a config can be
+/// logged with `{:?}` without leaking them.
+pub(crate) fn debug<C: Config + ?Sized>(config: &C, f: &mut fmt::Formatter) -> fmt::Result {
+    write_debug(config, f, |key, value| redact(key, value).into_owned())
+}
+
+/// Similar to `debug` but every value is redacted, for sources that
+/// only hold secrets (e.g. a secret manager).
+pub(crate) fn debug_secrets<C: Config + ?Sized>(config: &C, f: &mut fmt::Formatter) -> fmt::Result {
+    write_debug(config, f, |_, _| REDACTED.to_string())
+}
+
+/// A config shown like `debug` but with the keys the schema marks as
+/// secrets redacted too, created with `Schema::debug`.
+pub(crate) struct WithSchema<'a, C: ?Sized> {
+    pub(crate) config: &'a C,
+    pub(crate) schema: &'a Schema,
+}
+
+impl<C: Config + ?Sized> fmt::Debug for WithSchema<'_, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_debug(self.config, f, |key, value| {
+            self.schema.redact(key, value).into_owned()
+        })
+    }
+}
+
+fn write_debug<C, R>(config: &C, f: &mut fmt::Formatter, redact: R) -> fmt::Result
+where
+    C: Config + ?Sized,
+    R: Fn(&str, &str) -> String,
+{
+    let mut keys = config.keys();
+    keys.sort();
+    keys.dedup();
+    write!(f, "{} ", config.source_name())?;
+    let mut map = f.debug_map();
+    for key in keys.iter() {
+        if let Some(value) = config.get_ref(key) {
+            map.entry(key, &redact(key, &value));
+        }
+    }
+    map.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redact::{is_sensitive, mark_sensitive, redact};
+
+    #[test]
+    fn sensitive() {
+        for key in &[
+            "db.password",
+            "auth.CLIENT_SECRET",
+            "stripe.key",
+            "github.token",
+        ] {
+            assert!(is_sensitive(key), "{}", key);
+        }
+        for key in &["db.host", "keys.count", "password.min_length"] {
+            assert!(!is_sensitive(key), "{}", key);
+        }
+
+        assert!(!is_sensitive("redact.webhook"));
+        mark_sensitive("redact.webhook");
+        assert!(is_sensitive("redact.webhook"));
+        assert_eq!(redact("redact.webhook", "whsec_1"), "[redacted]");
+
+        assert_eq!(redact("db.password", "hunter2"), "[redacted]");
+        assert_eq!(redact("db.host", "localhost"), "localhost");
+        assert_eq!(
+            redact(
+                "db.uri",
+                "postgres://app:hunter2@db:5432/app?sslmode=require"
+            ),
+            "postgres://app:[redacted]@db:5432/app?sslmode=require"
+        );
+        assert_eq!(
+            redact("db.uri", "postgres://app@db/a:b@c"),
+            "postgres://app@db/a:b@c"
+        );
+    }
+
+    #[test]
+    fn debug() {
+        let cfg = crate::Simple::from_str("db.password = hunter2\ndb.host = localhost").unwrap();
+        assert_eq!(
+            format!("{:?}", cfg),
+            r#"Simple {"db.host": "localhost", "db.password": "[redacted]"}"#
+        );
+    }
+}
@@ -0,0 +1,496 @@
+//! A config that can be reloaded while it's in use (e.g. on `SIGHUP`).
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+use crate::{diff, load_auto, Change, Config, Error, Schema, SourceHealth, SourceStats};
+
+type Loader = Box<dyn Fn() -> Result<Box<dyn Config + Send + Sync>, Error> + Send + Sync>;
+type Listener = Box<dyn Fn(&[Change]) + Send + Sync>;
+type Logged = (DateTime<Utc>, Change);
+
+struct Current {
+    config: Arc<dyn Config + Send + Sync>,
+    fingerprint: u64,
+}
+
+/// Wraps a config that is loaded by a function, so it can be loaded
+/// again with `reload`. Every reload that loads swaps the new config in.
+/// The listeners are only told when its fingerprint differs from the
+/// current one and comparing the two finds changes.
+///
+/// Swapping only when the fingerprint changes would skip the diff, but
+/// sources that can't list their keys (e.g. a remote source read key by
+/// key) all have the same fingerprint, so their new values would never
+/// be used. Swapping every time costs a pointer swap; the fingerprint
+/// still spares the diff when nothing changed.
+///
+/// ```
+/// use dinglebit_config::{Config, Reloadable};
+///
+/// let cfg = Reloadable::file("example.cfg").unwrap();
+/// cfg.on_change(|changes| {
+///     for change in changes {
+///         println!("{}", change);
+///     }
+/// });
+/// assert_eq!(cfg.string("foo"), "bar");
+/// assert_eq!(cfg.reload(), Ok(false));
+/// ```
+pub struct Reloadable {
+    load: Loader,
+    current: RwLock<Current>,
+    listeners: Mutex<Vec<Listener>>,
+    schema: Option<Schema>,
+    restart_listeners: Mutex<Vec<Listener>>,
+    /// Why the last reload failed, until one works.
+    error: Mutex<Option<Error>>,
+    load_time: Duration,
+    /// How many reloads loaded the config and how long the last took.
+    refreshes: Mutex<(u64, Option<Duration>)>,
+}
+
+impl Reloadable {
+    /// Load the config with the given function, which is called again
+    /// for each `reload`.
+    pub fn new<F>(load: F) -> Result<Self, Error>
+    where
+        F: Fn() -> Result<Box<dyn Config + Send + Sync>, Error> + Send + Sync + 'static,
+    {
+        let start = Instant::now();
+        let config: Arc<dyn Config + Send + Sync> = Arc::from(load()?);
+        let load_time = start.elapsed();
+        Ok(Self {
+            load: Box::new(load),
+            current: RwLock::new(Current {
+                fingerprint: config.fingerprint(),
+                config,
+            }),
+            listeners: Mutex::new(vec![]),
+            schema: None,
+            restart_listeners: Mutex::new(vec![]),
+            error: Mutex::new(None),
+            load_time,
+            refreshes: Mutex::new((0, None)),
+        })
+    }
+
+    /// Load the file at the given path with `load_auto`.
+    pub fn file(path: &str) -> Result<Self, Error> {
+        let path = path.to_string();
+        Self::new(move || load_auto(&path))
+    }
+
+    /// Use the given schema to tell which changes only take effect
+    /// after a restart (see `Schema::restart_required`).
+    pub fn schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Call the given function with the changes whenever a reload
+    /// changes the config.
+    pub fn on_change<F: Fn(&[Change]) + Send + Sync + 'static>(&self, f: F) {
+        self.listeners.lock().unwrap().push(Box::new(f));
+    }
+
+    /// Call the given function with the changes that need a restart to
+    /// take effect, according to the schema, whenever a reload makes
+    /// any. The changes are still applied and passed to `on_change`.
+    pub fn on_restart_required<F: Fn(&[Change]) + Send + Sync + 'static>(&self, f: F) {
+        self.restart_listeners.lock().unwrap().push(Box::new(f));
+    }
+
+    /// Load the config again. Returns whether any of its values
+    /// changed, which can't be told for configs that can't list their
+    /// keys, although they're reloaded all the same. If loading fails,
+    /// the current config is kept and the error is returned.
+    pub fn reload(&self) -> Result<bool, Error> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!(
+            "config.reload",
+            source = %self.config().source_name()
+        )
+        .entered();
+        let start = Instant::now();
+        let loaded = (self.load)();
+        *self.error.lock().unwrap() = loaded.as_ref().err().cloned();
+        #[cfg(feature = "tracing")]
+        if let Err(e) = &loaded {
+            tracing::warn!(error = %e, "failed to reload config");
+        }
+        let config: Arc<dyn Config + Send + Sync> = Arc::from(loaded?);
+        {
+            let mut refreshes = self.refreshes.lock().unwrap();
+            refreshes.0 += 1;
+            refreshes.1 = Some(start.elapsed());
+        }
+        // The new config is always swapped in, as sources that can't
+        // list their keys all have the same fingerprint and no changes
+        // to tell. Comparing and swapping under one lock keeps two
+        // reloads from interleaving.
+        let fingerprint = config.fingerprint();
+        let changes = {
+            let mut current = self.current.write().unwrap();
+            let changes = match current.fingerprint == fingerprint {
+                true => vec![],
+                false => diff(current.config.as_ref(), config.as_ref()),
+            };
+            *current = Current {
+                config,
+                fingerprint,
+            };
+            changes
+        };
+        if changes.is_empty() {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(fingerprint, "config unchanged");
+            return Ok(false);
+        }
+        #[cfg(feature = "tracing")]
+        {
+            tracing::info!(changes = changes.len(), fingerprint, "config changed");
+            for change in changes.iter() {
+                // Only the keys, as the values may be secrets.
+                tracing::debug!(key = change.key(), "config change applied");
+            }
+        }
+        for listener in self.listeners.lock().unwrap().iter() {
+            listener(&changes);
+        }
+        if let Some(schema) = &self.schema {
+            let restart: Vec<Change> = changes
+                .into_iter()
+                .filter(|c| c.requires_restart(schema))
+                .collect();
+            if !restart.is_empty() {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(
+                    changes = restart.len(),
+                    "config changes need a restart to take effect"
+                );
+                for listener in self.restart_listeners.lock().unwrap().iter() {
+                    listener(&restart);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    fn config(&self) -> Arc<dyn Config + Send + Sync> {
+        self.current.read().unwrap().config.clone()
+    }
+}
+
+impl Config for Reloadable {
+    fn get(&self, key: &str) -> Option<String> {
+        self.config().get(key)
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.get(key).map(Cow::Owned)
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.config().keys()
+    }
+
+    /// The fingerprint recorded when the config was last loaded.
+    fn fingerprint(&self) -> u64 {
+        self.current.read().unwrap().fingerprint
+    }
+
+    /// The time taken by the first load and by the reloads that
+    /// loaded the config.
+    fn stats(&self) -> Vec<SourceStats> {
+        let config = self.config();
+        let (refreshes, last_refresh) = *self.refreshes.lock().unwrap();
+        vec![SourceStats {
+            load_time: Some(self.load_time),
+            refreshes,
+            last_refresh,
+            ..SourceStats::new(config.source_name(), config.keys().len())
+        }]
+    }
+
+    /// Degraded while the last reload failed, as the values are from
+    /// an earlier load.
+    fn source_health(&self) -> SourceHealth {
+        match self.error.lock().unwrap().as_ref() {
+            Some(e) => SourceHealth::Degraded(format!("reload failed: {}", e)),
+            None => self.config().source_health(),
+        }
+    }
+}
+
+impl fmt::Debug for Reloadable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Reloadable")
+            .field("source", &self.config().source_name())
+            .field("fingerprint", &self.fingerprint())
+            .finish()
+    }
+}
+
+/// The most recent changes made by reloads, for showing what changed
+/// and when (e.g. on a debug endpoint). Clones share the same log.
+///
+/// ```
+/// use dinglebit_config::{ChangeLog, Reloadable};
+///
+/// let cfg = Reloadable::file("example.cfg").unwrap();
+/// let log = ChangeLog::new(100);
+/// let recorder = log.clone();
+/// cfg.on_change(move |changes| recorder.record(changes));
+/// assert!(log.entries().is_empty());
+/// ```
+#[derive(Clone)]
+pub struct ChangeLog {
+    capacity: usize,
+    entries: Arc<Mutex<VecDeque<Logged>>>,
+}
+
+impl ChangeLog {
+    /// Keep up to the given number of changes, dropping the oldest.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+        }
+    }
+
+    /// Record the given changes as happening now.
+    pub fn record(&self, changes: &[Change]) {
+        let now = Utc::now();
+        let mut entries = self.entries.lock().unwrap();
+        for change in changes {
+            if entries.len() == self.capacity {
+                entries.pop_front();
+            }
+            if self.capacity > 0 {
+                entries.push_back((now, change.clone()));
+            }
+        }
+    }
+
+    /// The recorded changes, oldest first.
+    pub fn entries(&self) -> Vec<(DateTime<Utc>, Change)> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::{Schema, Type};
+    use crate::{Change, ChangeLog, Config, Error, Reloadable, Simple, SourceHealth};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn reload() {
+        let source = Arc::new(Mutex::new("a = 1\nb = 2".to_string()));
+        let loader = source.clone();
+        let cfg =
+            Reloadable::new(move || Ok(Box::new(Simple::from_str(&loader.lock().unwrap())?) as _))
+                .unwrap();
+        let seen = Arc::new(Mutex::new(vec![]));
+        let record = seen.clone();
+        cfg.on_change(move |changes| record.lock().unwrap().extend(changes.to_vec()));
+
+        let before = cfg.fingerprint();
+        assert_eq!(cfg.reload(), Ok(false));
+
+        // Same values in a different order are the same config.
+        *source.lock().unwrap() = "b = 2\n# comment\na = 1".to_string();
+        assert_eq!(cfg.reload(), Ok(false));
+        assert!(seen.lock().unwrap().is_empty());
+
+        *source.lock().unwrap() = "a = 1\nb = 3".to_string();
+        assert_eq!(cfg.reload(), Ok(true));
+        assert_ne!(cfg.fingerprint(), before);
+        assert_eq!(cfg.int("b"), 3);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![Change::Changed {
+                key: "b".to_string(),
+                old: "2".to_string(),
+                new: "3".to_string(),
+            }]
+        );
+
+        // A broken file keeps the current config.
+        *source.lock().unwrap() = "broken".to_string();
+        assert_eq!(cfg.reload(), Err(Error::InvalidKeyValuePair));
+        assert_eq!(cfg.int("b"), 3);
+        assert_eq!(
+            cfg.source_health(),
+            SourceHealth::Degraded("reload failed: invalid key/value pair".to_string())
+        );
+        *source.lock().unwrap() = "a = 1\nb = 3".to_string();
+        assert_eq!(cfg.reload(), Ok(false));
+        assert_eq!(cfg.source_health(), SourceHealth::Ok);
+
+        // The failed reload isn't counted.
+        let stats = &cfg.stats()[0];
+        assert_eq!((stats.source.as_str(), stats.keys), ("Simple", 2));
+        assert_eq!(stats.refreshes, 4);
+        assert!(stats.load_time.is_some() && stats.last_refresh.is_some());
+    }
+
+    #[test]
+    fn unlisted() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        /// Can't list its keys, so every version has the same
+        /// fingerprint.
+        struct Version(usize);
+
+        impl Config for Version {
+            fn get(&self, _key: &str) -> Option<String> {
+                Some(self.0.to_string())
+            }
+        }
+
+        let version = Arc::new(AtomicUsize::new(1));
+        let loader = version.clone();
+        let cfg =
+            Reloadable::new(move || Ok(Box::new(Version(loader.load(Ordering::SeqCst))) as _))
+                .unwrap();
+        assert_eq!(cfg.int("v"), 1);
+        version.store(2, Ordering::SeqCst);
+        assert_eq!(cfg.reload(), Ok(false));
+        assert_eq!(cfg.int("v"), 2);
+    }
+
+    #[test]
+    fn restart_required() {
+        let source = Arc::new(Mutex::new("port = 80\nlevel = info".to_string()));
+        let loader = source.clone();
+        let schema = Schema::new()
+            .required("port", Type::Int)
+            .restart_required("port")
+            .required("level", Type::String);
+        let cfg =
+            Reloadable::new(move || Ok(Box::new(Simple::from_str(&loader.lock().unwrap())?) as _))
+                .unwrap()
+                .schema(schema);
+        let seen = Arc::new(Mutex::new(vec![]));
+        let record = seen.clone();
+        cfg.on_restart_required(move |changes| record.lock().unwrap().extend(changes.to_vec()));
+
+        *source.lock().unwrap() = "port = 80\nlevel = debug".to_string();
+        assert_eq!(cfg.reload(), Ok(true));
+        assert!(seen.lock().unwrap().is_empty());
+
+        *source.lock().unwrap() = "port = 8080\nlevel = warn".to_string();
+        assert_eq!(cfg.reload(), Ok(true));
+        assert_eq!(cfg.int("port"), 8080);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![Change::Changed {
+                key: "port".to_string(),
+                old: "80".to_string(),
+                new: "8080".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn change_log() {
+        let change = |key: &str| Change::Added {
+            key: key.to_string(),
+            value: "1".to_string(),
+        };
+        let log = ChangeLog::new(2);
+        log.clone().record(&[change("a"), change("b")]);
+        log.record(&[change("c")]);
+        let keys: Vec<String> = log
+            .entries()
+            .iter()
+            .map(|(_, c)| c.key().to_string())
+            .collect();
+        assert_eq!(keys, vec!["b", "c"]);
+
+        let empty = ChangeLog::new(0);
+        empty.record(&[change("a")]);
+        assert!(empty.entries().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "tracing")]
+    fn tracing() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        /// Records the names of the spans and the messages of the events.
+        struct Recorder {
+            seen: Arc<Mutex<Vec<String>>>,
+            ids: AtomicU64,
+        }
+
+        struct Message<'a>(&'a mut String);
+
+        impl Visit for Message<'_> {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                if field.name() == "message" {
+                    *self.0 = format!("{:?}", value);
+                }
+            }
+        }
+
+        impl Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                self.seen
+                    .lock()
+                    .unwrap()
+                    .push(span.metadata().name().to_string());
+                Id::from_u64(self.ids.fetch_add(1, Ordering::SeqCst) + 1)
+            }
+
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, event: &Event<'_>) {
+                let mut message = String::new();
+                event.record(&mut Message(&mut message));
+                self.seen.lock().unwrap().push(message);
+            }
+
+            fn enter(&self, _: &Id) {}
+
+            fn exit(&self, _: &Id) {}
+        }
+
+        let seen = Arc::new(Mutex::new(vec![]));
+        let recorder = Recorder {
+            seen: seen.clone(),
+            ids: AtomicU64::new(0),
+        };
+        tracing::subscriber::with_default(recorder, || {
+            let cfg = Reloadable::file("example.cfg").unwrap();
+            cfg.reload().unwrap();
+        });
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                "config.load",
+                "loaded config",
+                "config.reload",
+                "config.load",
+                "loaded config",
+                "config unchanged",
+            ]
+        );
+    }
+}
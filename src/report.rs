@@ -0,0 +1,180 @@
+//! A report of the resolved configuration for logging at startup.
+
+use std::fmt;
+
+use crate::{Config, Schema};
+
+/// A key of a `StartupReport`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Row {
+    pub key: String,
+    /// The value, with secrets redacted, or `None` if it's missing.
+    pub value: Option<String>,
+    /// The `source_name` of the config the value came from.
+    pub source: Option<String>,
+    /// Whether the value came from `Defaults`.
+    pub default: bool,
+}
+
+/// The resolved value of each key of a registry (the keys a service
+/// knows about, as a `Schema`), created with `Config::startup_report`.
+/// It's shown as a table:
+///
+/// ```text
+/// KEY          VALUE       SOURCE       DEFAULT
+/// db.host      localhost   Toml         no
+/// db.password  [redacted]  Environment  no
+/// db.pool      10          Defaults     yes
+/// debug        -           -            -
+/// ```
+///
+/// ```
+/// use dinglebit_config::schema::Type;
+/// use dinglebit_config::{Config, Defaults, MultiConfig, Schema};
+///
+/// let cfg = MultiConfig::builder()
+///     .file("example.cfg")
+///     .defaults(Defaults::new().set("pool", "10"))
+///     .build()
+///     .unwrap();
+/// let registry = Schema::new()
+///     .required("foo", Type::String)
+///     .optional("pool", Type::Int);
+/// println!("{}", cfg.startup_report(&registry));
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct StartupReport {
+    rows: Vec<Row>,
+}
+
+impl StartupReport {
+    /// The keys in the order of the registry.
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    /// The keys without a value.
+    pub fn missing(&self) -> Vec<&str> {
+        self.rows
+            .iter()
+            .filter(|r| r.value.is_none())
+            .map(|r| r.key.as_str())
+            .collect()
+    }
+}
+
+pub(crate) fn startup_report<C: Config + ?Sized>(config: &C, registry: &Schema) -> StartupReport {
+    let rows = registry
+        .fields()
+        .iter()
+        .map(|field| {
+            let value = config.get_ref(&field.key);
+            let source = value.as_ref().and_then(|_| config.source_of(&field.key));
+            Row {
+                key: field.key.clone(),
+                value: value.map(|v| registry.redact(&field.key, &v).into_owned()),
+                default: source.as_deref() == Some("Defaults"),
+                source,
+            }
+        })
+        .collect();
+    StartupReport { rows }
+}
+
+impl fmt::Display for StartupReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cells: Vec<[&str; 4]> = self
+            .rows
+            .iter()
+            .map(|r| {
+                let default = match (&r.value, r.default) {
+                    (None, _) => "-",
+                    (Some(_), true) => "yes",
+                    (Some(_), false) => "no",
+                };
+                [
+                    r.key.as_str(),
+                    r.value.as_deref().unwrap_or("-"),
+                    r.source.as_deref().unwrap_or("-"),
+                    default,
+                ]
+            })
+            .collect();
+        table(f, ["KEY", "VALUE", "SOURCE", "DEFAULT"], &cells)
+    }
+}
+
+/// Write the rows under the header with the columns padded to line up.
+pub(crate) fn table<const N: usize>(
+    f: &mut fmt::Formatter,
+    header: [&str; N],
+    cells: &[[&str; N]],
+) -> fmt::Result {
+    let mut widths = header.map(str::len);
+    for row in cells {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in std::iter::once(&header).chain(cells.iter()) {
+        let line = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(f, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::report::Row;
+    use crate::schema::Type;
+    use crate::{Config, Defaults, MultiConfig, Schema, Simple};
+
+    #[test]
+    fn startup_report() {
+        let cfg = MultiConfig::builder()
+            .layer(Simple::from_str("db.host = localhost\ndb.password = hunter2").unwrap())
+            .defaults(Defaults::new().set("db.pool", "10"))
+            .build()
+            .unwrap();
+        let registry = Schema::new()
+            .required("db.host", Type::String)
+            .required("db.password", Type::String)
+            .optional("db.pool", Type::Int)
+            .optional("debug", Type::Bool);
+
+        let report = cfg.startup_report(&registry);
+        assert_eq!(
+            report.rows()[2],
+            Row {
+                key: "db.pool".to_string(),
+                value: Some("10".to_string()),
+                source: Some("Defaults".to_string()),
+                default: true,
+            }
+        );
+        assert_eq!(report.missing(), vec!["debug"]);
+        assert_eq!(
+            report.to_string(),
+            "KEY          VALUE       SOURCE    DEFAULT
+db.host      localhost   Simple    no
+db.password  [redacted]  Simple    no
+db.pool      10          Defaults  yes
+debug        -           -         -
+"
+        );
+
+        let registry = Schema::new()
+            .required("report.webhook", Type::String)
+            .sensitive("report.webhook");
+        let cfg = Simple::from_str("report.webhook = whsec_1").unwrap();
+        assert_eq!(
+            cfg.startup_report(&registry).rows()[0].value,
+            Some("[redacted]".to_string())
+        );
+    }
+}
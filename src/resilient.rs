@@ -0,0 +1,297 @@
+//! Retrying lookups and a circuit breaker for remote sources.
+
+use std::fmt;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{Config, Error, SourceHealth};
+
+/// How often a failed lookup is tried again, doubling the wait after
+/// each attempt up to a maximum.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Retry {
+    /// How many times a lookup is tried in all.
+    pub attempts: u32,
+
+    /// The wait before the first retry.
+    pub backoff: Duration,
+
+    /// The longest wait between retries.
+    pub max_backoff: Duration,
+}
+
+impl Retry {
+    /// Try lookups the given number of times in all, waiting 50ms
+    /// before the first retry and at most 1s between them.
+    pub fn new(attempts: u32) -> Self {
+        Self {
+            attempts,
+            backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+
+    /// Wait `backoff` before the first retry, doubling it after each
+    /// one up to `max`.
+    pub fn backoff(mut self, backoff: Duration, max: Duration) -> Self {
+        self.backoff = backoff;
+        self.max_backoff = max;
+        self
+    }
+}
+
+/// Only try once.
+impl Default for Retry {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+/// The state of the circuit breaker.
+#[derive(Default)]
+struct Breaker {
+    /// Lookups that failed in a row.
+    failures: u32,
+    /// While set, lookups fail without asking the source.
+    open_until: Option<Instant>,
+}
+
+/// A source whose lookups (see `Config::lookup`) are retried and
+/// guarded by a circuit breaker: after `threshold` lookups in a row
+/// fail, the circuit opens and lookups fail straight away for the
+/// `cooldown`. After that the source is asked again: a lookup that
+/// works closes the circuit and one that fails opens it again.
+///
+/// A failed lookup is a miss for `get`, so when the source is a layer
+/// of a `MultiConfig` the next layer answers without waiting on a
+/// source that's known to be down.
+///
+/// ```
+/// use dinglebit_config::{Config, MultiConfig, Resilient, Retry, Simple};
+/// use std::time::Duration;
+///
+/// # let remote = Simple::from_str("").unwrap();
+/// let cfg = MultiConfig::new(vec![
+///     Box::new(
+///         Resilient::new(remote)
+///             .retry(Retry::new(3).backoff(Duration::from_millis(20), Duration::from_secs(1)))
+///             .breaker(5, Duration::from_secs(30)),
+///     ),
+///     Box::new(Simple::from_str("db.host = localhost").unwrap()),
+/// ]);
+/// assert_eq!(cfg.get("db.host"), Some("localhost".to_string()));
+/// ```
+pub struct Resilient<C> {
+    source: C,
+    retry: Retry,
+    threshold: u32,
+    cooldown: Duration,
+    breaker: Mutex<Breaker>,
+}
+
+impl<C: Config> Resilient<C> {
+    /// Wrap the source, trying each lookup once and with the circuit
+    /// breaker opening for 30s after 5 failures in a row.
+    pub fn new(source: C) -> Self {
+        Self {
+            source,
+            retry: Retry::default(),
+            threshold: 5,
+            cooldown: Duration::from_secs(30),
+            breaker: Mutex::new(Breaker::default()),
+        }
+    }
+
+    /// Retry failed lookups.
+    pub fn retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Open the circuit after `threshold` failed lookups in a row
+    /// (counting the retries of a lookup as one) and keep it open for
+    /// the `cooldown`.
+    pub fn breaker(mut self, threshold: u32, cooldown: Duration) -> Self {
+        self.threshold = threshold.max(1);
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Whether lookups currently fail without asking the source.
+    pub fn is_open(&self) -> bool {
+        matches!(self.breaker.lock().unwrap().open_until, Some(until) if Instant::now() < until)
+    }
+
+    fn open_error(&self) -> Error {
+        Error::Remote(format!("{}: circuit open", self.source.source_name()))
+    }
+
+    fn record(&self, ok: bool) {
+        let mut breaker = self.breaker.lock().unwrap();
+        match ok {
+            true => *breaker = Breaker::default(),
+            false => {
+                breaker.failures += 1;
+                if breaker.failures >= self.threshold {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(
+                        source = %self.source.source_name(),
+                        failures = breaker.failures,
+                        "circuit opened"
+                    );
+                    breaker.open_until = Some(Instant::now() + self.cooldown);
+                }
+            }
+        }
+    }
+}
+
+impl<C: Config> Config for Resilient<C> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.lookup(key).ok().flatten()
+    }
+
+    fn lookup(&self, key: &str) -> Result<Option<String>, Error> {
+        if self.is_open() {
+            return Err(self.open_error());
+        }
+        let mut backoff = self.retry.backoff;
+        let mut attempt = 1;
+        loop {
+            match self.source.lookup(key) {
+                Ok(value) => {
+                    self.record(true);
+                    return Ok(value);
+                }
+                Err(e) if attempt >= self.retry.attempts => {
+                    self.record(false);
+                    return Err(e);
+                }
+                Err(_) => {
+                    thread::sleep(backoff);
+                    backoff = (backoff * 2).min(self.retry.max_backoff);
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.source.keys()
+    }
+
+    fn similar_keys(&self, key: &str) -> Vec<String> {
+        self.source.similar_keys(key)
+    }
+
+    /// Down while the circuit is open.
+    fn source_health(&self) -> SourceHealth {
+        match self.is_open() {
+            true => SourceHealth::Down(self.open_error().to_string()),
+            false => self.source.source_health(),
+        }
+    }
+
+    fn source_name(&self) -> String {
+        self.source.source_name()
+    }
+
+    fn source_of(&self, key: &str) -> Option<String> {
+        self.get_ref(key).map(|_| self.source_name())
+    }
+}
+
+impl<C: Config> fmt::Debug for Resilient<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Resilient")
+            .field("source", &self.source.source_name())
+            .field("retry", &self.retry)
+            .field("threshold", &self.threshold)
+            .field("cooldown", &self.cooldown)
+            .field("open", &self.is_open())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::resilient::{Resilient, Retry};
+    use crate::{Config, Error, MultiConfig};
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    /// Fails while `down` is set, counting the lookups.
+    struct Flaky {
+        down: Arc<AtomicU32>,
+        calls: Arc<AtomicU32>,
+    }
+
+    impl Config for Flaky {
+        fn get(&self, key: &str) -> Option<String> {
+            self.lookup(key).ok().flatten()
+        }
+
+        fn lookup(&self, key: &str) -> Result<Option<String>, Error> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match self.down.load(Ordering::SeqCst) {
+                0 => Ok(Some(format!("remote {}", key))),
+                _ => Err(Error::Remote("unreachable".to_string())),
+            }
+        }
+    }
+
+    #[test]
+    fn resilient() {
+        let down = Arc::new(AtomicU32::new(1));
+        let calls = Arc::new(AtomicU32::new(0));
+        let source = Flaky {
+            down: down.clone(),
+            calls: calls.clone(),
+        };
+        let remote = Resilient::new(source)
+            .retry(Retry::new(3).backoff(Duration::from_millis(1), Duration::from_millis(2)))
+            .breaker(2, Duration::from_millis(50));
+        let cfg = MultiConfig::new(vec![
+            Box::new(remote),
+            Box::new(HashMap::from([("a", "local")])),
+        ]);
+
+        // Each lookup is tried 3 times, then the circuit opens after
+        // 2 failed lookups and the source isn't asked any more.
+        assert_eq!(cfg.get("a"), Some("local".to_string()));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(cfg.get("a"), Some("local".to_string()));
+        assert_eq!(calls.load(Ordering::SeqCst), 6);
+        assert_eq!(cfg.get("a"), Some("local".to_string()));
+        assert_eq!(calls.load(Ordering::SeqCst), 6);
+
+        // After the cooldown a lookup gets through and closes it.
+        down.store(0, Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(60));
+        assert_eq!(cfg.get("a"), Some("remote a".to_string()));
+        assert_eq!(calls.load(Ordering::SeqCst), 7);
+    }
+
+    #[test]
+    fn open() {
+        let source = Flaky {
+            down: Arc::new(AtomicU32::new(1)),
+            calls: Arc::new(AtomicU32::new(0)),
+        };
+        let remote = Resilient::new(source).breaker(1, Duration::from_secs(60));
+        assert_eq!(
+            remote.lookup("a"),
+            Err(Error::Remote("unreachable".to_string()))
+        );
+        assert!(remote.is_open());
+        assert_eq!(
+            remote.lookup("a"),
+            Err(Error::Remote("Flaky: circuit open".to_string()))
+        );
+        assert!(remote.health().is_err());
+    }
+}
@@ -0,0 +1,138 @@
+//! A config limited to some keys, for code that shouldn't see the
+//! rest (e.g. credentials).
+
+use std::borrow::Cow;
+
+use crate::{Config, Value, ValueError};
+
+/// Only exposes the keys starting with one of the allowed prefixes
+/// (e.g. `plugins.cache.`) of a borrowed config. Everything else is
+/// missing from `get` and `keys`, and the fallible getters return
+/// `ValueError::AccessDenied` for it.
+///
+/// ```
+/// use dinglebit_config::{Config, Restricted, ValueError};
+/// use std::collections::HashMap;
+///
+/// let cfg = HashMap::from([("plugins.cache.size", "10"), ("db.password", "hunter2")]);
+/// let plugin = Restricted::new(&cfg, &["plugins.cache."]);
+/// assert_eq!(plugin.int("plugins.cache.size"), 10);
+/// assert_eq!(plugin.get("db.password"), None);
+/// assert_eq!(
+///     plugin.try_get("db.password"),
+///     Err(ValueError::AccessDenied("db.password".to_string()))
+/// );
+/// ```
+pub struct Restricted<'a> {
+    config: &'a dyn Config,
+    prefixes: Vec<String>,
+}
+
+impl<'a> Restricted<'a> {
+    /// Restrict the config to the keys starting with the given
+    /// prefixes. A prefix that is a whole key (e.g. `db.host`) allows
+    /// just that key and the keys under it (e.g. `db.host.0`), not
+    /// other keys that happen to start the same (e.g. `db.hostname`).
+    pub fn new(config: &'a dyn Config, prefixes: &[&str]) -> Self {
+        Self {
+            config,
+            prefixes: prefixes.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    /// Whether the key may be read.
+    pub fn allowed(&self, key: &str) -> bool {
+        self.prefixes
+            .iter()
+            .any(|p| match key.strip_prefix(p.as_str()) {
+                Some(rest) => rest.is_empty() || p.ends_with('.') || rest.starts_with('.'),
+                None => false,
+            })
+    }
+}
+
+impl Config for Restricted<'_> {
+    fn get(&self, key: &str) -> Option<String> {
+        match self.allowed(key) {
+            true => self.config.get(key),
+            false => None,
+        }
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        match self.allowed(key) {
+            true => self.config.get_ref(key),
+            false => None,
+        }
+    }
+
+    fn get_value(&self, key: &str) -> Option<Value> {
+        match self.allowed(key) {
+            true => self.config.get_value(key),
+            false => None,
+        }
+    }
+
+    fn try_get_ref(&self, key: &str) -> Result<Cow<'_, str>, ValueError> {
+        match self.allowed(key) {
+            true => self.config.try_get_ref(key),
+            false => Err(ValueError::AccessDenied(key.to_string())),
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        let mut keys = self.config.keys();
+        keys.retain(|k| self.allowed(k));
+        keys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Config, Restricted, ValueError};
+    use std::collections::HashMap;
+
+    #[test]
+    fn restricted() {
+        let cfg = HashMap::from([
+            ("plugins.cache.size", "10"),
+            ("plugins.cache.hosts", "[a, b]"),
+            ("plugins.other.token", "secret"),
+            ("db.host", "localhost"),
+            ("db.hostname", "db1"),
+            ("db.password", "hunter2"),
+        ]);
+        let r = Restricted::new(&cfg, &["plugins.cache.", "db.host"]);
+
+        let mut keys = r.keys();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec!["db.host", "plugins.cache.hosts", "plugins.cache.size"]
+        );
+        assert_eq!(r.get("db.hostname"), None);
+        assert_eq!(
+            r.try_get("db.hostname"),
+            Err(ValueError::AccessDenied("db.hostname".to_string()))
+        );
+        assert_eq!(r.string("plugins.cache.hosts.1"), "b");
+        assert_eq!(r.get("plugins.other.token"), None);
+        assert_eq!(
+            r.try_int("db.password"),
+            Err(ValueError::AccessDenied("db.password".to_string()))
+        );
+        assert_eq!(
+            r.try_get("plugins.cache.missing"),
+            Err(ValueError::Missing("plugins.cache.missing".to_string()))
+        );
+        // Keys that can't be read aren't suggested.
+        assert_eq!(
+            r.try_get("plugins.cache.hots")
+                .map_err(|e| e.with_suggestions(&r)),
+            Err(ValueError::Misspelled {
+                key: "plugins.cache.hots".to_string(),
+                suggestions: vec!["plugins.cache.hosts".to_string()],
+            })
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! Configuration computed by a Rhai script from the layers below it.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+
+use ::rhai::{Dynamic, Engine};
+
+use crate::format::read_file;
+use crate::value::Value;
+use crate::{redact, Config, Error};
+
+/// The values of a map returned by a Rhai script, for values that are
+/// easier to compute than to write down (e.g. the number of workers
+/// from the number of cores). Nested maps are flattened into
+/// dot-notation keys and arrays are rendered using the syntax
+/// `Config::list` expects.
+///
+/// Besides the standard library, the script can call:
+///
+/// - `get(key)`: the value of the key in the lower layers as a string,
+///   or `()` if there isn't one.
+/// - `cpu_count()`: the number of CPUs the process can use.
+///
+/// ```
+/// use dinglebit_config::{Config, MultiConfig, Rhai};
+/// use std::collections::HashMap;
+///
+/// let lower = HashMap::from([("workers.per_cpu", "2")]);
+/// let computed = Rhai::from_str(
+///     r#"#{ workers: #{ count: cpu_count() * parse_int(get("workers.per_cpu")) } }"#,
+///     &lower,
+/// )
+/// .unwrap();
+/// let cfg = MultiConfig::new(vec![Box::new(computed), Box::new(lower)]);
+/// assert!(cfg.int("workers.count") >= 2);
+/// ```
+///
+/// The script is run once, when it's loaded, against the keys the
+/// lower layers list at that time.
+#[derive(PartialEq, Clone)]
+pub struct Rhai {
+    values: HashMap<String, String>,
+}
+
+impl Rhai {
+    /// Run the given script with access to the values of `lower`. The
+    /// script must return a map.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(script: &str, lower: &dyn Config) -> Result<Self, Error> {
+        let values: Arc<BTreeMap<String, String>> = Arc::new(
+            lower
+                .keys()
+                .into_iter()
+                .filter_map(|k| lower.get(&k).map(|v| (k, v)))
+                .collect(),
+        );
+
+        let mut engine = Engine::new();
+        engine.register_fn("get", move |key: &str| match values.get(key) {
+            Some(v) => Dynamic::from(v.clone()),
+            None => Dynamic::UNIT,
+        });
+        engine.register_fn("cpu_count", || {
+            std::thread::available_parallelism().map_or(1, |n| n.get()) as i64
+        });
+
+        let result: Dynamic = engine
+            .eval(script)
+            .map_err(|e| Error::Eval(format!("rhai: {}", e)))?;
+        match to_value(result) {
+            v @ Value::Map(_) => Ok(Self {
+                values: v.flatten(),
+            }),
+            _ => Err(Error::Eval("rhai script didn't return a map".to_string())),
+        }
+    }
+
+    /// Similar to `from_str` except that the script is read from the
+    /// given path.
+    pub fn from_file(path: &str, lower: &dyn Config) -> Result<Self, Error> {
+        Self::from_str(&read_file(path)?, lower)
+    }
+}
+
+fn to_value(d: Dynamic) -> Value {
+    if d.is_unit() {
+        return Value::Null;
+    }
+    if let Some(b) = d.clone().try_cast::<bool>() {
+        return Value::Bool(b);
+    }
+    if let Some(i) = d.clone().try_cast::<i64>() {
+        return Value::Int(i);
+    }
+    if let Some(f) = d.clone().try_cast::<f64>() {
+        return Value::Float(f);
+    }
+    if d.is_array() {
+        let items = d.into_array().unwrap_or_default();
+        return Value::List(items.into_iter().map(to_value).collect());
+    }
+    if d.is_map() {
+        let map = d.cast::<::rhai::Map>();
+        return Value::Map(
+            map.into_iter()
+                .map(|(k, v)| (k.to_string(), to_value(v)))
+                .collect(),
+        );
+    }
+    Value::String(d.to_string())
+}
+
+impl Config for Rhai {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values.get(key).map(|value| value.to_string())
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.values
+            .get(key)
+            .map(|value| Cow::Borrowed(value.as_str()))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+}
+
+impl fmt::Debug for Rhai {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redact::debug(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rhai::Rhai;
+    use crate::{Config, Error};
+    use std::collections::HashMap;
+
+    #[test]
+    fn from_str() {
+        let lower = HashMap::from([("pool.base", "4"), ("region", "eu")]);
+        let cfg = Rhai::from_str(
+            r#"
+            let base = parse_int(get("pool.base"));
+            #{
+                pool: #{ max: base * 4, ratio: 0.5 },
+                hosts: ["a." + get("region"), "b." + get("region")],
+                verbose: get("verbose") == (),
+                cpus: cpu_count(),
+            }
+            "#,
+            &lower,
+        )
+        .unwrap();
+        assert_eq!(cfg.int("pool.max"), 16);
+        assert_eq!(cfg.float("pool.ratio"), 0.5);
+        assert_eq!(cfg.list("hosts"), vec!["a.eu", "b.eu"]);
+        assert!(cfg.bool("verbose"));
+        assert!(cfg.int("cpus") >= 1);
+
+        assert!(matches!(
+            Rhai::from_str("40 + 2", &lower),
+            Err(Error::Eval(_))
+        ));
+        assert!(matches!(
+            Rhai::from_str("#{ a: ", &lower),
+            Err(Error::Eval(_))
+        ));
+    }
+}
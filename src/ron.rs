@@ -0,0 +1,149 @@
+//! Configuration from RON (Rusty Object Notation) documents.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use ::ron::value::Number;
+
+use crate::format::read_file;
+use crate::value::Value;
+use crate::{redact, Config, Error};
+
+#[derive(PartialEq, Clone)]
+pub struct Ron {
+    root: Value,
+    values: HashMap<String, String>,
+}
+
+impl Ron {
+    /// Create a new configuration from the given RON document. The
+    /// document must be a struct or map and is flattened into
+    /// dot-notation keys, so `(mongo: (uri: "..."))` is available as
+    /// `mongo.uri`. Sequences are rendered using the syntax
+    /// `Config::list` expects and `None`/`()` values are skipped.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Self, Error> {
+        let root = match ::ron::from_str::<::ron::Value>(s) {
+            Ok(v) => convert(v),
+            Err(e) => return Err(Error::Parse(e.to_string())),
+        };
+        match root {
+            Value::Map(_) => Ok(Self {
+                values: root.flatten(),
+                root,
+            }),
+            _ => Err(Error::Parse("expected a struct or map".to_string())),
+        }
+    }
+
+    /// Similar to `from_str` except that the given path is used as
+    /// the contents for the string to parse.
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let file = read_file(path)?;
+        Self::from_str(&file)
+    }
+}
+
+fn convert(v: ::ron::Value) -> Value {
+    match v {
+        ::ron::Value::Bool(b) => Value::Bool(b),
+        ::ron::Value::Char(c) => Value::String(c.to_string()),
+        ::ron::Value::String(s) => Value::String(s),
+        ::ron::Value::Bytes(b) => Value::String(String::from_utf8_lossy(&b).to_string()),
+        ::ron::Value::Unit | ::ron::Value::Option(None) => Value::Null,
+        ::ron::Value::Option(Some(v)) => convert(*v),
+        ::ron::Value::Seq(l) => Value::List(l.into_iter().map(convert).collect()),
+        ::ron::Value::Map(m) => Value::Map(
+            m.into_iter()
+                .map(|(k, v)| (convert(k).to_string(), convert(v)))
+                .collect::<BTreeMap<String, Value>>(),
+        ),
+        ::ron::Value::Number(n) => match n {
+            Number::I8(i) => Value::Int(i as i64),
+            Number::I16(i) => Value::Int(i as i64),
+            Number::I32(i) => Value::Int(i as i64),
+            Number::I64(i) => Value::Int(i),
+            Number::U8(i) => Value::Int(i as i64),
+            Number::U16(i) => Value::Int(i as i64),
+            Number::U32(i) => Value::Int(i as i64),
+            Number::U64(i) if i <= i64::MAX as u64 => Value::Int(i as i64),
+            n => Value::Float(n.into_f64()),
+        },
+    }
+}
+
+impl Config for Ron {
+    /// Elements of arrays are available by index (e.g.
+    /// `servers.0.host`).
+    fn get(&self, key: &str) -> Option<String> {
+        match self.values.get(key) {
+            Some(value) => Some(value.to_string()),
+            None => self.root.lookup(key).and_then(Value::rendered),
+        }
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        match self.values.get(key) {
+            Some(value) => Some(Cow::Borrowed(value.as_str())),
+            None => self
+                .root
+                .lookup(key)
+                .and_then(Value::rendered)
+                .map(Cow::Owned),
+        }
+    }
+
+    fn get_value(&self, key: &str) -> Option<Value> {
+        self.root.lookup(key).cloned()
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+}
+
+impl fmt::Debug for Ron {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redact::debug(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ron::Ron;
+    use crate::{Config, Error};
+
+    #[test]
+    fn parse() {
+        let cfg = Ron::from_str(
+            r#"
+            // a comment
+            Settings(
+                mongo: (uri: "mongodb://localhost/", port: 27017),
+                ratio: 0.5,
+                debug: true,
+                tags: ["a", "b"],
+                missing: None,
+                named: Some('x'),
+                lookup: {"a": 1},
+            )
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            cfg.get("mongo.uri"),
+            Some("mongodb://localhost/".to_string())
+        );
+        assert_eq!(cfg.int("mongo.port"), 27017);
+        assert_eq!(cfg.float("ratio"), 0.5);
+        assert!(cfg.bool("debug"));
+        assert_eq!(cfg.list("tags"), vec!["a", "b"]);
+        assert_eq!(cfg.get("missing"), None);
+        assert_eq!(cfg.get("named"), Some("x".to_string()));
+        assert_eq!(cfg.get("lookup.a"), Some("1".to_string()));
+
+        assert!(matches!(Ron::from_str("[1, 2]"), Err(Error::Parse(_))));
+        assert!(matches!(Ron::from_str("(a: "), Err(Error::Parse(_))));
+    }
+}
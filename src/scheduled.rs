@@ -0,0 +1,220 @@
+//! Overrides that only apply at certain times.
+
+use std::borrow::Cow;
+
+use chrono::{DateTime, NaiveTime, Utc};
+
+use crate::{Config, SourceHealth};
+
+type Clock = Box<dyn Fn() -> DateTime<Utc> + Send + Sync>;
+
+/// When an override applies.
+enum Window {
+    /// Every day from `start` until `end` (UTC), wrapping past midnight
+    /// if `end` is before `start`.
+    Daily { start: NaiveTime, end: NaiveTime },
+
+    /// From `from` (if set) until `until` (if set).
+    Dated {
+        from: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    },
+}
+
+impl Window {
+    fn contains(&self, now: DateTime<Utc>) -> bool {
+        match self {
+            Window::Daily { start, end } => {
+                let t = now.time();
+                match start <= end {
+                    true => *start <= t && t < *end,
+                    false => *start <= t || t < *end,
+                }
+            }
+            Window::Dated { from, until } => {
+                from.map_or(true, |f| f <= now) && until.map_or(true, |u| now < u)
+            }
+        }
+    }
+}
+
+/// Wraps a config with layers of overrides that only apply during
+/// their window (e.g. a maintenance flag every night, or a setting
+/// that expires after a launch). The windows are checked against the
+/// clock on every lookup, so nothing needs to be reloaded when one
+/// opens or closes. When several windows are open, the override added
+/// first wins.
+///
+/// ```
+/// use chrono::{NaiveTime, TimeZone, Utc};
+/// use dinglebit_config::{Config, Scheduled};
+/// use std::collections::HashMap;
+///
+/// let cfg = Scheduled::new(HashMap::from([("maintenance", "false")]))
+///     .daily(
+///         NaiveTime::from_hms_opt(2, 0, 0).unwrap(),
+///         NaiveTime::from_hms_opt(3, 0, 0).unwrap(),
+///         HashMap::from([("maintenance", "true")]),
+///     )
+///     .until(
+///         Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap(),
+///         HashMap::from([("banner", "Happy new year!")]),
+///     );
+/// assert!(cfg.get("banner").is_none());
+/// ```
+pub struct Scheduled<C> {
+    config: C,
+    overrides: Vec<(Window, Box<dyn Config + Send + Sync>)>,
+    clock: Clock,
+}
+
+impl<C: Config> Scheduled<C> {
+    /// Wrap the given config, without any overrides yet.
+    pub fn new(config: C) -> Self {
+        Self {
+            config,
+            overrides: vec![],
+            clock: Box::new(Utc::now),
+        }
+    }
+
+    /// Apply the layer every day from `start` until `end` (UTC). If
+    /// `end` is before `start`, the window runs past midnight.
+    pub fn daily<L>(self, start: NaiveTime, end: NaiveTime, layer: L) -> Self
+    where
+        L: Config + Send + Sync + 'static,
+    {
+        self.add(Window::Daily { start, end }, layer)
+    }
+
+    /// Apply the layer from `from` until `until`.
+    pub fn between<L>(self, from: DateTime<Utc>, until: DateTime<Utc>, layer: L) -> Self
+    where
+        L: Config + Send + Sync + 'static,
+    {
+        let (from, until) = (Some(from), Some(until));
+        self.add(Window::Dated { from, until }, layer)
+    }
+
+    /// Apply the layer from now until it expires at `until`.
+    pub fn until<L>(self, until: DateTime<Utc>, layer: L) -> Self
+    where
+        L: Config + Send + Sync + 'static,
+    {
+        let until = Some(until);
+        self.add(Window::Dated { from: None, until }, layer)
+    }
+
+    /// Use the given function instead of the system clock (e.g. in
+    /// tests).
+    pub fn clock<F>(mut self, clock: F) -> Self
+    where
+        F: Fn() -> DateTime<Utc> + Send + Sync + 'static,
+    {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    fn add<L>(mut self, window: Window, layer: L) -> Self
+    where
+        L: Config + Send + Sync + 'static,
+    {
+        self.overrides.push((window, Box::new(layer)));
+        self
+    }
+
+    /// The layers whose windows are open right now.
+    fn active(&self) -> impl Iterator<Item = &(dyn Config + Send + Sync)> {
+        let now = (self.clock)();
+        self.overrides
+            .iter()
+            .filter(move |(w, _)| w.contains(now))
+            .map(|(_, layer)| layer.as_ref())
+    }
+}
+
+impl<C: Config> Config for Scheduled<C> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.get_ref(key).map(|v| v.into_owned())
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.active()
+            .find_map(|layer| layer.get_ref(key))
+            .or_else(|| self.config.get_ref(key))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        let mut keys = self.config.keys();
+        for layer in self.active() {
+            for key in layer.keys() {
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        }
+        keys
+    }
+
+    fn source_health(&self) -> SourceHealth {
+        self.config.source_health()
+    }
+
+    fn source_name(&self) -> String {
+        self.config.source_name()
+    }
+
+    fn source_of(&self, key: &str) -> Option<String> {
+        match self.active().find(|layer| layer.get_ref(key).is_some()) {
+            Some(layer) => Some(layer.source_name()),
+            None => self.config.source_of(key),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Config, Scheduled};
+    use chrono::{DateTime, NaiveTime, TimeZone, Utc};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn windows() {
+        let at = |h, m| Utc.with_ymd_and_hms(2024, 6, 1, h, m, 0).unwrap();
+        let now: Arc<Mutex<DateTime<Utc>>> = Arc::new(Mutex::new(at(1, 0)));
+        let clock = now.clone();
+        let time = |h| NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+        let cfg = Scheduled::new(HashMap::from([("mode", "normal"), ("batch", "off")]))
+            .daily(time(2), time(3), HashMap::from([("mode", "maintenance")]))
+            .daily(time(22), time(4), HashMap::from([("batch", "on")]))
+            .between(
+                at(12, 0),
+                at(13, 0),
+                HashMap::from([("mode", "launch"), ("banner", "new!")]),
+            )
+            .until(at(1, 30), HashMap::from([("mode", "early")]))
+            .clock(move || *clock.lock().unwrap());
+
+        let set = |h, m| *now.lock().unwrap() = at(h, m);
+        assert_eq!(cfg.string("mode"), "early");
+        assert_eq!(cfg.string("batch"), "on");
+
+        set(2, 30);
+        assert_eq!(cfg.string("mode"), "maintenance");
+        set(3, 0);
+        assert_eq!(cfg.string("mode"), "normal");
+        assert_eq!(cfg.string("batch"), "on");
+        set(4, 0);
+        assert_eq!(cfg.string("batch"), "off");
+        assert!(cfg.get("banner").is_none());
+        assert!(!cfg.keys().contains(&"banner".to_string()));
+
+        set(12, 15);
+        assert_eq!(cfg.string("mode"), "launch");
+        assert_eq!(cfg.string("banner"), "new!");
+        assert!(cfg.keys().contains(&"banner".to_string()));
+        set(13, 0);
+        assert_eq!(cfg.string("mode"), "normal");
+    }
+}
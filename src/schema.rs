@@ -0,0 +1,483 @@
+//! Describe the keys an application expects and check a config
+//! against them.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::convert::{properties_escape, quote};
+use crate::redact::{self, WithSchema, REDACTED};
+use crate::{Config, Environment, Error, Format, ValueError};
+
+/// The types a value can be checked against. These match the typed
+/// getters of `Config`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Type {
+    String,
+    Int,
+    Float,
+    Bool,
+    Duration,
+    DateTime,
+    List,
+    Map,
+}
+
+impl Type {
+    /// The lowercase name of the type (e.g. `int`).
+    pub fn name(&self) -> &'static str {
+        match self {
+            Type::String => "string",
+            Type::Int => "int",
+            Type::Float => "float",
+            Type::Bool => "bool",
+            Type::Duration => "duration",
+            Type::DateTime => "datetime",
+            Type::List => "list",
+            Type::Map => "map",
+        }
+    }
+
+    /// The type with the given name, if there is one.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "string" => Some(Type::String),
+            "int" => Some(Type::Int),
+            "float" => Some(Type::Float),
+            "bool" => Some(Type::Bool),
+            "duration" => Some(Type::Duration),
+            "datetime" => Some(Type::DateTime),
+            "list" => Some(Type::List),
+            "map" => Some(Type::Map),
+            _ => None,
+        }
+    }
+
+    /// Check that the value of the given key can be read as this type.
+    pub fn check(&self, config: &dyn Config, key: &str) -> Result<(), ValueError> {
+        match self {
+            Type::String => config.try_get(key).map(|_| ()),
+            Type::Int => config.try_int(key).map(|_| ()),
+            Type::Float => config.try_float(key).map(|_| ()),
+            Type::Bool => config.try_bool(key).map(|_| ()),
+            Type::Duration => config.try_duration(key).map(|_| ()),
+            Type::DateTime => config.try_datetime(key).map(|_| ()),
+            Type::List => bracketed(config, key, '[', ']', "list"),
+            Type::Map => bracketed(config, key, '{', '}', "map"),
+        }
+    }
+}
+
+fn bracketed(
+    config: &dyn Config,
+    key: &str,
+    open: char,
+    close: char,
+    expected: &str,
+) -> Result<(), ValueError> {
+    let value = config.try_get(key)?;
+    let trimmed = value.trim();
+    match trimmed.starts_with(open) && trimmed.ends_with(close) {
+        true => Ok(()),
+        false => Err(ValueError::invalid(key, value, expected)),
+    }
+}
+
+/// A key the schema expects.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Field {
+    pub key: String,
+    pub kind: Type,
+    pub required: bool,
+    /// Whether a change to the value only takes effect after a restart
+    /// (e.g. the port to listen on).
+    pub restart_required: bool,
+    /// What the key is for, used when documenting it.
+    pub description: Option<String>,
+    /// The value used when the key isn't set, shown in a `scaffold`.
+    pub default: Option<String>,
+    /// Whether the value is a secret (see `Schema::sensitive`).
+    pub sensitive: bool,
+}
+
+/// The keys an application expects along with their types.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Schema {
+    fields: Vec<Field>,
+}
+
+impl Schema {
+    /// Create an empty schema.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a key that must be present and have the given type.
+    pub fn required(mut self, key: &str, kind: Type) -> Self {
+        self.fields.push(Field {
+            key: key.to_string(),
+            kind,
+            required: true,
+            restart_required: false,
+            description: None,
+            default: None,
+            sensitive: false,
+        });
+        self
+    }
+
+    /// Add a key that may be missing but must have the given type
+    /// when it's present.
+    pub fn optional(mut self, key: &str, kind: Type) -> Self {
+        self.fields.push(Field {
+            key: key.to_string(),
+            kind,
+            required: false,
+            restart_required: false,
+            description: None,
+            default: None,
+            sensitive: false,
+        });
+        self
+    }
+
+    /// Mark a key that has already been added as only taking effect
+    /// after a restart, so reloads can flag changes to it (see
+    /// `Change::requires_restart`).
+    pub fn restart_required(mut self, key: &str) -> Self {
+        for field in self.fields.iter_mut().filter(|f| f.key == key) {
+            field.restart_required = true;
+        }
+        self
+    }
+
+    /// Describe a key that has already been added, for documentation
+    /// such as `Environment::markdown`.
+    pub fn describe(mut self, key: &str, description: &str) -> Self {
+        for field in self.fields.iter_mut().filter(|f| f.key == key) {
+            field.description = Some(description.to_string());
+        }
+        self
+    }
+
+    /// Give a key that has already been added the value it has when
+    /// it isn't set, for `scaffold`. This is only documentation; use
+    /// `Defaults` to provide the value.
+    pub fn with_default(mut self, key: &str, value: &str) -> Self {
+        for field in self.fields.iter_mut().filter(|f| f.key == key) {
+            field.default = Some(value.to_string());
+        }
+        self
+    }
+
+    /// Mark a key that has already been added as holding a secret, for
+    /// secrets whose names don't give them away. Wherever the schema is
+    /// given, its value is redacted like those of keys named e.g.
+    /// `password`: in startup reports, the debug endpoint,
+    /// `Snapshot::display`, `Change::redacted_with` and `Schema::debug`.
+    /// Use `redact::mark_sensitive` to redact it everywhere instead.
+    ///
+    /// ```
+    /// use dinglebit_config::schema::{Schema, Type};
+    /// use dinglebit_config::{Config, Simple};
+    ///
+    /// let schema = Schema::new()
+    ///     .required("stripe.webhook", Type::String)
+    ///     .sensitive("stripe.webhook");
+    /// let cfg = Simple::from_str("stripe.webhook = whsec_1").unwrap();
+    /// assert!(!format!("{:?}", schema.debug(&cfg)).contains("whsec_1"));
+    /// ```
+    pub fn sensitive(mut self, key: &str) -> Self {
+        for field in self.fields.iter_mut().filter(|f| f.key == key) {
+            field.sensitive = true;
+        }
+        self
+    }
+
+    /// Whether the key holds a secret: the schema marks it as one or
+    /// its name gives it away (see `redact::is_sensitive`).
+    pub fn is_sensitive(&self, key: &str) -> bool {
+        self.fields.iter().any(|f| f.key == key && f.sensitive) || redact::is_sensitive(key)
+    }
+
+    /// The value of the key as it can be shown, like `redact::redact`
+    /// but with the keys the schema marks as secrets redacted too.
+    pub fn redact<'a>(&self, key: &str, value: &'a str) -> Cow<'a, str> {
+        match self.is_sensitive(key) {
+            true => Cow::Borrowed(REDACTED),
+            false => redact::redact(key, value),
+        }
+    }
+
+    /// The config for `Debug`, shown like sources show themselves but
+    /// with the keys the schema marks as secrets redacted too.
+    pub fn debug<'a, C: Config + ?Sized>(&'a self, config: &'a C) -> impl fmt::Debug + 'a {
+        WithSchema {
+            config,
+            schema: self,
+        }
+    }
+
+    /// Whether the key is marked as only taking effect after a
+    /// restart.
+    pub fn requires_restart(&self, key: &str) -> bool {
+        self.fields
+            .iter()
+            .any(|f| f.key == key && f.restart_required)
+    }
+
+    /// Create a schema from a config whose values are type names. A
+    /// trailing `?` makes the key optional and a trailing `!` marks it
+    /// as requiring a restart. For example:
+    ///
+    /// ```ini
+    /// mongo.uri = string!
+    /// mongo.pool = int?
+    /// debug = bool?
+    /// listen.port = int?!
+    /// ```
+    ///
+    /// The config must be able to list its keys.
+    pub fn from_config(config: &dyn Config) -> Result<Self, Error> {
+        let mut keys = config.keys();
+        keys.sort();
+
+        let mut schema = Schema::new();
+        for key in keys {
+            let value = match config.get(&key) {
+                Some(v) => v,
+                None => continue,
+            };
+            let (mut name, mut required, mut restart) = (value.trim(), true, false);
+            loop {
+                if let Some(rest) = name.strip_suffix('?') {
+                    (name, required) = (rest, false);
+                } else if let Some(rest) = name.strip_suffix('!') {
+                    (name, restart) = (rest, true);
+                } else {
+                    break;
+                }
+            }
+            let kind = match Type::from_name(name) {
+                Some(kind) => kind,
+                None => {
+                    return Err(Error::Parse(format!(
+                        "unknown type '{}' for '{}'",
+                        value, key
+                    )))
+                }
+            };
+            schema = match required {
+                true => schema.required(&key, kind),
+                false => schema.optional(&key, kind),
+            };
+            if restart {
+                schema = schema.restart_required(&key);
+            }
+        }
+        Ok(schema)
+    }
+
+    /// The fields of the schema in the order they were added.
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Write a starter config file in the given format (e.g. for an
+    /// `init` command), with a comment describing each key. Keys with
+    /// a default are set to it; the others are commented out, and the
+    /// required ones are marked `TODO` so `validate` reports them until
+    /// they're filled in.
+    ///
+    /// ```
+    /// use dinglebit_config::schema::{Schema, Type};
+    /// use dinglebit_config::Format;
+    ///
+    /// let schema = Schema::new()
+    ///     .required("db.uri", Type::String)
+    ///     .describe("db.uri", "Where the database is.")
+    ///     .optional("db.pool", Type::Int)
+    ///     .with_default("db.pool", "10");
+    /// let starter = schema.scaffold(Format::Simple).unwrap();
+    /// ```
+    ///
+    /// writes:
+    ///
+    /// ```text
+    /// # Where the database is. (string, required)
+    /// # TODO db.uri =
+    ///
+    /// # int, optional
+    /// db.pool = 10
+    /// ```
+    ///
+    /// Only formats with line comments are supported: `Simple`, `Ini`,
+    /// `Env`, `Properties`, `Toml`, `Yaml` and `Hocon`. For the others
+    /// `Error::Unsupported` is returned.
+    pub fn scaffold(&self, format: Format) -> Result<String, Error> {
+        let line: fn(&str, &str) -> String = match format {
+            Format::Simple | Format::Ini => |k, v| format!("{} = {}", k, v),
+            Format::Env => {
+                |k, v| format!("{}={}", Environment::new("").var_name(k), quote(v, false))
+            }
+            Format::Properties => |k, v| {
+                format!(
+                    "{} = {}",
+                    properties_escape(k, true),
+                    properties_escape(v, false)
+                )
+            },
+            Format::Toml | Format::Hocon => |k, v| format!("{} = {}", k, quote(v, false)),
+            Format::Yaml => |k, v| format!("{}: {}", k, quote(v, false)),
+            f => return Err(Error::Unsupported(f.name().to_string())),
+        };
+        let entries: Vec<String> = self
+            .fields
+            .iter()
+            .map(|field| {
+                let about = format!(
+                    "{}, {}",
+                    field.kind.name(),
+                    if field.required {
+                        "required"
+                    } else {
+                        "optional"
+                    }
+                );
+                let mut out = match &field.description {
+                    Some(d) => format!("# {} ({})\n", d, about),
+                    None => format!("# {}\n", about),
+                };
+                match (&field.default, field.required) {
+                    (Some(v), _) => out.push_str(&line(&field.key, v)),
+                    (None, true) => {
+                        out.push_str(&format!("# TODO {}", line(&field.key, "").trim_end()))
+                    }
+                    (None, false) => {
+                        out.push_str(&format!("# {}", line(&field.key, "").trim_end()))
+                    }
+                }
+                out.push('\n');
+                out
+            })
+            .collect();
+        Ok(entries.join("\n"))
+    }
+
+    /// Check the given config against the schema, returning every
+    /// problem found rather than just the first one.
+    pub fn validate(&self, config: &dyn Config) -> Result<(), Vec<ValueError>> {
+        let errors: Vec<ValueError> = self
+            .fields
+            .iter()
+            .filter_map(|field| match field.kind.check(config, &field.key) {
+                Err(e) if e.is_missing() && !field.required => None,
+                Err(e) => Some(e),
+                Ok(()) => None,
+            })
+            .collect();
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::{Schema, Type};
+    use crate::{Config, Error, Format, Simple, ValueError};
+    use std::collections::HashMap;
+
+    #[test]
+    fn validate() {
+        let schema = Schema::from_config(
+            &Simple::from_str(
+                "mongo.uri = string\nmongo.pool = int?\ndebug = bool?\nstarted = datetime\ntags = list",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(schema.fields().len(), 5);
+        assert_eq!(schema.fields()[0].key, "debug");
+        assert!(!schema.fields()[0].required);
+
+        let good = HashMap::from([
+            ("mongo.uri", "mongodb://"),
+            ("started", "2020-01-01T00:00:00Z"),
+            ("tags", "[a, b]"),
+        ]);
+        assert_eq!(schema.validate(&good), Ok(()));
+
+        let bad = HashMap::from([("mongo.pool", "ten"), ("tags", "a, b")]);
+        let errors = schema.validate(&bad).unwrap_err();
+        assert_eq!(errors.len(), 4);
+        assert!(errors.contains(&ValueError::Missing("mongo.uri".to_string())));
+        assert!(errors.contains(&ValueError::Invalid {
+            key: "tags".to_string(),
+            value: "a, b".to_string(),
+            expected: "list".to_string(),
+        }));
+
+        assert!(!schema.requires_restart("debug"));
+
+        let schema = Schema::from_config(&HashMap::from([
+            ("listen.port", "int?!"),
+            ("db.uri", "string!"),
+            ("log.level", "string?"),
+        ]))
+        .unwrap();
+        assert!(schema.requires_restart("listen.port"));
+        assert!(schema.requires_restart("db.uri"));
+        assert!(!schema.requires_restart("log.level"));
+        assert!(!schema.requires_restart("missing"));
+
+        let schema = Schema::new().required("debug", Type::Bool);
+        assert!(schema.validate(&HashMap::from([("debug", "yes")])).is_ok());
+
+        assert!(matches!(
+            Schema::from_config(&HashMap::from([("a", "number")])),
+            Err(Error::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn scaffold() {
+        let schema = Schema::new()
+            .required("db.uri", Type::String)
+            .describe("db.uri", "Where the database is.")
+            .optional("log.level", Type::String)
+            .required("listen.port", Type::Int)
+            .with_default("listen.port", "8080");
+
+        let starter = schema.scaffold(Format::Simple).unwrap();
+        let cfg = Simple::from_str(&starter).unwrap();
+        assert_eq!(cfg.int("listen.port"), 8080);
+        assert_eq!(
+            schema.validate(&cfg),
+            Err(vec![ValueError::Missing("db.uri".to_string())])
+        );
+        assert!(
+            starter.starts_with("# Where the database is. (string, required)\n# TODO db.uri =\n")
+        );
+
+        assert_eq!(
+            schema.scaffold(Format::Env).unwrap(),
+            "# Where the database is. (string, required)\n\
+             # TODO DB_URI=\"\"\n\
+             \n\
+             # string, optional\n\
+             # LOG_LEVEL=\"\"\n\
+             \n\
+             # int, required\n\
+             LISTEN_PORT=\"8080\"\n"
+        );
+        assert!(schema
+            .scaffold(Format::Yaml)
+            .unwrap()
+            .ends_with("listen.port: \"8080\"\n"));
+        assert_eq!(
+            schema.scaffold(Format::Json),
+            Err(Error::Unsupported("json".to_string()))
+        );
+    }
+}
@@ -0,0 +1,473 @@
+//! A serde `Serializer` that turns any `Serialize` value into a
+//! `Value`, so structs can be flattened into dot-notation keys.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::ser::{self, Serialize};
+
+use crate::value::Value;
+
+#[derive(Debug)]
+pub(crate) struct SerError(pub(crate) String);
+
+impl fmt::Display for SerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for SerError {}
+
+impl ser::Error for SerError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        SerError(msg.to_string())
+    }
+}
+
+/// Serialize the given value. Structs and maps become `Value::Map`,
+/// sequences and tuples become `Value::List`, `None` and units become
+/// `Value::Null` and enum variants with data are maps keyed by the
+/// variant name.
+pub(crate) fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, SerError> {
+    value.serialize(ValueSerializer)
+}
+
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = SerError;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = SeqSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = MapSerializer;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, SerError> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, SerError> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, SerError> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, SerError> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, SerError> {
+        Ok(Value::Int(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Value, SerError> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, SerError> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, SerError> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, SerError> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, SerError> {
+        match v <= i64::MAX as u64 {
+            true => Ok(Value::Int(v as i64)),
+            false => Ok(Value::String(v.to_string())),
+        }
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value, SerError> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, SerError> {
+        Ok(Value::Float(v as f64))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, SerError> {
+        Ok(Value::Float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, SerError> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, SerError> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, SerError> {
+        Ok(Value::String(String::from_utf8_lossy(v).to_string()))
+    }
+
+    fn serialize_none(self) -> Result<Value, SerError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, SerError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, SerError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Value, SerError> {
+        Ok(Value::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, SerError> {
+        let mut m = BTreeMap::new();
+        m.insert(variant.to_string(), to_value(value)?);
+        Ok(Value::Map(m))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, SerError> {
+        Ok(SeqSerializer {
+            variant: None,
+            values: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, SerError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, SerError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, SerError> {
+        Ok(SeqSerializer {
+            variant: Some(variant),
+            values: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer, SerError> {
+        Ok(MapSerializer {
+            variant: None,
+            values: BTreeMap::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<MapSerializer, SerError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<MapSerializer, SerError> {
+        Ok(MapSerializer {
+            variant: Some(variant),
+            values: BTreeMap::new(),
+            key: None,
+        })
+    }
+}
+
+/// Wrap the value in a map keyed by the variant name, if there is one.
+fn wrap(variant: Option<&'static str>, value: Value) -> Value {
+    match variant {
+        Some(variant) => {
+            let mut m = BTreeMap::new();
+            m.insert(variant.to_string(), value);
+            Value::Map(m)
+        }
+        None => value,
+    }
+}
+
+struct SeqSerializer {
+    variant: Option<&'static str>,
+    values: Vec<Value>,
+}
+
+impl SeqSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.values.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, SerError> {
+        Ok(wrap(self.variant, Value::List(self.values)))
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value;
+    type Error = SerError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, SerError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value;
+    type Error = SerError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, SerError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value;
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, SerError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqSerializer {
+    type Ok = Value;
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, SerError> {
+        self.finish()
+    }
+}
+
+struct MapSerializer {
+    variant: Option<&'static str>,
+    values: BTreeMap<String, Value>,
+    key: Option<String>,
+}
+
+impl MapSerializer {
+    fn insert<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> Result<(), SerError> {
+        self.values.insert(key, to_value(value)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, SerError> {
+        Ok(wrap(self.variant, Value::Map(self.values)))
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Value;
+    type Error = SerError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerError> {
+        self.key = match to_value(key)? {
+            Value::List(_) | Value::Map(_) | Value::Null => {
+                return Err(SerError("map keys must be scalars".to_string()))
+            }
+            key => Some(key.to_string()),
+        };
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        match self.key.take() {
+            Some(key) => self.insert(key, value),
+            None => Err(SerError("map value without a key".to_string())),
+        }
+    }
+
+    fn end(self) -> Result<Value, SerError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = Value;
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerError> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<Value, SerError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for MapSerializer {
+    type Ok = Value;
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerError> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<Value, SerError> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ser::to_value;
+    use crate::value::Value;
+    use serde::Serialize;
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Serialize)]
+    enum Mode {
+        Fast,
+        Limited(u8),
+        Pair(i32, i32),
+        Custom { depth: u8 },
+    }
+
+    #[derive(Serialize)]
+    struct Unit;
+
+    #[derive(Serialize)]
+    struct Port(u16);
+
+    fn map(pairs: &[(&str, Value)]) -> Value {
+        Value::Map(
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect::<BTreeMap<_, _>>(),
+        )
+    }
+
+    #[test]
+    fn scalars() {
+        assert_eq!(to_value(&true).unwrap(), Value::Bool(true));
+        assert_eq!(to_value(&-3i8).unwrap(), Value::Int(-3));
+        assert_eq!(to_value(&0.5f32).unwrap(), Value::Float(0.5));
+        assert_eq!(to_value(&'x').unwrap(), Value::String("x".to_string()));
+        assert_eq!(to_value(&Some(1)).unwrap(), Value::Int(1));
+        assert_eq!(to_value(&None::<i32>).unwrap(), Value::Null);
+        assert_eq!(to_value(&()).unwrap(), Value::Null);
+        assert_eq!(to_value(&Unit).unwrap(), Value::Null);
+        assert_eq!(to_value(&Port(80)).unwrap(), Value::Int(80));
+
+        // Integers that don't fit an i64 are kept as strings.
+        assert_eq!(
+            to_value(&u64::MAX).unwrap(),
+            Value::String("18446744073709551615".to_string())
+        );
+        assert_eq!(
+            to_value(&i128::MIN).unwrap(),
+            Value::String(i128::MIN.to_string())
+        );
+        assert_eq!(to_value(&7u64).unwrap(), Value::Int(7));
+    }
+
+    #[test]
+    fn enums() {
+        assert_eq!(
+            to_value(&Mode::Fast).unwrap(),
+            Value::String("Fast".to_string())
+        );
+        assert_eq!(
+            to_value(&Mode::Limited(3)).unwrap(),
+            map(&[("Limited", Value::Int(3))])
+        );
+        assert_eq!(
+            to_value(&Mode::Pair(1, 2)).unwrap(),
+            map(&[("Pair", Value::List(vec![Value::Int(1), Value::Int(2)]))])
+        );
+        assert_eq!(
+            to_value(&Mode::Custom { depth: 2 }).unwrap(),
+            map(&[("Custom", map(&[("depth", Value::Int(2))]))])
+        );
+    }
+
+    #[test]
+    fn collections() {
+        assert_eq!(
+            to_value(&vec!["a", "b"]).unwrap(),
+            Value::List(vec![
+                Value::String("a".to_string()),
+                Value::String("b".to_string())
+            ])
+        );
+        assert_eq!(
+            to_value(&(1, "a")).unwrap(),
+            Value::List(vec![Value::Int(1), Value::String("a".to_string())])
+        );
+        assert_eq!(
+            to_value(&HashMap::from([(1, true)])).unwrap(),
+            map(&[("1", Value::Bool(true))])
+        );
+
+        let e = to_value(&HashMap::from([((1, 2), 3)])).unwrap_err();
+        assert_eq!(e.to_string(), "map keys must be scalars");
+        assert!(to_value(&HashMap::from([(None::<i32>, 3)])).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+//! Splitting command lines into words the way a POSIX shell does.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Split the command line into words. Words are separated by
+/// whitespace; single quotes keep everything up to the next single
+/// quote; double quotes keep everything up to the next unescaped double
+/// quote, where a backslash escapes `"`, `\`, `$` and `` ` ``; and a
+/// backslash outside quotes escapes the next character. Returns `None`
+/// if a quote isn't closed or the line ends with a backslash.
+pub(crate) fn split(s: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if let Some(w) = word.take() {
+                    words.push(w);
+                }
+            }
+            '\'' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => w.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            c @ ('"' | '\\' | '$' | '`') => w.push(c),
+                            '\n' => {}
+                            c => {
+                                w.push('\\');
+                                w.push(c);
+                            }
+                        },
+                        c => w.push(c),
+                    }
+                }
+            }
+            '\\' => match chars.next()? {
+                '\n' => {}
+                c => word.get_or_insert_with(String::new).push(c),
+            },
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if let Some(w) = word {
+        words.push(w);
+    }
+    Some(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::split;
+
+    #[test]
+    fn words() {
+        assert_eq!(
+            split("notify-send  'Build done' --icon=/opt/My\\ Apps/icon.png"),
+            Some(vec![
+                "notify-send".to_string(),
+                "Build done".to_string(),
+                "--icon=/opt/My Apps/icon.png".to_string(),
+            ])
+        );
+        assert_eq!(
+            split(r#""C:\Program Files\app.exe" "say \"hi\"" '' a"b"'c'"#),
+            Some(vec![
+                r"C:\Program Files\app.exe".to_string(),
+                r#"say "hi""#.to_string(),
+                "".to_string(),
+                "abc".to_string(),
+            ])
+        );
+        assert_eq!(split("  "), Some(vec![]));
+        assert_eq!(split("echo 'unterminated"), None);
+        assert_eq!(split("echo \"unterminated"), None);
+        assert_eq!(split("echo \\"), None);
+    }
+}
@@ -0,0 +1,86 @@
+//! Verification of config files against detached SSH signatures, so
+//! tampered config is detected before it's loaded.
+//!
+//! Signatures are the ones made by `ssh-keygen -Y sign -f key -n file
+//! app.cfg`, stored next to the file as `app.cfg.sig`.
+
+use std::fs::read_to_string;
+
+use ssh_key::{PublicKey, SshSig};
+
+use crate::Error;
+
+/// The namespace `ssh-keygen -Y sign` uses for files by default.
+const NAMESPACE: &str = "file";
+
+/// Read the file at the given path after checking its signature
+/// (`<path>.sig`) against the public key, given in OpenSSH format
+/// (e.g. `ssh-ed25519 AAAA... deploy`). A missing signature is an
+/// `Error::File` and one that doesn't match is `Error::Signature`.
+pub fn read_verified(path: &str, public_key: &str) -> Result<String, Error> {
+    let key = PublicKey::from_openssh(public_key.trim())
+        .map_err(|e| Error::Signature(format!("invalid public key: {}", e)))?;
+    let contents = read_to_string(path).map_err(|e| Error::file(path, &e))?;
+    let sig_path = format!("{}.sig", path);
+    let sig = read_to_string(&sig_path).map_err(|e| Error::file(&sig_path, &e))?;
+    let sig =
+        SshSig::from_pem(sig).map_err(|e| Error::Signature(format!("invalid signature: {}", e)))?;
+    match key.verify(NAMESPACE, contents.as_bytes(), &sig) {
+        Ok(()) => Ok(contents),
+        Err(e) => Err(Error::Signature(format!("{}: {}", path, e))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::signing::{read_verified, NAMESPACE};
+    use crate::testing::TempDir;
+    use crate::{Config, Error, Simple};
+    use ssh_key::private::Ed25519Keypair;
+    use ssh_key::{HashAlg, LineEnding, PrivateKey};
+    use std::fs;
+
+    #[test]
+    fn verified() {
+        let key = PrivateKey::from(Ed25519Keypair::from_seed(&[3; 32]));
+        let public = key.public_key().to_openssh().unwrap();
+        let other = PrivateKey::from(Ed25519Keypair::from_seed(&[4; 32]));
+
+        let dir = TempDir::new("signing");
+        let path = dir.join("app.cfg");
+        let path = path.to_str().unwrap();
+        let contents = "foo = bar\n";
+        fs::write(path, contents).unwrap();
+        assert!(matches!(
+            read_verified(path, &public),
+            Err(Error::File { .. })
+        ));
+
+        let sign = |key: &PrivateKey, msg: &str| {
+            let sig = key
+                .sign(NAMESPACE, HashAlg::Sha512, msg.as_bytes())
+                .unwrap();
+            fs::write(format!("{}.sig", path), sig.to_pem(LineEnding::LF).unwrap()).unwrap();
+        };
+
+        sign(&key, contents);
+        let cfg = Simple::from_file_verified(path, &public).unwrap();
+        assert_eq!(cfg.get("foo"), Some("bar".to_string()));
+
+        // Signed by someone else or for different contents.
+        sign(&other, contents);
+        assert!(matches!(
+            Simple::from_file_verified(path, &public),
+            Err(Error::Signature(_))
+        ));
+        sign(&key, "foo = baz\n");
+        assert!(matches!(
+            read_verified(path, &public),
+            Err(Error::Signature(_))
+        ));
+        assert!(matches!(
+            read_verified(path, "not a key"),
+            Err(Error::Signature(_))
+        ));
+    }
+}
@@ -1,58 +1,122 @@
 //! Extremely simplistic configuration from a file or string.
 
-use std::collections::HashMap;
-use std::fs::read_to_string;
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
 
-use crate::Config;
+use crate::format::read_file;
+use crate::hash::FnvHashMap;
+use crate::{element, redact, Config};
 
-#[derive(Debug, PartialEq)]
+#[derive(PartialEq, Clone)]
 pub struct Simple {
-    values: HashMap<String, String>,
+    /// The pairs in the order their keys first appear, so listing and
+    /// saving them is deterministic.
+    pairs: Vec<(String, String)>,
+    /// The position of each key in `pairs`. Keys are kept whole rather
+    /// than as interned prefix segments: `iter` lends them out as
+    /// `&str` and `get_ref` finds them with a single hash, and sharing
+    /// one allocation between `pairs` and `index` measured slower to
+    /// parse than copying.
+    index: FnvHashMap<String, usize>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Error {
-    File(String),
+    /// A file couldn't be read. The kind tells apart e.g. a file that
+    /// doesn't exist (`io::ErrorKind::NotFound`) from one that can't be
+    /// read (`io::ErrorKind::PermissionDenied`).
+    File {
+        path: PathBuf,
+        kind: io::ErrorKind,
+    },
     InvalidKeyValuePair,
+    Parse(String),
+    Eval(String),
+    Unsupported(String),
+    /// A file's signature or the public key is malformed, or the
+    /// signature doesn't match the file's contents.
+    Signature(String),
+    /// A remote source couldn't be reached or didn't answer with
+    /// configuration.
+    Remote(String),
+    /// A source took longer to answer than allowed (see `Timeout`).
+    Timeout(String),
+    /// A value couldn't be turned into configuration, e.g. a struct
+    /// given to `Defaults::from_struct` that isn't shaped like a map.
+    Serialize(String),
+    /// A source can't provide values, with the reason its
+    /// `source_health` gives (see `Config::health`).
+    Unhealthy(String),
 }
 
-fn parse_line(line: &str) -> Result<Option<(String, String)>, Error> {
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::File { path, kind } => write!(f, "{}: {}", path.display(), kind),
+            Error::InvalidKeyValuePair => write!(f, "invalid key/value pair"),
+            Error::Parse(m) => write!(f, "parse error: {}", m),
+            Error::Eval(m) => write!(f, "evaluation failed: {}", m),
+            Error::Unsupported(m) => write!(f, "unsupported: {}", m),
+            Error::Signature(m) => write!(f, "bad signature: {}", m),
+            Error::Remote(m) => write!(f, "remote source failed: {}", m),
+            Error::Timeout(m) => write!(f, "timed out: {}", m),
+            Error::Serialize(m) => write!(f, "can't serialize: {}", m),
+            Error::Unhealthy(m) => write!(f, "unhealthy: {}", m),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    pub(crate) fn file<P: AsRef<Path>>(path: P, e: &io::Error) -> Self {
+        Error::File {
+            path: path.as_ref().to_path_buf(),
+            kind: e.kind(),
+        }
+    }
+}
+
+fn parse_line(line: &str) -> Result<Option<(&str, &str)>, Error> {
     // Cleanup and check for comments
     let line = line.trim();
-    if line.starts_with("#") {
-        return Ok(None);
-    } else if line.len() < 1 {
+    if line.starts_with('#') || line.is_empty() {
         return Ok(None);
     }
 
-    // Split by the equal sign. Expect exactly two.
-    let parts: Vec<&str> = line.splitn(2, "=").collect();
-    if parts.len() < 2 {
-        return Err(Error::InvalidKeyValuePair);
+    // Split at the first equal sign.
+    match line.split_once('=') {
+        Some((key, value)) => Ok(Some((key.trim(), value.trim()))),
+        None => Err(Error::InvalidKeyValuePair),
     }
-
-    Ok(Some((
-        parts[0].trim().to_string(),
-        parts[1].trim().to_string(),
-    )))
 }
 
-fn parse(s: &str) -> Result<HashMap<String, String>, Error> {
-    let mut values = HashMap::new();
+fn parse(s: &str) -> Result<Simple, Error> {
+    // Each line holds at most one pair, so sizing by the number of
+    // lines means nothing has to grow.
+    let lines = s.bytes().filter(|&b| b == b'\n').count() + 1;
+    let mut pairs: Vec<(String, String)> = Vec::with_capacity(lines);
+    let mut index: FnvHashMap<String, usize> =
+        FnvHashMap::with_capacity_and_hasher(lines, Default::default());
 
-    for line in s.split("\n") {
-        match parse_line(&line) {
-            Err(e) => return Err(e),
-            Ok(v) => match v {
-                None => continue,
-                Some(s) => {
-                    values.insert(s.0, s.1);
+    // Lines are borrowed from the input; only the pairs that are kept
+    // are copied. A repeated key keeps its first position but takes
+    // the last value.
+    for line in s.lines() {
+        if let Some((key, value)) = parse_line(line)? {
+            match index.get(key) {
+                Some(&i) => pairs[i].1 = value.to_string(),
+                None => {
+                    index.insert(key.to_string(), pairs.len());
+                    pairs.push((key.to_string(), value.to_string()));
                 }
-            },
+            }
         }
     }
 
-    Ok(values)
+    Ok(Simple { pairs, index })
 }
 
 impl Simple {
@@ -69,52 +133,104 @@ impl Simple {
     /// mongo.uri = mongodb://localhost/
     /// mongo.db  = test
     /// ```
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Result<Self, Error> {
-        Ok(Self { values: parse(s)? })
+        parse(s)
     }
 
     /// Similar to `from_str` except that the given path is used as
     /// the contents for the string to parse.
     pub fn from_file(path: &str) -> Result<Self, Error> {
-        let file = match read_to_string(path) {
-            Ok(s) => s,
-            Err(e) => return Err(Error::File(e.to_string())),
-        };
-        Ok(Self {
-            values: parse(&file)?,
-        })
+        parse(&read_file(path)?)
+    }
+
+    /// Similar to `from_str` except that the contents are read from
+    /// the given reader.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self, Error> {
+        let mut s = String::new();
+        if let Err(e) = reader.read_to_string(&mut s) {
+            return Err(Error::file("-", &e));
+        }
+        Self::from_str(&s)
+    }
+
+    /// Similar to `from_str` except that the contents are read from
+    /// standard input.
+    pub fn from_stdin() -> Result<Self, Error> {
+        Self::from_reader(io::stdin().lock())
+    }
+
+    /// The key/value pairs in the order the keys first appear in the
+    /// file.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.pairs.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Write the pairs to the file at the given path, one `key =
+    /// value` line each, in the order of `iter`. Comments and blank
+    /// lines aren't kept.
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        let mut s = String::new();
+        for (key, value) in self.iter() {
+            s.push_str(&format!("{} = {}\n", key, value));
+        }
+        std::fs::write(path, s).map_err(|e| Error::file(path, &e))
+    }
+
+    /// Similar to `from_file` except that the file must have a valid
+    /// detached SSH signature at `<path>.sig` made by the given public
+    /// key. See `signing::read_verified`.
+    #[cfg(feature = "signing")]
+    pub fn from_file_verified(path: &str, public_key: &str) -> Result<Self, Error> {
+        Self::from_str(&crate::signing::read_verified(path, public_key)?)
     }
 }
 
 impl Config for Simple {
+    /// Elements of lists are available by index (e.g. `servers.0`).
     fn get(&self, key: &str) -> Option<String> {
-        match self.values.get(key) {
-            Some(value) => Some(value.to_string()),
-            None => None,
-        }
+        self.get_ref(key)
+            .or_else(|| element(key, |k| self.get_ref(k)))
+            .map(Cow::into_owned)
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.index
+            .get(key)
+            .map(|&i| Cow::Borrowed(self.pairs[i].1.as_str()))
+    }
+
+    /// The keys in the order they first appear in the file.
+    fn keys(&self) -> Vec<String> {
+        self.pairs.iter().map(|(k, _)| k.clone()).collect()
+    }
+}
+
+impl fmt::Debug for Simple {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redact::debug(self, f)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::simple::{parse_line, Error, Simple};
+    use crate::testing::TempDir;
     use crate::Config;
 
-    use std::array::IntoIter;
+    use std::borrow::Cow;
     use std::collections::HashMap;
-    use std::iter::FromIterator;
+    use std::io;
+    use std::path::PathBuf;
 
     #[test]
     fn test_parse_line() {
-        let tests =
-            HashMap::<&str, Result<Option<(String, String)>, Error>>::from_iter(IntoIter::new([
-                ("     # comment   ", Ok(None)),
-                ("  test", Err(Error::InvalidKeyValuePair)),
-                (
-                    "  foo    =    bar    ",
-                    Ok(Some(("foo".to_string(), "bar".to_string()))),
-                ),
-            ]));
+        let tests = HashMap::<&str, Result<Option<(&str, &str)>, Error>>::from([
+            ("     # comment   ", Ok(None)),
+            ("  test", Err(Error::InvalidKeyValuePair)),
+            ("  foo    =    bar    ", Ok(Some(("foo", "bar")))),
+            ("a = b = c", Ok(Some(("a", "b = c")))),
+        ]);
         tests.iter().for_each(|(k, v)| {
             assert_eq!(parse_line(k), *v);
         });
@@ -123,9 +239,10 @@ mod tests {
     #[test]
     fn test_file() {
         // not found
-        let exp: Result<Simple, Error> = Err(Error::File(
-            "No such file or directory (os error 2)".to_string(),
-        ));
+        let exp: Result<Simple, Error> = Err(Error::File {
+            path: PathBuf::from("/i/hope/i/do/not/exist.cfg"),
+            kind: io::ErrorKind::NotFound,
+        });
         assert_eq!(Simple::from_file("/i/hope/i/do/not/exist.cfg"), exp);
 
         // our example config
@@ -135,5 +252,54 @@ mod tests {
         };
         assert_eq!(cfg.get("foo"), Some("bar".to_string()));
         assert_eq!(cfg.get("list"), Some("one, two, three".to_string()));
+        assert!(matches!(cfg.get_ref("foo"), Some(Cow::Borrowed("bar"))));
+    }
+
+    #[test]
+    fn order() {
+        let cfg = Simple::from_str("b = 1\na = 2\nc = 3\nb = 4\n").unwrap();
+        assert_eq!(cfg.keys(), vec!["b", "a", "c"]);
+        assert_eq!(
+            cfg.iter().collect::<Vec<_>>(),
+            vec![("b", "4"), ("a", "2"), ("c", "3")]
+        );
+
+        let dir = TempDir::new("simple-save");
+        let path = dir.join("save.cfg");
+        let path = path.to_str().unwrap();
+        cfg.save(path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            "b = 4\na = 2\nc = 3\n"
+        );
+        assert_eq!(Simple::from_file(path).unwrap(), cfg);
+    }
+
+    #[test]
+    fn error_display() {
+        let e = Error::File {
+            path: PathBuf::from("app.cfg"),
+            kind: io::ErrorKind::NotFound,
+        };
+        assert_eq!(e.to_string(), "app.cfg: entity not found");
+        assert_eq!(
+            Error::InvalidKeyValuePair.to_string(),
+            "invalid key/value pair"
+        );
+        assert_eq!(
+            Error::Remote("unreachable".to_string()).to_string(),
+            "remote source failed: unreachable"
+        );
+    }
+
+    #[test]
+    fn test_reader() {
+        let cfg = Simple::from_reader(
+            "foo = bar
+"
+            .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(cfg.get("foo"), Some("bar".to_string()));
     }
 }
@@ -0,0 +1,207 @@
+//! The values of a config frozen at a point in time.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::redact::{self, redact};
+use crate::report::table;
+use crate::{Config, Schema};
+
+/// The values of a config and the sources they came from, copied with
+/// `Config::snapshot` so they don't change when the config is reloaded.
+/// It's a config itself and it's shown as a table with the secrets
+/// redacted (see `redact::redact`):
+///
+/// ```text
+/// KEY          VALUE       SOURCE
+/// db.host      localhost   Toml
+/// db.password  [redacted]  Environment
+/// ```
+///
+/// ```
+/// use dinglebit_config::{Config, Simple};
+///
+/// let cfg = Simple::from_str("db.host = localhost\ndb.password = hunter2").unwrap();
+/// let snapshot = cfg.snapshot();
+/// assert_eq!(snapshot.get("db.password"), Some("hunter2".to_string()));
+/// assert!(snapshot.to_string().contains("[redacted]"));
+/// ```
+///
+/// With the `serde` feature it can be serialized, e.g. to keep the last
+/// config that loaded successfully on disk and restore it when the
+/// sources can't be reached. The secrets are kept as they are, so store
+/// it somewhere only the service can read.
+#[derive(PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Snapshot {
+    values: BTreeMap<String, String>,
+    sources: BTreeMap<String, String>,
+}
+
+impl Snapshot {
+    /// The number of keys.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Whether there are no keys.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The table `Display` shows, but with the keys the schema marks
+    /// as secrets (see `Schema::sensitive`) redacted too.
+    pub fn display<'a>(&'a self, schema: &'a Schema) -> impl fmt::Display + 'a {
+        Table {
+            snapshot: self,
+            schema: Some(schema),
+        }
+    }
+}
+
+/// The table of a snapshot, redacted with the schema if there is one.
+struct Table<'a> {
+    snapshot: &'a Snapshot,
+    schema: Option<&'a Schema>,
+}
+
+pub(crate) fn snapshot<C: Config + ?Sized>(config: &C) -> Snapshot {
+    let mut snapshot = Snapshot::default();
+    for key in config.keys().into_iter() {
+        if let Some(value) = config.get(&key) {
+            if let Some(source) = config.source_of(&key) {
+                snapshot.sources.insert(key.clone(), source);
+            }
+            snapshot.values.insert(key, value);
+        }
+    }
+    snapshot
+}
+
+impl Config for Snapshot {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values.get(key).cloned()
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.values.get(key).map(|v| Cow::Borrowed(v.as_str()))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+
+    fn source_of(&self, key: &str) -> Option<String> {
+        self.sources.get(key).cloned()
+    }
+}
+
+impl fmt::Debug for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redact::debug(self, f)
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Table {
+            snapshot: self,
+            schema: None,
+        }
+        .fmt(f)
+    }
+}
+
+impl fmt::Display for Table<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let values: Vec<(&str, Cow<str>, &str)> = self
+            .snapshot
+            .values
+            .iter()
+            .map(|(k, v)| {
+                let source = self.snapshot.sources.get(k).map(String::as_str);
+                let value = match self.schema {
+                    Some(schema) => schema.redact(k, v),
+                    None => redact(k, v),
+                };
+                (k.as_str(), value, source.unwrap_or("-"))
+            })
+            .collect();
+        let cells: Vec<[&str; 3]> = values
+            .iter()
+            .map(|(k, v, source)| [*k, v.as_ref(), *source])
+            .collect();
+        table(f, ["KEY", "VALUE", "SOURCE"], &cells)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::Type;
+    use crate::{Config, Defaults, MultiConfig, Schema, Simple, Snapshot};
+
+    #[test]
+    fn snapshot() {
+        let cfg = MultiConfig::builder()
+            .layer(Simple::from_str("db.host = localhost\ndb.password = hunter2").unwrap())
+            .defaults(Defaults::new().set("db.pool", "10"))
+            .build()
+            .unwrap();
+        let snapshot = cfg.snapshot();
+        assert_eq!(snapshot.len(), 3);
+        assert_eq!(snapshot.get("db.password"), Some("hunter2".to_string()));
+        assert_eq!(snapshot.source_of("db.pool"), Some("Defaults".to_string()));
+        assert_eq!(
+            snapshot.to_string(),
+            "KEY          VALUE       SOURCE\n\
+             db.host      localhost   Simple\n\
+             db.password  [redacted]  Simple\n\
+             db.pool      10          Defaults\n"
+        );
+        assert_eq!(
+            format!("{:?}", snapshot),
+            r#"Snapshot {"db.host": "localhost", "db.password": "[redacted]", "db.pool": "10"}"#
+        );
+        assert_eq!(cfg.snapshot(), snapshot);
+        assert_ne!(Snapshot::default(), snapshot);
+        assert!(format!("{:?}", cfg).starts_with(r#"MultiConfig { layers: ["Simple", "Defaults"]"#));
+
+        let schema = Schema::new()
+            .required("snapshot.webhook", Type::String)
+            .sensitive("snapshot.webhook");
+        let snapshot = Simple::from_str("snapshot.webhook = whsec_1\ndb.password = hunter2")
+            .unwrap()
+            .snapshot();
+        assert_eq!(
+            snapshot.display(&schema).to_string(),
+            "KEY               VALUE       SOURCE\n\
+             db.password       [redacted]  Simple\n\
+             snapshot.webhook  [redacted]  Simple\n"
+        );
+        assert_eq!(
+            format!("{:?}", schema.debug(&snapshot)),
+            r#"Snapshot {"db.password": "[redacted]", "snapshot.webhook": "[redacted]"}"#
+        );
+        // The mark stays with the schema.
+        assert!(snapshot.to_string().contains("whsec_1"));
+        assert!(format!("{:?}", snapshot).contains("whsec_1"));
+    }
+
+    #[test]
+    #[cfg(all(feature = "serde", feature = "json"))]
+    fn persist() {
+        let cfg = Simple::from_str("db.host = localhost").unwrap();
+        let saved = serde_json::to_string(&cfg.snapshot()).unwrap();
+        assert_eq!(
+            saved,
+            r#"{"values":{"db.host":"localhost"},"sources":{"db.host":"Simple"}}"#
+        );
+        let restored: Snapshot = serde_json::from_str(&saved).unwrap();
+        assert_eq!(restored, cfg.snapshot());
+        assert_eq!(restored.source_of("db.host"), Some("Simple".to_string()));
+    }
+}
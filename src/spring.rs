@@ -0,0 +1,195 @@
+//! Configuration from a Spring Cloud Config server.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::http::get_json;
+use crate::value::Value;
+use crate::{redact, Config, Error};
+
+/// The properties a Spring Cloud Config server serves for an
+/// application and profile. Use `SpringCloud::builder` to fetch them.
+///
+/// The server answers with a list of property sources, most specific
+/// first (e.g. `app-prod.yml` before `application.yml`), and they are
+/// merged in that order, so the first source with a key wins. Indexed
+/// keys (`servers[0]`) are available with dots (`servers.0`).
+#[derive(PartialEq)]
+pub struct SpringCloud {
+    values: HashMap<String, String>,
+    version: Option<String>,
+}
+
+/// Build a `SpringCloud`.
+pub struct SpringCloudBuilder {
+    url: String,
+    application: String,
+    profile: String,
+    label: Option<String>,
+    credentials: Option<String>,
+}
+
+impl SpringCloud {
+    /// Start building a request for the given application and
+    /// profiles (comma separated, e.g. `prod,east`) to the server at
+    /// the given URL.
+    pub fn builder(url: &str, application: &str, profile: &str) -> SpringCloudBuilder {
+        SpringCloudBuilder {
+            url: url.trim_end_matches('/').to_string(),
+            application: application.to_string(),
+            profile: profile.to_string(),
+            label: None,
+            credentials: None,
+        }
+    }
+
+    /// Create a new configuration from the given server response
+    /// (`/{application}/{profile}/{label}`).
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Self, Error> {
+        match serde_json::from_str(s) {
+            Ok(v) => Self::from_json(v),
+            Err(e) => Err(Error::Parse(e.to_string())),
+        }
+    }
+
+    fn from_json(v: serde_json::Value) -> Result<Self, Error> {
+        let sources = match v.get("propertySources").and_then(|s| s.as_array()) {
+            Some(sources) => sources,
+            None => return Err(Error::Parse("expected propertySources".to_string())),
+        };
+
+        let mut values = HashMap::new();
+        for source in sources.iter().rev() {
+            let properties = match source.get("source").and_then(|s| s.as_object()) {
+                Some(properties) => properties,
+                None => return Err(Error::Parse("expected a source object".to_string())),
+            };
+            for (key, value) in properties {
+                if let Some(value) = Value::from(value.clone()).rendered() {
+                    values.insert(key.replace('[', ".").replace(']', ""), value);
+                }
+            }
+        }
+
+        Ok(Self {
+            values,
+            version: v
+                .get("version")
+                .and_then(|v| v.as_str())
+                .map(str::to_string),
+        })
+    }
+
+    /// The version the server reported for the properties, such as the
+    /// commit of its git backend.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+}
+
+impl SpringCloudBuilder {
+    /// Request the given label (e.g. a git branch or tag) instead of
+    /// the server's default.
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_string());
+        self
+    }
+
+    /// Authenticate with HTTP basic authentication.
+    pub fn credentials(mut self, user: &str, password: &str) -> Self {
+        let encoded = STANDARD.encode(format!("{}:{}", user, password));
+        self.credentials = Some(format!("Basic {}", encoded));
+        self
+    }
+
+    /// Fetch the properties from the server.
+    pub fn build(self) -> Result<SpringCloud, Error> {
+        let mut url = format!("{}/{}/{}", self.url, self.application, self.profile);
+        if let Some(label) = &self.label {
+            // Spring uses `(_)` for slashes in labels (`feature/x`).
+            url = format!("{}/{}", url, label.replace('/', "(_)"));
+        }
+        let mut headers = vec![("Accept", "application/json")];
+        if let Some(credentials) = &self.credentials {
+            headers.push(("Authorization", credentials));
+        }
+        SpringCloud::from_json(get_json(&url, &headers)?)
+    }
+}
+
+impl Config for SpringCloud {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values.get(key).cloned()
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.values.get(key).map(|v| Cow::Borrowed(v.as_str()))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+}
+
+impl fmt::Debug for SpringCloud {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redact::debug(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::http::serve;
+    use crate::{Config, Error, SpringCloud};
+
+    const RESPONSE: &str = r#"{
+        "name": "app",
+        "profiles": ["prod"],
+        "label": "main",
+        "version": "1a2b3c",
+        "propertySources": [
+            {"name": "app-prod.yml", "source": {"db.host": "prod", "servers[0]": "a"}},
+            {"name": "application.yml", "source": {"db.host": "base", "db.port": 5432, "debug": false}}
+        ]
+    }"#;
+
+    #[test]
+    fn from_str() {
+        let cfg = SpringCloud::from_str(RESPONSE).unwrap();
+        assert_eq!(cfg.version(), Some("1a2b3c"));
+        assert_eq!(cfg.string("db.host"), "prod");
+        assert_eq!(cfg.int("db.port"), 5432);
+        assert!(!cfg.bool("debug"));
+        assert_eq!(cfg.string("servers.0"), "a");
+        assert!(SpringCloud::from_str("{}").is_err());
+    }
+
+    #[test]
+    fn build() {
+        let (url, server) = serve(1, |_| (200, RESPONSE.to_string()));
+        let cfg = SpringCloud::builder(&url, "app", "prod")
+            .label("feature/x")
+            .credentials("user", "secret")
+            .build()
+            .unwrap();
+        assert_eq!(cfg.string("db.host"), "prod");
+
+        let request = &server.join().unwrap()[0];
+        assert_eq!(request[0], "GET /app/prod/feature(_)x HTTP/1.1");
+        assert!(request
+            .iter()
+            .any(|h| h.to_lowercase() == "authorization: basic dxnlcjpzzwnyzxq="));
+
+        let (url, server) = serve(1, |_| (404, "{}".to_string()));
+        assert!(matches!(
+            SpringCloud::builder(&url, "app", "prod").build(),
+            Err(Error::Remote(_))
+        ));
+        server.join().unwrap();
+    }
+}
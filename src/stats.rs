@@ -0,0 +1,46 @@
+//! How long config sources took to load, for diagnosing slow starts.
+
+use std::time::Duration;
+
+/// The statistics of a source, from `Config::stats`.
+///
+/// ```
+/// use dinglebit_config::{Config, MultiConfig};
+///
+/// let cfg = MultiConfig::builder().file("example.cfg").build().unwrap();
+/// for stats in cfg.stats() {
+///     println!("{} has {} keys, loaded in {:?}", stats.source, stats.keys, stats.load_time);
+/// }
+/// assert!(cfg.stats()[0].load_time.is_some());
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct SourceStats {
+    /// The `source_name` of the source.
+    pub source: String,
+
+    /// How many keys the source has.
+    pub keys: usize,
+
+    /// How long the source took to load, if it's known (e.g. files
+    /// added with `Builder::file` and a `Reloadable`).
+    pub load_time: Option<Duration>,
+
+    /// How many times the source was loaded again since it was created.
+    pub refreshes: u64,
+
+    /// How long the last refresh took.
+    pub last_refresh: Option<Duration>,
+}
+
+impl SourceStats {
+    /// The statistics of a source nothing else is known about.
+    pub fn new(source: String, keys: usize) -> Self {
+        Self {
+            source,
+            keys,
+            load_time: None,
+            refreshes: 0,
+            last_refresh: None,
+        }
+    }
+}
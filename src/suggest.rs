@@ -0,0 +1,67 @@
+//! Suggesting the key that was meant when one is misspelled.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// The most keys that are suggested.
+const MAX: usize = 3;
+
+/// The keys that are one or two edits away from the key (at most a
+/// third of its length), closest first. Keys too short to tell a typo
+/// from a different key get no suggestions.
+pub(crate) fn similar(key: &str, keys: &[String]) -> Vec<String> {
+    let allowed = (key.chars().count() / 3).min(2);
+    let mut close: Vec<(usize, &String)> = keys
+        .iter()
+        .filter(|k| k.as_str() != key)
+        .map(|k| (distance(key, k), k))
+        .filter(|(d, _)| *d <= allowed)
+        .collect();
+    close.sort();
+    close.dedup();
+    close
+        .into_iter()
+        .take(MAX)
+        .map(|(_, k)| k.to_string())
+        .collect()
+}
+
+/// The Levenshtein distance between the strings: the number of
+/// characters that need to be inserted, deleted or replaced to turn one
+/// into the other.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let replaced = diagonal + (ca != *cb) as usize;
+            diagonal = row[j + 1];
+            row[j + 1] = replaced.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::suggest::{distance, similar};
+
+    #[test]
+    fn suggest() {
+        assert_eq!(distance("db.uri", "db.uri"), 0);
+        assert_eq!(distance("db.uir", "db.uri"), 2);
+        assert_eq!(distance("db.ur", "db.uri"), 1);
+        assert_eq!(distance("", "abc"), 3);
+
+        let keys: Vec<String> = ["db.uri", "db.url", "db.pool", "c"]
+            .iter()
+            .map(|k| k.to_string())
+            .collect();
+        assert_eq!(similar("db.ur", &keys), vec!["db.uri", "db.url"]);
+        assert_eq!(similar("db.pol", &keys), vec!["db.pool", "db.url"]);
+        assert!(similar("cache.size", &keys).is_empty());
+        assert!(similar("b", &keys).is_empty());
+    }
+}
@@ -0,0 +1,113 @@
+//! Information about the machine the process is running on.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::env::{self, consts};
+use std::fs::read_to_string;
+use std::thread;
+
+use crate::Config;
+
+/// Facts about the running process and the machine, so configs (e.g.
+/// `Expressions`) can refer to them without custom code:
+///
+/// | Key             | Value                                                |
+/// |-----------------|------------------------------------------------------|
+/// | `sys.hostname`  | the name of the host, if it can be found             |
+/// | `sys.cpu.count` | the number of CPUs the process can use               |
+/// | `sys.os`        | the operating system (`linux`, `macos`, ...)         |
+/// | `sys.arch`      | the CPU architecture (`x86_64`, `aarch64`, ...)      |
+/// | `sys.pid`       | the ID of the process                                |
+/// | `sys.user`      | the user from `USER` or `USERNAME`, if either is set |
+///
+/// ```
+/// use dinglebit_config::{Config, Expressions, MultiConfig, SystemInfo};
+/// use std::collections::HashMap;
+///
+/// let cfg = Expressions::new(MultiConfig::new(vec![
+///     Box::new(HashMap::from([("workers", "expr: ${sys.cpu.count} * 2")])),
+///     Box::new(SystemInfo::new()),
+/// ]));
+/// assert_eq!(cfg.int("workers"), cfg.int("sys.cpu.count") * 2);
+/// ```
+///
+/// The facts are gathered when it's created.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SystemInfo {
+    values: BTreeMap<String, String>,
+}
+
+impl SystemInfo {
+    /// Gather the facts about the current process.
+    pub fn new() -> Self {
+        let mut values = BTreeMap::new();
+        let mut set = |key: &str, value: String| values.insert(key.to_string(), value);
+        if let Some(host) = hostname() {
+            set("sys.hostname", host);
+        }
+        let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+        set("sys.cpu.count", cpus.to_string());
+        set("sys.os", consts::OS.to_string());
+        set("sys.arch", consts::ARCH.to_string());
+        set("sys.pid", std::process::id().to_string());
+        if let Some(user) = ["USER", "USERNAME"].iter().find_map(|n| env::var(n).ok()) {
+            set("sys.user", user);
+        }
+        Self { values }
+    }
+}
+
+impl Default for SystemInfo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Config for SystemInfo {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values.get(key).cloned()
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.values.get(key).map(|v| Cow::Borrowed(v.as_str()))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+}
+
+/// The name of the host, if it can be found. The `HOSTNAME` and
+/// `COMPUTERNAME` variables are checked before the files Linux and
+/// most other Unix systems keep it in.
+pub(crate) fn hostname() -> Option<String> {
+    let from_env = ["HOSTNAME", "COMPUTERNAME"]
+        .iter()
+        .find_map(|name| env::var(name).ok());
+    let from_file = || {
+        ["/proc/sys/kernel/hostname", "/etc/hostname"]
+            .iter()
+            .find_map(|path| read_to_string(path).ok())
+    };
+    from_env
+        .or_else(from_file)
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sys::SystemInfo;
+    use crate::Config;
+
+    #[test]
+    fn system_info() {
+        let sys = SystemInfo::new();
+        assert!(sys.int("sys.cpu.count") >= 1);
+        assert_eq!(sys.string("sys.os"), std::env::consts::OS);
+        assert_eq!(sys.string("sys.arch"), std::env::consts::ARCH);
+        assert_eq!(sys.int("sys.pid"), std::process::id() as i64);
+        assert!(sys.keys().iter().all(|k| k.starts_with("sys.")));
+        assert_eq!(sys.source_name(), "SystemInfo");
+    }
+}
@@ -0,0 +1,131 @@
+//! Render config values into text files (e.g. an nginx config or a
+//! systemd unit) from a template.
+
+use std::fs;
+
+use crate::{Config, Error};
+
+/// Replace the `{{ key }}` placeholders in the template with the
+/// values of the config. A placeholder may pass the value through
+/// filters separated by `|`:
+///
+/// - `default("value")` is used when the key has no value.
+/// - `upper` and `lower` change the case.
+/// - `trim` removes surrounding whitespace.
+///
+/// ```
+/// use dinglebit_config::render;
+/// use std::collections::HashMap;
+///
+/// let cfg = HashMap::from([("server.name", "example.com")]);
+/// let out = render(
+///     "server_name {{ server.name }};\nlisten {{ server.port | default(\"80\") }};",
+///     &cfg,
+/// );
+/// assert_eq!(out.unwrap(), "server_name example.com;\nlisten 80;");
+/// ```
+///
+/// A key without a value or default is an `Error::Eval` and a
+/// placeholder that isn't closed or uses an unknown filter is an
+/// `Error::Parse`.
+pub fn render(template: &str, config: &dyn Config) -> Result<String, Error> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let end = match rest[start..].find("}}") {
+            Some(end) => start + end,
+            None => return Err(Error::Parse("unclosed '{{' in template".to_string())),
+        };
+        out.push_str(&placeholder(&rest[start + 2..end], config)?);
+        rest = &rest[end + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Similar to `render` but reads the template from a file and writes
+/// the result to another.
+pub fn render_file(template: &str, output: &str, config: &dyn Config) -> Result<(), Error> {
+    let s = fs::read_to_string(template).map_err(|e| Error::file(template, &e))?;
+    let out = render(&s, config)?;
+    fs::write(output, out).map_err(|e| Error::file(output, &e))
+}
+
+fn placeholder(inner: &str, config: &dyn Config) -> Result<String, Error> {
+    let mut parts = inner.split('|').map(str::trim);
+    let key = parts.next().unwrap_or_default();
+    let mut value = match config.try_get_ref(key) {
+        Ok(value) => Some(value.into_owned()),
+        Err(e) if e.is_missing() => None,
+        Err(e) => return Err(Error::Eval(e.to_string())),
+    };
+    for filter in parts {
+        match filter {
+            "upper" => value = value.map(|v| v.to_uppercase()),
+            "lower" => value = value.map(|v| v.to_lowercase()),
+            "trim" => value = value.map(|v| v.trim().to_string()),
+            f => match f.strip_prefix("default(").and_then(|a| a.strip_suffix(')')) {
+                Some(arg) => {
+                    let arg = arg.trim();
+                    let arg = arg
+                        .strip_prefix('"')
+                        .and_then(|a| a.strip_suffix('"'))
+                        .or_else(|| arg.strip_prefix('\'').and_then(|a| a.strip_suffix('\'')))
+                        .unwrap_or(arg);
+                    value.get_or_insert_with(|| arg.to_string());
+                }
+                None => return Err(Error::Parse(format!("unknown filter '{}'", f))),
+            },
+        }
+    }
+    value.ok_or_else(|| Error::Eval(format!("missing value for '{}'", key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::template::{render, render_file};
+    use crate::testing::TempDir;
+    use crate::Error;
+    use std::collections::HashMap;
+    use std::fs;
+
+    #[test]
+    fn filters() {
+        let cfg = HashMap::from([("name", " Web "), ("ports", "[80, 443]")]);
+        assert_eq!(
+            render("{{name|trim|upper}}-{{ name | trim | lower }}", &cfg),
+            Ok("WEB-web".to_string())
+        );
+        assert_eq!(render("{{ ports.1 }}", &cfg), Ok("443".to_string()));
+        assert_eq!(
+            render("{{ user | default('nobody') | upper }}", &cfg),
+            Ok("NOBODY".to_string())
+        );
+        assert_eq!(
+            render("no placeholders", &cfg),
+            Ok("no placeholders".to_string())
+        );
+        assert!(matches!(render("{{ user }}", &cfg), Err(Error::Eval(_))));
+        assert!(matches!(
+            render("{{ name | reverse }}", &cfg),
+            Err(Error::Parse(_))
+        ));
+        assert!(matches!(render("{{ name", &cfg), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn file() {
+        let dir = TempDir::new("template");
+        let template = dir.join("unit.tmpl");
+        let output = dir.join("app.service");
+        fs::write(&template, "[Service]\nExecStart={{ bin }}\n").unwrap();
+
+        let cfg = HashMap::from([("bin", "/usr/bin/app")]);
+        render_file(template.to_str().unwrap(), output.to_str().unwrap(), &cfg).unwrap();
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "[Service]\nExecStart=/usr/bin/app\n"
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Bounding how long a slow source can hold up a lookup.
+
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::{Config, Error, SourceHealth};
+
+/// A source whose lookups, prefetches and health checks give up after
+/// a timeout, so a hung source (e.g. a config file on an NFS mount that
+/// went away, or a remote source that stopped answering) can't hold up
+/// a request. A lookup that takes too long is a miss for `get` and an
+/// `Error::Timeout` for `lookup`, so in a `MultiConfig` the next layer
+/// answers instead.
+///
+/// Each call runs on a thread of its own. A call that times out is left
+/// to finish in the background, since there's no way to stop it, so at
+/// most 8 calls (see `in_flight`) can be running at once. Beyond that,
+/// calls fail right away until some finish, which keeps a hung source
+/// from piling up threads.
+///
+/// ```
+/// use dinglebit_config::{Config, MultiConfig, Simple, Timeout};
+/// use std::time::Duration;
+///
+/// let cfg = MultiConfig::new(vec![
+///     Box::new(Timeout::wrap(
+///         Simple::from_str("foo = bar").unwrap(),
+///         Duration::from_millis(200),
+///     )),
+///     Box::new(Simple::from_str("foo = fallback").unwrap()),
+/// ]);
+/// assert_eq!(cfg.get("foo"), Some("bar".to_string()));
+/// ```
+pub struct Timeout<C> {
+    source: Arc<C>,
+    timeout: Duration,
+    limit: usize,
+    running: Arc<AtomicUsize>,
+}
+
+/// Counts a call as running until it's dropped, even if the call
+/// panics.
+pub(crate) struct Running(Arc<AtomicUsize>);
+
+impl Running {
+    /// Count another call as running, unless `limit` calls already are.
+    pub(crate) fn start(running: &Arc<AtomicUsize>, limit: usize) -> Option<Self> {
+        match running.fetch_add(1, Ordering::SeqCst) >= limit {
+            true => {
+                running.fetch_sub(1, Ordering::SeqCst);
+                None
+            }
+            false => Some(Self(running.clone())),
+        }
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl<C: Config + Send + Sync + 'static> Timeout<C> {
+    /// Give up on calls to the source after the timeout.
+    pub fn wrap(source: C, timeout: Duration) -> Self {
+        Self {
+            source: Arc::new(source),
+            timeout,
+            limit: 8,
+            running: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Set how many calls can be running at once, including those
+    /// that timed out but haven't finished yet.
+    pub fn in_flight(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Run `f` on the source, giving up after the timeout.
+    fn bounded<T, F>(&self, f: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&C) -> T + Send + 'static,
+    {
+        let running = match Running::start(&self.running, self.limit) {
+            Some(running) => running,
+            None => {
+                return Err(Error::Timeout(format!(
+                    "{}: {} calls still running",
+                    self.source.source_name(),
+                    self.limit
+                )))
+            }
+        };
+        let (tx, rx) = mpsc::channel();
+        let source = self.source.clone();
+        thread::spawn(move || {
+            let _running = running;
+            let _ = tx.send(f(&source));
+        });
+        rx.recv_timeout(self.timeout).map_err(|_| {
+            Error::Timeout(format!(
+                "{}: no answer after {:?}",
+                self.source.source_name(),
+                self.timeout
+            ))
+        })
+    }
+}
+
+impl<C: Config + Send + Sync + 'static> Config for Timeout<C> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.lookup(key).ok().flatten()
+    }
+
+    fn lookup(&self, key: &str) -> Result<Option<String>, Error> {
+        let key = key.to_string();
+        self.bounded(move |c| c.lookup(&key))?
+    }
+
+    /// Keys that don't get an answer in time are all misses.
+    fn get_many(&self, keys: &[&str]) -> Vec<Option<String>> {
+        let owned: Vec<String> = keys.iter().map(|k| k.to_string()).collect();
+        self.bounded(move |c| {
+            let keys: Vec<&str> = owned.iter().map(String::as_str).collect();
+            c.get_many(&keys)
+        })
+        .unwrap_or_else(|_| vec![None; keys.len()])
+    }
+
+    fn prefetch(&self, prefixes: &[&str]) -> Vec<Result<usize, Error>> {
+        let owned: Vec<String> = prefixes.iter().map(|p| p.to_string()).collect();
+        match self.bounded(move |c| {
+            let prefixes: Vec<&str> = owned.iter().map(String::as_str).collect();
+            c.prefetch(&prefixes)
+        }) {
+            Ok(results) => results,
+            Err(e) => prefixes.iter().map(|_| Err(e.clone())).collect(),
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.bounded(|c| c.keys()).unwrap_or_default()
+    }
+
+    fn similar_keys(&self, key: &str) -> Vec<String> {
+        let key = key.to_string();
+        self.bounded(move |c| c.similar_keys(&key))
+            .unwrap_or_default()
+    }
+
+    fn source_health(&self) -> SourceHealth {
+        self.bounded(|c| c.source_health())
+            .unwrap_or_else(|e| SourceHealth::Down(e.to_string()))
+    }
+
+    fn source_name(&self) -> String {
+        self.source.source_name()
+    }
+
+    fn source_of(&self, key: &str) -> Option<String> {
+        self.get_ref(key).map(|_| self.source_name())
+    }
+}
+
+impl<C: Config> fmt::Debug for Timeout<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Timeout")
+            .field("source", &self.source.source_name())
+            .field("timeout", &self.timeout)
+            .field("in_flight", &self.limit)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Config, Error, MultiConfig, Timeout};
+    use std::collections::HashMap;
+    use std::sync::atomic::Ordering;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// Doesn't answer until the gate is released.
+    struct Hung {
+        gate: Arc<Mutex<()>>,
+    }
+
+    impl Config for Hung {
+        fn get(&self, key: &str) -> Option<String> {
+            let _open = self.gate.lock().unwrap();
+            Some(key.to_string())
+        }
+    }
+
+    #[test]
+    fn timeout() {
+        let gate = Arc::new(Mutex::new(()));
+        let closed = gate.lock().unwrap();
+        let hung = || Hung { gate: gate.clone() };
+
+        let cfg = MultiConfig::new(vec![
+            Box::new(Timeout::wrap(hung(), Duration::from_millis(20))),
+            Box::new(HashMap::from([("a", "fallback")])),
+        ]);
+        assert_eq!(cfg.get("a"), Some("fallback".to_string()));
+
+        let hung = Timeout::wrap(hung(), Duration::from_millis(20)).in_flight(2);
+        assert!(matches!(hung.lookup("a"), Err(Error::Timeout(_))));
+        assert_eq!(hung.get_many(&["a", "b"]), vec![None, None]);
+        // Both calls are still stuck, so no more threads are started.
+        assert!(matches!(hung.lookup("a"), Err(Error::Timeout(_))));
+        assert_eq!(hung.get("a"), None);
+        assert_eq!(hung.running.load(Ordering::SeqCst), 2);
+        drop(closed);
+
+        let fast = Timeout::wrap(HashMap::from([("a", "b")]), Duration::from_secs(5));
+        assert_eq!(fast.lookup("a"), Ok(Some("b".to_string())));
+        assert_eq!(fast.keys(), vec!["a".to_string()]);
+    }
+}
@@ -0,0 +1,111 @@
+//! Configuration from TOML documents.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::format::read_file;
+use crate::value::Value;
+use crate::{redact, Config, Error};
+
+#[derive(PartialEq, Clone)]
+pub struct Toml {
+    root: Value,
+    values: HashMap<String, String>,
+}
+
+impl Toml {
+    /// Create a new configuration from the given TOML document.
+    /// Tables are flattened into dot-notation keys, so a `uri` in the
+    /// `[mongo]` table is available as `mongo.uri`. Arrays are
+    /// rendered using the syntax `Config::list` expects and dates are
+    /// written in RFC 3339 format.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Self, Error> {
+        match s.parse::<::toml::Table>() {
+            Ok(t) => {
+                let root = Value::from(::toml::Value::Table(t));
+                Ok(Self {
+                    values: root.flatten(),
+                    root,
+                })
+            }
+            Err(e) => Err(Error::Parse(e.to_string())),
+        }
+    }
+
+    /// Similar to `from_str` except that the given path is used as
+    /// the contents for the string to parse.
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let file = read_file(path)?;
+        Self::from_str(&file)
+    }
+}
+
+impl Config for Toml {
+    /// Elements of arrays are available by index (e.g.
+    /// `servers.0.host`).
+    fn get(&self, key: &str) -> Option<String> {
+        match self.values.get(key) {
+            Some(value) => Some(value.to_string()),
+            None => self.root.lookup(key).and_then(Value::rendered),
+        }
+    }
+
+    fn get_value(&self, key: &str) -> Option<Value> {
+        self.root.lookup(key).cloned()
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        match self.values.get(key) {
+            Some(value) => Some(Cow::Borrowed(value.as_str())),
+            None => self
+                .root
+                .lookup(key)
+                .and_then(Value::rendered)
+                .map(Cow::Owned),
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+}
+
+impl fmt::Debug for Toml {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redact::debug(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::toml::Toml;
+    use crate::{Config, Error};
+
+    #[test]
+    fn parse() {
+        let cfg = Toml::from_str(
+            r#"
+            debug = true
+            tags = ["a", "b"]
+
+            [mongo]
+            uri = "mongodb://"
+            port = 27017
+            started = 2015-05-15T05:05:05Z
+            "#,
+        )
+        .unwrap();
+        assert!(cfg.bool("debug"));
+        assert_eq!(cfg.list("tags"), vec!["a", "b"]);
+        assert_eq!(cfg.get("mongo.uri"), Some("mongodb://".to_string()));
+        assert_eq!(cfg.int("mongo.port"), 27017);
+        assert_eq!(
+            cfg.get("mongo.started"),
+            Some("2015-05-15T05:05:05Z".to_string())
+        );
+
+        assert!(matches!(Toml::from_str("a = "), Err(Error::Parse(_))));
+    }
+}
@@ -0,0 +1,192 @@
+//! Feature flags from an Unleash server.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::RwLock;
+
+use crate::flags::FlagService;
+use crate::http::get_json;
+use crate::redact::REDACTED;
+use crate::Error;
+
+/// A flag as far as it can be evaluated without a context.
+#[derive(Debug, PartialEq)]
+struct Flag {
+    enabled: bool,
+    variant: Option<String>,
+}
+
+/// The feature flags of an Unleash server, for use with `FlagLayer`.
+///
+/// A config lookup has no user or session to evaluate strategies
+/// against, so a flag is only on if it's enabled and has no
+/// strategies, the `default` strategy, or a rollout at 100% without
+/// constraints. Its variant is the one with the largest weight.
+/// Anything that depends on a context needs a full Unleash client.
+///
+/// The flags are fetched when connecting and again with `refresh`.
+pub struct Unleash {
+    url: String,
+    token: String,
+    flags: RwLock<HashMap<String, Flag>>,
+}
+
+impl Unleash {
+    /// Fetch the flags from the client API at the given URL (e.g.
+    /// `https://unleash.example.com/api`) with the given client token.
+    pub fn connect(url: &str, token: &str) -> Result<Self, Error> {
+        let unleash = Self {
+            url: url.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+            flags: RwLock::new(HashMap::new()),
+        };
+        unleash.refresh()?;
+        Ok(unleash)
+    }
+
+    /// Fetch the flags again. If that fails, the current flags are
+    /// kept and the error is returned.
+    pub fn refresh(&self) -> Result<(), Error> {
+        let v = get_json(
+            &format!("{}/client/features", self.url),
+            &[
+                ("Authorization", &self.token),
+                ("UNLEASH-APPNAME", env!("CARGO_PKG_NAME")),
+            ],
+        )?;
+        let features = match v.get("features").and_then(|f| f.as_array()) {
+            Some(features) => features,
+            None => return Err(Error::Parse("expected features".to_string())),
+        };
+
+        let mut flags = HashMap::new();
+        for feature in features {
+            let name = match feature.get("name").and_then(|n| n.as_str()) {
+                Some(name) => name,
+                None => continue,
+            };
+            let enabled = feature
+                .get("enabled")
+                .and_then(|e| e.as_bool())
+                .unwrap_or(false)
+                && always_on(feature.get("strategies"));
+            let variant = match enabled {
+                true => heaviest(feature.get("variants")),
+                false => None,
+            };
+            flags.insert(name.to_string(), Flag { enabled, variant });
+        }
+        *self.flags.write().unwrap() = flags;
+        Ok(())
+    }
+}
+
+/// Whether the strategies turn a flag on for everyone.
+fn always_on(strategies: Option<&serde_json::Value>) -> bool {
+    let strategies = match strategies.and_then(|s| s.as_array()) {
+        Some(strategies) if !strategies.is_empty() => strategies,
+        _ => return true,
+    };
+    strategies.iter().any(|s| {
+        let constrained = s
+            .get("constraints")
+            .and_then(|c| c.as_array())
+            .is_some_and(|c| !c.is_empty());
+        let rollout = ["rollout", "percentage"].iter().find_map(|p| {
+            let p = s.get("parameters")?.get(p)?;
+            p.as_str()
+                .and_then(|p| p.parse().ok())
+                .or_else(|| p.as_f64())
+        });
+        !constrained
+            && match s.get("name").and_then(|n| n.as_str()) {
+                Some("default") => true,
+                Some("flexibleRollout") | Some("gradualRolloutRandom") => {
+                    rollout.is_some_and(|r: f64| r >= 100.0)
+                }
+                _ => false,
+            }
+    })
+}
+
+/// The name of the variant with the largest weight.
+fn heaviest(variants: Option<&serde_json::Value>) -> Option<String> {
+    let mut best: Option<(i64, &str)> = None;
+    for v in variants?.as_array()? {
+        let weight = v.get("weight").and_then(|w| w.as_i64()).unwrap_or(0);
+        let name = v.get("name").and_then(|n| n.as_str())?;
+        if best.map_or(true, |(w, _)| weight > w) {
+            best = Some((weight, name));
+        }
+    }
+    best.map(|(_, name)| name.to_string())
+}
+
+impl FlagService for Unleash {
+    fn enabled(&self, name: &str) -> Option<bool> {
+        self.flags.read().unwrap().get(name).map(|f| f.enabled)
+    }
+
+    fn variant(&self, name: &str) -> Option<String> {
+        self.flags.read().unwrap().get(name)?.variant.clone()
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.flags.read().unwrap().keys().cloned().collect()
+    }
+}
+
+impl fmt::Debug for Unleash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Unleash")
+            .field("url", &self.url)
+            .field("token", &REDACTED)
+            .field("flags", &self.flags.read().unwrap().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::flags::FlagLayer;
+    use crate::http::serve;
+    use crate::unleash::Unleash;
+    use crate::Config;
+
+    #[test]
+    fn unleash() {
+        let (url, server) = serve(1, |_| {
+            let body = r#"{"version": 2, "features": [
+                {"name": "new_ui", "enabled": true, "strategies": [{"name": "default"}],
+                 "variants": [{"name": "blue", "weight": 300}, {"name": "green", "weight": 700}]},
+                {"name": "everyone", "enabled": true,
+                 "strategies": [{"name": "flexibleRollout", "parameters": {"rollout": "100"}}]},
+                {"name": "half", "enabled": true,
+                 "strategies": [{"name": "flexibleRollout", "parameters": {"rollout": "50"}}]},
+                {"name": "admins", "enabled": true, "strategies": [{"name": "default",
+                 "constraints": [{"contextName": "userId", "operator": "IN", "values": ["a"]}]}]},
+                {"name": "off", "enabled": false}
+            ]}"#;
+            (200, body.to_string())
+        });
+        let unleash = Unleash::connect(&format!("{}/api/", url), "token");
+        let request = &server.join().unwrap()[0];
+        assert_eq!(request[0], "GET /api/client/features HTTP/1.1");
+        assert!(request
+            .iter()
+            .any(|h| h.to_lowercase() == "authorization: token"));
+
+        let cfg = FlagLayer::new(unleash.unwrap(), "flags");
+        assert!(cfg.bool("flags.new_ui"));
+        assert_eq!(cfg.string("flags.new_ui.variant"), "green");
+        assert!(cfg.bool("flags.everyone"));
+        assert!(!cfg.bool("flags.half"));
+        assert!(!cfg.bool("flags.admins"));
+        assert!(!cfg.bool("flags.off"));
+        assert_eq!(cfg.get("flags.missing"), None);
+
+        // The server is gone, so the flags are kept.
+        assert!(cfg.service().refresh().is_err());
+        assert!(cfg.bool("flags.new_ui"));
+    }
+}
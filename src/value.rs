@@ -0,0 +1,364 @@
+//! A structured value, used by the hierarchical formats before they
+//! are flattened into dot-notation keys and by `Config::get_value`.
+
+use alloc::collections::BTreeMap;
+#[cfg(feature = "std")]
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+/// A value with its structure intact, rather than rendered into a
+/// string.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    List(Vec<Value>),
+    Map(BTreeMap<String, Value>),
+}
+
+impl Value {
+    /// Flatten the value into dot-notation keys (e.g. `{"mongo":
+    /// {"uri": "..."}}` => `mongo.uri`). Lists are rendered with the
+    /// bracketed syntax `Config::list` understands and nulls are
+    /// skipped.
+    #[cfg(feature = "std")]
+    pub fn flatten(&self) -> HashMap<String, String> {
+        let mut values = HashMap::new();
+        self.flatten_into("", &mut values);
+        values
+    }
+
+    /// Find the value at the given dot-notation key. Parts of the key
+    /// index into lists (e.g. `servers.0.host`) and map keys that
+    /// contain dots themselves are matched too.
+    pub fn lookup(&self, key: &str) -> Option<&Value> {
+        let parts: Vec<&str> = key.split('.').collect();
+        self.lookup_parts(&parts)
+    }
+
+    fn lookup_parts(&self, parts: &[&str]) -> Option<&Value> {
+        if parts.is_empty() {
+            return Some(self);
+        }
+        let m = match self {
+            Value::Map(m) => m,
+            Value::List(l) => {
+                let i = parts[0].parse::<usize>().ok()?;
+                return l.get(i)?.lookup_parts(&parts[1..]);
+            }
+            _ => return None,
+        };
+        // Try the longest key first so `a.b` matches before `a`.
+        (1..=parts.len()).rev().find_map(|i| {
+            m.get(&parts[..i].join("."))
+                .and_then(|v| v.lookup_parts(&parts[i..]))
+        })
+    }
+
+    /// The value rendered the way `flatten` would, so indexed keys
+    /// read like any other. Nulls and maps aren't values of their own
+    /// and give `None`.
+    // Only the structured formats use it, which are all optional.
+    #[allow(dead_code)]
+    pub(crate) fn rendered(&self) -> Option<String> {
+        match self {
+            Value::Null | Value::Map(_) => None,
+            v => Some(v.to_string()),
+        }
+    }
+
+    /// Parse a string value using the syntax `Config::list` and
+    /// `Config::map` understand: `[a, b]` is a list, `{a=>1, b=>2}` is
+    /// a map (both may be nested), `true` and `false` are bools and
+    /// numbers are ints or floats. Anything else is a string.
+    pub fn parse(s: &str) -> Value {
+        Value::parse_with(s, Value::scalar)
+    }
+
+    /// Similar to `parse` but scalars are kept as strings, exactly as
+    /// they're written (e.g. `02134` or `1.10`), so the value renders
+    /// back the same.
+    #[cfg(feature = "std")]
+    pub(crate) fn parse_raw(s: &str) -> Value {
+        Value::parse_with(s, |s| Value::String(s.to_string()))
+    }
+
+    fn parse_with(s: &str, scalar: fn(&str) -> Value) -> Value {
+        let s = s.trim();
+        if s.len() >= 2 && s.starts_with('[') && s.ends_with(']') {
+            return Value::List(
+                split(&s[1..s.len() - 1])
+                    .map(|v| Value::parse_with(v, scalar))
+                    .collect(),
+            );
+        }
+        if s.len() >= 2 && s.starts_with('{') && s.ends_with('}') {
+            let m = split(&s[1..s.len() - 1])
+                .map(|pair| match pair.split_once("=>") {
+                    Some((k, v)) => (k.trim().to_string(), Value::parse_with(v, scalar)),
+                    None => (pair.trim().to_string(), Value::Null),
+                })
+                .collect();
+            return Value::Map(m);
+        }
+        scalar(s)
+    }
+
+    fn scalar(s: &str) -> Value {
+        match s {
+            "true" => return Value::Bool(true),
+            "false" => return Value::Bool(false),
+            _ => {}
+        }
+        if let Ok(i) = s.parse::<i64>() {
+            return Value::Int(i);
+        }
+        // Only plain numbers, not `inf` or `NaN`.
+        let numeric = s
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | 'e' | 'E'));
+        match s.parse::<f64>() {
+            Ok(f) if numeric => Value::Float(f),
+            _ => Value::String(s.to_string()),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn flatten_into(&self, prefix: &str, values: &mut HashMap<String, String>) {
+        match self {
+            Value::Null => {}
+            Value::Map(m) => {
+                for (k, v) in m.iter() {
+                    let key = match prefix.is_empty() {
+                        true => k.to_string(),
+                        false => format!("{}.{}", prefix, k),
+                    };
+                    v.flatten_into(&key, values);
+                }
+            }
+            _ => {
+                if !prefix.is_empty() {
+                    values.insert(prefix.to_string(), self.to_string());
+                }
+            }
+        }
+    }
+}
+
+/// The text of the element at the dot-notation path (e.g. `0.host`)
+/// within a list or map written in the syntax of `Value::parse`,
+/// exactly as it's written, so `02134` isn't read as the number 2134.
+pub(crate) fn element_text<'a>(s: &'a str, path: &[&str]) -> Option<&'a str> {
+    let s = s.trim();
+    if path.is_empty() {
+        return Some(s);
+    }
+    if s.len() >= 2 && s.starts_with('[') && s.ends_with(']') {
+        let i = path[0].parse::<usize>().ok()?;
+        return element_text(split(&s[1..s.len() - 1]).nth(i)?, &path[1..]);
+    }
+    if s.len() >= 2 && s.starts_with('{') && s.ends_with('}') {
+        let pairs: Vec<(&str, &str)> = split(&s[1..s.len() - 1])
+            .filter_map(|pair| pair.split_once("=>"))
+            .map(|(k, v)| (k.trim(), v))
+            .collect();
+        // Try the longest key first so `a.b` matches before `a`.
+        return (1..=path.len()).rev().find_map(|n| {
+            let key = path[..n].join(".");
+            let (_, v) = pairs.iter().find(|(k, _)| *k == key)?;
+            element_text(v, &path[n..])
+        });
+    }
+    None
+}
+
+/// Split the inside of a list or map on the commas that aren't within
+/// a nested list or map. Empty items are skipped.
+fn split(s: &str) -> impl Iterator<Item = &str> {
+    let mut items = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '[' | '{' => depth += 1,
+            ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&s[start..]);
+    items.into_iter().map(str::trim).filter(|i| !i.is_empty())
+}
+
+#[cfg(feature = "serde_json")]
+impl From<serde_json::Value> for Value {
+    fn from(v: serde_json::Value) -> Self {
+        match v {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(b) => Value::Bool(b),
+            serde_json::Value::Number(n) => match n.as_i64() {
+                Some(i) => Value::Int(i),
+                None => Value::Float(n.as_f64().unwrap_or(f64::NAN)),
+            },
+            serde_json::Value::String(s) => Value::String(s),
+            serde_json::Value::Array(l) => Value::List(l.into_iter().map(Value::from).collect()),
+            serde_json::Value::Object(m) => {
+                Value::Map(m.into_iter().map(|(k, v)| (k, Value::from(v))).collect())
+            }
+        }
+    }
+}
+
+#[cfg(feature = "toml")]
+impl From<::toml::Value> for Value {
+    fn from(v: ::toml::Value) -> Self {
+        match v {
+            ::toml::Value::String(s) => Value::String(s),
+            ::toml::Value::Integer(i) => Value::Int(i),
+            ::toml::Value::Float(f) => Value::Float(f),
+            ::toml::Value::Boolean(b) => Value::Bool(b),
+            ::toml::Value::Datetime(d) => Value::String(d.to_string()),
+            ::toml::Value::Array(l) => Value::List(l.into_iter().map(Value::from).collect()),
+            ::toml::Value::Table(m) => {
+                Value::Map(m.into_iter().map(|(k, v)| (k, Value::from(v))).collect())
+            }
+        }
+    }
+}
+
+#[cfg(feature = "serde_yaml")]
+impl From<serde_yaml::Value> for Value {
+    fn from(v: serde_yaml::Value) -> Self {
+        match v {
+            serde_yaml::Value::Null => Value::Null,
+            serde_yaml::Value::Bool(b) => Value::Bool(b),
+            serde_yaml::Value::Number(n) => match n.as_i64() {
+                Some(i) => Value::Int(i),
+                None => Value::Float(n.as_f64().unwrap_or(f64::NAN)),
+            },
+            serde_yaml::Value::String(s) => Value::String(s),
+            serde_yaml::Value::Sequence(l) => Value::List(l.into_iter().map(Value::from).collect()),
+            serde_yaml::Value::Mapping(m) => Value::Map(
+                m.into_iter()
+                    .map(|(k, v)| (Value::from(k).to_string(), Value::from(v)))
+                    .collect(),
+            ),
+            serde_yaml::Value::Tagged(t) => Value::from(t.value),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    /// Scalars are written as-is, lists as `[a, b]` and maps as
+    /// `{a=>1, b=>2}`, matching the syntax of `Config::list` and
+    /// `Config::map`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::String(s) => write!(f, "{}", s),
+            Value::List(l) => {
+                write!(f, "[")?;
+                for (i, v) in l.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                write!(f, "]")
+            }
+            Value::Map(m) => {
+                write!(f, "{{")?;
+                for (i, (k, v)) in m.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}=>{}", k, v)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::value::Value;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn element_text() {
+        use crate::value::element_text;
+
+        let s = "[02134, {host=>a, ports=>[80, 443]}, 1.10]";
+        assert_eq!(element_text(s, &["0"]), Some("02134"));
+        assert_eq!(element_text(s, &["1", "host"]), Some("a"));
+        assert_eq!(element_text(s, &["1", "ports", "1"]), Some("443"));
+        assert_eq!(element_text(s, &["1", "ports"]), Some("[80, 443]"));
+        assert_eq!(element_text(s, &["2"]), Some("1.10"));
+        assert_eq!(element_text(s, &["3"]), None);
+        assert_eq!(element_text(s, &["host"]), None);
+        assert_eq!(element_text("plain", &["0"]), None);
+    }
+
+    #[test]
+    fn flatten() {
+        let mut mongo = BTreeMap::new();
+        mongo.insert("uri".to_string(), Value::String("mongodb://".to_string()));
+        mongo.insert("port".to_string(), Value::Int(27017));
+        mongo.insert("skip".to_string(), Value::Null);
+        let mut root = BTreeMap::new();
+        root.insert("mongo".to_string(), Value::Map(mongo));
+        root.insert(
+            "list".to_string(),
+            Value::List(vec![Value::Int(1), Value::Bool(true)]),
+        );
+
+        let flat = Value::Map(root).flatten();
+        assert_eq!(flat.len(), 3);
+        assert_eq!(flat["mongo.uri"], "mongodb://");
+        assert_eq!(flat["mongo.port"], "27017");
+        assert_eq!(flat["list"], "[1, true]");
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(Value::parse(" 42 "), Value::Int(42));
+        assert_eq!(Value::parse("1.5"), Value::Float(1.5));
+        assert_eq!(Value::parse("true"), Value::Bool(true));
+        assert_eq!(Value::parse("inf"), Value::String("inf".to_string()));
+        assert_eq!(Value::parse("[]"), Value::List(vec![]));
+        let nested = Value::parse("{a=>[1, x], b=>{c=>false}}");
+        assert_eq!(
+            nested.lookup("a"),
+            Some(&Value::List(vec![
+                Value::Int(1),
+                Value::String("x".to_string())
+            ]))
+        );
+        assert_eq!(nested.lookup("b.c"), Some(&Value::Bool(false)));
+        assert_eq!(nested.lookup("b.d"), None);
+        assert_eq!(nested.lookup("a.1"), Some(&Value::String("x".to_string())));
+        assert_eq!(nested.lookup("a.2"), None);
+        assert_eq!(nested.lookup("a.x"), None);
+        assert_eq!(
+            nested.lookup("a.0").unwrap().rendered(),
+            Some("1".to_string())
+        );
+        assert_eq!(nested.lookup("b").unwrap().rendered(), None);
+        assert_eq!(Value::parse(&nested.to_string()), nested);
+    }
+}
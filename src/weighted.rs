@@ -0,0 +1,74 @@
+//! Values that pick one of several options by weight.
+
+use alloc::vec::Vec;
+
+/// What a weighted value should look like, for errors.
+pub(crate) const EXPECTED: &str = "weighted choice like `choose[a:80, b:20]`";
+
+/// Parse a value like `choose[control:80, treatment:20]` into its
+/// options and weights. Returns `None` if it isn't a weighted value
+/// or it's malformed (e.g. a missing weight or all weights are zero).
+pub(crate) fn parse(s: &str) -> Option<Vec<(&str, u64)>> {
+    let inner = s.trim().strip_prefix("choose[")?.strip_suffix(']')?;
+    let options = inner
+        .split(',')
+        .map(|option| {
+            let (name, weight) = option.rsplit_once(':')?;
+            let name = name.trim();
+            match name.is_empty() {
+                true => None,
+                false => Some((name, weight.trim().parse().ok()?)),
+            }
+        })
+        .collect::<Option<Vec<(&str, u64)>>>()?;
+    let total = options
+        .iter()
+        .try_fold(0u64, |t, (_, w)| t.checked_add(*w))?;
+    match total > 0 {
+        true => Some(options),
+        false => None,
+    }
+}
+
+/// Whether the value is meant to be a weighted value, so malformed
+/// ones are errors instead of plain values.
+pub(crate) fn is_weighted(s: &str) -> bool {
+    s.trim_start().starts_with("choose[")
+}
+
+/// Pick an option using the given hash. The same hash always picks
+/// the same option and each option gets its share of hashes.
+pub(crate) fn pick<'a>(options: &[(&'a str, u64)], hash: u64) -> &'a str {
+    let total: u64 = options.iter().map(|(_, w)| w).sum();
+    let mut point = hash % total;
+    for (name, weight) in options {
+        if point < *weight {
+            return name;
+        }
+        point -= weight;
+    }
+    unreachable!("the point is less than the total weight")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::weighted::{parse, pick};
+
+    #[test]
+    fn weighted() {
+        let options = parse("choose[control:80, treatment : 20]").unwrap();
+        assert_eq!(options, vec![("control", 80), ("treatment", 20)]);
+        assert_eq!(pick(&options, 0), "control");
+        assert_eq!(pick(&options, 79), "control");
+        assert_eq!(pick(&options, 80), "treatment");
+        assert_eq!(pick(&options, 199), "treatment");
+        assert_eq!(parse("choose[a:0, b:1]"), Some(vec![("a", 0), ("b", 1)]));
+
+        assert_eq!(parse("control"), None);
+        assert_eq!(parse("choose[a:0]"), None);
+        assert_eq!(parse("choose[a:50, b]"), None);
+        assert_eq!(parse("choose[:50]"), None);
+        assert_eq!(parse("choose[a:-1]"), None);
+        assert_eq!(parse("choose[a:50"), None);
+    }
+}
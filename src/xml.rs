@@ -0,0 +1,150 @@
+//! Configuration from XML documents.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::format::read_file;
+use crate::{redact, Config, Error};
+
+#[derive(PartialEq, Clone)]
+pub struct Xml {
+    values: HashMap<String, String>,
+}
+
+impl Xml {
+    /// Create a new configuration from the given XML document.
+    /// Elements are flattened into dot-notation keys starting with the
+    /// root element, attributes are prefixed with `@` and the trimmed
+    /// text of an element is the value of the element's key. Sibling
+    /// elements with the same name are indexed. For example:
+    ///
+    /// ```xml
+    /// <server>
+    ///   <name>main</name>
+    ///   <listener port="80"/>
+    ///   <listener port="443" tls="true"/>
+    /// </server>
+    /// ```
+    ///
+    /// provides `server.name`, `server.listener.0.@port`,
+    /// `server.listener.1.@port` and `server.listener.1.@tls`.
+    /// Namespace prefixes are dropped.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Self, Error> {
+        let doc = match roxmltree::Document::parse(s) {
+            Ok(doc) => doc,
+            Err(e) => return Err(Error::Parse(e.to_string())),
+        };
+        let mut values = HashMap::new();
+        let root = doc.root_element();
+        flatten(root, root.tag_name().name(), &mut values);
+        Ok(Self { values })
+    }
+
+    /// Similar to `from_str` except that the given path is used as
+    /// the contents for the string to parse.
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let file = read_file(path)?;
+        Self::from_str(&file)
+    }
+}
+
+fn flatten(node: roxmltree::Node, prefix: &str, values: &mut HashMap<String, String>) {
+    for attr in node.attributes() {
+        values.insert(
+            format!("{}.@{}", prefix, attr.name()),
+            attr.value().to_string(),
+        );
+    }
+
+    let text: String = node
+        .children()
+        .filter(|c| c.is_text())
+        .filter_map(|c| c.text())
+        .collect();
+    let text = text.trim();
+    if !text.is_empty() {
+        values.insert(prefix.to_string(), text.to_string());
+    }
+
+    // Count the children by name so repeated ones can be indexed.
+    let elements: Vec<roxmltree::Node> = node.children().filter(|c| c.is_element()).collect();
+    let mut counts = BTreeMap::new();
+    for child in elements.iter() {
+        *counts.entry(child.tag_name().name()).or_insert(0) += 1;
+    }
+
+    let mut seen: BTreeMap<&str, usize> = BTreeMap::new();
+    for child in elements.iter() {
+        let name = child.tag_name().name();
+        let key = match counts[name] {
+            1 => format!("{}.{}", prefix, name),
+            _ => {
+                let i = seen.entry(name).or_insert(0);
+                *i += 1;
+                format!("{}.{}.{}", prefix, name, *i - 1)
+            }
+        };
+        flatten(*child, &key, values);
+    }
+}
+
+impl Config for Xml {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values.get(key).map(|value| value.to_string())
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.values
+            .get(key)
+            .map(|value| Cow::Borrowed(value.as_str()))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+}
+
+impl fmt::Debug for Xml {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redact::debug(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::xml::Xml;
+    use crate::{Config, Error};
+
+    #[test]
+    fn parse() {
+        let cfg = Xml::from_str(
+            r#"<?xml version="1.0"?>
+            <!-- a comment -->
+            <server xmlns:x="urn:example" version="2">
+              <name>  main  </name>
+              <listener port="80"/>
+              <listener port="443" tls="true"><cert>/etc/cert.pem</cert></listener>
+              <x:limits max="10"/>
+              <motd><![CDATA[hello & welcome]]></motd>
+            </server>"#,
+        )
+        .unwrap();
+
+        assert_eq!(cfg.get("server.@version"), Some("2".to_string()));
+        assert_eq!(cfg.get("server.name"), Some("main".to_string()));
+        assert_eq!(cfg.int("server.listener.0.@port"), 80);
+        assert_eq!(cfg.int("server.listener.1.@port"), 443);
+        assert!(cfg.bool("server.listener.1.@tls"));
+        assert_eq!(
+            cfg.get("server.listener.1.cert"),
+            Some("/etc/cert.pem".to_string())
+        );
+        assert_eq!(cfg.get("server.listener.@port"), None);
+        assert_eq!(cfg.int("server.limits.@max"), 10);
+        assert_eq!(cfg.get("server.motd"), Some("hello & welcome".to_string()));
+
+        assert!(matches!(Xml::from_str("<a><b></a>"), Err(Error::Parse(_))));
+    }
+}
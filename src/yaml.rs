@@ -0,0 +1,109 @@
+//! Configuration from YAML documents.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::format::read_file;
+use crate::value::Value;
+use crate::{redact, Config, Error};
+
+#[derive(PartialEq, Clone)]
+pub struct Yaml {
+    root: Value,
+    values: HashMap<String, String>,
+}
+
+impl Yaml {
+    /// Create a new configuration from the given YAML document. The
+    /// document must be a mapping and is flattened into dot-notation
+    /// keys, so `mongo: {uri: ...}` is available as `mongo.uri`.
+    /// Sequences are rendered using the syntax `Config::list` expects,
+    /// nulls are skipped and tags are ignored.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Self, Error> {
+        match serde_yaml::from_str::<serde_yaml::Value>(s) {
+            Ok(v @ serde_yaml::Value::Mapping(_)) => {
+                let root = Value::from(v);
+                Ok(Self {
+                    values: root.flatten(),
+                    root,
+                })
+            }
+            Ok(_) => Err(Error::Parse("expected a mapping".to_string())),
+            Err(e) => Err(Error::Parse(e.to_string())),
+        }
+    }
+
+    /// Similar to `from_str` except that the given path is used as
+    /// the contents for the string to parse.
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let file = read_file(path)?;
+        Self::from_str(&file)
+    }
+}
+
+impl Config for Yaml {
+    /// Elements of arrays are available by index (e.g.
+    /// `servers.0.host`).
+    fn get(&self, key: &str) -> Option<String> {
+        match self.values.get(key) {
+            Some(value) => Some(value.to_string()),
+            None => self.root.lookup(key).and_then(Value::rendered),
+        }
+    }
+
+    fn get_value(&self, key: &str) -> Option<Value> {
+        self.root.lookup(key).cloned()
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        match self.values.get(key) {
+            Some(value) => Some(Cow::Borrowed(value.as_str())),
+            None => self
+                .root
+                .lookup(key)
+                .and_then(Value::rendered)
+                .map(Cow::Owned),
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+}
+
+impl fmt::Debug for Yaml {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redact::debug(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::yaml::Yaml;
+    use crate::{Config, Error};
+
+    #[test]
+    fn parse() {
+        let cfg = Yaml::from_str(
+            r#"
+            debug: yes
+            tags: [a, b]
+            mongo:
+              uri: "mongodb://"
+              port: 27017
+              replica: ~
+            "#,
+        )
+        .unwrap();
+        assert!(cfg.bool("debug"));
+        assert_eq!(cfg.list("tags"), vec!["a", "b"]);
+        assert_eq!(cfg.get("mongo.uri"), Some("mongodb://".to_string()));
+        assert_eq!(cfg.int("mongo.port"), 27017);
+        assert_eq!(cfg.get("mongo.replica"), None);
+
+        assert!(matches!(Yaml::from_str("- a"), Err(Error::Parse(_))));
+        assert!(matches!(Yaml::from_str("a: [b"), Err(Error::Parse(_))));
+    }
+}
@@ -0,0 +1,599 @@
+//! Configuration from a ZooKeeper znode subtree.
+//!
+//! This speaks just enough of the ZooKeeper protocol to read a subtree
+//! and watch it for changes; there is no support for writing, ACLs or
+//! authentication.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{Config, Error, SourceHealth};
+
+const SESSION_TIMEOUT: Duration = Duration::from_secs(10);
+const RETRY: Duration = Duration::from_secs(1);
+/// The largest frame read from the server, the same as the default
+/// `jute.maxbuffer` of the Java client, so a corrupt or hostile length
+/// can't make the client allocate gigabytes.
+const MAX_FRAME: usize = 4 << 20;
+
+const GET_DATA: i32 = 4;
+const GET_CHILDREN: i32 = 8;
+const PING: i32 = 11;
+const CLOSE: i32 = -11;
+
+const WATCH_XID: i32 = -1;
+const PING_XID: i32 = -2;
+const NO_NODE: i32 = -101;
+
+/// State shared with the thread watching the subtree.
+struct Shared {
+    values: RwLock<HashMap<String, String>>,
+    stream: Mutex<Option<TcpStream>>,
+    error: Mutex<Option<Error>>,
+    closed: AtomicBool,
+}
+
+/// The znodes under a root path, as keys relative to the root with
+/// dots for slashes (`/app/db/host` under `/app` is `db.host`). Znodes
+/// without data (e.g. ones that only have children) have no key.
+///
+/// The subtree is watched, so changes on the server show up without
+/// reloading. If the connection is lost, or the server stops answering
+/// for the length of the session timeout (10 seconds), the last values
+/// are kept and `health` reports the error until a reconnect succeeds.
+pub struct ZooKeeper {
+    shared: Arc<Shared>,
+}
+
+impl ZooKeeper {
+    /// Connect to the server at the given address (`host:port`), read
+    /// the subtree under the given root and watch it.
+    pub fn connect(addr: &str, root: &str) -> Result<Self, Error> {
+        Self::connect_with(addr, root, SESSION_TIMEOUT)
+    }
+
+    fn connect_with(addr: &str, root: &str, session: Duration) -> Result<Self, Error> {
+        let root = root.trim_end_matches('/').to_string();
+        let remote = |e: io::Error| Error::Remote(format!("{}: {}", addr, e));
+        let mut conn = Connection::open(addr, session).map_err(remote)?;
+        let mut dirty = false;
+        let values = load(&mut conn, &root, &mut dirty).map_err(remote)?;
+
+        let shared = Arc::new(Shared {
+            values: RwLock::new(values),
+            stream: Mutex::new(conn.stream.try_clone().ok()),
+            error: Mutex::new(None),
+            closed: AtomicBool::new(false),
+        });
+        let watcher = shared.clone();
+        let addr = addr.to_string();
+        thread::spawn(move || watch(conn, &addr, &root, dirty, &watcher));
+        Ok(Self { shared })
+    }
+}
+
+impl Drop for ZooKeeper {
+    fn drop(&mut self) {
+        self.shared.closed.store(true, Ordering::SeqCst);
+        if let Some(stream) = self.shared.stream.lock().unwrap().as_ref() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// Reload the subtree whenever a watch fires, reconnecting when the
+/// connection is lost, until the `ZooKeeper` is dropped.
+fn watch(mut conn: Connection, addr: &str, root: &str, mut dirty: bool, shared: &Shared) {
+    loop {
+        let err = match serve(&mut conn, root, dirty, shared) {
+            Ok(()) => return,
+            Err(e) => e,
+        };
+        loop {
+            if shared.closed.load(Ordering::SeqCst) {
+                let _ = conn.close();
+                return;
+            }
+            *shared.error.lock().unwrap() = Some(Error::Remote(format!("{}: {}", addr, err)));
+            thread::sleep(RETRY);
+
+            let mut reconnected = match Connection::open(addr, conn.session) {
+                Ok(conn) => conn,
+                Err(_) => continue,
+            };
+            dirty = false;
+            if let Ok(values) = load(&mut reconnected, root, &mut dirty) {
+                *shared.values.write().unwrap() = values;
+                *shared.stream.lock().unwrap() = reconnected.stream.try_clone().ok();
+                *shared.error.lock().unwrap() = None;
+                conn = reconnected;
+                break;
+            }
+        }
+    }
+}
+
+/// Wait for watch events on the connection, pinging it to keep the
+/// session alive. Returns once the `ZooKeeper` is dropped, or with an
+/// error when nothing (not even a ping's answer) has been heard from
+/// the server for the length of the session.
+fn serve(conn: &mut Connection, root: &str, mut dirty: bool, shared: &Shared) -> io::Result<()> {
+    conn.stream.set_read_timeout(Some(conn.session / 3))?;
+    let mut heard = Instant::now();
+    loop {
+        if shared.closed.load(Ordering::SeqCst) {
+            return conn.close();
+        }
+        if dirty {
+            dirty = false;
+            let values = load(conn, root, &mut dirty)?;
+            *shared.values.write().unwrap() = values;
+            continue;
+        }
+        match conn.recv() {
+            Ok(mut frame) => {
+                heard = Instant::now();
+                dirty |= frame.int()? == WATCH_XID;
+            }
+            Err(e) if idle(&e) && heard.elapsed() >= conn.session => {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "the server stopped answering",
+                ))
+            }
+            Err(e) if idle(&e) => conn.send(PING_XID, PING, &[])?,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Whether the error is a read timing out, rather than the connection
+/// failing.
+fn idle(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+/// Read the subtree under root, setting watches on every znode.
+fn load(
+    conn: &mut Connection,
+    root: &str,
+    dirty: &mut bool,
+) -> io::Result<HashMap<String, String>> {
+    let mut values = HashMap::new();
+    let mut pending = vec![root.to_string()];
+    while let Some(path) = pending.pop() {
+        let node = if path.is_empty() { "/" } else { path.as_str() };
+        let mut request = Frame::default();
+        request.put_string(node);
+        request.put_bool(true);
+
+        let mut children = match conn.call(GET_CHILDREN, &request, dirty)? {
+            Some(children) => children,
+            // Deleted since its parent was read.
+            None => continue,
+        };
+        for _ in 0..children.int()? {
+            pending.push(format!("{}/{}", path, children.string()?));
+        }
+
+        if path.len() > root.len() {
+            if let Some(mut data) = conn.call(GET_DATA, &request, dirty)? {
+                let value = data.buffer()?;
+                if !value.is_empty() {
+                    let key = path[root.len() + 1..].replace('/', ".");
+                    values.insert(key, String::from_utf8_lossy(&value).to_string());
+                }
+            }
+        }
+    }
+    Ok(values)
+}
+
+/// A connection with an established session.
+struct Connection {
+    stream: TcpStream,
+    xid: i32,
+    session: Duration,
+}
+
+impl Connection {
+    fn open(addr: &str, session: Duration) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_read_timeout(Some(session))?;
+        let mut conn = Self {
+            stream,
+            xid: 0,
+            session,
+        };
+
+        let mut request = Frame::default();
+        request.put_int(0); // protocol version
+        request.put_long(0); // last zxid seen
+        request.put_int(session.as_millis() as i32);
+        request.put_long(0); // session id
+        request.put_bytes(&[0; 16]); // password
+        conn.write(&request.0)?;
+        conn.recv()?;
+        Ok(conn)
+    }
+
+    fn write(&mut self, body: &[u8]) -> io::Result<()> {
+        let mut frame = (body.len() as i32).to_be_bytes().to_vec();
+        frame.extend_from_slice(body);
+        self.stream.write_all(&frame)
+    }
+
+    fn send(&mut self, xid: i32, op: i32, body: &[u8]) -> io::Result<()> {
+        let mut request = Frame::default();
+        request.put_int(xid);
+        request.put_int(op);
+        request.0.extend_from_slice(body);
+        self.write(&request.0)
+    }
+
+    /// Read a frame. Timing out before any of it has arrived only
+    /// means the server had nothing to say; timing out part way
+    /// through leaves the stream out of step, so that's an error like
+    /// any other.
+    fn recv(&mut self) -> io::Result<Frame> {
+        let mut len = [0; 4];
+        let mut read = 0;
+        while read < len.len() {
+            match self.stream.read(&mut len[read..]) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => read += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) if read == 0 => return Err(e),
+                Err(e) => return Err(cut_short(e)),
+            }
+        }
+        let len = i32::from_be_bytes(len);
+        if len < 0 || len as usize > MAX_FRAME {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("frame of {} bytes", len),
+            ));
+        }
+        let mut body = vec![0; len as usize];
+        self.stream.read_exact(&mut body).map_err(cut_short)?;
+        Ok(Frame(body))
+    }
+
+    /// Send a request and return its response, or `None` if the znode
+    /// doesn't exist. Watch events that arrive in the meantime mark
+    /// the subtree dirty.
+    fn call(&mut self, op: i32, request: &Frame, dirty: &mut bool) -> io::Result<Option<Frame>> {
+        self.xid += 1;
+        self.send(self.xid, op, &request.0)?;
+        loop {
+            let mut response = self.recv()?;
+            let xid = response.int()?;
+            response.long()?; // zxid
+            let err = response.int()?;
+            match xid {
+                WATCH_XID => *dirty = true,
+                PING_XID => {}
+                _ if err == NO_NODE => return Ok(None),
+                _ if err != 0 => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        format!("error code {}", err),
+                    ))
+                }
+                _ => return Ok(Some(response)),
+            }
+        }
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        self.xid += 1;
+        self.send(self.xid, CLOSE, &[])
+    }
+}
+
+/// The error for a frame that stopped part way through.
+fn cut_short(e: io::Error) -> io::Error {
+    match idle(&e) {
+        true => io::Error::new(io::ErrorKind::InvalidData, "frame cut short"),
+        false => e,
+    }
+}
+
+/// A message of the protocol, written at the end or read from the
+/// front.
+#[derive(Default)]
+struct Frame(Vec<u8>);
+
+impl Frame {
+    fn put_int(&mut self, i: i32) {
+        self.0.extend_from_slice(&i.to_be_bytes());
+    }
+
+    fn put_long(&mut self, l: i64) {
+        self.0.extend_from_slice(&l.to_be_bytes());
+    }
+
+    fn put_bool(&mut self, b: bool) {
+        self.0.push(b as u8);
+    }
+
+    fn put_bytes(&mut self, b: &[u8]) {
+        self.put_int(b.len() as i32);
+        self.0.extend_from_slice(b);
+    }
+
+    fn put_string(&mut self, s: &str) {
+        self.put_bytes(s.as_bytes())
+    }
+
+    fn take(&mut self, n: usize) -> io::Result<Vec<u8>> {
+        if self.0.len() < n {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let rest = self.0.split_off(n);
+        Ok(std::mem::replace(&mut self.0, rest))
+    }
+
+    fn int(&mut self) -> io::Result<i32> {
+        let mut i = [0; 4];
+        i.copy_from_slice(&self.take(4)?);
+        Ok(i32::from_be_bytes(i))
+    }
+
+    fn long(&mut self) -> io::Result<i64> {
+        let mut l = [0; 8];
+        l.copy_from_slice(&self.take(8)?);
+        Ok(i64::from_be_bytes(l))
+    }
+
+    /// A length-prefixed buffer; a length of `-1` is an empty one.
+    fn buffer(&mut self) -> io::Result<Vec<u8>> {
+        let len = self.int()?;
+        self.take(len.max(0) as usize)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        Ok(String::from_utf8_lossy(&self.buffer()?).to_string())
+    }
+}
+
+impl Config for ZooKeeper {
+    fn get(&self, key: &str) -> Option<String> {
+        self.shared.values.read().unwrap().get(key).cloned()
+    }
+
+    fn get_ref(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.get(key).map(Cow::Owned)
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.shared.values.read().unwrap().keys().cloned().collect()
+    }
+
+    /// Degraded while the connection is lost, as the values last read
+    /// are still served.
+    fn source_health(&self) -> SourceHealth {
+        match self.shared.error.lock().unwrap().as_ref() {
+            Some(e) => SourceHealth::Degraded(e.to_string()),
+            None => SourceHealth::Ok,
+        }
+    }
+}
+
+impl fmt::Debug for ZooKeeper {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ZooKeeper")
+            .field("keys", &self.shared.values.read().unwrap().len())
+            .field("healthy", &self.source_health().is_ok())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::zookeeper::{idle, Connection, Frame, MAX_FRAME, WATCH_XID};
+    use crate::{Config, ZooKeeper};
+    use std::collections::BTreeMap;
+    use std::io::{ErrorKind, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    type Tree = Arc<Mutex<BTreeMap<String, String>>>;
+
+    /// A server for a single session over the given tree. Setting the
+    /// event sends a watch event to the client, and setting mute stops
+    /// it answering.
+    fn server(tree: Tree, event: Arc<Mutex<bool>>, mute: Arc<Mutex<bool>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            stream
+                .set_read_timeout(Some(Duration::from_millis(20)))
+                .unwrap();
+            let mut conn = Connection {
+                stream,
+                xid: 0,
+                session: Duration::from_secs(10),
+            };
+            loop {
+                if let Ok(mut connect) = conn.recv() {
+                    connect.int().unwrap();
+                    break;
+                }
+            }
+            let mut response = Frame::default();
+            response.put_int(0);
+            response.put_int(10000);
+            response.put_long(1);
+            response.put_bytes(&[0; 16]);
+            conn.write(&response.0).unwrap();
+
+            loop {
+                let mut request = match conn.recv() {
+                    Ok(request) => request,
+                    Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return,
+                    Err(_) => {
+                        if std::mem::replace(&mut *event.lock().unwrap(), false) {
+                            let mut response = Frame::default();
+                            response.put_int(WATCH_XID);
+                            response.put_long(0);
+                            response.put_int(0);
+                            response.put_int(3); // NodeDataChanged
+                            response.put_int(3); // SyncConnected
+                            response.put_string("/app/db/host");
+                            conn.write(&response.0).unwrap();
+                        }
+                        continue;
+                    }
+                };
+                if *mute.lock().unwrap() {
+                    continue;
+                }
+                let xid = request.int().unwrap();
+                let op = request.int().unwrap();
+                let mut response = Frame::default();
+                response.put_int(xid);
+                response.put_long(0);
+                let tree = tree.lock().unwrap();
+                let path = match op {
+                    4 | 8 => request.string().unwrap(),
+                    _ => {
+                        response.put_int(0);
+                        conn.write(&response.0).unwrap();
+                        continue;
+                    }
+                };
+                let prefix = format!("{}/", path.trim_end_matches('/'));
+                let children: Vec<&str> = tree
+                    .keys()
+                    .filter_map(|k| k.strip_prefix(&prefix))
+                    .filter(|k| !k.contains('/'))
+                    .collect();
+                match tree.get(&path) {
+                    None => response.put_int(-101),
+                    Some(data) if op == 4 => {
+                        response.put_int(0);
+                        response.put_bytes(data.as_bytes());
+                        response.0.extend_from_slice(&[0; 68]);
+                    }
+                    Some(_) => {
+                        response.put_int(0);
+                        response.put_int(children.len() as i32);
+                        for child in children {
+                            response.put_string(child);
+                        }
+                    }
+                }
+                conn.write(&response.0).unwrap();
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn zookeeper() {
+        let tree: Tree = Arc::new(Mutex::new(
+            [
+                ("/", ""),
+                ("/app", ""),
+                ("/app/name", "svc"),
+                ("/app/db", ""),
+                ("/app/db/host", "localhost"),
+                ("/app/db/port", "5432"),
+                ("/other", "x"),
+            ]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        ));
+        let event = Arc::new(Mutex::new(false));
+        let addr = server(tree.clone(), event.clone(), Arc::new(Mutex::new(false)));
+
+        let cfg = ZooKeeper::connect(&addr, "/app/").unwrap();
+        assert_eq!(cfg.health(), Ok(()));
+        assert_eq!(cfg.string("name"), "svc");
+        assert_eq!(cfg.string("db.host"), "localhost");
+        assert_eq!(cfg.int("db.port"), 5432);
+        assert_eq!(cfg.get("db"), None);
+        assert_eq!(cfg.keys().len(), 3);
+
+        tree.lock()
+            .unwrap()
+            .insert("/app/db/host".to_string(), "db.internal".to_string());
+        *event.lock().unwrap() = true;
+        let start = Instant::now();
+        while cfg.string("db.host") != "db.internal" {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn silent() {
+        let tree: Tree = Arc::new(Mutex::new(
+            [("/", ""), ("/app", ""), ("/app/name", "svc")]
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        ));
+        let mute = Arc::new(Mutex::new(false));
+        let addr = server(tree, Arc::new(Mutex::new(false)), mute.clone());
+
+        let cfg = ZooKeeper::connect_with(&addr, "/app", Duration::from_millis(300)).unwrap();
+        assert_eq!(cfg.health(), Ok(()));
+        *mute.lock().unwrap() = true;
+        let start = Instant::now();
+        while cfg.source_health().is_ok() {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(cfg.string("name"), "svc");
+    }
+
+    #[test]
+    fn frames() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_millis(50)))
+            .unwrap();
+        let mut conn = Connection {
+            stream,
+            xid: 0,
+            session: Duration::from_secs(1),
+        };
+
+        // Nothing arriving is only the server being idle.
+        assert!(idle(&conn.recv().err().unwrap()));
+
+        server.write_all(&[0, 0, 0, 2, 7, 7]).unwrap();
+        assert_eq!(conn.recv().unwrap().0, vec![7, 7]);
+
+        for len in [MAX_FRAME as i32 + 1, -5] {
+            server.write_all(&len.to_be_bytes()).unwrap();
+            assert_eq!(conn.recv().err().unwrap().kind(), ErrorKind::InvalidData);
+        }
+
+        // A frame that stops part way through its body or its length.
+        server.write_all(&[0, 0, 0, 8, 1, 2, 3]).unwrap();
+        assert_eq!(conn.recv().err().unwrap().kind(), ErrorKind::InvalidData);
+        server.write_all(&[0, 0]).unwrap();
+        assert_eq!(conn.recv().err().unwrap().kind(), ErrorKind::InvalidData);
+
+        drop(server);
+        assert_eq!(conn.recv().err().unwrap().kind(), ErrorKind::UnexpectedEof);
+    }
+}
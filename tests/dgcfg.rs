@@ -0,0 +1,110 @@
+//! Run the `dgcfg` binary against the files in `tests/fixtures/dgcfg`.
+
+use std::process::{Command, Output};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/dgcfg");
+
+fn dgcfg(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_dgcfg"))
+        .current_dir(FIXTURES)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn get() {
+    let output = dgcfg(&["get", "db.pool", "-f", "new.cfg", "-f", "old.cfg"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "10\n");
+
+    let output = dgcfg(&["get", "db.host", "-f", "new.cfg", "-f", "old.cfg"]);
+    assert_eq!(stdout(&output), "db.internal\n");
+
+    let output = dgcfg(&["get", "missing", "-f", "old.cfg"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn env() {
+    let output = Command::new(env!("CARGO_BIN_EXE_dgcfg"))
+        .current_dir(FIXTURES)
+        .args(["get", "db.host", "-e", "DGCFG_TEST", "-f", "old.cfg"])
+        .env("DGCFG_TEST_DB_HOST", "from-env")
+        .output()
+        .unwrap();
+    assert_eq!(stdout(&output), "from-env\n");
+}
+
+#[test]
+fn list() {
+    let output = dgcfg(&["list", "-f", "old.cfg"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        "db.host = localhost\ndb.password = [redacted]\ndb.pool = 10\n"
+    );
+
+    let output = dgcfg(&["list", "-f", "old.cfg", "--show-secrets"]);
+    assert!(stdout(&output).contains("db.password = hunter2\n"));
+
+    let output = dgcfg(&["list", "-f", "old.cfg", "-o", "env"]);
+    assert!(stdout(&output).contains("DB_PASSWORD=\"[redacted]\"\n"));
+}
+
+#[test]
+fn diff() {
+    let output = dgcfg(&["diff", "old.cfg", "new.cfg"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "~ db.host = localhost -> db.internal\n\
+         ~ db.password = [redacted] -> [redacted]\n"
+    );
+    assert!(!stdout(&output).contains("hunter"));
+
+    let output = dgcfg(&["diff", "old.cfg", "new.cfg", "--show-secrets"]);
+    assert!(stdout(&output).contains("~ db.password = hunter2 -> hunter3\n"));
+
+    let output = dgcfg(&["diff", "old.cfg", "old.cfg"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn validate() {
+    let output = dgcfg(&["validate", "schema.cfg", "-f", "old.cfg"]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let output = dgcfg(&["validate", "strict.cfg", "-f", "old.cfg"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("db.replicas"));
+}
+
+#[test]
+fn convert() {
+    let output = dgcfg(&["convert", "old.cfg", "-o", "env"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).contains("DB_PASSWORD=\"hunter2\"\n"));
+
+    let output = dgcfg(&["convert", "old.cfg"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn usage() {
+    let output = dgcfg(&["--help"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).starts_with("usage: dgcfg"));
+
+    for args in [&["nope"][..], &["list", "--nope"], &[]] {
+        let output = dgcfg(args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(String::from_utf8_lossy(&output.stderr).starts_with("dgcfg: "));
+    }
+}